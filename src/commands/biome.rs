@@ -0,0 +1,90 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::world::biomes::Biome;
+use crate::world::locate::LocateSearches;
+use crate::world::{GameState, Overworld, WorldGenerator};
+
+/// Shows the biome the executor is standing in.
+#[derive(Command, Debug, Clone)]
+#[paths("biome")]
+#[scopes("crystal.command.biome")]
+pub struct BiomeCommand;
+
+/// Finds the nearest column of a biome in the executor's world.
+#[derive(Command, Debug, Clone)]
+#[paths("locatebiome {biome}")]
+#[scopes("crystal.command.locatebiome")]
+pub struct LocateBiomeCommand {
+    biome: String,
+}
+
+pub fn handle_biome_command(
+    mut events: EventReader<CommandResultEvent<BiomeCommand>>,
+    mut clients: Query<(&mut Client, &Position, &VisibleChunkLayer)>,
+    layers: Query<&ChunkLayer>,
+    biomes: Res<BiomeRegistry>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Ok(layer) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        let block = BlockPos::from(pos.0);
+        let Some(chunk) = layer.chunk(ChunkPos::from(block)) else {
+            client.send_chat_message("[biome] the chunk you're in isn't loaded".color(Color::GRAY));
+            continue;
+        };
+        let y = (block.y - layer.min_y()).clamp(0, layer.height() as i32 - 1) as u32;
+        let id = chunk.biome(block.x.rem_euclid(16) as u32 / 4, y / 4, block.z.rem_euclid(16) as u32 / 4);
+        match biomes.iter().find(|(known, _, _)| *known == id) {
+            Some((_, name, _)) => client.send_chat_message(format!("[biome] you're in {name}").color(Color::GREEN)),
+            None => client.send_chat_message("[biome] you're in a biome the server doesn't know".color(Color::GRAY)),
+        }
+    }
+}
+
+pub fn handle_locatebiome_command(
+    mut events: EventReader<CommandResultEvent<LocateBiomeCommand>>,
+    mut clients: Query<(&mut Client, &Position, &VisibleChunkLayer)>,
+    layers: Query<(&ChunkLayer, Option<&GameState>, Has<Overworld>)>,
+    world_gen: Res<WorldGenerator>,
+    mut searches: ResMut<LocateSearches>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Some(biome) = Biome::from_ident(&event.result.biome) else {
+            let options = Biome::ALL.map(Biome::ident);
+            CommandError::unknown_of("biome", &event.result.biome, options).report(&mut client, "locatebiome");
+            continue;
+        };
+        let Ok((layer, state, overworld)) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        let generator = match state {
+            _ if overworld => world_gen.generator.clone(),
+            Some(state) => state.generator(),
+            None => {
+                CommandError::NotAllowed("this world doesn't generate biomes to search".to_owned())
+                    .report(&mut client, "locatebiome");
+                continue;
+            }
+        };
+        if generator.is_flat() {
+            CommandError::NotAllowed("this world is flat, it's one biome everywhere".to_owned())
+                .report(&mut client, "locatebiome");
+            continue;
+        }
+
+        let (from, min_y) = (pos.0, layer.min_y());
+        client.send_chat_message(format!("[locatebiome] searching for {}...", biome.ident()).color(Color::GRAY));
+        searches.start(event.executor, "locatebiome", biome.ident().to_owned(), from, move || {
+            let (x, z) = generator.find_biome(biome, from.x.floor() as i32, from.z.floor() as i32)?;
+            Some(generator.standing_spot(x, z, min_y))
+        });
+    }
+}
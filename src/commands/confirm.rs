@@ -0,0 +1,39 @@
+use valence::{
+    command::{handler::CommandResultEvent, CommandExecutionEvent},
+    command_macros::Command,
+    prelude::*,
+};
+
+use super::error::CommandError;
+use crate::components::confirm::{Confirmations, CONFIRM_WINDOW};
+
+#[derive(Command, Debug, Clone)]
+#[paths("confirm")]
+#[scopes("crystal.command.confirm")]
+pub struct ConfirmCommand;
+
+pub fn handle_confirm_command(
+    mut events: EventReader<CommandResultEvent<ConfirmCommand>>,
+    mut clients: Query<&mut Client>,
+    mut confirmations: ResMut<Confirmations>,
+    mut executions: EventWriter<CommandExecutionEvent>,
+) {
+    for event in events.read() {
+        let Ok(mut client) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Some(command) = confirmations.confirm(event.executor) else {
+            CommandError::NothingFound(format!(
+                "nothing to confirm, commands have to be confirmed within {} seconds",
+                CONFIRM_WINDOW.as_secs()
+            ))
+            .report(&mut client, "confirm");
+            continue;
+        };
+        client.send_chat_message(format!("[confirm] running /{command}").color(Color::GOLD));
+        executions.send(CommandExecutionEvent {
+            command,
+            executor: event.executor,
+        });
+    }
+}
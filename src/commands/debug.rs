@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use crate::components::ghost::GhostBlocks;
+use crate::world::ChunkTimings;
+
+/// How long the visualizations stay up.
+const DEBUG_TIME: Duration = Duration::from_secs(30);
+/// Chunks around the executor's that `heightmap` and `biomes` cover.
+const DEBUG_RADIUS: i32 = 1;
+/// Blocks above and below the executor that chunk border corners reach.
+const BORDER_HEIGHT: i32 = 8;
+
+/// Colors for heightmap bands, low to high, and biomes.
+const COLORS: [BlockState; 16] = [
+    BlockState::BLACK_WOOL,
+    BlockState::BLUE_WOOL,
+    BlockState::CYAN_WOOL,
+    BlockState::LIGHT_BLUE_WOOL,
+    BlockState::GREEN_WOOL,
+    BlockState::LIME_WOOL,
+    BlockState::YELLOW_WOOL,
+    BlockState::ORANGE_WOOL,
+    BlockState::RED_WOOL,
+    BlockState::MAGENTA_WOOL,
+    BlockState::PINK_WOOL,
+    BlockState::PURPLE_WOOL,
+    BlockState::BROWN_WOOL,
+    BlockState::GRAY_WOOL,
+    BlockState::LIGHT_GRAY_WOOL,
+    BlockState::WHITE_WOOL,
+];
+/// Heightmap colors change every this many blocks.
+const HEIGHT_BAND: i32 = 16;
+
+#[derive(Command, Debug, Clone)]
+#[paths("debug")]
+#[scopes("crystal.command.debug")]
+pub enum DebugCommand {
+    #[paths("chunkborders")]
+    ChunkBorders,
+    #[paths("heightmap")]
+    Heightmap,
+    #[paths("biomes")]
+    Biomes,
+    #[paths("chunkinfo")]
+    ChunkInfo,
+    #[paths("clear")]
+    Clear,
+}
+
+/// The highest block in a column that isn't air.
+fn top_block(layer: &ChunkLayer, x: i32, z: i32) -> Option<BlockPos> {
+    let top = layer.min_y() + layer.height() as i32 - 1;
+    (layer.min_y()..=top)
+        .rev()
+        .map(|y| BlockPos::new(x, y, z))
+        .find(|pos| layer.block(*pos).is_some_and(|block| !block.state.is_air()))
+}
+
+/// Every column in the chunks around `center`.
+fn columns_around(center: ChunkPos) -> impl Iterator<Item = (i32, i32)> {
+    (-DEBUG_RADIUS..=DEBUG_RADIUS).flat_map(move |dx| {
+        (-DEBUG_RADIUS..=DEBUG_RADIUS).flat_map(move |dz| {
+            let (min_x, min_z) = ((center.x + dx) * 16, (center.z + dz) * 16);
+            (min_x..min_x + 16).flat_map(move |x| (min_z..min_z + 16).map(move |z| (x, z)))
+        })
+    })
+}
+
+/// The edges of `chunk` at `y`, and its corners from `BORDER_HEIGHT` below
+/// to above, wherever there's air.
+fn chunk_border(layer: &ChunkLayer, chunk: ChunkPos, y: i32) -> Vec<(BlockPos, BlockState)> {
+    let (min_x, min_z) = (chunk.x * 16, chunk.z * 16);
+    let (max_x, max_z) = (min_x + 15, min_z + 15);
+    let edges = (min_x..=max_x)
+        .flat_map(|x| [BlockPos::new(x, y, min_z), BlockPos::new(x, y, max_z)])
+        .chain((min_z..=max_z).flat_map(|z| [BlockPos::new(min_x, y, z), BlockPos::new(max_x, y, z)]))
+        .map(|pos| (pos, BlockState::YELLOW_STAINED_GLASS));
+    let corners = [(min_x, min_z), (min_x, max_z), (max_x, min_z), (max_x, max_z)]
+        .into_iter()
+        .flat_map(|(x, z)| (y - BORDER_HEIGHT..=y + BORDER_HEIGHT).map(move |y| BlockPos::new(x, y, z)))
+        .map(|pos| (pos, BlockState::ORANGE_STAINED_GLASS));
+    edges
+        .chain(corners)
+        .filter(|(pos, _)| layer.block(*pos).is_some_and(|block| block.state.is_air()))
+        .collect()
+}
+
+pub fn handle_debug_command(
+    mut events: EventReader<CommandResultEvent<DebugCommand>>,
+    mut clients: Query<(&mut Client, &Position, &VisibleChunkLayer)>,
+    layers: Query<&ChunkLayer>,
+    biomes: Res<BiomeRegistry>,
+    timings: Res<ChunkTimings>,
+    mut ghosts: ResMut<GhostBlocks>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Ok(layer) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        let here = BlockPos::from(pos.0);
+        let chunk_pos = ChunkPos::from(here);
+
+        match &event.result {
+            DebugCommand::ChunkBorders => {
+                ghosts.clear(event.executor);
+                let shown = ghosts.show(event.executor, chunk_border(layer, chunk_pos, here.y), DEBUG_TIME);
+                client.send_chat_message(
+                    format!("[debug] showing the borders of chunk {} {} ({shown} blocks)", chunk_pos.x, chunk_pos.z)
+                        .color(Color::GREEN),
+                );
+            }
+            DebugCommand::Heightmap => {
+                ghosts.clear(event.executor);
+                let blocks = columns_around(chunk_pos).filter_map(|(x, z)| {
+                    let top = top_block(layer, x, z)?;
+                    let band = (top.y - layer.min_y()) / HEIGHT_BAND;
+                    Some((top, COLORS[band.clamp(0, COLORS.len() as i32 - 1) as usize]))
+                });
+                ghosts.show(event.executor, blocks, DEBUG_TIME);
+                client.send_chat_message(
+                    format!("[debug] showing surface heights, colors change every {HEIGHT_BAND} blocks from black up to white")
+                        .color(Color::GREEN),
+                );
+            }
+            DebugCommand::Biomes => {
+                ghosts.clear(event.executor);
+                let ids: Vec<BiomeId> = biomes.iter().map(|(id, _, _)| id).collect();
+                let color_of = |id: BiomeId| COLORS[ids.iter().position(|known| *known == id).unwrap_or(0) % COLORS.len()];
+                let mut seen = Vec::new();
+                let blocks: Vec<_> = columns_around(chunk_pos)
+                    .filter_map(|(x, z)| {
+                        let top = top_block(layer, x, z)?;
+                        let chunk = layer.chunk(ChunkPos::from(top))?;
+                        let y = (top.y - layer.min_y()) as u32;
+                        let biome = chunk.biome(x.rem_euclid(16) as u32 / 4, y / 4, z.rem_euclid(16) as u32 / 4);
+                        if !seen.contains(&biome) {
+                            seen.push(biome);
+                        }
+                        Some((top, color_of(biome)))
+                    })
+                    .collect();
+                ghosts.show(event.executor, blocks, DEBUG_TIME);
+                client.send_chat_message("[debug] showing biomes:".color(Color::GREEN));
+                for (id, name, _) in biomes.iter().filter(|(id, _, _)| seen.contains(id)) {
+                    let color = color_of(id).to_kind().to_str().trim_end_matches("_wool").replace('_', " ");
+                    client.send_chat_message(format!(" {name}: {color}").color(Color::WHITE));
+                }
+            }
+            DebugCommand::ChunkInfo => {
+                let Some(chunk) = layer.chunk(chunk_pos) else {
+                    client.send_chat_message("[debug] the chunk you're in isn't loaded".color(Color::GRAY));
+                    continue;
+                };
+                let block_entities = (0..layer.height())
+                    .flat_map(|y| (0..16).flat_map(move |z| (0..16).map(move |x| (x, y, z))))
+                    .filter(|&(x, y, z)| chunk.block_state(x, y, z).block_entity_kind().is_some())
+                    .count();
+                let generated = match timings.0.get(&chunk_pos) {
+                    Some(took) => format!("generated in {:.1} ms", took.as_secs_f64() * 1000.0),
+                    None => "no generation time, it was loaded or made before this run".to_owned(),
+                };
+                client.send_chat_message(format!("[debug] chunk {} {}:", chunk_pos.x, chunk_pos.z).color(Color::GOLD));
+                for line in [
+                    generated,
+                    format!("{} viewers", chunk.viewer_count()),
+                    format!("{block_entities} block entities"),
+                ] {
+                    client.send_chat_message(format!(" {line}").color(Color::WHITE));
+                }
+            }
+            DebugCommand::Clear => {
+                ghosts.clear(event.executor);
+                client.send_chat_message("[debug] cleared".color(Color::GREEN));
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// src/commands/error.rs
+//
+// Why a command couldn't do what it was asked, after its arguments parsed.
+// Every handler reports failures through `CommandError::report`, so players
+// always get a red `[command] ...` line instead of silence.
+//
+// Where vanilla has a message for the same failure, the client's own
+// translation is used, so those show up in the player's language. The rest
+// are English. Storage errors only tell the player something went wrong;
+// the details go to the server log.
+
+use std::fmt;
+
+use tracing::error;
+use valence::prelude::*;
+
+use crate::components::logging::COMMANDS;
+
+/// At most this many options are listed after an unknown name.
+const MAX_OPTIONS: usize = 20;
+
+#[derive(Clone, Debug)]
+pub enum CommandError {
+    /// Nobody online, or known to the server, by that name.
+    PlayerNotFound(String),
+    /// There's no `what` called `name`. `options` are the valid names, if
+    /// there's a reasonably short list of them.
+    Unknown { what: &'static str, name: String, options: Vec<String> },
+    AlreadyExists { what: &'static str, name: String },
+    /// An argument parsed but can't be used; the text says why.
+    InvalidArgument(String),
+    /// The executor isn't allowed to do this, or not right now.
+    NotAllowed(String),
+    /// The command ran but found nothing to act on.
+    NothingFound(String),
+    /// Something the command doesn't handle yet.
+    Unsupported(&'static str),
+    /// Reading or writing `name` failed.
+    Storage { action: &'static str, name: String, error: String },
+}
+
+impl CommandError {
+    pub fn unknown(what: &'static str, name: impl Into<String>) -> Self {
+        Self::Unknown { what, name: name.into(), options: Vec::new() }
+    }
+
+    /// Like `unknown`, listing `options` alphabetically.
+    pub fn unknown_of<S: ToString>(what: &'static str, name: impl Into<String>, options: impl IntoIterator<Item = S>) -> Self {
+        let mut options: Vec<String> = options.into_iter().map(|option| option.to_string()).collect();
+        options.sort_unstable();
+        Self::Unknown { what, name: name.into(), options }
+    }
+
+    pub fn storage(action: &'static str, name: impl Into<String>, error: impl ToString) -> Self {
+        Self::Storage { action, name: name.into(), error: error.to_string() }
+    }
+
+    /// The message shown to the player.
+    pub fn text(&self) -> Text {
+        match self {
+            CommandError::PlayerNotFound(name) => {
+                Text::translate("argument.player.unknown", Vec::<Text>::new()) + format!(" ({name})")
+            }
+            CommandError::Unknown { what: "team", name, .. } => Text::translate("team.notFound", [Text::from(name.clone())]),
+            CommandError::Unknown { what: "block", name, .. } => {
+                Text::translate("argument.block.id.invalid", [Text::from(name.clone())])
+            }
+            CommandError::AlreadyExists { what: "team", .. } => {
+                Text::translate("commands.team.add.duplicate", Vec::<Text>::new())
+            }
+            CommandError::Storage { action, name, .. } => format!("couldn't {action} {name}, see the server log").into(),
+            other => other.to_string().into(),
+        }
+    }
+
+    /// Tells the executor what went wrong. `command` is the prefix their
+    /// messages from this command already use, e.g. `team`.
+    pub fn report(&self, client: &mut Client, command: &str) {
+        if let CommandError::Storage { action, name, error } = self {
+            error!(target: COMMANDS, "[{command}] failed to {action} {name}: {error}");
+        }
+        let mut text = format!("[{command}] ").color(Color::RED) + self.text().color(Color::RED);
+        if let CommandError::Unknown { options, .. } = self
+            && !options.is_empty()
+            && options.len() <= MAX_OPTIONS
+        {
+            text = text + format!(", try one of: {}", options.join(", ")).color(Color::GRAY);
+        }
+        client.send_chat_message(text);
+    }
+}
+
+/// English versions of every message, also used in logs.
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::PlayerNotFound(name) => write!(f, "could not find player {name}"),
+            CommandError::Unknown { what, name, .. } => write!(f, "unknown {what}: {name}"),
+            CommandError::AlreadyExists { what, name } => write!(f, "{what} {name} already exists"),
+            CommandError::InvalidArgument(reason) | CommandError::NotAllowed(reason) | CommandError::NothingFound(reason) => {
+                f.write_str(reason)
+            }
+            CommandError::Unsupported(what) => write!(f, "{what} isn't supported yet"),
+            CommandError::Storage { action, name, error } => write!(f, "failed to {action} {name}: {error}"),
+        }
+    }
+}
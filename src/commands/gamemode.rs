@@ -9,6 +9,9 @@ use valence::{
     rand::seq::IteratorRandom,
 };
 
+use super::error::CommandError;
+use crate::components::logging::COMMANDS;
+
 #[derive(Command, Debug, Clone)]
 #[paths("gamemode", "gm")]
 #[scopes("crystal.command.gamemode")]
@@ -34,7 +37,7 @@ fn set_player_gamemode(
         *components.1 = gm; // Mutate GameMode directly
         true
     } else {
-        error!("failed to get gamemode components for entity {:?}", target);
+        error!(target: COMMANDS, "failed to get gamemode components for entity {:?}", target);
         false
     }
 }
@@ -42,19 +45,26 @@ fn set_player_gamemode(
 // Helper function to send a message to the command executor
 fn send_feedback_to_executor(
     message: Text,
-    is_error: bool,
     clients: &mut Query<(&mut Client, &mut GameMode, &Username, Entity)>,
     executor: Entity,
 ) {
     if let Ok(mut components) = clients.get_mut(executor) {
-        let formatted_message = if is_error {
-            "[gm] ".color(Color::RED) + message.color(Color::RED)
-        } else {
-            "[gm] ".color(Color::GOLD) + message
-        };
-        components.0.send_chat_message(formatted_message); // Mutate Client
+        components.0.send_chat_message("[gm] ".color(Color::GOLD) + message); // Mutate Client
     } else {
-        error!("failed to get client component for executor {:?}", executor);
+        error!(target: COMMANDS, "failed to get client component for executor {:?}", executor);
+    }
+}
+
+// Helper function to tell the command executor what went wrong
+fn report_to_executor(
+    error: CommandError,
+    clients: &mut Query<(&mut Client, &mut GameMode, &Username, Entity)>,
+    executor: Entity,
+) {
+    if let Ok(mut components) = clients.get_mut(executor) {
+        error.report(&mut components.0, "gm");
+    } else {
+        error!(target: COMMANDS, "failed to get client component for executor {:?}", executor);
     }
 }
 
@@ -115,7 +125,6 @@ pub fn handle_gamemode_command(
                 if set_player_gamemode(target, &mut clients, game_mode_to_set) {
                     send_feedback_to_executor(
                         format_gamemode_message("changed", None, game_mode_to_set),
-                        false,
                         &mut clients,
                         event.executor,
                     );
@@ -151,7 +160,6 @@ pub fn handle_gamemode_command(
                                 "[gm] changed gamemode of {} players to {:?}.",
                                 success_count, game_mode_to_set
                             ).color(Color::GOLD),
-                            false,
                             &mut clients,
                             event.executor,
                         );
@@ -171,18 +179,12 @@ pub fn handle_gamemode_command(
                                         Some(&target_username),
                                         game_mode_to_set,
                                     ),
-                                    false,
                                     &mut clients,
                                     event.executor,
                                 );
                             }
                         } else {
-                            send_feedback_to_executor(
-                                format!("could not find target: {}", name).into(),
-                                true,
-                                &mut clients,
-                                event.executor,
-                            );
+                            report_to_executor(CommandError::PlayerNotFound(name.clone()), &mut clients, event.executor);
                         }
                     }
                     // --- Subcase: Executor Self ---
@@ -191,7 +193,6 @@ pub fn handle_gamemode_command(
                         if set_player_gamemode(target, &mut clients, game_mode_to_set) {
                             send_feedback_to_executor(
                                 format_gamemode_message("changed", None, game_mode_to_set),
-                                false,
                                 &mut clients,
                                 event.executor,
                             );
@@ -202,12 +203,7 @@ pub fn handle_gamemode_command(
                         let executor_pos = match positions.get(event.executor) {
                             Ok(pos) => **pos,
                             Err(_) => {
-                                send_feedback_to_executor(
-                                    "could not get executor position.".into(),
-                                    true,
-                                    &mut clients,
-                                    event.executor,
-                                );
+                                report_to_executor(CommandError::NotAllowed("could not get your position".to_owned()), &mut clients, event.executor);
                                 continue;
                             }
                         };
@@ -235,18 +231,12 @@ pub fn handle_gamemode_command(
                                         Some(&target_username),
                                         game_mode_to_set,
                                     ),
-                                    false,
                                     &mut clients,
                                     event.executor,
                                 );
                             }
                         } else {
-                            send_feedback_to_executor(
-                                "could not find nearest player.".into(),
-                                true,
-                                &mut clients,
-                                event.executor,
-                            );
+                            report_to_executor(CommandError::NothingFound("could not find nearest player".to_owned()), &mut clients, event.executor);
                         }
                     }
                     // --- Subcase: Random Player ---
@@ -264,29 +254,18 @@ pub fn handle_gamemode_command(
                                         Some(&target_username),
                                         game_mode_to_set,
                                     ),
-                                    false,
                                     &mut clients,
                                     event.executor,
                                 );
                             }
                         } else {
-                            send_feedback_to_executor(
-                                "could not find a random player.".into(),
-                                true,
-                                &mut clients,
-                                event.executor,
-                            );
+                            report_to_executor(CommandError::NothingFound("could not find a random player".to_owned()), &mut clients, event.executor);
                         }
                     }
                 },
                 // --- Subcase: Complex Selector (Not Implemented) ---
                 EntitySelector::ComplexSelector(_, _) => {
-                    send_feedback_to_executor(
-                        "complex selectors are not implemented.".into(),
-                        true,
-                        &mut clients,
-                        event.executor,
-                    );
+                    report_to_executor(CommandError::Unsupported("complex selectors"), &mut clients, event.executor);
                 }
             },
         }
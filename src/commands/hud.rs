@@ -0,0 +1,52 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::hud::HudElements;
+use crate::components::playerdata::PlayerData;
+
+#[derive(Command, Debug, Clone)]
+#[paths("hud")]
+#[scopes("crystal.command.hud")]
+pub enum HudCommand {
+    #[paths("toggle {element}")]
+    Toggle { element: String },
+    #[paths("list")]
+    List,
+}
+
+pub fn handle_hud_command(
+    mut events: EventReader<CommandResultEvent<HudCommand>>,
+    mut clients: Query<(&mut Client, &mut PlayerData)>,
+    elements: Res<HudElements>,
+) {
+    for event in events.read() {
+        let Ok((mut client, mut data)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        match &event.result {
+            HudCommand::Toggle { element } => {
+                let Some(element) = elements.find(element) else {
+                    CommandError::unknown_of("element", element, &elements.0).report(&mut client, "hud");
+                    continue;
+                };
+                if let Some(index) = data.hud_elements.iter().position(|e| e == element) {
+                    data.hud_elements.remove(index);
+                    client.send_chat_message(format!("[hud] {element} hidden").color(Color::GRAY));
+                } else {
+                    data.hud_elements.push(element.to_owned());
+                    client.send_chat_message(format!("[hud] {element} shown").color(Color::GREEN));
+                }
+            }
+            HudCommand::List => {
+                client.send_chat_message("[hud] elements:".color(Color::GOLD));
+                for element in &elements.0 {
+                    let shown = data.hud_elements.iter().any(|e| e == element);
+                    client.send_chat_message(
+                        format!(" {element} ").color(Color::WHITE)
+                            + if shown { "(shown)".color(Color::GREEN) } else { "(hidden)".color(Color::GRAY) },
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::creative::CreativeRules;
+
+#[derive(Command, Debug, Clone)]
+#[paths("kit {name?}")]
+#[scopes("crystal.command.kit")]
+pub struct KitCommand {
+    name: Option<String>,
+}
+
+pub fn handle_kit_command(
+    mut events: EventReader<CommandResultEvent<KitCommand>>,
+    mut clients: Query<(&mut Client, &mut Inventory)>,
+    rules: Res<CreativeRules>,
+) {
+    for event in events.read() {
+        let Ok((mut client, mut inventory)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+
+        let Some(name) = &event.result.name else {
+            let mut kits: Vec<&str> = rules.kits.keys().map(String::as_str).collect();
+            kits.sort_unstable();
+            client.send_chat_message(
+                "[kit] available kits: ".color(Color::GOLD) + kits.join(", ").color(Color::RED),
+            );
+            continue;
+        };
+        let Some(kit) = rules.kits.get(name) else {
+            CommandError::unknown_of("kit", name, rules.kits.keys()).report(&mut client, "kit");
+            continue;
+        };
+
+        // Fill the main inventory (slots 9..=44, hotbar last) in kit order.
+        let mut given = 0;
+        for stack in kit {
+            let Some(slot) = (9..=44).find(|slot| inventory.slot(*slot).is_empty()) else {
+                break;
+            };
+            inventory.set_slot(slot, stack.clone());
+            given += 1;
+        }
+        client.send_chat_message(
+            format!("[kit] received {given}/{} items from {name}", kit.len()).color(Color::GREEN),
+        );
+    }
+}
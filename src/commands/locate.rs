@@ -0,0 +1,58 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::world::locate::LocateSearches;
+use crate::world::{GameState, Overworld, WorldGenerator};
+
+/// Finds the nearest generated structure in the executor's world, from the
+/// seed alone, so it doesn't have to be loaded or even generated yet.
+#[derive(Command, Debug, Clone)]
+#[paths("locate")]
+#[scopes("crystal.command.locate")]
+pub enum LocateCommand {
+    #[paths("structure {name}")]
+    Structure { name: String },
+}
+
+pub fn handle_locate_command(
+    mut events: EventReader<CommandResultEvent<LocateCommand>>,
+    mut clients: Query<(&mut Client, &Position, &VisibleChunkLayer)>,
+    layers: Query<(&ChunkLayer, Option<&GameState>, Has<Overworld>)>,
+    world_gen: Res<WorldGenerator>,
+    mut searches: ResMut<LocateSearches>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Ok((layer, state, overworld)) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        let generator = match state {
+            _ if overworld => world_gen.generator.clone(),
+            Some(state) => state.generator(),
+            None => {
+                CommandError::NotAllowed("this world doesn't generate structures to search".to_owned())
+                    .report(&mut client, "locate");
+                continue;
+            }
+        };
+
+        match &event.result {
+            LocateCommand::Structure { name } => {
+                let names = generator.structure_names();
+                if !names.contains(name) {
+                    CommandError::unknown_of("structure", name, names).report(&mut client, "locate");
+                    continue;
+                }
+                let (from, min_y) = (pos.0, layer.min_y());
+                client.send_chat_message(format!("[locate] searching for {name}...").color(Color::GRAY));
+                let name = name.clone();
+                searches.start(event.executor, "locate", name.clone(), from, move || {
+                    let (x, z) = generator.find_structure(&name, from.x.floor() as i32, from.z.floor() as i32)?;
+                    Some(generator.standing_spot(x, z, min_y))
+                });
+            }
+        }
+    }
+}
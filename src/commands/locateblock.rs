@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+/// Loaded chunks checked in each direction around the player.
+const SEARCH_RADIUS: i32 = 8;
+
+#[derive(Command, Debug, Clone)]
+#[paths("locateblock {block}")]
+#[scopes("crystal.command.locateblock")]
+pub struct LocateBlockCommand {
+    block: String,
+}
+
+fn find_nearest(layer: &ChunkLayer, from: DVec3, kind: BlockKind) -> Option<(BlockPos, usize)> {
+    let center = ChunkPos::from(from);
+    let mut nearest: Option<(BlockPos, f64)> = None;
+    let mut chunks_searched = 0;
+
+    for cz in center.z - SEARCH_RADIUS..=center.z + SEARCH_RADIUS {
+        for cx in center.x - SEARCH_RADIUS..=center.x + SEARCH_RADIUS {
+            let Some(chunk) = layer.chunk(ChunkPos::new(cx, cz)) else {
+                continue;
+            };
+            chunks_searched += 1;
+            for y in 0..chunk.height() {
+                for z in 0..16 {
+                    for x in 0..16 {
+                        if chunk.block_state(x, y, z).to_kind() != kind {
+                            continue;
+                        }
+                        let pos = BlockPos::new(cx * 16 + x as i32, layer.min_y() + y as i32, cz * 16 + z as i32);
+                        let distance = DVec3::new(pos.x as f64, pos.y as f64, pos.z as f64).distance_squared(from);
+                        if nearest.is_none_or(|(_, best)| distance < best) {
+                            nearest = Some((pos, distance));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    nearest.map(|(pos, _)| (pos, chunks_searched))
+}
+
+pub fn handle_locateblock_command(
+    mut events: EventReader<CommandResultEvent<LocateBlockCommand>>,
+    mut clients: Query<(&mut Client, &Position, &VisibleChunkLayer)>,
+    layers: Query<&ChunkLayer>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let name = event.result.block.strip_prefix("minecraft:").unwrap_or(&event.result.block);
+        let Some(kind) = BlockKind::from_str(name) else {
+            CommandError::unknown("block", name).report(&mut client, "locateblock");
+            continue;
+        };
+        let Ok(layer) = layers.get(visible_layer.0) else {
+            continue;
+        };
+
+        match find_nearest(layer, pos.0, kind) {
+            Some((found, chunks)) => {
+                let distance = pos.0.distance(DVec3::new(found.x as f64, found.y as f64, found.z as f64));
+                client.send_chat_message(
+                    format!(
+                        "[locateblock] nearest {name} at {} {} {} ({distance:.0} blocks away, {chunks} chunks searched)",
+                        found.x, found.y, found.z
+                    )
+                    .color(Color::GREEN),
+                );
+            }
+            None => CommandError::NothingFound(format!("no {name} in loaded chunks within {SEARCH_RADIUS} chunks"))
+                .report(&mut client, "locateblock"),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, log::Level, prelude::*};
+
+use super::error::CommandError;
+use crate::components::logging::recent_logs;
+
+const DEFAULT_LINES: i32 = 10;
+/// More than this scrolls off the chat anyway.
+const MAX_LINES: i32 = 50;
+
+#[derive(Command, Debug, Clone)]
+#[paths("logs")]
+#[scopes("crystal.command.logs")]
+pub enum LogsCommand {
+    #[paths("tail {lines?} {level?}")]
+    Tail { lines: Option<i32>, level: Option<String> },
+}
+
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::ERROR => Color::RED,
+        Level::WARN => Color::GOLD,
+        Level::INFO => Color::WHITE,
+        _ => Color::GRAY,
+    }
+}
+
+pub fn handle_logs_command(mut events: EventReader<CommandResultEvent<LogsCommand>>, mut clients: Query<&mut Client>) {
+    for event in events.read() {
+        let Ok(mut client) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let LogsCommand::Tail { lines, level } = &event.result;
+        let count = lines.unwrap_or(DEFAULT_LINES).clamp(1, MAX_LINES) as usize;
+        let min_level = match level.as_deref().map(str::parse::<Level>) {
+            None => Level::INFO,
+            Some(Ok(level)) => level,
+            Some(Err(_)) => {
+                CommandError::unknown_of("level", level.as_deref().unwrap_or_default(), ["error", "warn", "info", "debug", "trace"])
+                    .report(&mut client, "logs");
+                continue;
+            }
+        };
+
+        let logs = recent_logs(count, min_level);
+        if logs.is_empty() {
+            client.send_chat_message(format!("[logs] nothing at {min_level} or above").color(Color::GRAY));
+            continue;
+        }
+        client.send_chat_message(format!("[logs] last {} lines ({min_level}+):", logs.len()).color(Color::GOLD));
+        for line in logs {
+            client.send_chat_message(
+                format!("{} ", line.timestamp()).color(Color::DARK_GRAY)
+                    + format!("{} ", line.target).color(Color::GRAY)
+                    + line.message.color(level_color(line.level)),
+            );
+        }
+    }
+}
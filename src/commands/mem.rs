@@ -0,0 +1,50 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use crate::components::memory::{MemoryConfig, MemoryReport};
+use crate::world::deferred::PendingBlocks;
+use crate::world::storage::ChunkSaver;
+use crate::world::GameState;
+
+#[derive(Command, Debug, Clone)]
+#[paths("mem")]
+#[scopes("crystal.command.mem")]
+pub struct MemCommand;
+
+pub fn handle_mem_command(
+    mut events: EventReader<CommandResultEvent<MemCommand>>,
+    mut clients: Query<&mut Client>,
+    layers: Query<&ChunkLayer>,
+    entities: Query<&EntityKind>,
+    state: Option<Res<GameState>>,
+    pending: Option<Res<PendingBlocks>>,
+    saver: Option<Res<ChunkSaver>>,
+    config: Res<MemoryConfig>,
+) {
+    for event in events.read() {
+        let Ok(mut client) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let report = MemoryReport::gather(&layers, &entities, state.as_deref(), pending.as_deref(), saver.as_deref());
+        client.send_chat_message(format!("[mem] process memory: {}", report.rss_text()).color(Color::GOLD));
+        client.send_chat_message(format!("[mem] loaded chunks: {}", report.chunks).color(Color::WHITE));
+        let top: Vec<String> = report
+            .top_entities
+            .iter()
+            .map(|(kind, count)| format!("{count} {kind:?}"))
+            .collect();
+        client.send_chat_message(
+            format!("[mem] entities: {}", report.entities).color(Color::WHITE)
+                + format!(" ({})", top.join(", ")).color(Color::GRAY),
+        );
+        client.send_chat_message(
+            format!(
+                "[mem] queued: {} chunks to generate, {} block changes, {} unsaved chunks",
+                report.queued_chunks, report.queued_blocks, report.unsaved_chunks
+            )
+            .color(Color::WHITE),
+        );
+        for warning in report.warnings(&config) {
+            client.send_chat_message(format!("[mem] {warning}").color(Color::RED));
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::{
+    core::new_crystal_message,
+    minigame::{JoinMinigameRequest, LeaveMinigameRequest, Minigames},
+    party::Parties,
+};
+
+#[derive(Command, Debug, Clone)]
+#[paths("minigame", "mg")]
+#[scopes("crystal.command.minigame")]
+pub enum MinigameCommand {
+    #[paths("join {arena}")]
+    Join { arena: String },
+    #[paths("leave")]
+    Leave,
+    #[paths("list")]
+    List,
+}
+
+pub fn handle_minigame_command(
+    mut events: EventReader<CommandResultEvent<MinigameCommand>>,
+    mut clients: Query<&mut Client>,
+    minigames: Res<Minigames>,
+    parties: Res<Parties>,
+    mut joins: EventWriter<JoinMinigameRequest>,
+    mut leaves: EventWriter<LeaveMinigameRequest>,
+) {
+    for event in events.read() {
+        match &event.result {
+            MinigameCommand::Join { arena } => {
+                // Party leaders bring their whole party along.
+                let requests = parties.queue_for_minigame(event.executor, arena);
+                if requests.is_empty() {
+                    if let Ok(mut client) = clients.get_mut(event.executor) {
+                        CommandError::NotAllowed("only your party leader can join games".to_owned())
+                            .report(&mut client, "minigame");
+                    }
+                }
+                joins.send_batch(requests);
+            }
+            MinigameCommand::Leave => {
+                leaves.send(LeaveMinigameRequest {
+                    player: event.executor,
+                });
+            }
+            MinigameCommand::List => {
+                let Ok(mut client) = clients.get_mut(event.executor) else {
+                    continue;
+                };
+                if minigames.games.is_empty() {
+                    CommandError::NothingFound("no arenas configured".to_owned()).report(&mut client, "minigame");
+                    continue;
+                }
+                client.send_chat_message(new_crystal_message("Arenas:".color(Color::GOLD)));
+                for (name, game) in &minigames.games {
+                    client.send_chat_message(
+                        format!(" - {name}").color(Color::RED)
+                            + format!(
+                                " [{}] {}/{} players, {:?}",
+                                game.arena.mode,
+                                game.players.len(),
+                                game.arena.max_players,
+                                game.stage
+                            )
+                            .color(Color::GOLD),
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,36 @@
 pub mod core;
+pub mod error;
 pub mod teleport;
 pub mod gamemode;
 pub mod op;
+pub mod minigame;
+pub mod party;
+pub mod team;
+pub mod kit;
+pub mod trader;
+pub mod locateblock;
+pub mod position;
+pub mod logs;
+pub mod playtime;
+pub mod hud;
+pub mod structure;
+pub mod setspawner;
+pub mod mem;
+pub mod sudo;
+pub mod nether;
+pub mod confirm;
+pub mod stop;
+pub mod world;
+pub mod spectate;
+pub mod setworldspawn;
+pub mod replay;
+pub mod debug;
+pub mod worldgen;
+pub mod biome;
+pub mod locate;
+pub mod time;
+pub mod warp;
+pub mod weather;
+pub mod seed;
+pub mod settings;
+pub mod save_all;
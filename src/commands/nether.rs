@@ -0,0 +1,43 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, entity::EntityLayerId, prelude::*};
+
+use super::error::CommandError;
+use crate::world::nether::{TheNether, NETHER_SPAWN_POS};
+use crate::world::{send_to_layer, Overworld, WorldSpawn};
+
+/// Moves the executor between the overworld and the Nether.
+#[derive(Command, Debug, Clone)]
+#[paths("nether")]
+#[scopes("crystal.command.nether")]
+pub struct NetherCommand;
+
+pub fn handle_nether_command(
+    mut events: EventReader<CommandResultEvent<NetherCommand>>,
+    mut clients: Query<(
+        &mut Client,
+        &mut EntityLayerId,
+        &mut VisibleChunkLayer,
+        &mut VisibleEntityLayers,
+        &mut Position,
+    )>,
+    overworld: Query<Entity, With<Overworld>>,
+    nether: Query<Entity, With<TheNether>>,
+    spawn: Res<WorldSpawn>,
+) {
+    for event in events.read() {
+        let Ok((mut client, mut layer_id, mut visible_chunk, mut visible_entities, mut pos)) = clients.get_mut(event.executor)
+        else {
+            continue;
+        };
+        let (Ok(overworld), Ok(nether)) = (overworld.get_single(), nether.get_single()) else {
+            CommandError::Unsupported("the Nether on this server").report(&mut client, "nether");
+            continue;
+        };
+        let (layer, destination, name) = if visible_chunk.0 == nether {
+            (overworld, spawn.0, "the overworld")
+        } else {
+            (nether, NETHER_SPAWN_POS, "the Nether")
+        };
+        send_to_layer(layer, destination, &mut layer_id, &mut visible_chunk, &mut visible_entities, &mut pos);
+        client.send_chat_message(format!("[nether] sent you to {name}").color(Color::GREEN));
+    }
+}
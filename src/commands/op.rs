@@ -1,6 +1,7 @@
 use valence::{command::{handler::CommandResultEvent, parsers::{entity_selector::EntitySelectors, EntitySelector}, scopes::CommandScopes}, command_macros::Command, op_level::OpLevel, prelude::*};
 
-use crate::components::core::set_op_status;
+use super::error::CommandError;
+use crate::components::core::{executor, set_op_status};
 
 #[derive(Command, Debug, Clone)]
 #[paths("op {target?}")]
@@ -9,27 +10,39 @@ pub struct OpCommand {
     target: Option<EntitySelector>
 }
 
-fn send_message(client: &mut Client, message: &str, color: Color) {
-    client.send_chat_message(message.to_string().color(color));
+type OpQuery<'w, 's> = Query<'w, 's, (&'static mut Client, &'static Username, Entity, &'static mut OpLevel, &'static mut CommandScopes)>;
+
+/// Tells the executor how it went, if they're still connected.
+fn send_message(clients: &mut OpQuery, executor_entity: Entity, message: &str, color: Color) {
+    if let Some((mut client, ..)) = executor(clients.get_mut(executor_entity), "op") {
+        client.send_chat_message(message.to_string().color(color));
+    }
+}
+
+fn report(clients: &mut OpQuery, executor_entity: Entity, error: CommandError) {
+    if let Some((mut client, ..)) = executor(clients.get_mut(executor_entity), "op") {
+        error.report(&mut client, "op");
+    }
 }
 
 pub fn handle_op_command(
     mut events: EventReader<CommandResultEvent<OpCommand>>,
-    mut clients: Query<(&mut Client, &Username, Entity, &mut OpLevel, &mut CommandScopes)>,
+    mut clients: OpQuery,
 ) {
     for event in events.read() {
         let selector = &event.result.target;
 
         match selector {
             None => {
-                let (mut client, username, _, mut oplevel, mut permissions) = clients.get_mut(event.executor).unwrap();
+                let Some((mut client, username, _, mut oplevel, mut permissions)) = executor(clients.get_mut(event.executor), "op") else {
+                    continue;
+                };
                 set_op_status(&mut client, &username, &mut oplevel, Some(true), &mut permissions);
             }
             Some(selector) => match selector {
                 EntitySelector::SimpleSelector(selector) => match selector {
                     EntitySelectors::AllEntities => {
-                        let client = &mut clients.get_mut(event.executor).unwrap().0;
-                        send_message(client, "[op] can't op entities", Color::RED);
+                        report(&mut clients, event.executor, CommandError::InvalidArgument("can't op entities".to_owned()));
                     }
                     EntitySelectors::SinglePlayer(name) => {
                         let target = clients
@@ -37,31 +50,26 @@ pub fn handle_op_command(
                             .find(|(_, username, _, ..)| username.0 == *name)
                             .map(|(_, _, target, ..)| target);
 
-                        let client = &mut clients.get_mut(event.executor).unwrap().0;
                         match target {
-                            None => send_message(client, &format!("[op] could not find target: {name}"), Color::RED),
-                            Some(_) => send_message(client, &format!("[op] successfully opped {name}"), Color::GREEN),
+                            None => report(&mut clients, event.executor, CommandError::PlayerNotFound(name.clone())),
+                            Some(_) => send_message(&mut clients, event.executor, &format!("[op] successfully opped {name}"), Color::GREEN),
                         }
                     }
                     EntitySelectors::AllPlayers => {
                         for (mut client, username, _, mut oplevel, mut permissions) in &mut clients.iter_mut() {
                             set_op_status(&mut client, &username, &mut oplevel, Some(true), &mut permissions);
                         }
-                        let clientexec = &mut clients.get_mut(event.executor).unwrap().0;
-                        send_message(clientexec, "[op] successfully opped everyone", Color::GREEN);
+                        send_message(&mut clients, event.executor, "[op] successfully opped everyone", Color::GREEN);
                     }
                     EntitySelectors::SelfPlayer => {
-                        let client = &mut clients.get_mut(event.executor).unwrap().0;
-                        send_message(client, "[op] can't op yourself", Color::RED);
+                        report(&mut clients, event.executor, CommandError::NotAllowed("can't op yourself".to_owned()));
                     }
                     EntitySelectors::NearestPlayer | EntitySelectors::RandomPlayer => {
-                        let client = &mut clients.get_mut(event.executor).unwrap().0;
-                        send_message(client, "[op] work in progress", Color::RED);
+                        report(&mut clients, event.executor, CommandError::Unsupported("@p and @r"));
                     }
                 },
                 EntitySelector::ComplexSelector(_, _) => {
-                    let client = &mut clients.get_mut(event.executor).unwrap().0;
-                    send_message(client, "[op] complex selector not implemented", Color::RED);
+                    report(&mut clients, event.executor, CommandError::Unsupported("complex selectors"));
                 }
             },
         }
@@ -0,0 +1,185 @@
+use valence::{
+    command::{handler::CommandResultEvent, parsers::GreedyString},
+    command_macros::Command,
+    prelude::*,
+};
+
+use super::error::CommandError;
+use crate::components::party::Parties;
+
+#[derive(Command, Debug, Clone)]
+#[paths("party", "p")]
+#[scopes("crystal.command.party")]
+pub enum PartyCommand {
+    #[paths("create")]
+    Create,
+    #[paths("invite {target}")]
+    Invite { target: String },
+    #[paths("accept")]
+    Accept,
+    #[paths("leave")]
+    Leave,
+    #[paths("list")]
+    List,
+    #[paths("chat {message}", "{/} pc {message}")]
+    Chat { message: GreedyString },
+}
+
+fn send_message(client: &mut Client, message: &str, color: Color) {
+    client.send_chat_message(format!("[party] {message}").color(color));
+}
+
+fn broadcast(members: &[Entity], clients: &mut Query<(Entity, &mut Client, &Username)>, message: Text) {
+    for member in members {
+        if let Ok((_, mut client, _)) = clients.get_mut(*member) {
+            client.send_chat_message(message.clone());
+        }
+    }
+}
+
+pub fn handle_party_command(
+    mut events: EventReader<CommandResultEvent<PartyCommand>>,
+    mut parties: ResMut<Parties>,
+    mut clients: Query<(Entity, &mut Client, &Username)>,
+) {
+    for event in events.read() {
+        let executor = event.executor;
+        let Ok((_, _, username)) = clients.get(executor) else {
+            continue;
+        };
+        let username = username.0.clone();
+
+        match &event.result {
+            PartyCommand::Create => {
+                let created = parties.create(executor).is_some();
+                let Ok((_, mut client, _)) = clients.get_mut(executor) else {
+                    continue;
+                };
+                if created {
+                    send_message(&mut client, "created a party, invite people with /party invite <player>", Color::GREEN);
+                } else {
+                    CommandError::NotAllowed("you are already in a party".to_owned()).report(&mut client, "party");
+                }
+            }
+            PartyCommand::Invite { target } => {
+                let party = parties.party_of(executor);
+                if party.is_some_and(|id| parties.parties[&id].leader != executor) {
+                    if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                        CommandError::NotAllowed("only the party leader can invite".to_owned()).report(&mut client, "party");
+                    }
+                    continue;
+                }
+                let invited = clients
+                    .iter()
+                    .find(|(_, _, name)| name.0 == *target)
+                    .map(|(entity, ..)| entity);
+
+                match invited {
+                    Some(invited) if invited != executor => {
+                        // Only now that someone's invited does the executor get a party
+                        let party = match party {
+                            Some(id) => id,
+                            None => parties.create(executor).unwrap(),
+                        };
+                        parties.invite(party, executor, invited);
+                        if let Ok((_, mut client, _)) = clients.get_mut(invited) {
+                            send_message(
+                                &mut client,
+                                &format!("{username} invited you to their party, type /party accept to join"),
+                                Color::GOLD,
+                            );
+                        }
+                        if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                            send_message(&mut client, &format!("invited {target}"), Color::GREEN);
+                        }
+                    }
+                    Some(_) => {
+                        if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                            CommandError::InvalidArgument("you can't invite yourself".to_owned()).report(&mut client, "party");
+                        }
+                    }
+                    None => {
+                        if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                            CommandError::PlayerNotFound(target.clone()).report(&mut client, "party");
+                        }
+                    }
+                }
+            }
+            PartyCommand::Accept => match parties.accept(executor) {
+                Some(id) => {
+                    let members = parties.parties[&id].members.clone();
+                    broadcast(
+                        &members,
+                        &mut clients,
+                        format!("[party] {username} joined the party").color(Color::GREEN),
+                    );
+                }
+                None => {
+                    if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                        CommandError::NothingFound("you have no pending invites".to_owned()).report(&mut client, "party");
+                    }
+                }
+            },
+            PartyCommand::Leave => match parties.leave(executor) {
+                Some(id) => {
+                    if let Some(party) = parties.parties.get(&id) {
+                        let members = party.members.clone();
+                        broadcast(
+                            &members,
+                            &mut clients,
+                            format!("[party] {username} left the party").color(Color::GOLD),
+                        );
+                    }
+                    if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                        send_message(&mut client, "you left the party", Color::GOLD);
+                    }
+                }
+                None => {
+                    if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                        CommandError::NotAllowed("you are not in a party".to_owned()).report(&mut client, "party");
+                    }
+                }
+            },
+            PartyCommand::List => {
+                let Some(id) = parties.party_of(executor) else {
+                    if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                        CommandError::NotAllowed("you are not in a party".to_owned()).report(&mut client, "party");
+                    }
+                    continue;
+                };
+                let party = &parties.parties[&id];
+                let names: Vec<String> = party
+                    .members
+                    .iter()
+                    .filter_map(|m| clients.get(*m).ok())
+                    .map(|(entity, _, name)| {
+                        if entity == party.leader {
+                            format!("{} (leader)", name.0)
+                        } else {
+                            name.0.clone()
+                        }
+                    })
+                    .collect();
+                if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                    send_message(&mut client, &format!("members: {}", names.join(", ")), Color::GOLD);
+                }
+            }
+            PartyCommand::Chat { message } => {
+                let Some(id) = parties.party_of(executor) else {
+                    if let Ok((_, mut client, _)) = clients.get_mut(executor) {
+                        CommandError::NotAllowed("you are not in a party".to_owned()).report(&mut client, "party");
+                    }
+                    continue;
+                };
+                let members = parties.parties[&id].members.clone();
+                broadcast(
+                    &members,
+                    &mut clients,
+                    "[party] ".color(Color::LIGHT_PURPLE)
+                        + format!("<{username}> ").color(Color::AQUA)
+                        + message.0.clone().color(Color::WHITE),
+                );
+            }
+        }
+    }
+}
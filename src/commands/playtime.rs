@@ -0,0 +1,54 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::playerdata::{find_offline_player_data, PlayerData};
+use crate::components::playtime::{format_duration, Session};
+
+#[derive(Command, Debug, Clone)]
+#[paths("playtime {player?}")]
+#[scopes("crystal.command.playtime")]
+pub struct PlaytimeCommand {
+    player: Option<String>,
+}
+
+pub fn handle_playtime_command(
+    mut events: EventReader<CommandResultEvent<PlaytimeCommand>>,
+    mut clients: Query<&mut Client>,
+    players: Query<(Entity, &Username, &PlayerData, Option<&Session>)>,
+) {
+    for event in events.read() {
+        let online = match &event.result.player {
+            Some(name) => players.iter().find(|(_, username, ..)| username.0.eq_ignore_ascii_case(name)),
+            None => players.get(event.executor).ok(),
+        };
+
+        let message: Result<Text, CommandError> = match (online, &event.result.player) {
+            (Some((_, username, data, session)), _) => {
+                let session_secs = session.map_or(0, Session::elapsed_secs);
+                Ok(format!(
+                    "[playtime] {} has played {} (this session: {})",
+                    username.0,
+                    format_duration(data.playtime_secs + session_secs),
+                    format_duration(session_secs)
+                )
+                .color(Color::GREEN))
+            }
+            (None, Some(name)) => match find_offline_player_data(name) {
+                Some(data) => Ok(format!(
+                    "[playtime] {} has played {} (offline)",
+                    data.last_username,
+                    format_duration(data.playtime_secs)
+                )
+                .color(Color::GREEN)),
+                None => Err(CommandError::NothingFound(format!("{name} has never joined"))),
+            },
+            (None, None) => continue,
+        };
+        if let Ok(mut client) = clients.get_mut(event.executor) {
+            match message {
+                Ok(message) => client.send_chat_message(message),
+                Err(error) => error.report(&mut client, "playtime"),
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::hud::facing;
+use crate::world::raycast::{raycast, FluidMode};
+use crate::world::teleport::SafeTeleportRequest;
+
+/// How far `/jumpto` looks for a block.
+const JUMPTO_RANGE: f64 = 160.0;
+const EYE_HEIGHT: f64 = 1.62;
+
+#[derive(Command, Debug, Clone)]
+#[paths("top")]
+#[scopes("crystal.command.top")]
+pub struct TopCommand;
+
+#[derive(Command, Debug, Clone)]
+#[paths("jumpto", "j")]
+#[scopes("crystal.command.jumpto")]
+pub struct JumpToCommand;
+
+#[derive(Command, Debug, Clone)]
+#[paths("pos")]
+#[scopes("crystal.command.pos")]
+pub struct PosCommand;
+
+fn highest_block(layer: &ChunkLayer, x: i32, z: i32) -> Option<i32> {
+    let top = layer.min_y() + layer.height() as i32 - 1;
+    (layer.min_y()..=top)
+        .rev()
+        .find(|y| layer.block([x, *y, z]).is_some_and(|block| block.state.blocks_motion()))
+}
+
+pub fn handle_top_command(
+    mut events: EventReader<CommandResultEvent<TopCommand>>,
+    mut clients: Query<(&mut Client, &mut Position, &VisibleChunkLayer)>,
+    layers: Query<&ChunkLayer>,
+) {
+    for event in events.read() {
+        let Ok((mut client, mut pos, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Ok(layer) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        let (x, z) = (pos.0.x.floor() as i32, pos.0.z.floor() as i32);
+        match highest_block(layer, x, z) {
+            Some(y) => {
+                pos.set(DVec3::new(x as f64 + 0.5, (y + 1) as f64, z as f64 + 0.5));
+                client.send_chat_message(format!("[top] teleported to y={}", y + 1).color(Color::GREEN));
+            }
+            None => CommandError::NothingFound("no blocks above or below you".to_owned()).report(&mut client, "top"),
+        }
+    }
+}
+
+pub fn handle_jumpto_command(
+    mut events: EventReader<CommandResultEvent<JumpToCommand>>,
+    mut clients: Query<(&mut Client, &Position, &Look, &VisibleChunkLayer)>,
+    layers: Query<&ChunkLayer>,
+    mut teleports: EventWriter<SafeTeleportRequest>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, look, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Ok(layer) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        let eye = pos.0 + DVec3::new(0.0, EYE_HEIGHT, 0.0);
+        match raycast(layer, eye, look.vec().as_dvec3(), JUMPTO_RANGE, FluidMode::Ignore) {
+            Some(hit) => {
+                let BlockPos { x, y, z } = hit.block;
+                teleports.send(SafeTeleportRequest {
+                    entity: event.executor,
+                    target: DVec3::new(x as f64 + 0.5, (y + 1) as f64, z as f64 + 0.5),
+                });
+                client.send_chat_message(format!("[jumpto] jumping to {x} {} {z}", y + 1).color(Color::GREEN));
+            }
+            None => CommandError::NothingFound(format!("no block within {JUMPTO_RANGE} blocks")).report(&mut client, "jumpto"),
+        }
+    }
+}
+
+pub fn handle_pos_command(
+    mut events: EventReader<CommandResultEvent<PosCommand>>,
+    mut clients: Query<(&mut Client, &Position, &Look)>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, look)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let chunk = ChunkPos::from(pos.0);
+        client.send_chat_message(
+            "[pos] ".color(Color::GOLD)
+                + format!("{:.3} {:.3} {:.3}", pos.0.x, pos.0.y, pos.0.z).color(Color::WHITE)
+                + format!(" chunk {} {}", chunk.x, chunk.z).color(Color::GRAY)
+                + format!(" facing {} (yaw {:.1}, pitch {:.1})", facing(look.yaw), look.yaw, look.pitch).color(Color::GRAY),
+        );
+    }
+}
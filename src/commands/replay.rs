@@ -0,0 +1,87 @@
+use tracing::info;
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::components::replay::{Recorder, Replays};
+
+#[derive(Command, Debug, Clone)]
+#[paths("replay")]
+#[scopes("crystal.command.replay")]
+pub enum ReplayCommand {
+    #[paths("flag {player}")]
+    Flag { player: String },
+    #[paths("list {player?}")]
+    List { player: Option<String> },
+    #[paths("play {recording}")]
+    Play { recording: String },
+    #[paths("stop")]
+    Stop,
+}
+
+pub fn handle_replay_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<ReplayCommand>>,
+    mut clients: Query<(&mut Client, &Username, &mut VisibleEntityLayers)>,
+    mut recorder: ResMut<Recorder>,
+    mut replays: ResMut<Replays>,
+    server: Res<Server>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username, mut visible)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+
+        match &event.result {
+            ReplayCommand::Flag { player } => {
+                let flagged = !recorder.is_flagged(player);
+                if let Err(e) = recorder.set_flagged(player, flagged) {
+                    CommandError::storage("save", "the flagged players", e).report(&mut client, "replay");
+                    continue;
+                }
+                let message = if flagged {
+                    format!("[replay] recording {player} whenever they're online")
+                } else {
+                    format!("[replay] stopped recording {player}")
+                };
+                client.send_chat_message(message.color(Color::GREEN));
+                info!(target: AUDIT, player = %username.0, "{} recording {player}", if flagged { "started" } else { "stopped" });
+            }
+            ReplayCommand::List { player } => {
+                let recordings = Recorder::list(player.as_deref());
+                if recordings.is_empty() {
+                    client.send_chat_message("[replay] no recordings".color(Color::GRAY));
+                    continue;
+                }
+                client.send_chat_message("[replay] recordings:".color(Color::GOLD));
+                for name in recordings {
+                    client.send_chat_message(format!(" {name}").color(Color::WHITE));
+                }
+            }
+            ReplayCommand::Play { recording } => {
+                let frames = match Recorder::read(recording) {
+                    Ok(frames) if frames.is_empty() => {
+                        CommandError::NothingFound(format!("{recording} is empty")).report(&mut client, "replay");
+                        continue;
+                    }
+                    Ok(frames) => frames,
+                    Err(e) => {
+                        CommandError::storage("load", recording, e).report(&mut client, "replay");
+                        continue;
+                    }
+                };
+                let name = recording.rsplit_once('-').map_or(recording.as_str(), |(name, _)| name);
+                replays.start(&mut commands, &server, event.executor, &mut visible, name, frames);
+                client.send_chat_message(format!("[replay] playing {recording}, /replay stop to end it").color(Color::GREEN));
+                info!(target: AUDIT, player = %username.0, "started replaying {recording}");
+            }
+            ReplayCommand::Stop => {
+                if replays.stop(&mut commands, event.executor, &mut visible) {
+                    client.send_chat_message("[replay] stopped".color(Color::GREEN));
+                } else {
+                    CommandError::NothingFound("you aren't watching a replay".to_owned()).report(&mut client, "replay");
+                }
+            }
+        }
+    }
+}
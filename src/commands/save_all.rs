@@ -0,0 +1,36 @@
+use tracing::info;
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use crate::components::logging::AUDIT;
+use crate::components::playerdata::{save_all_player_data, PlayerData};
+use crate::world::storage::ChunkSaver;
+use crate::world::Overworld;
+
+/// Writes every edited chunk and all player data to disk now, like
+/// `save-all` in the console, instead of waiting for the autosave.
+#[derive(Command, Debug, Clone)]
+#[paths("save-all")]
+#[scopes("crystal.command.save-all")]
+pub struct SaveAllCommand;
+
+pub fn handle_save_all_command(
+    mut events: EventReader<CommandResultEvent<SaveAllCommand>>,
+    mut clients: Query<(&mut Client, &Username)>,
+    players: Query<(&UniqueId, &PlayerData)>,
+    overworld: Query<&ChunkLayer, With<Overworld>>,
+    saver: Option<ResMut<ChunkSaver>>,
+) {
+    let Some(mut saver) = saver else {
+        return;
+    };
+    for event in events.read() {
+        let Ok((mut client, username)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        client.send_chat_message("[save-all] saving...".color(Color::GRAY));
+        let chunks = saver.save_all(overworld.get_single().ok());
+        let players = save_all_player_data(&players);
+        client.send_chat_message(format!("[save-all] saved {chunks} chunks and {players} players").color(Color::GREEN));
+        info!(target: AUDIT, player = %username.0, "saved the world: {chunks} chunks and {players} players");
+    }
+}
@@ -0,0 +1,40 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::world::worlds::NamedWorld;
+use crate::world::{Overworld, WorldGenerator};
+
+/// Shows the seed of the world the executor is in, so it can be generated
+/// again somewhere else. Clicking it copies it.
+#[derive(Command, Debug, Clone)]
+#[paths("seed")]
+#[scopes("crystal.command.seed")]
+pub struct SeedCommand;
+
+pub fn handle_seed_command(
+    mut events: EventReader<CommandResultEvent<SeedCommand>>,
+    mut clients: Query<(&mut Client, &VisibleChunkLayer)>,
+    layers: Query<(Option<&NamedWorld>, Has<Overworld>)>,
+    world_gen: Res<WorldGenerator>,
+) {
+    for event in events.read() {
+        let Ok((mut client, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let seed = match layers.get(visible_layer.0) {
+            Ok((_, true)) => world_gen.seed,
+            Ok((Some(world), _)) => world.seed,
+            _ => {
+                CommandError::NothingFound("this world isn't generated from a seed".to_owned()).report(&mut client, "seed");
+                continue;
+            }
+        };
+        client.send_chat_message(
+            "[seed] seed: ".color(Color::GOLD)
+                + format!("[{seed}]")
+                    .color(Color::GREEN)
+                    .on_click_copy_to_clipboard(seed.to_string())
+                    .on_hover_show_text("Click to copy"),
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use tracing::info;
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::components::spawner::{find_mob, Spawner, SpawnerConfig, Spawners, SPAWNER_MOBS};
+use crate::world::raycast::{raycast, FluidMode};
+use crate::world::Overworld;
+
+/// How far away the spawner being looked at can be.
+const REACH: f64 = 6.0;
+const EYE_HEIGHT: f64 = 1.62;
+
+#[derive(Command, Debug, Clone)]
+#[paths("setspawner {mob}")]
+#[scopes("crystal.command.setspawner")]
+pub struct SetSpawnerCommand {
+    mob: String,
+}
+
+pub fn handle_setspawner_command(
+    mut events: EventReader<CommandResultEvent<SetSpawnerCommand>>,
+    mut clients: Query<(&mut Client, &Username, &Position, &Look, &VisibleChunkLayer)>,
+    layers: Query<(&ChunkLayer, Has<Overworld>)>,
+    mut spawners: ResMut<Spawners>,
+    config: Res<SpawnerConfig>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username, pos, look, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Some((mob, _)) = find_mob(&event.result.mob) else {
+            CommandError::unknown_of("mob", &event.result.mob, SPAWNER_MOBS.iter().map(|(name, _)| *name))
+                .report(&mut client, "setspawner");
+            continue;
+        };
+        let Ok((layer, overworld)) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        if !overworld {
+            CommandError::NotAllowed("spawners only work in the overworld".to_owned()).report(&mut client, "setspawner");
+            continue;
+        }
+        let eye = pos.0 + DVec3::new(0.0, EYE_HEIGHT, 0.0);
+        let Some(hit) = raycast(layer, eye, look.vec().as_dvec3(), REACH, FluidMode::Ignore)
+            .filter(|hit| hit.state.to_kind() == BlockKind::Spawner)
+        else {
+            CommandError::NothingFound("look at a spawner first".to_owned()).report(&mut client, "setspawner");
+            continue;
+        };
+        spawners.spawners.insert(
+            hit.block,
+            Spawner {
+                mob,
+                delay: config.min_delay,
+            },
+        );
+        spawners.dirty = true;
+
+        let BlockPos { x, y, z } = hit.block;
+        client.send_chat_message(format!("[setspawner] spawner at {x} {y} {z} now spawns {mob}").color(Color::GREEN));
+        info!(target: AUDIT, player = %username.0, "set spawner at {x} {y} {z} to {mob}");
+    }
+}
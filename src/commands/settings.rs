@@ -0,0 +1,90 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use crate::components::chat::ChatChannel;
+use crate::components::hud::HudElements;
+use crate::components::menu::{icon, open_menu, Menu, MenuAction, MenuButton};
+use crate::components::playerdata::PlayerData;
+
+const JOIN_MESSAGES_SLOT: u16 = 11;
+const CHAT_CHANNEL_SLOT: u16 = 15;
+/// HUD elements fill the rows from here down.
+const HUD_SLOT: u16 = 27;
+
+/// Opens a menu of the executor's own preferences. Clicking one changes it
+/// and it's saved with the rest of their player data.
+#[derive(Command, Debug, Clone)]
+#[paths("settings")]
+#[scopes("crystal.command.settings")]
+pub struct SettingsCommand;
+
+pub fn handle_settings_command(mut commands: Commands, mut events: EventReader<CommandResultEvent<SettingsCommand>>) {
+    for event in events.read() {
+        let player = event.executor;
+        commands.add(move |world: &mut World| open_settings(world, player));
+    }
+}
+
+fn toggle_icon(on: bool, name: &str, description: &str) -> ItemStack {
+    let (item, state) = match on {
+        true => (ItemKind::LimeDye, "On".color(Color::GREEN)),
+        false => (ItemKind::GrayDye, "Off".color(Color::GRAY)),
+    };
+    icon(item, name.color(Color::GOLD), [description.color(Color::GRAY), state, "Click to toggle".color(Color::YELLOW)])
+}
+
+/// A button that changes the player's data, then shows the menu again so
+/// it has the new value.
+fn setting(icon: ItemStack, change: impl Fn(&mut PlayerData) + Send + Sync + 'static) -> MenuButton {
+    MenuButton::new(
+        icon,
+        MenuAction::callback(move |world, player| {
+            if let Some(mut data) = world.get_mut::<PlayerData>(player) {
+                change(&mut data);
+            }
+            open_settings(world, player);
+        }),
+    )
+}
+
+fn open_settings(world: &mut World, player: Entity) {
+    let Some(data) = world.get::<PlayerData>(player) else {
+        return;
+    };
+    let elements = world.resource::<HudElements>();
+    let rows = 3 + elements.0.len().div_ceil(9) as u8;
+
+    let channel = data.chat_channel;
+    let next = ChatChannel::ALL[(ChatChannel::ALL.iter().position(|c| *c == channel).unwrap_or(0) + 1) % ChatChannel::ALL.len()];
+    let channel_icon = icon(
+        ItemKind::WritableBook,
+        "Chat channel".color(Color::GOLD),
+        [
+            "Where your chat goes without a command".color(Color::GRAY),
+            channel.name().color(Color::AQUA),
+            format!("Click to switch to {}", next.name()).color(Color::YELLOW),
+        ],
+    );
+    let mut menu = Menu::new("Settings".color(Color::DARK_PURPLE), rows)
+        .button(
+            JOIN_MESSAGES_SLOT,
+            setting(toggle_icon(!data.hide_join_messages, "Join messages", "Players joining and leaving"), |data| {
+                data.hide_join_messages = !data.hide_join_messages;
+            }),
+        )
+        .button(CHAT_CHANNEL_SLOT, setting(channel_icon, move |data| data.chat_channel = next));
+
+    for (slot, &element) in (HUD_SLOT..).zip(&elements.0) {
+        let shown = data.hud_elements.iter().any(|e| e == element);
+        let icon = toggle_icon(shown, &format!("HUD: {element}"), "Shown in your action bar");
+        menu = menu.button(
+            slot,
+            setting(icon, move |data| match data.hud_elements.iter().position(|e| e == element) {
+                Some(index) => {
+                    data.hud_elements.remove(index);
+                }
+                None => data.hud_elements.push(element.to_owned()),
+            }),
+        );
+    }
+    open_menu(world, player, menu);
+}
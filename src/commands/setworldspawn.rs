@@ -0,0 +1,53 @@
+use tracing::info;
+use valence::{
+    command::{handler::CommandResultEvent, parsers::Vec3},
+    command_macros::Command,
+    prelude::*,
+};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::world::{Overworld, WorldSpawn};
+
+/// Moves the overworld spawn to where the executor is standing, or to the
+/// given coordinates. It's saved, so it stays after a restart.
+#[derive(Command, Debug, Clone)]
+#[paths("setworldspawn {pos?}")]
+#[scopes("crystal.command.setworldspawn")]
+pub struct SetWorldSpawnCommand {
+    pos: Option<Vec3>,
+}
+
+pub fn handle_setworldspawn_command(
+    mut events: EventReader<CommandResultEvent<SetWorldSpawnCommand>>,
+    mut clients: Query<(&mut Client, &Username, &Position, &VisibleChunkLayer)>,
+    overworld: Query<Entity, With<Overworld>>,
+    mut spawn: ResMut<WorldSpawn>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username, pos, visible_layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        if overworld.get_single().is_ok_and(|overworld| overworld != visible_layer.0) {
+            CommandError::NotAllowed("the world spawn has to be in the overworld".to_owned()).report(&mut client, "setworldspawn");
+            continue;
+        }
+        let new_spawn = match &event.result.pos {
+            Some(target) => DVec3::new(
+                f64::from(target.x.get(pos.0.x as f32)),
+                f64::from(target.y.get(pos.0.y as f32)),
+                f64::from(target.z.get(pos.0.z as f32)),
+            ),
+            None => pos.0,
+        };
+        // Centered on the block, like vanilla
+        let block = BlockPos::from(new_spawn);
+        spawn.0 = DVec3::new(block.x as f64 + 0.5, block.y as f64, block.z as f64 + 0.5);
+
+        if let Err(e) = spawn.save() {
+            CommandError::storage("save", "the world spawn", e).report(&mut client, "setworldspawn");
+        }
+        client.send_chat_message(format!("[setworldspawn] world spawn set to {} {} {}", block.x, block.y, block.z).color(Color::GREEN));
+        info!(target: AUDIT, player = %username.0, "set the world spawn to {block:?}");
+    }
+}
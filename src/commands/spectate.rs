@@ -0,0 +1,71 @@
+use tracing::info;
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::components::spectate::{stop_spectating, SpectatorData, Spectating};
+
+/// Watches another player from their point of view, or stops watching.
+#[derive(Command, Debug, Clone)]
+#[paths("spectate {player?}")]
+#[scopes("crystal.command.spectate")]
+pub struct SpectateCommand {
+    player: Option<String>,
+}
+
+pub fn handle_spectate_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<SpectateCommand>>,
+    mut clients: Query<SpectatorData>,
+    mut spectators: Query<&mut Spectating>,
+    usernames: Query<(Entity, &Username)>,
+) {
+    for event in events.read() {
+        let Ok((_, executor_name)) = usernames.get(event.executor) else {
+            continue;
+        };
+        let target = event.result.player.as_ref().map(|name| {
+            usernames
+                .iter()
+                .find(|(_, username)| username.0.eq_ignore_ascii_case(name))
+                .map(|(target, username)| (target, username.0.clone()))
+                .ok_or_else(|| CommandError::PlayerNotFound(name.clone()))
+        });
+        let Ok((mut client, mut game_mode, pos, layer_id, ..)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+
+        match target {
+            None => {
+                let Ok(spectating) = spectators.get(event.executor) else {
+                    CommandError::NotAllowed("you aren't spectating anyone".to_owned()).report(&mut client, "spectate");
+                    continue;
+                };
+                client.send_chat_message("[spectate] put you back where you were".color(Color::GREEN));
+                stop_spectating(&mut commands, &mut clients, event.executor, spectating);
+                info!(target: AUDIT, player = %executor_name.0, "stopped spectating");
+            }
+            Some(Err(e)) => e.report(&mut client, "spectate"),
+            Some(Ok((target, _))) if target == event.executor => {
+                CommandError::InvalidArgument("you can't spectate yourself".to_owned()).report(&mut client, "spectate");
+            }
+            Some(Ok((target, name))) if spectators.contains(target) => {
+                CommandError::NotAllowed(format!("{name} is spectating someone too")).report(&mut client, "spectate");
+            }
+            Some(Ok((target, name))) => {
+                if let Ok(mut spectating) = spectators.get_mut(event.executor) {
+                    spectating.retarget(target);
+                } else {
+                    commands
+                        .entity(event.executor)
+                        .insert(Spectating::new(target, *game_mode, pos.0, layer_id.0));
+                    *game_mode = GameMode::Spectator;
+                }
+                client.send_chat_message(
+                    format!("[spectate] watching {name}, /spectate on its own to stop").color(Color::GREEN),
+                );
+                info!(target: AUDIT, player = %executor_name.0, "started spectating {name}");
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use tracing::info;
+use valence::{client::DisconnectClient, command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use crate::components::confirm::{Confirmations, CONFIRM_WINDOW};
+use crate::components::logging::AUDIT;
+
+/// Stops the server, like `stop` in the console. Needs `/confirm`.
+#[derive(Command, Debug, Clone)]
+#[paths("stop")]
+#[scopes("crystal.command.stop")]
+pub struct StopCommand;
+
+pub fn handle_stop_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<StopCommand>>,
+    mut clients: Query<(Entity, &mut Client, &Username)>,
+    mut confirmations: ResMut<Confirmations>,
+) {
+    for event in events.read() {
+        let Ok((_, _, username)) = clients.get(event.executor) else {
+            continue;
+        };
+        let username = username.0.clone();
+        if !confirmations.confirmed(event.executor, "stop") {
+            let online = clients.iter().count();
+            if let Ok((_, mut client, _)) = clients.get_mut(event.executor) {
+                client.send_chat_message(
+                    format!(
+                        "[stop] this disconnects {online} players and shuts the server down, type /confirm within {} seconds to go ahead",
+                        CONFIRM_WINDOW.as_secs()
+                    )
+                    .color(Color::GOLD),
+                );
+            }
+            continue;
+        }
+
+        info!(target: AUDIT, player = %username, "stopped the server");
+        for (client, ..) in &clients {
+            commands.add(DisconnectClient { client, reason: "Server closed".into() });
+        }
+        std::process::exit(0);
+    }
+}
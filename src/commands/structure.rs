@@ -0,0 +1,327 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::info;
+use valence::{
+    command::handler::CommandResultEvent,
+    command_macros::Command,
+    entity::{painting, Despawned},
+    prelude::*,
+};
+
+use super::error::CommandError;
+use crate::components::confirm::{Confirmations, CONFIRM_WINDOW};
+use crate::components::container::{ContainerBlock, Containers};
+use crate::components::decoration::{FrameData, PaintingData, SavedDecorations, SavedStack, StandData};
+use crate::components::ghost::GhostBlocks;
+use crate::components::logging::AUDIT;
+use crate::components::schematic::{SavedContainer, Schematic};
+use crate::world::storage::ChunkSaver;
+use crate::world::structures::StructurePlaced;
+use crate::world::{Overworld, WorldGenerator};
+
+/// Bigger selections take too long to copy in one tick.
+const MAX_VOLUME: i64 = 128 * 128 * 128;
+/// Pasting more blocks than this needs `/confirm`.
+const CONFIRM_VOLUME: i64 = 32 * 32 * 32;
+/// How long `--preview` shows the paste to the executor.
+const PREVIEW_TIME: Duration = Duration::from_secs(15);
+
+#[derive(Command, Debug, Clone)]
+#[paths("structure")]
+#[scopes("crystal.command.structure")]
+pub enum StructureCommand {
+    #[paths("pos1")]
+    Pos1,
+    #[paths("pos2")]
+    Pos2,
+    #[paths("anchor")]
+    Anchor,
+    #[paths("include {what}")]
+    Include { what: String },
+    #[paths("save {name}")]
+    Save { name: String },
+    #[paths("place {name} {flag?}")]
+    Place { name: String, flag: Option<String> },
+    #[paths("register {name} {weight}")]
+    Register { name: String, weight: i32 },
+    #[paths("unregister {name}")]
+    Unregister { name: String },
+    #[paths("list")]
+    List,
+}
+
+/// What a player has marked with `/structure` so far.
+#[derive(Component, Default)]
+pub struct StructureSelection {
+    pos1: Option<BlockPos>,
+    pos2: Option<BlockPos>,
+    anchor: Option<BlockPos>,
+    entities: bool,
+    containers: bool,
+}
+
+fn capture_containers(
+    containers: &Containers,
+    inventories: &Query<&Inventory, With<ContainerBlock>>,
+    min: BlockPos,
+    max: BlockPos,
+) -> Vec<SavedContainer> {
+    containers
+        .inventories
+        .iter()
+        .filter(|(pos, _)| {
+            (min.x..=max.x).contains(&pos.x) && (min.y..=max.y).contains(&pos.y) && (min.z..=max.z).contains(&pos.z)
+        })
+        .filter_map(|(pos, entity)| {
+            let inventory = inventories.get(*entity).ok()?;
+            let items: Vec<_> = (0..inventory.slot_count())
+                .filter_map(|slot| Some((slot, SavedStack::of(inventory.slot(slot))?)))
+                .collect();
+            (!items.is_empty()).then(|| SavedContainer {
+                offset: [pos.x - min.x, pos.y - min.y, pos.z - min.z],
+                items,
+            })
+        })
+        .collect()
+}
+
+pub fn handle_structure_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<StructureCommand>>,
+    mut clients: Query<(&mut Client, &Username, &Position, &VisibleChunkLayer, Option<&mut StructureSelection>)>,
+    mut layers: Query<(&mut ChunkLayer, Has<Overworld>)>,
+    containers: Res<Containers>,
+    inventories: Query<&Inventory, With<ContainerBlock>>,
+    frames: Query<FrameData, Without<Despawned>>,
+    paintings: Query<PaintingData, (With<painting::Variant>, Without<Despawned>)>,
+    stands: Query<StandData, Without<Despawned>>,
+    world_gen: Res<WorldGenerator>,
+    mut placed: EventWriter<StructurePlaced>,
+    mut saver: ResMut<ChunkSaver>,
+    mut confirmations: ResMut<Confirmations>,
+    mut ghosts: ResMut<GhostBlocks>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username, pos, visible_layer, selection)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let mut new_selection = None;
+        let selection = match selection {
+            Some(selection) => selection.into_inner(),
+            None => new_selection.insert(StructureSelection::default()),
+        };
+        let here = BlockPos::from(pos.0);
+
+        match &event.result {
+            StructureCommand::Pos1 => {
+                selection.pos1 = Some(here);
+                client.send_chat_message(format!("[structure] pos1 set to {} {} {}", here.x, here.y, here.z).color(Color::GREEN));
+            }
+            StructureCommand::Pos2 => {
+                selection.pos2 = Some(here);
+                client.send_chat_message(format!("[structure] pos2 set to {} {} {}", here.x, here.y, here.z).color(Color::GREEN));
+            }
+            StructureCommand::Anchor => {
+                selection.anchor = Some(here);
+                client.send_chat_message(
+                    format!("[structure] anchor set to {} {} {}, this block goes on the ground", here.x, here.y, here.z)
+                        .color(Color::GREEN),
+                );
+            }
+            StructureCommand::Include { what } => {
+                let (name, flag) = match what.as_str() {
+                    "entities" => ("entities", &mut selection.entities),
+                    "containers" => ("container contents", &mut selection.containers),
+                    _ => {
+                        CommandError::unknown_of("option", what, ["entities", "containers"]).report(&mut client, "structure");
+                        continue;
+                    }
+                };
+                *flag = !*flag;
+                let state = if *flag { "included" } else { "left out" };
+                client.send_chat_message(format!("[structure] {name} will be {state}").color(Color::GREEN));
+            }
+            StructureCommand::Save { name } => {
+                if !Schematic::valid_name(name) {
+                    CommandError::InvalidArgument("names can only use letters, digits, - and _".to_owned()).report(&mut client, "structure");
+                    continue;
+                }
+                let (Some(pos1), Some(pos2)) = (selection.pos1, selection.pos2) else {
+                    CommandError::NotAllowed("set pos1 and pos2 first".to_owned()).report(&mut client, "structure");
+                    continue;
+                };
+                let min = BlockPos::new(pos1.x.min(pos2.x), pos1.y.min(pos2.y), pos1.z.min(pos2.z));
+                let max = BlockPos::new(pos1.x.max(pos2.x), pos1.y.max(pos2.y), pos1.z.max(pos2.z));
+                let volume = (max.x - min.x + 1) as i64 * (max.y - min.y + 1) as i64 * (max.z - min.z + 1) as i64;
+                if volume > MAX_VOLUME {
+                    CommandError::InvalidArgument(format!("selection is {volume} blocks, the limit is {MAX_VOLUME}"))
+                        .report(&mut client, "structure");
+                    continue;
+                }
+                let Ok((layer, _)) = layers.get(visible_layer.0) else {
+                    continue;
+                };
+
+                let mut schematic = Schematic::capture(layer, min, max);
+                if let Some(anchor) = selection.anchor {
+                    schematic.anchor = [anchor.x - min.x, anchor.y - min.y, anchor.z - min.z];
+                }
+                if selection.containers {
+                    schematic.containers = capture_containers(&containers, &inventories, min, max);
+                }
+                if selection.entities {
+                    let inside = |p: DVec3| {
+                        p.x >= min.x as f64
+                            && p.x < (max.x + 1) as f64
+                            && p.y >= min.y as f64
+                            && p.y < (max.y + 1) as f64
+                            && p.z >= min.z as f64
+                            && p.z < (max.z + 1) as f64
+                    };
+                    schematic.decorations = SavedDecorations::capture(&frames, &paintings, &stands, inside);
+                    schematic.decorations.translate([-min.x, -min.y, -min.z]);
+                }
+
+                match schematic.save(name) {
+                    Ok(()) => {
+                        client.send_chat_message(
+                            format!(
+                                "[structure] saved {name}: {} blocks, {} containers, {} entities",
+                                schematic.volume(),
+                                schematic.containers.len(),
+                                schematic.decorations.len()
+                            )
+                            .color(Color::GREEN),
+                        );
+                        info!(target: AUDIT, player = %username.0, "saved structure {name} from {min:?} to {max:?}");
+                    }
+                    Err(e) => CommandError::storage("save", name, e).report(&mut client, "structure"),
+                }
+            }
+            StructureCommand::Place { name, flag } => {
+                let preview = match flag.as_deref() {
+                    None => false,
+                    Some("--preview") => true,
+                    Some(other) => {
+                        CommandError::unknown_of("flag", other, ["--preview"]).report(&mut client, "structure");
+                        continue;
+                    }
+                };
+                let schematic = match Schematic::load(name) {
+                    Ok(schematic) => schematic,
+                    Err(e) => {
+                        CommandError::storage("load", name, e).report(&mut client, "structure");
+                        continue;
+                    }
+                };
+                if preview {
+                    let Ok((layer, _)) = layers.get(visible_layer.0) else {
+                        continue;
+                    };
+                    let origin = schematic.origin_for_anchor(here);
+                    let changes: Vec<_> = schematic.changes(layer, origin).collect();
+                    client.send_chat_message(
+                        format!(
+                            "[structure] placing {name} here would change {} of its {} blocks, starting at {} {} {}",
+                            changes.len(),
+                            schematic.volume(),
+                            origin.x,
+                            origin.y,
+                            origin.z
+                        )
+                        .color(Color::GOLD),
+                    );
+                    ghosts.clear(event.executor);
+                    let shown = ghosts.show(event.executor, changes.iter().copied(), PREVIEW_TIME);
+                    let shown = if shown < changes.len() { format!("{shown} of them") } else { "them".to_owned() };
+                    client.send_chat_message(
+                        format!("[structure] showing {shown} to you for {} seconds", PREVIEW_TIME.as_secs()).color(Color::GRAY),
+                    );
+                    continue;
+                }
+                let volume = schematic.volume() as i64;
+                if volume > CONFIRM_VOLUME && !confirmations.confirmed(event.executor, &format!("structure place {name}")) {
+                    client.send_chat_message(
+                        format!(
+                            "[structure] {name} is {volume} blocks, type /confirm within {} seconds to paste it where you're standing then",
+                            CONFIRM_WINDOW.as_secs()
+                        )
+                        .color(Color::GOLD),
+                    );
+                    continue;
+                }
+                let Ok((mut layer, overworld)) = layers.get_mut(visible_layer.0) else {
+                    continue;
+                };
+                let origin = schematic.origin_for_anchor(here);
+                let changed = schematic.paste(&mut layer, origin);
+                if overworld {
+                    let [sx, _, sz] = schematic.size;
+                    for cx in origin.x.div_euclid(16)..=(origin.x + sx - 1).div_euclid(16) {
+                        for cz in origin.z.div_euclid(16)..=(origin.z + sz - 1).div_euclid(16) {
+                            saver.mark_dirty(ChunkPos::new(cx, cz));
+                        }
+                    }
+                }
+                placed.send(StructurePlaced {
+                    layer: visible_layer.0,
+                    name: name.clone(),
+                    origin,
+                    schematic: Arc::new(schematic),
+                });
+                client.send_chat_message(format!("[structure] placed {name}, {changed} blocks changed").color(Color::GREEN));
+                info!(target: AUDIT, player = %username.0, "placed structure {name} at {origin:?}");
+            }
+            StructureCommand::Register { name, weight } => {
+                if *weight <= 0 {
+                    CommandError::InvalidArgument("weight has to be at least 1".to_owned()).report(&mut client, "structure");
+                    continue;
+                }
+                let schematic = match Schematic::load(name) {
+                    Ok(schematic) => schematic,
+                    Err(e) => {
+                        CommandError::storage("load", name, e).report(&mut client, "structure");
+                        continue;
+                    }
+                };
+                let mut pool = world_gen.generator.structures_mut();
+                pool.register(name, *weight as u32, schematic);
+                pool.save();
+                client.send_chat_message(
+                    format!("[structure] {name} will generate in new terrain with weight {weight}").color(Color::GREEN),
+                );
+                info!(target: AUDIT, player = %username.0, "registered structure {name} with weight {weight}");
+            }
+            StructureCommand::Unregister { name } => {
+                let mut pool = world_gen.generator.structures_mut();
+                if pool.remove(name) {
+                    pool.save();
+                    client.send_chat_message(format!("[structure] {name} won't generate anymore").color(Color::GREEN));
+                    info!(target: AUDIT, player = %username.0, "unregistered structure {name}");
+                } else {
+                    CommandError::NothingFound(format!("{name} isn't in the pool")).report(&mut client, "structure");
+                }
+            }
+            StructureCommand::List => {
+                let saved = Schematic::list();
+                if saved.is_empty() {
+                    client.send_chat_message("[structure] no saved structures".color(Color::GRAY));
+                    continue;
+                }
+                let pool = world_gen.generator.structures();
+                client.send_chat_message("[structure] saved structures:".color(Color::GOLD));
+                for name in saved {
+                    let line = match pool.entries().find(|entry| entry.name == name) {
+                        Some(entry) => format!(" {name} (weight {})", entry.weight),
+                        None => format!(" {name}"),
+                    };
+                    client.send_chat_message(line.color(Color::WHITE));
+                }
+            }
+        }
+        if let Some(selection) = new_selection {
+            commands.entity(event.executor).insert(selection);
+        }
+    }
+}
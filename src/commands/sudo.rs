@@ -0,0 +1,50 @@
+use valence::{
+    command::{handler::CommandResultEvent, parsers::GreedyString, CommandExecutionEvent},
+    command_macros::Command,
+    prelude::*,
+};
+
+use super::error::CommandError;
+use crate::components::core::sudo;
+
+/// Runs a command as another player, with their permissions rather than the
+/// executor's. Handy for support and for checking what a permission-gated
+/// command looks like to someone without op.
+#[derive(Command, Debug, Clone)]
+#[paths("sudo {player} {command}")]
+#[scopes("crystal.command.sudo")]
+pub struct SudoCommand {
+    player: String,
+    command: GreedyString,
+}
+
+pub fn handle_sudo_command(
+    mut events: EventReader<CommandResultEvent<SudoCommand>>,
+    mut clients: Query<(Entity, &mut Client, &Username)>,
+    mut executions: EventWriter<CommandExecutionEvent>,
+) {
+    for event in events.read() {
+        let Ok((_, _, executor_name)) = clients.get(event.executor) else {
+            continue;
+        };
+        let executor_name = executor_name.0.clone();
+        let target = clients
+            .iter()
+            .find(|(_, _, username)| username.0.eq_ignore_ascii_case(&event.result.player))
+            .map(|(player, _, username)| (player, username.clone()));
+        let Ok((_, mut client, _)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let Some((player, username)) = target else {
+            CommandError::PlayerNotFound(event.result.player.clone()).report(&mut client, "sudo");
+            continue;
+        };
+        let command = &event.result.command.0;
+        match sudo(&mut executions, (player, &username), command, &executor_name) {
+            Ok(()) => client.send_chat_message(
+                format!("[sudo] ran /{} as {}", command.trim().trim_start_matches('/'), username.0).color(Color::GREEN),
+            ),
+            Err(reason) => CommandError::InvalidArgument(reason.to_owned()).report(&mut client, "sudo"),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use valence::{
+    command::{handler::CommandResultEvent, parsers::GreedyString},
+    command_macros::Command,
+    prelude::*,
+    protocol::packets::play::team_s2c::TeamColor,
+};
+
+use super::error::CommandError;
+use crate::components::team::{
+    parse_team_color, write_create_team, write_remove_team, write_team_members, write_update_team,
+    Team, Teams, COLOR_NAMES,
+};
+
+#[derive(Command, Debug, Clone)]
+#[paths("team")]
+#[scopes("crystal.command.team")]
+pub enum TeamCommand {
+    #[paths("add {name} {color?}")]
+    Add { name: String, color: Option<String> },
+    #[paths("remove {name}")]
+    Remove { name: String },
+    #[paths("join {name} {target?}")]
+    Join { name: String, target: Option<String> },
+    #[paths("leave {target?}")]
+    Leave { target: Option<String> },
+    #[paths("modify {name} color {color}")]
+    ModifyColor { name: String, color: String },
+    #[paths("modify {name} friendlyfire {value}")]
+    ModifyFriendlyFire { name: String, value: bool },
+    #[paths("list")]
+    List,
+    #[paths("chat {message}", "{/} teammsg {message}", "{/} tm {message}")]
+    Chat { message: GreedyString },
+}
+
+fn send_message(client: &mut Client, message: &str, color: Color) {
+    client.send_chat_message(format!("[team] {message}").color(color));
+}
+
+pub fn handle_team_command(
+    mut events: EventReader<CommandResultEvent<TeamCommand>>,
+    mut teams: ResMut<Teams>,
+    mut clients: Query<(&mut Client, &Username)>,
+) {
+    for event in events.read() {
+        let Ok((_, executor_name)) = clients.get(event.executor) else {
+            continue;
+        };
+        let executor_name = executor_name.0.clone();
+
+        // Feedback for the executor, sent once the packets have gone out.
+        let feedback: Result<String, CommandError> = match &event.result {
+            TeamCommand::Add { name, color } => {
+                let parsed_color = color.as_deref().map(parse_team_color);
+                if teams.teams.contains_key(name) {
+                    Err(CommandError::AlreadyExists { what: "team", name: name.clone() })
+                } else if let (Some(color), Some(None)) = (color, parsed_color) {
+                    Err(invalid_color(color))
+                } else {
+                    let team = Team {
+                        color: parsed_color.flatten().unwrap_or(TeamColor::White),
+                        friendly_fire: true,
+                        members: Vec::new(),
+                    };
+                    for (mut client, _) in &mut clients {
+                        write_create_team(&mut client, name, &team);
+                    }
+                    teams.teams.insert(name.clone(), team);
+                    Ok(format!("created team {name}"))
+                }
+            }
+            TeamCommand::Remove { name } => {
+                if teams.teams.remove(name).is_some() {
+                    for (mut client, _) in &mut clients {
+                        write_remove_team(&mut client, name);
+                    }
+                    Ok(format!("removed team {name}"))
+                } else {
+                    Err(CommandError::unknown("team", name.clone()))
+                }
+            }
+            TeamCommand::Join { name, target } => {
+                let target = target.clone().unwrap_or_else(|| executor_name.clone());
+                if !teams.teams.contains_key(name) {
+                    Err(CommandError::unknown("team", name.clone()))
+                } else if !clients.iter().any(|(_, u)| u.0 == target) {
+                    Err(CommandError::PlayerNotFound(target))
+                } else {
+                    // Players can only be on one team at a time.
+                    if let Some(old) = teams.team_of(&target).map(str::to_owned) {
+                        teams.teams.get_mut(&old).unwrap().members.retain(|m| *m != target);
+                        for (mut client, _) in &mut clients {
+                            write_team_members(&mut client, &old, &[&target], false);
+                        }
+                    }
+                    teams.teams.get_mut(name).unwrap().members.push(target.clone());
+                    for (mut client, _) in &mut clients {
+                        write_team_members(&mut client, name, &[&target], true);
+                    }
+                    Ok(format!("{target} joined team {name}"))
+                }
+            }
+            TeamCommand::Leave { target } => {
+                let target = target.clone().unwrap_or_else(|| executor_name.clone());
+                match teams.team_of(&target).map(str::to_owned) {
+                    Some(name) => {
+                        teams.teams.get_mut(&name).unwrap().members.retain(|m| *m != target);
+                        for (mut client, _) in &mut clients {
+                            write_team_members(&mut client, &name, &[&target], false);
+                        }
+                        Ok(format!("{target} left team {name}"))
+                    }
+                    None => Err(CommandError::NothingFound(format!("{target} is not on a team"))),
+                }
+            }
+            TeamCommand::ModifyColor { name, color } => match (teams.teams.get_mut(name), parse_team_color(color)) {
+                (None, _) => Err(CommandError::unknown("team", name.clone())),
+                (_, None) => Err(invalid_color(color)),
+                (Some(team), Some(color)) => {
+                    team.color = color;
+                    for (mut client, _) in &mut clients {
+                        write_update_team(&mut client, name, team);
+                    }
+                    Ok(format!("updated color of {name}"))
+                }
+            },
+            TeamCommand::ModifyFriendlyFire { name, value } => match teams.teams.get_mut(name) {
+                None => Err(CommandError::unknown("team", name.clone())),
+                Some(team) => {
+                    team.friendly_fire = *value;
+                    for (mut client, _) in &mut clients {
+                        write_update_team(&mut client, name, team);
+                    }
+                    Ok(format!("friendly fire for {name} is now {value}"))
+                }
+            },
+            TeamCommand::List => {
+                if teams.teams.is_empty() {
+                    Err(CommandError::NothingFound("there are no teams".to_owned()))
+                } else {
+                    let Ok((mut client, _)) = clients.get_mut(event.executor) else {
+                        continue;
+                    };
+                    for (name, team) in &teams.teams {
+                        client.send_chat_message(
+                            format!(" - {name}").color(team.text_color())
+                                + format!(" ({}): {}", team.members.len(), team.members.join(", "))
+                                    .color(Color::GOLD),
+                        );
+                    }
+                    continue;
+                }
+            }
+            TeamCommand::Chat { message } => match teams.team_of(&executor_name) {
+                None => Err(CommandError::NotAllowed("you are not on a team".to_owned())),
+                Some(name) => {
+                    let team = &teams.teams[name];
+                    let text = format!("[{name}] ").color(team.text_color())
+                        + format!("<{executor_name}> ").color(Color::AQUA)
+                        + message.0.clone().color(Color::WHITE);
+                    for (mut client, username) in &mut clients {
+                        if team.members.contains(&username.0) {
+                            client.send_chat_message(text.clone());
+                        }
+                    }
+                    continue;
+                }
+            },
+        };
+
+        if let Ok((mut client, _)) = clients.get_mut(event.executor) {
+            match feedback {
+                Ok(message) => send_message(&mut client, &message, Color::GREEN),
+                Err(error) => error.report(&mut client, "team"),
+            }
+        }
+    }
+}
+
+fn invalid_color(color: &str) -> CommandError {
+    CommandError::unknown_of("color", color, COLOR_NAMES.iter().map(|(name, _)| *name))
+}
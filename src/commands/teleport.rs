@@ -1,5 +1,9 @@
 use tracing::info;
+use crate::world::teleport::SafeTeleportRequest;
 use valence::{command::{handler::CommandResultEvent, parsers::{entity_selector::EntitySelectors, EntitySelector, Vec3}}, command_macros::Command, entity::living::LivingEntity, prelude::*, rand::seq::IteratorRandom};
+use super::error::CommandError;
+use crate::components::core::executor;
+use crate::components::logging::COMMANDS;
 
 enum TeleportTarget {
     Targets(Vec<Entity>),
@@ -39,6 +43,7 @@ pub fn handle_teleport_command(
     mut positions: Query<&mut Position>,
     usernames: Query<(Entity, &Username)>,
     entity_names: Query<&EntityKind>,
+    mut teleports: EventWriter<SafeTeleportRequest>,
 ) {
     for event in events.read() {
         let compiled_command = match &event.result {
@@ -107,33 +112,63 @@ pub fn handle_teleport_command(
         };
 
         let (TeleportTarget::Targets(targets), destination) = compiled_command;
-        let (_, mut client) = clients.get_mut(event.executor).unwrap();
-        info!("executing teleport command {targets:#?} -> {destination:#?}");
+        let Some((_, mut client)) = executor(clients.get_mut(event.executor), "tp") else {
+            continue;
+        };
+        info!(target: COMMANDS, "executing teleport command {targets:#?} -> {destination:#?}");
         match destination {
             TeleportDestination::Location(location) => {
                 for target in targets {
-                    let mut pos = positions.get_mut(target).unwrap();
-                    pos.0.x = f64::from(location.x.get(pos.0.x as f32));
-                    pos.0.y = f64::from(location.y.get(pos.0.y as f32));
-                    pos.0.z = f64::from(location.z.get(pos.0.z as f32));
-                    
-                    client.send_chat_message("[tp] teleported ".color(Color::GOLD) + <std::string::String as Clone>::clone(&usernames.get(target).unwrap_or((target, &Username(entity_names.get(target).unwrap().get().to_string()))).1).color(Color::RED) + " to ".color(Color::GOLD) + pos.0.x.color(Color::RED) + ' ' + pos.0.y.color(Color::RED) + ' ' + pos.0.z.color(Color::RED));
+                    let Ok(pos) = positions.get(target) else {
+                        continue;
+                    };
+                    let destination = DVec3::new(
+                        f64::from(location.x.get(pos.0.x as f32)),
+                        f64::from(location.y.get(pos.0.y as f32)),
+                        f64::from(location.z.get(pos.0.z as f32)),
+                    );
+                    // Loads the destination and finds solid ground before moving them
+                    teleports.send(SafeTeleportRequest { entity: target, target: destination });
+
+                    client.send_chat_message("[tp] teleporting ".color(Color::GOLD) + display_name(target, &usernames, &entity_names).color(Color::RED) + " to ".color(Color::GOLD) + destination.x.color(Color::RED) + ' ' + destination.y.color(Color::RED) + ' ' + destination.z.color(Color::RED));
                 }
             }
             TeleportDestination::Target(target) => {
-                let teleport_target = target.unwrap();
-                let target_pos = **positions.get(teleport_target).unwrap();
+                let Some(teleport_target) = target else {
+                    CommandError::NothingFound("no destination found".to_owned()).report(&mut client, "tp");
+                    continue;
+                };
+                let Ok(target_pos) = positions.get(teleport_target).map(|pos| pos.0) else {
+                    continue;
+                };
                 for target in targets {
-                    let mut position = positions.get_mut(target).unwrap();
+                    let Ok(mut position) = positions.get_mut(target) else {
+                        continue;
+                    };
                     position.0 = target_pos;
 
-                    client.send_chat_message("[tp] teleported ".color(Color::GOLD) + <std::string::String as Clone>::clone(&usernames.get(teleport_target).unwrap_or((teleport_target, &Username(entity_names.get(teleport_target).unwrap().get().to_string()))).1).color(Color::RED) + " to ".color(Color::GOLD) + <std::string::String as Clone>::clone(&usernames.get(target).unwrap_or((target, &Username(entity_names.get(target).unwrap().get().to_string()))).1).color(Color::RED));
+                    client.send_chat_message("[tp] teleported ".color(Color::GOLD) + display_name(teleport_target, &usernames, &entity_names).color(Color::RED) + " to ".color(Color::GOLD) + display_name(target, &usernames, &entity_names).color(Color::RED));
                 }
             }
         }
     }
 }
 
+/// A player's name, or the entity's kind for anything else.
+fn display_name(entity: Entity, usernames: &Query<(Entity, &Username)>, kinds: &Query<&EntityKind>) -> String {
+    match usernames.get(entity) {
+        Ok((_, username)) => username.0.clone(),
+        Err(_) => kinds.get(entity).map_or_else(|_| format!("{entity:?}"), |kind| kind.get().to_string()),
+    }
+}
+
+/// Sends `message` to the executor, if they're still around.
+fn report(clients: &mut Query<(Entity, &mut Client)>, executor: Entity, error: CommandError) {
+    if let Ok((_, mut client)) = clients.get_mut(executor) {
+        error.report(&mut client, "tp");
+    }
+}
+
 fn find_targets(
     living_entities: &Query<Entity, With<LivingEntity>>,
     clients: &mut Query<(Entity, &mut Client)>,
@@ -143,24 +178,19 @@ fn find_targets(
     event: &CommandResultEvent<TeleportCommand>,
     target: &EntitySelector,
 ) -> Vec<Entity> {
+    // Selectors that look around the executor need to know where they are
+    let (Ok(executor_layer), Ok(executor_pos)) = (entity_layers.get(event.executor), positions.get(event.executor)) else {
+        return vec![];
+    };
+    let same_layer = |entity: Entity| entity_layers.get(entity).is_ok_and(|layer| layer.0 == executor_layer.0);
     match target {
         EntitySelector::SimpleSelector(selector) => match selector {
-            EntitySelectors::AllEntities => {
-                let executor_entity_layer = *entity_layers.get(event.executor).unwrap();
-                living_entities
-                    .iter()
-                    .filter(|entity| {
-                        let entity_layer = entity_layers.get(*entity).unwrap();
-                        entity_layer.0 == executor_entity_layer.0
-                    })
-                    .collect()
-            }
+            EntitySelectors::AllEntities => living_entities.iter().filter(|entity| same_layer(*entity)).collect(),
             EntitySelectors::SinglePlayer(name) => {
                 let target = usernames.iter().find(|(_, username)| username.0 == *name);
                 match target {
                     None => {
-                        let client = &mut clients.get_mut(event.executor).unwrap().1;
-                        client.send_chat_message(format!("Could not find target: {name}"));
+                        report(clients, event.executor, CommandError::PlayerNotFound(name.clone()));
                         vec![]
                     }
                     Some(target_entity) => {
@@ -168,44 +198,21 @@ fn find_targets(
                     }
                 }
             }
-            EntitySelectors::AllPlayers => {
-                let executor_entity_layer = *entity_layers.get(event.executor).unwrap();
-                clients
-                    .iter_mut()
-                    .filter_map(|(entity, ..)| {
-                        let entity_layer = entity_layers.get(entity).unwrap();
-                        if entity_layer.0 == executor_entity_layer.0 {
-                            Some(entity)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect()
-            }
+            EntitySelectors::AllPlayers => clients.iter().map(|(entity, _)| entity).filter(|entity| same_layer(*entity)).collect(),
             EntitySelectors::SelfPlayer => {
                 vec![event.executor]
             }
             EntitySelectors::NearestPlayer => {
-                let executor_entity_layer = *entity_layers.get(event.executor).unwrap();
-                let executor_pos = positions.get(event.executor).unwrap();
                 let target = clients
-                    .iter_mut()
-                    .filter(|(entity, ..)| {
-                        *entity_layers.get(*entity).unwrap() == executor_entity_layer
-                    })
-                    .filter(|(target, ..)| *target != event.executor)
-                    .map(|(target, ..)| target)
-                    .min_by(|target, target2| {
-                        let target_pos = positions.get(*target).unwrap();
-                        let target2_pos = positions.get(*target2).unwrap();
-                        let target_dist = target_pos.distance(**executor_pos);
-                        let target2_dist = target2_pos.distance(**executor_pos);
-                        target_dist.partial_cmp(&target2_dist).unwrap()
-                    });
+                    .iter()
+                    .map(|(target, _)| target)
+                    .filter(|target| *target != event.executor && same_layer(*target))
+                    .filter_map(|target| positions.get(target).ok().map(|pos| (target, pos.distance(executor_pos.0))))
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(target, _)| target);
                 match target {
                     None => {
-                        let mut client = clients.get_mut(event.executor).unwrap().1;
-                        client.send_chat_message("Could not find target".to_owned());
+                        report(clients, event.executor, CommandError::NothingFound("could not find target".to_owned()));
                         vec![]
                     }
                     Some(target_entity) => {
@@ -214,18 +221,14 @@ fn find_targets(
                 }
             }
             EntitySelectors::RandomPlayer => {
-                let executor_entity_layer = *entity_layers.get(event.executor).unwrap();
                 let target = clients
-                    .iter_mut()
-                    .filter(|(entity, ..)| {
-                        *entity_layers.get(*entity).unwrap() == executor_entity_layer
-                    })
-                    .choose(&mut valence::rand::thread_rng())
-                    .map(|(target, ..)| target);
+                    .iter()
+                    .map(|(target, _)| target)
+                    .filter(|target| same_layer(*target))
+                    .choose(&mut valence::rand::thread_rng());
                 match target {
                     None => {
-                        let mut client = clients.get_mut(event.executor).unwrap().1;
-                        client.send_chat_message("Could not find target".to_owned());
+                        report(clients, event.executor, CommandError::NothingFound("could not find target".to_owned()));
                         vec![]
                     }
                     Some(target_entity) => {
@@ -235,8 +238,7 @@ fn find_targets(
             }
         },
         EntitySelector::ComplexSelector(_, _) => {
-            let mut client = clients.get_mut(event.executor).unwrap().1;
-            client.send_chat_message("[tp] complex selector not implemented".color(Color::RED));
+            report(clients, event.executor, CommandError::Unsupported("complex selectors"));
             vec![]
         }
     }
@@ -0,0 +1,95 @@
+use tracing::info;
+use valence::{
+    command::handler::CommandResultEvent,
+    command_macros::Command,
+    prelude::*,
+    protocol::WritePacket,
+};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::components::time::{WorldTime, TICKS_PER_DAY};
+
+/// Times of day `set day` and `set night` go to, same as vanilla.
+const DAY: i32 = 1000;
+const NIGHT: i32 = 13000;
+
+/// Sets, moves on or shows the world's time. Setting it keeps the day count,
+/// so the moon phase doesn't jump back to a full moon.
+#[derive(Command, Debug, Clone)]
+#[paths("time")]
+#[scopes("crystal.command.time")]
+pub enum TimeCommand {
+    #[paths("set day")]
+    SetDay,
+    #[paths("set night")]
+    SetNight,
+    #[paths("set {ticks}")]
+    Set { ticks: i32 },
+    #[paths("add {ticks}")]
+    Add { ticks: i32 },
+    #[paths("query")]
+    Query,
+}
+
+pub fn handle_time_command(
+    mut events: EventReader<CommandResultEvent<TimeCommand>>,
+    mut clients: Query<(&mut Client, &Username)>,
+    mut time: ResMut<WorldTime>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let set_to = match event.result {
+            TimeCommand::SetDay => DAY,
+            TimeCommand::SetNight => NIGHT,
+            TimeCommand::Set { ticks } => ticks,
+            TimeCommand::Add { ticks } if ticks < 0 => {
+                CommandError::InvalidArgument("time can't be turned back, add a positive number of ticks".to_owned())
+                    .report(&mut client, "time");
+                continue;
+            }
+            TimeCommand::Add { ticks } => {
+                time.time_of_day += i64::from(ticks);
+                client.send_chat_message(
+                    format!("[time] added {ticks} ticks, the time is now {}", time.day_time()).color(Color::GREEN),
+                );
+                info!(target: AUDIT, player = %username.0, "added {ticks} ticks to the time");
+                broadcast_time(&mut clients, &time);
+                continue;
+            }
+            TimeCommand::Query => {
+                let days = time.time_of_day.div_euclid(TICKS_PER_DAY);
+                client.send_chat_message(
+                    format!(
+                        "[time] the time is {} on day {days}, the world is {} ticks old{}",
+                        time.day_time(),
+                        time.world_age,
+                        if time.frozen { " (frozen)" } else { "" }
+                    )
+                    .color(Color::WHITE),
+                );
+                continue;
+            }
+        };
+        if !(0..TICKS_PER_DAY as i32).contains(&set_to) {
+            CommandError::InvalidArgument(format!("the time has to be between 0 and {}", TICKS_PER_DAY - 1))
+                .report(&mut client, "time");
+            continue;
+        }
+
+        time.time_of_day += i64::from(set_to) - time.day_time();
+        client.send_chat_message(format!("[time] set the time to {set_to}").color(Color::GREEN));
+        info!(target: AUDIT, player = %username.0, "set the time to {set_to}");
+        broadcast_time(&mut clients, &time);
+    }
+}
+
+/// Tells everyone about the new time now instead of at the next sync.
+fn broadcast_time(clients: &mut Query<(&mut Client, &Username)>, time: &WorldTime) {
+    let packet = time.packet();
+    for (mut client, _) in clients.iter_mut() {
+        client.write_packet(&packet);
+    }
+}
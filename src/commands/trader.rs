@@ -0,0 +1,46 @@
+use valence::{command::handler::CommandResultEvent, command_macros::Command, entity::EntityLayerId, prelude::*};
+
+use super::error::CommandError;
+use crate::components::trading::{spawn_trader, Trader, TraderTypes};
+
+#[derive(Command, Debug, Clone)]
+#[paths("trader")]
+#[scopes("crystal.command.trader")]
+pub enum TraderCommand {
+    #[paths("spawn {kind}")]
+    Spawn { kind: String },
+    #[paths("list")]
+    List,
+}
+
+pub fn handle_trader_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<TraderCommand>>,
+    mut clients: Query<(&mut Client, &Position, &Look, &EntityLayerId)>,
+    types: Res<TraderTypes>,
+) {
+    for event in events.read() {
+        let Ok((mut client, pos, look, layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        match &event.result {
+            TraderCommand::Spawn { kind } => {
+                let Some(trader_type) = types.types.get(kind) else {
+                    CommandError::unknown_of("trader", kind, types.types.keys()).report(&mut client, "trader");
+                    continue;
+                };
+                spawn_trader(&mut commands, *layer, pos.0, look.yaw, Trader::new(kind.clone()));
+                client.send_chat_message(
+                    format!("[trader] spawned {}", trader_type.name).color(Color::GREEN),
+                );
+            }
+            TraderCommand::List => {
+                let mut kinds: Vec<&str> = types.types.keys().map(String::as_str).collect();
+                kinds.sort_unstable();
+                client.send_chat_message(
+                    "[trader] trader types: ".color(Color::GOLD) + kinds.join(", ").color(Color::RED),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use tracing::info;
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::components::menu::open_menu;
+use crate::components::playerdata::PlayerData;
+use crate::components::warps::{places_menu, Warps, DEFAULT_HOME, MAX_HOMES};
+use crate::world::teleport::SafeTeleportRequest;
+use crate::world::Overworld;
+
+/// Teleports to a warp, or lists them in chat or a menu.
+#[derive(Command, Debug, Clone)]
+#[paths("warp")]
+#[scopes("crystal.command.warp")]
+pub enum WarpCommand {
+    #[paths("{name}")]
+    Go { name: String },
+    #[paths("{/} warps")]
+    List,
+    #[paths("{/} warps gui")]
+    Gui,
+}
+
+/// Sets a warp where the executor is standing, or removes one.
+#[derive(Command, Debug, Clone)]
+#[paths("setwarp")]
+#[scopes("crystal.command.setwarp")]
+pub enum SetWarpCommand {
+    #[paths("{name}")]
+    Set { name: String },
+    #[paths("{/} delwarp {name}")]
+    Delete { name: String },
+}
+
+/// The executor's own homes: teleporting to, setting, removing and listing.
+#[derive(Command, Debug, Clone)]
+#[paths("home")]
+#[scopes("crystal.command.home")]
+pub enum HomeCommand {
+    #[paths("{name?}")]
+    Go { name: Option<String> },
+    #[paths("{/} sethome {name?}")]
+    Set { name: Option<String> },
+    #[paths("{/} delhome {name}")]
+    Delete { name: String },
+    #[paths("{/} homes")]
+    List,
+    #[paths("{/} homes gui")]
+    Gui,
+}
+
+/// Warps and homes are overworld spots, so both only work from there.
+fn check_overworld(client: &mut Client, layer: &VisibleChunkLayer, overworld: &Query<Entity, With<Overworld>>, command: &str) -> bool {
+    if overworld.get_single().is_ok_and(|overworld| overworld == layer.0) {
+        return true;
+    }
+    CommandError::NotAllowed(format!("{command} only works in the overworld")).report(client, command);
+    false
+}
+
+fn list_places<'a>(client: &mut Client, command: &str, what: &str, names: impl ExactSizeIterator<Item = &'a String>) {
+    if names.len() == 0 {
+        client.send_chat_message(format!("[{command}] there are no {what} yet").color(Color::GRAY));
+        return;
+    }
+    let names: Vec<&str> = names.map(String::as_str).collect();
+    client.send_chat_message(format!("[{command}] {what}: {}", names.join(", ")).color(Color::GOLD));
+}
+
+pub fn handle_warp_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<WarpCommand>>,
+    mut clients: Query<(&mut Client, &VisibleChunkLayer)>,
+    overworld: Query<Entity, With<Overworld>>,
+    warps: Res<Warps>,
+    mut teleports: EventWriter<SafeTeleportRequest>,
+) {
+    for event in events.read() {
+        let Ok((mut client, layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        match &event.result {
+            WarpCommand::Go { name } => {
+                if !check_overworld(&mut client, layer, &overworld, "warp") {
+                    continue;
+                }
+                let Some(&[x, y, z]) = warps.0.get(name) else {
+                    CommandError::unknown_of("warp", name, warps.0.keys()).report(&mut client, "warp");
+                    continue;
+                };
+                teleports.send(SafeTeleportRequest { entity: event.executor, target: DVec3::new(x, y, z) });
+                client.send_chat_message(format!("[warp] warping to {name}").color(Color::GREEN));
+            }
+            WarpCommand::List => list_places(&mut client, "warp", "warps", warps.0.keys()),
+            WarpCommand::Gui => {
+                let (player, menu) = (event.executor, places_menu("Warps", "warp", ItemKind::EnderPearl, &warps.0));
+                commands.add(move |world: &mut World| open_menu(world, player, menu));
+            }
+        }
+    }
+}
+
+pub fn handle_setwarp_command(
+    mut events: EventReader<CommandResultEvent<SetWarpCommand>>,
+    mut clients: Query<(&mut Client, &Username, &Position, &VisibleChunkLayer)>,
+    overworld: Query<Entity, With<Overworld>>,
+    mut warps: ResMut<Warps>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username, pos, layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        match &event.result {
+            SetWarpCommand::Set { name } => {
+                if !check_overworld(&mut client, layer, &overworld, "setwarp") {
+                    continue;
+                }
+                let replaced = warps.0.insert(name.clone(), pos.0.to_array()).is_some();
+                if let Err(e) = warps.save() {
+                    CommandError::storage("save", "the warps", e).report(&mut client, "setwarp");
+                    continue;
+                }
+                let verb = if replaced { "moved" } else { "set" };
+                client.send_chat_message(format!("[setwarp] {verb} warp {name}").color(Color::GREEN));
+                info!(target: AUDIT, player = %username.0, "{verb} warp {name} at {:?}", pos.0);
+            }
+            SetWarpCommand::Delete { name } => {
+                if warps.0.remove(name).is_none() {
+                    CommandError::unknown_of("warp", name, warps.0.keys()).report(&mut client, "delwarp");
+                    continue;
+                }
+                if let Err(e) = warps.save() {
+                    CommandError::storage("save", "the warps", e).report(&mut client, "delwarp");
+                    continue;
+                }
+                client.send_chat_message(format!("[delwarp] removed warp {name}").color(Color::GREEN));
+                info!(target: AUDIT, player = %username.0, "removed warp {name}");
+            }
+        }
+    }
+}
+
+pub fn handle_home_command(
+    mut commands: Commands,
+    mut events: EventReader<CommandResultEvent<HomeCommand>>,
+    mut clients: Query<(&mut Client, &mut PlayerData, &Position, &VisibleChunkLayer)>,
+    overworld: Query<Entity, With<Overworld>>,
+    mut teleports: EventWriter<SafeTeleportRequest>,
+) {
+    for event in events.read() {
+        let Ok((mut client, mut data, pos, layer)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        match &event.result {
+            HomeCommand::Go { name } => {
+                if !check_overworld(&mut client, layer, &overworld, "home") {
+                    continue;
+                }
+                let name = name.as_deref().unwrap_or(DEFAULT_HOME);
+                let Some(&[x, y, z]) = data.homes.get(name) else {
+                    CommandError::unknown_of("home", name, data.homes.keys()).report(&mut client, "home");
+                    continue;
+                };
+                teleports.send(SafeTeleportRequest { entity: event.executor, target: DVec3::new(x, y, z) });
+                client.send_chat_message(format!("[home] going home to {name}").color(Color::GREEN));
+            }
+            HomeCommand::Set { name } => {
+                if !check_overworld(&mut client, layer, &overworld, "sethome") {
+                    continue;
+                }
+                let name = name.as_deref().unwrap_or(DEFAULT_HOME);
+                if !data.homes.contains_key(name) && data.homes.len() >= MAX_HOMES {
+                    CommandError::NotAllowed(format!("you already have {MAX_HOMES} homes, remove one with /delhome first"))
+                        .report(&mut client, "sethome");
+                    continue;
+                }
+                data.homes.insert(name.to_owned(), pos.0.to_array());
+                client.send_chat_message(format!("[sethome] set home {name}").color(Color::GREEN));
+            }
+            HomeCommand::Delete { name } => {
+                if data.homes.remove(name).is_none() {
+                    CommandError::unknown_of("home", name, data.homes.keys()).report(&mut client, "delhome");
+                    continue;
+                }
+                client.send_chat_message(format!("[delhome] removed home {name}").color(Color::GREEN));
+            }
+            HomeCommand::List => list_places(&mut client, "home", "homes", data.homes.keys()),
+            HomeCommand::Gui => {
+                let (player, menu) = (event.executor, places_menu("Homes", "home", ItemKind::RedBed, &data.homes));
+                commands.add(move |world: &mut World| open_menu(world, player, menu));
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use tracing::info;
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::components::weather::{Weather, WeatherKind};
+
+/// Sets the overworld's weather, for `duration` seconds or a random while
+/// like it would have changed on its own.
+#[derive(Command, Debug, Clone)]
+#[paths("weather")]
+#[scopes("crystal.command.weather")]
+pub enum WeatherCommand {
+    #[paths("clear {duration?}")]
+    Clear { duration: Option<i32> },
+    #[paths("rain {duration?}")]
+    Rain { duration: Option<i32> },
+    #[paths("thunder {duration?}")]
+    Thunder { duration: Option<i32> },
+    #[paths("query")]
+    Query,
+}
+
+pub fn handle_weather_command(
+    mut events: EventReader<CommandResultEvent<WeatherCommand>>,
+    mut clients: Query<(&mut Client, &Username)>,
+    mut weather: ResMut<Weather>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let (kind, duration) = match event.result {
+            WeatherCommand::Clear { duration } => (WeatherKind::Clear, duration),
+            WeatherCommand::Rain { duration } => (WeatherKind::Rain, duration),
+            WeatherCommand::Thunder { duration } => (WeatherKind::Thunder, duration),
+            WeatherCommand::Query => {
+                let until = match weather.frozen {
+                    true => "until it's changed".to_owned(),
+                    false => format!("for another {}s", weather.remaining / 20),
+                };
+                client.send_chat_message(format!("[weather] the weather is {} {until}", weather.kind).color(Color::WHITE));
+                continue;
+            }
+        };
+        if duration.is_some_and(|duration| duration <= 0) {
+            CommandError::InvalidArgument("the duration has to be at least a second".to_owned()).report(&mut client, "weather");
+            continue;
+        }
+
+        weather.set(kind, duration.map(|seconds| i64::from(seconds) * 20));
+        client.send_chat_message(format!("[weather] set the weather to {kind}").color(Color::GREEN));
+        info!(target: AUDIT, player = %username.0, "set the weather to {kind} for {}s", weather.remaining / 20);
+    }
+}
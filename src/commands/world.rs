@@ -0,0 +1,74 @@
+use valence::{
+    command::handler::CommandResultEvent, command_macros::Command, entity::EntityLayerId, prelude::*, spawn::IsFlat,
+};
+
+use super::error::CommandError;
+use crate::world::worlds::{NamedWorld, OVERWORLD_NAME};
+use crate::world::{send_to_layer, Overworld, WorldGenerator, WorldSpawn};
+
+/// Lists the worlds, or moves the executor to one of them.
+#[derive(Command, Debug, Clone)]
+#[paths("world {name?}")]
+#[scopes("crystal.command.world")]
+pub struct WorldCommand {
+    name: Option<String>,
+}
+
+pub fn handle_world_command(
+    mut events: EventReader<CommandResultEvent<WorldCommand>>,
+    mut clients: Query<(
+        &mut Client,
+        &mut EntityLayerId,
+        &mut VisibleChunkLayer,
+        &mut VisibleEntityLayers,
+        &mut Position,
+        &mut IsFlat,
+    )>,
+    overworld: Query<Entity, With<Overworld>>,
+    worlds: Query<(Entity, &NamedWorld)>,
+    spawn: Res<WorldSpawn>,
+    world_gen: Res<WorldGenerator>,
+) {
+    for event in events.read() {
+        let Ok((mut client, mut layer_id, mut visible_chunk, mut visible_entities, mut pos, mut is_flat)) =
+            clients.get_mut(event.executor)
+        else {
+            continue;
+        };
+        let Ok(overworld) = overworld.get_single() else {
+            continue;
+        };
+
+        let Some(name) = &event.result.name else {
+            let mut names: Vec<(&str, Entity)> = worlds.iter().map(|(entity, world)| (world.name.as_str(), entity)).collect();
+            names.sort_unstable();
+            client.send_chat_message("[world] worlds:".color(Color::GOLD));
+            for (name, layer) in [(OVERWORLD_NAME, overworld)].into_iter().chain(names) {
+                let here = if layer == visible_chunk.0 { " (you're here)" } else { "" };
+                client.send_chat_message(format!(" {name}{here}").color(Color::WHITE));
+            }
+            continue;
+        };
+
+        let destination = if name == OVERWORLD_NAME {
+            Some((overworld, spawn.0, world_gen.generator.is_flat()))
+        } else {
+            worlds
+                .iter()
+                .find(|(_, world)| world.name == *name)
+                .map(|(entity, world)| (entity, world.spawn, world.flat))
+        };
+        let Some((layer, destination, flat)) = destination else {
+            let options = worlds.iter().map(|(_, world)| world.name.clone()).chain([OVERWORLD_NAME.to_owned()]);
+            CommandError::unknown_of("world", name, options).report(&mut client, "world");
+            continue;
+        };
+        if layer == visible_chunk.0 {
+            CommandError::NotAllowed(format!("you're already in {name}")).report(&mut client, "world");
+            continue;
+        }
+        is_flat.0 = flat;
+        send_to_layer(layer, destination, &mut layer_id, &mut visible_chunk, &mut visible_entities, &mut pos);
+        client.send_chat_message(format!("[world] sent you to {name}").color(Color::GREEN));
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use tracing::info;
+use valence::{command::handler::CommandResultEvent, command_macros::Command, prelude::*};
+
+use super::error::CommandError;
+use crate::components::logging::AUDIT;
+use crate::world::worlds::{NamedWorld, OVERWORLD_NAME};
+use crate::world::{build_generator, GameState, TerrainPreset, WorldGenerator};
+
+/// Switches which preset generates a world's new chunks. Chunks that are
+/// already loaded or saved keep their terrain.
+#[derive(Command, Debug, Clone)]
+#[paths("worldgen")]
+#[scopes("crystal.command.worldgen")]
+pub enum WorldGenCommand {
+    #[paths("set {world} {preset}")]
+    Set { world: String, preset: String },
+}
+
+pub fn handle_worldgen_command(
+    mut events: EventReader<CommandResultEvent<WorldGenCommand>>,
+    mut clients: Query<(&mut Client, &Username)>,
+    mut world_gen: ResMut<WorldGenerator>,
+    state: Res<GameState>,
+    mut worlds: Query<(&mut NamedWorld, &GameState)>,
+    biomes: Res<BiomeRegistry>,
+) {
+    for event in events.read() {
+        let Ok((mut client, username)) = clients.get_mut(event.executor) else {
+            continue;
+        };
+        let WorldGenCommand::Set { world, preset } = &event.result;
+
+        let target = if world == OVERWORLD_NAME {
+            None
+        } else {
+            let Some(found) = worlds.iter_mut().find(|(named, _)| named.name == *world) else {
+                let options = worlds.iter().map(|(named, _)| named.name.clone()).chain([OVERWORLD_NAME.to_owned()]);
+                CommandError::unknown_of("world", world, options).report(&mut client, "worldgen");
+                continue;
+            };
+            Some(found)
+        };
+        let (seed, config) = match &target {
+            None => (world_gen.seed, &world_gen.config),
+            Some((named, _)) => (named.seed, &named.config),
+        };
+        if !config.has_preset(preset) {
+            let options = TerrainPreset::BUILTIN
+                .iter()
+                .map(|name| (*name).to_owned())
+                .chain(config.presets.keys().cloned())
+                .chain(["flat".to_owned()]);
+            CommandError::unknown_of("preset", preset, options).report(&mut client, "worldgen");
+            continue;
+        }
+
+        let mut config = config.clone();
+        config.preset = preset.clone();
+        // A custom superflat string would win over the new preset
+        config.superflat = None;
+        let generator = Arc::new(build_generator(&config, seed, &biomes));
+        let flat = generator.is_flat();
+        match target {
+            None => {
+                state.set_generator(generator.clone());
+                world_gen.generator = generator;
+                world_gen.config = config;
+            }
+            Some((mut named, world_state)) => {
+                world_state.set_generator(generator);
+                named.flat = flat;
+                named.config = config;
+            }
+        }
+
+        client.send_chat_message(format!("[worldgen] {world} now generates new chunks with {preset}").color(Color::GREEN));
+        client.send_chat_message(
+            "[worldgen] chunks that were already generated keep their terrain, so expect seams where old and new chunks meet. \
+             This lasts until restart unless the config is changed too"
+                .color(Color::GOLD),
+        );
+        info!(target: AUDIT, player = %username.0, "switched {world} to the {preset} generator");
+    }
+}
@@ -0,0 +1,265 @@
+// src/components/attempts.rs
+//
+// Things players try to do that other systems may want a say in: breaking and
+// placing blocks, chatting, and damage. Instead of each system reading
+// valence's events on its own and racing the others, the events are turned
+// into attempts once, and listeners go through them in fixed stages:
+// protection first, then anti-cheat, then anything that wants to change or
+// take over an attempt, like minigames and scripts, then the gameplay that
+// carries it out, and last whatever just watches the outcome.
+//
+// Any stage before `Apply` can cancel an attempt, with a reason for the
+// player or silently. Later stages skip cancelled attempts with `pending`;
+// `Monitor` sees them all. Cancelled block changes are put back on the
+// player's screen, since their client already made them, and the reason is
+// shown in their action bar, or the attacker's for damage.
+//
+// A listener joins a stage with `.in_set(AttemptStage::Protect)` and reads or
+// cancels attempts through `ResMut<Attempts<BlockBreakAttempt>>` and the like.
+// Attempts only last for the tick they were collected in.
+
+use valence::{
+    interact_block::InteractBlockEvent,
+    inventory::HeldItem,
+    message::ChatMessageEvent,
+    prelude::*,
+};
+
+use super::block_rules::revert_block;
+use super::container::container_kind;
+use super::hud::HudMessage;
+use super::redstone::{is_openable, is_redstone_input};
+use super::sleep::is_bed;
+
+/// The stages attempts go through each tick, in this order.
+#[derive(SystemSet, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AttemptStage {
+    /// Valence's events are turned into attempts.
+    Collect,
+    /// Protection: block rules, the bedrock floor, claimed land.
+    Protect,
+    /// Anti-cheat checks.
+    Validate,
+    /// Minigames and scripts, which can change an attempt or take it over.
+    Modify,
+    /// The gameplay systems that carry out what's left.
+    Apply,
+    /// Sees every attempt and whether it went through, e.g. for logging.
+    Monitor,
+}
+
+pub struct Attempt<T> {
+    pub event: T,
+    cancelled: bool,
+    reason: Option<Text>,
+}
+
+impl<T> Attempt<T> {
+    /// Stops the attempt, telling the player why.
+    pub fn cancel(&mut self, reason: impl Into<Text>) {
+        self.cancelled = true;
+        self.reason = Some(reason.into());
+    }
+
+    /// Stops the attempt without a word, for listeners that dealt with it
+    /// themselves. Replaces an earlier stage's reason, if it had one.
+    pub fn take_over(&mut self) {
+        self.cancelled = true;
+        self.reason = None;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+}
+
+/// This tick's attempts of one kind.
+#[derive(Resource)]
+pub struct Attempts<T: Send + Sync + 'static>(Vec<Attempt<T>>);
+
+impl<T: Send + Sync + 'static> Default for Attempts<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T: Send + Sync + 'static> Attempts<T> {
+    pub fn push(&mut self, event: T) {
+        self.0.push(Attempt {
+            event,
+            cancelled: false,
+            reason: None,
+        });
+    }
+
+    /// Attempts nobody has cancelled.
+    pub fn pending(&self) -> impl Iterator<Item = &T> {
+        self.0.iter().filter(|attempt| !attempt.cancelled).map(|attempt| &attempt.event)
+    }
+
+    /// Attempts nobody has cancelled, to change or cancel them.
+    pub fn pending_mut(&mut self) -> impl Iterator<Item = &mut Attempt<T>> {
+        self.0.iter_mut().filter(|attempt| !attempt.cancelled)
+    }
+
+    /// Every attempt, cancelled or not.
+    pub fn all(&self) -> impl Iterator<Item = &Attempt<T>> {
+        self.0.iter()
+    }
+
+    /// Every attempt, for listeners that own where it happened and can
+    /// overrule earlier stages, like a minigame in its arena.
+    pub fn all_mut(&mut self) -> impl Iterator<Item = &mut Attempt<T>> {
+        self.0.iter_mut()
+    }
+}
+
+/// A player digging at a block. In survival the block only breaks if the
+/// dig took long enough, which `digging` checks when it's applied.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockBreakAttempt {
+    pub player: Entity,
+    pub layer: Entity,
+    pub pos: BlockPos,
+    pub state: DiggingState,
+}
+
+/// A player using their held item on the side of a block, to place it in
+/// `pos`.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockPlaceAttempt {
+    pub player: Entity,
+    pub layer: Entity,
+    pub pos: BlockPos,
+    /// The side of the block that was clicked.
+    pub face: Direction,
+    pub item: ItemKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatAttempt {
+    pub player: Entity,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DamageCause {
+    /// A player hitting the victim.
+    Attack,
+    Fall,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DamageAttempt {
+    pub victim: Entity,
+    pub attacker: Option<Entity>,
+    pub amount: f32,
+    pub cause: DamageCause,
+}
+
+pub fn clear_attempts(
+    mut breaks: ResMut<Attempts<BlockBreakAttempt>>,
+    mut places: ResMut<Attempts<BlockPlaceAttempt>>,
+    mut chats: ResMut<Attempts<ChatAttempt>>,
+    mut damage: ResMut<Attempts<DamageAttempt>>,
+) {
+    breaks.0.clear();
+    places.0.clear();
+    chats.0.clear();
+    damage.0.clear();
+}
+
+pub fn collect_block_attempts(
+    mut digs: EventReader<DiggingEvent>,
+    mut interactions: EventReader<InteractBlockEvent>,
+    clients: Query<(&VisibleChunkLayer, &Inventory, &HeldItem)>,
+    layers: Query<&ChunkLayer>,
+    mut breaks: ResMut<Attempts<BlockBreakAttempt>>,
+    mut places: ResMut<Attempts<BlockPlaceAttempt>>,
+) {
+    for event in digs.read() {
+        if let Ok((layer, ..)) = clients.get(event.client) {
+            breaks.push(BlockBreakAttempt {
+                player: event.client,
+                layer: layer.0,
+                pos: event.position,
+                state: event.state,
+            });
+        }
+    }
+
+    for event in interactions.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Ok((visible_layer, inventory, held)) = clients.get(event.client) else {
+            continue;
+        };
+        let Ok(layer) = layers.get(visible_layer.0) else {
+            continue;
+        };
+        // clicking a container, beacon, bed, lever or door uses it instead
+        if layer.block(event.position).is_some_and(|block| {
+            let kind = block.state.to_kind();
+            container_kind(kind).is_some()
+                || is_redstone_input(kind)
+                || is_openable(kind)
+                || is_bed(kind)
+                || kind == BlockKind::Beacon
+        }) {
+            continue;
+        }
+        let stack = inventory.slot(held.slot());
+        if stack.is_empty() {
+            continue;
+        }
+        places.push(BlockPlaceAttempt {
+            player: event.client,
+            layer: visible_layer.0,
+            pos: event.position.get_in_direction(event.face),
+            face: event.face,
+            item: stack.item,
+        });
+    }
+}
+
+pub fn collect_chat_attempts(mut events: EventReader<ChatMessageEvent>, mut chats: ResMut<Attempts<ChatAttempt>>) {
+    for event in events.read() {
+        chats.push(ChatAttempt {
+            player: event.client,
+            message: event.message.to_string(),
+        });
+    }
+}
+
+// Puts cancelled block changes back and tells players why they were stopped
+pub fn report_cancelled_attempts(
+    mut clients: Query<&mut Client>,
+    layers: Query<&ChunkLayer>,
+    breaks: Res<Attempts<BlockBreakAttempt>>,
+    places: Res<Attempts<BlockPlaceAttempt>>,
+    chats: Res<Attempts<ChatAttempt>>,
+    damage: Res<Attempts<DamageAttempt>>,
+    mut hud_messages: EventWriter<HudMessage>,
+) {
+    let outcomes = breaks
+        .all()
+        .map(|a| (a.event.player, Some((a.event.layer, a.event.pos)), a.cancelled, &a.reason))
+        .chain(places.all().map(|a| (a.event.player, Some((a.event.layer, a.event.pos)), a.cancelled, &a.reason)))
+        .chain(chats.all().map(|a| (a.event.player, None, a.cancelled, &a.reason)))
+        .chain(damage.all().filter_map(|a| Some((a.event.attacker?, None, a.cancelled, &a.reason))));
+
+    for (player, block, cancelled, reason) in outcomes {
+        if !cancelled {
+            continue;
+        }
+        if let Some((layer, pos)) = block
+            && let (Ok(mut client), Ok(layer)) = (clients.get_mut(player), layers.get(layer))
+        {
+            revert_block(&mut client, layer, pos);
+        }
+        if let Some(reason) = reason {
+            hud_messages.send(HudMessage::warning(player, reason.clone().color(Color::RED)));
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use valence::{
+    event_loop::PacketEvent,
+    interact_block::InteractBlockEvent,
+    inventory::ClientInventoryState,
+    nbt::{compound, Compound, Value},
+    protocol::{
+        packets::play::{
+            entity_status_effect_s2c, EntityStatusEffectS2c, ScreenHandlerPropertyUpdateS2c,
+            UpdateBeaconC2s,
+        },
+        VarInt, WritePacket,
+    },
+    prelude::*,
+};
+
+use super::container::drop_stack;
+use super::hud::HudMessage;
+use crate::world::storage::{ChunkRestored, ChunkSaver};
+use crate::world::{in_overworld, Overworld};
+
+const EFFECT_INTERVAL_TICKS: i64 = 80;
+
+// Status effect ids as used by the protocol
+pub const SPEED: i32 = 1;
+pub const HASTE: i32 = 3;
+pub const STRENGTH: i32 = 5;
+pub const JUMP_BOOST: i32 = 8;
+pub const REGENERATION: i32 = 10;
+pub const RESISTANCE: i32 = 11;
+
+/// Status effects the server has given a player, with the tick they wear off,
+/// for gameplay that depends on them (like Haste speeding up mining).
+#[derive(Component, Default, Debug)]
+pub struct ActiveEffects(HashMap<i32, (u8, i64)>);
+
+impl ActiveEffects {
+    /// The effect's amplifier (0 for level I), if it's active at tick `now`.
+    pub fn amplifier(&self, effect: i32, now: i64) -> Option<u8> {
+        self.0
+            .get(&effect)
+            .filter(|(_, until)| *until > now)
+            .map(|(amplifier, _)| *amplifier)
+    }
+
+    fn apply(&mut self, effect: i32, amplifier: u8, until: i64) {
+        let current = self.0.entry(effect).or_insert((amplifier, until));
+        *current = (amplifier.max(current.0), until.max(current.1));
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BeaconState {
+    pub primary: Option<i32>,
+    pub secondary: Option<i32>,
+    /// Pyramid levels found at the last check, 0-4.
+    pub levels: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct Beacons {
+    pub beacons: HashMap<BlockPos, BeaconState>,
+}
+
+impl Beacons {
+    /// The beacon at `pos`, picking up the effect chosen before from its
+    /// block entity the first time it's seen.
+    pub fn get_or_load(&mut self, layer: &ChunkLayer, pos: BlockPos) -> &mut BeaconState {
+        self.beacons
+            .entry(pos)
+            .or_insert_with(|| layer.block(pos).and_then(|block| block.nbt).map(beacon_state).unwrap_or_default())
+    }
+}
+
+/// Put on a client while they have a beacon's screen open.
+#[derive(Component)]
+pub struct ViewingBeacon {
+    pub pos: BlockPos,
+    inventory: Entity,
+    synced: bool,
+}
+
+fn is_base_block(kind: BlockKind) -> bool {
+    matches!(
+        kind,
+        BlockKind::IronBlock
+            | BlockKind::GoldBlock
+            | BlockKind::DiamondBlock
+            | BlockKind::EmeraldBlock
+            | BlockKind::NetheriteBlock
+    )
+}
+
+fn is_payment(item: ItemKind) -> bool {
+    matches!(
+        item,
+        ItemKind::IronIngot
+            | ItemKind::GoldIngot
+            | ItemKind::Emerald
+            | ItemKind::Diamond
+            | ItemKind::NetheriteIngot
+    )
+}
+
+/// Counts how many complete pyramid layers are under the beacon.
+pub fn pyramid_levels(layer: &ChunkLayer, pos: BlockPos) -> u32 {
+    for level in 1..=4 {
+        let y = pos.y - level;
+        let complete = (-level..=level).all(|dx| {
+            (-level..=level).all(|dz| {
+                layer
+                    .block(BlockPos::new(pos.x + dx, y, pos.z + dz))
+                    .is_some_and(|block| is_base_block(block.state.to_kind()))
+            })
+        });
+        if !complete {
+            return (level - 1) as u32;
+        }
+    }
+    4
+}
+
+/// Whether `effect` may be chosen as the primary effect at this level.
+fn primary_allowed(effect: i32, levels: u32) -> bool {
+    match effect {
+        SPEED | HASTE => levels >= 1,
+        RESISTANCE | JUMP_BOOST => levels >= 2,
+        STRENGTH => levels >= 3,
+        _ => false,
+    }
+}
+
+fn beacon_nbt(state: &BeaconState) -> valence::nbt::Compound {
+    compound! {
+        "Primary" => state.primary.unwrap_or(-1),
+        "Secondary" => state.secondary.unwrap_or(-1),
+        "Levels" => state.levels as i32,
+    }
+}
+
+fn beacon_state(nbt: &Compound) -> BeaconState {
+    let effect = |key| match nbt.get(key) {
+        Some(Value::Int(effect)) if *effect >= 0 => Some(*effect),
+        _ => None,
+    };
+    let levels = match nbt.get("Levels") {
+        Some(Value::Int(levels)) => (*levels).clamp(0, 4) as u32,
+        _ => 0,
+    };
+    BeaconState {
+        primary: effect("Primary"),
+        secondary: effect("Secondary"),
+        levels,
+    }
+}
+
+fn sync_properties(client: &mut Client, window_id: u8, state: &BeaconState) {
+    let properties = [
+        state.levels as i16,
+        state.primary.unwrap_or(-1) as i16,
+        state.secondary.unwrap_or(-1) as i16,
+    ];
+    for (property, value) in properties.into_iter().enumerate() {
+        client.write_packet(&ScreenHandlerPropertyUpdateS2c {
+            window_id,
+            property: property as i16,
+            value,
+        });
+    }
+}
+
+// Opens the beacon screen and registers newly placed beacons
+pub fn open_beacons(
+    mut commands: Commands,
+    mut events: EventReader<InteractBlockEvent>,
+    mut beacons: ResMut<Beacons>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    clients: Query<&VisibleChunkLayer>,
+) {
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if !in_overworld(&clients, event.client, overworld) {
+            continue;
+        }
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let placed = event.position.get_in_direction(event.face);
+        if layer.block(placed).is_some_and(|b| b.state.to_kind() == BlockKind::Beacon) {
+            beacons.get_or_load(layer, placed);
+        }
+
+        if layer.block(event.position).is_some_and(|b| b.state.to_kind() == BlockKind::Beacon) {
+            let state = beacons.get_or_load(layer, event.position);
+            state.levels = pyramid_levels(layer, event.position);
+            let inventory = commands
+                .spawn(Inventory::with_title(InventoryKind::Beacon, "Beacon"))
+                .id();
+            commands.entity(event.client).insert((
+                OpenInventory::new(inventory),
+                ViewingBeacon {
+                    pos: event.position,
+                    inventory,
+                    synced: false,
+                },
+            ));
+        }
+    }
+}
+
+pub fn sync_beacon_screens(
+    mut clients: Query<(&mut Client, &ClientInventoryState, &mut ViewingBeacon), With<OpenInventory>>,
+    beacons: Res<Beacons>,
+) {
+    for (mut client, inv_state, mut viewing) in &mut clients {
+        if viewing.synced || inv_state.window_id() == 0 {
+            continue;
+        }
+        if let Some(state) = beacons.beacons.get(&viewing.pos) {
+            sync_properties(&mut client, inv_state.window_id(), state);
+        }
+        viewing.synced = true;
+    }
+}
+
+// Drops any unused payment item and cleans up the screen's inventory
+pub fn close_beacon_screens(
+    mut commands: Commands,
+    clients: Query<(Entity, &ViewingBeacon), Without<OpenInventory>>,
+    inventories: Query<&Inventory, Without<Client>>,
+    layers: Query<Entity, With<Overworld>>,
+) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    for (entity, viewing) in &clients {
+        if let Ok(inventory) = inventories.get(viewing.inventory) {
+            let payment = inventory.slot(0);
+            if !payment.is_empty() {
+                drop_stack(&mut commands, EntityLayerId(layer), viewing.pos, payment.clone());
+            }
+        }
+        commands.entity(viewing.inventory).despawn();
+        commands.entity(entity).remove::<ViewingBeacon>();
+    }
+}
+
+// Validates and applies the effect chosen in the beacon screen
+pub fn handle_beacon_updates(
+    mut packets: EventReader<PacketEvent>,
+    mut beacons: ResMut<Beacons>,
+    mut layers: Query<&mut ChunkLayer, With<Overworld>>,
+    mut clients: Query<(&mut Client, &ViewingBeacon, &OpenInventory, &ClientInventoryState)>,
+    mut inventories: Query<&mut Inventory, Without<Client>>,
+    mut hud_messages: EventWriter<HudMessage>,
+    mut saver: ResMut<ChunkSaver>,
+) {
+    let Ok(mut layer) = layers.get_single_mut() else {
+        return;
+    };
+    for packet in packets.read() {
+        let Some(pkt) = packet.decode::<UpdateBeaconC2s>() else {
+            continue;
+        };
+        let Ok((mut client, viewing, open, inv_state)) = clients.get_mut(packet.client) else {
+            continue;
+        };
+        let Ok(mut inventory) = inventories.get_mut(open.entity) else {
+            continue;
+        };
+        let state = beacons.get_or_load(&layer, viewing.pos);
+
+        let levels = pyramid_levels(&layer, viewing.pos);
+        let primary = pkt.primary_effect.map(|e| e.0);
+        let secondary = pkt.secondary_effect.map(|e| e.0);
+        let valid_primary = primary.is_some_and(|e| primary_allowed(e, levels));
+        // Level 4 unlocks regeneration or a stronger primary.
+        let valid_secondary =
+            secondary.map_or(true, |e| levels >= 4 && (e == REGENERATION || Some(e) == primary));
+        let payment = inventory.slot(0).clone();
+
+        if !valid_primary || !valid_secondary || !is_payment(payment.item) {
+            hud_messages.send(HudMessage::warning(packet.client, "Invalid beacon configuration".color(Color::RED)));
+            continue;
+        }
+
+        if payment.count > 1 {
+            inventory.set_slot_amount(0, payment.count - 1);
+        } else {
+            inventory.set_slot(0, ItemStack::EMPTY);
+        }
+        state.primary = primary;
+        state.secondary = secondary;
+        state.levels = levels;
+
+        // Keep the choice in the block entity so it stays with the chunk.
+        if let Some(block) = layer.block(viewing.pos).map(|b| b.state) {
+            layer.set_block(viewing.pos, Block::new(block, Some(beacon_nbt(state))));
+            saver.mark_block_dirty(viewing.pos);
+        }
+        sync_properties(&mut client, inv_state.window_id(), state);
+    }
+}
+
+// Gives nearby players the beacon's effects every few seconds
+pub fn apply_beacon_effects(
+    mut commands: Commands,
+    server: Res<Server>,
+    mut beacons: ResMut<Beacons>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    mut clients: Query<(Entity, &mut Client, &Position, &EntityId, &EntityLayerId, Option<&mut ActiveEffects>)>,
+) {
+    if server.current_tick() % EFFECT_INTERVAL_TICKS != 0 {
+        return;
+    }
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+
+    beacons.beacons.retain(|pos, _| {
+        layer
+            .block(*pos)
+            .map_or(true, |b| b.state.to_kind() == BlockKind::Beacon)
+    });
+
+    for (pos, state) in beacons.beacons.iter_mut() {
+        // The block entity is what's saved, so its effects are the ones
+        // that count
+        if let Some(nbt) = layer.block(*pos).and_then(|block| block.nbt) {
+            let saved = beacon_state(nbt);
+            (state.primary, state.secondary) = (saved.primary, saved.secondary);
+        }
+        state.levels = pyramid_levels(layer, *pos);
+        let Some(primary) = state.primary else {
+            continue;
+        };
+        if state.levels == 0 || !primary_allowed(primary, state.levels) {
+            continue;
+        }
+
+        let range = 10.0 + 10.0 * state.levels as f64;
+        let duration = (9 + 2 * state.levels as i32) * 20;
+        let mut effects = vec![(primary, 0u8)];
+        match state.secondary {
+            Some(REGENERATION) if state.levels >= 4 => effects.push((REGENERATION, 0)),
+            Some(secondary) if state.levels >= 4 && secondary == primary => effects[0].1 = 1,
+            _ => {}
+        }
+
+        for (player, mut client, player_pos, entity_id, player_layer, mut active) in &mut clients {
+            if player_layer.0 != overworld {
+                continue;
+            }
+            let dx = (player_pos.0.x - (pos.x as f64 + 0.5)).abs();
+            let dz = (player_pos.0.z - (pos.z as f64 + 0.5)).abs();
+            // Vanilla beacons reach the whole column above and below.
+            if dx > range || dz > range {
+                continue;
+            }
+            let mut new_effects = ActiveEffects::default();
+            let active = active.as_deref_mut().unwrap_or(&mut new_effects);
+            for (effect, amplifier) in &effects {
+                active.apply(*effect, *amplifier, server.current_tick() + duration as i64);
+                client.write_packet(&EntityStatusEffectS2c {
+                    entity_id: VarInt(entity_id.get()),
+                    effect_id: VarInt(*effect),
+                    amplifier: *amplifier,
+                    duration: VarInt(duration),
+                    flags: entity_status_effect_s2c::Flags::new()
+                        .with_is_ambient(true)
+                        .with_show_icon(true),
+                    factor_codec: None,
+                });
+            }
+            if !new_effects.0.is_empty() {
+                commands.entity(player).insert(new_effects);
+            }
+        }
+    }
+}
+
+// Picks up beacons in chunks loaded from disk, with the effect they had
+pub fn restore_beacons(
+    mut events: EventReader<ChunkRestored>,
+    mut beacons: ResMut<Beacons>,
+    layers: Query<&ChunkLayer, With<Overworld>>,
+) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        for pos in &event.block_entities {
+            if layer.block(*pos).is_some_and(|block| block.state.to_kind() == BlockKind::Beacon) {
+                beacons.get_or_load(layer, *pos);
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// src/components/block_rules.rs
+//
+// Server-wide limits on what non-ops can place and break, for servers that
+// don't want TNT, lava or spawners in players' hands but don't need regions.
+// Each list is either a blacklist (listed things are denied) or a whitelist
+// (only listed things are allowed).
+//
+// `config/block_rules.json`:
+// `{ "place": { "mode": "blacklist", "entries": ["tnt", "lava_bucket"] },
+//    "break": { "mode": "blacklist", "entries": ["bedrock", "spawner"] } }`
+//
+// Place entries are the held item, so buckets and spawn eggs can be listed
+// too; break entries are blocks.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use serde::Deserialize;
+use tracing::{error, info};
+use valence::{
+    op_level::OpLevel,
+    protocol::{packets::play::BlockUpdateS2c, WritePacket},
+    prelude::*,
+};
+
+use super::config::load_config;
+use super::logging::CONFIG;
+
+/// Ops at this level or above ignore the rules.
+const BYPASS_OP_LEVEL: u8 = 2;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListMode {
+    #[default]
+    Blacklist,
+    Whitelist,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ListConfig {
+    pub mode: ListMode,
+    pub entries: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BlockRulesConfig {
+    pub place: ListConfig,
+    #[serde(rename = "break")]
+    pub break_: ListConfig,
+}
+
+impl Default for BlockRulesConfig {
+    fn default() -> Self {
+        let blacklist = |entries: &[&str]| ListConfig {
+            mode: ListMode::Blacklist,
+            entries: entries.iter().map(|e| (*e).to_owned()).collect(),
+        };
+        Self {
+            place: blacklist(&["tnt", "bedrock", "lava_bucket", "spawner"]),
+            break_: blacklist(&["bedrock", "spawner"]),
+        }
+    }
+}
+
+pub struct ListRule<T> {
+    mode: ListMode,
+    entries: HashSet<T>,
+}
+
+impl<T: Eq + Hash> ListRule<T> {
+    fn parse(config: &ListConfig, what: &str, parse: impl Fn(&str) -> Option<T>) -> Self {
+        let entries = config
+            .entries
+            .iter()
+            .filter_map(|name| {
+                let parsed = parse(name.trim_start_matches("minecraft:"));
+                if parsed.is_none() {
+                    error!(target: CONFIG, "unknown {what} in block_rules.json: {name}");
+                }
+                parsed
+            })
+            .collect();
+        Self { mode: config.mode, entries }
+    }
+
+    pub fn allows(&self, value: &T) -> bool {
+        self.entries.contains(value) == (self.mode == ListMode::Whitelist)
+    }
+}
+
+#[derive(Resource)]
+pub struct BlockRules {
+    pub place: ListRule<ItemKind>,
+    pub break_: ListRule<BlockKind>,
+}
+
+impl BlockRules {
+    pub fn exempt(op_level: &OpLevel) -> bool {
+        op_level.get() >= BYPASS_OP_LEVEL
+    }
+
+    pub fn can_place(&self, op_level: &OpLevel, item: ItemKind) -> bool {
+        Self::exempt(op_level) || self.place.allows(&item)
+    }
+
+    pub fn can_break(&self, op_level: &OpLevel, block: BlockKind) -> bool {
+        Self::exempt(op_level) || self.break_.allows(&block)
+    }
+}
+
+pub fn setup_block_rules(mut commands: Commands) {
+    let config: BlockRulesConfig = load_config("block_rules.json");
+    let rules = BlockRules {
+        place: ListRule::parse(&config.place, "item", ItemKind::from_str),
+        break_: ListRule::parse(&config.break_, "block", BlockKind::from_str),
+    };
+    info!(
+        target: CONFIG,
+        "block rules: placing {:?} of {}, breaking {:?} of {}",
+        rules.place.mode,
+        rules.place.entries.len(),
+        rules.break_.mode,
+        rules.break_.entries.len()
+    );
+    commands.insert_resource(rules);
+}
+
+/// The client already changed the block when it predicted the action, so a
+/// denied action has to put the real block back.
+pub fn revert_block(client: &mut Client, layer: &ChunkLayer, pos: BlockPos) {
+    if let Some(block) = layer.block(pos) {
+        client.write_packet(&BlockUpdateS2c {
+            position: pos,
+            block_id: block.state,
+        });
+    }
+}
@@ -0,0 +1,420 @@
+use tracing::info;
+use valence::{
+    boss_bar::{BossBarBundle, BossBarColor, BossBarDivision, BossBarHealth, BossBarStyle, BossBarTitle},
+    entity::{
+        item::{ItemEntityBundle, Stack},
+        living::Health,
+        wither::WitherEntityBundle,
+        wither_skull::WitherSkullEntityBundle,
+        dragon_fireball::DragonFireballEntityBundle,
+        Despawned, EntityLayerId, Velocity,
+    },
+    interact_block::InteractBlockEvent,
+    interact_entity::{EntityInteraction, InteractEntityEvent},
+    inventory::HeldItem,
+    message::SendMessage,
+    prelude::*,
+};
+
+use super::core::new_crystal_message;
+use super::enchantment::{self, sharpness_bonus, SHARPNESS};
+use super::explosion::{explode, ExplosionTargets};
+use crate::world::deferred::PendingBlocks;
+use crate::world::events::WorldEvent;
+use crate::world::physics::{sweep, Aabb};
+
+pub const WITHER_MAX_HEALTH: f32 = 300.0;
+pub const DRAGON_MAX_HEALTH: f32 = 200.0;
+/// Ticks the wither spends charging up after being built, like vanilla.
+const WITHER_SPAWN_TICKS: u32 = 220;
+const WITHER_SPAWN_EXPLOSION: f32 = 7.0;
+const WITHER_TARGET_RANGE: f64 = 32.0;
+const SKULL_SPEED: f64 = 1.0;
+const SKULL_EXPLOSION: f32 = 1.0;
+const SKULL_LIFETIME_TICKS: u32 = 100;
+/// Hitbox size of wither skulls and dragon fireballs.
+const PROJECTILE_SIZE: f64 = 0.3125;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BossPhase {
+    /// Charging up; can't be hurt and doesn't attack.
+    Spawning { ticks_left: u32 },
+    /// Shoots a skull at the nearest player every couple of seconds.
+    Ranged,
+    /// Below half health: fires volleys faster and blows up the ground below
+    /// itself now and then.
+    Enraged,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BossKind {
+    Wither,
+    EnderDragon,
+}
+
+impl BossKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            BossKind::Wither => "Wither",
+            BossKind::EnderDragon => "Ender Dragon",
+        }
+    }
+}
+
+/// A boss encounter. The boss bar is a separate entity that follows the boss's
+/// health.
+#[derive(Component)]
+pub struct Boss {
+    pub kind: BossKind,
+    pub max_health: f32,
+    pub phase: BossPhase,
+    pub attack_cooldown: u32,
+    pub bar: Entity,
+}
+
+impl Boss {
+    fn attack_interval(&self) -> u32 {
+        match self.phase {
+            BossPhase::Enraged => 20,
+            _ => 40,
+        }
+    }
+}
+
+/// Sent when a boss's health reaches zero, before it's despawned. Encounters
+/// use this for their own rewards (e.g. the End's exit portal).
+#[derive(Event, Clone, Debug)]
+pub struct BossDefeatedEvent {
+    pub kind: BossKind,
+    pub position: DVec3,
+    pub layer: Entity,
+}
+
+/// A projectile fired by a boss. Valence doesn't simulate entity movement,
+/// so these are stepped manually.
+#[derive(Component)]
+pub struct BossProjectile {
+    pub velocity: DVec3,
+    pub power: f32,
+    pub ticks_left: u32,
+}
+
+fn is_soul_block(kind: BlockKind) -> bool {
+    matches!(kind, BlockKind::SoulSand | BlockKind::SoulSoil)
+}
+
+fn is_wither_skull(kind: BlockKind) -> bool {
+    matches!(kind, BlockKind::WitherSkeletonSkull | BlockKind::WitherSkeletonWallSkull)
+}
+
+/// Looks for a wither build (T of soul sand topped by three skulls) that uses
+/// the skull at `skull`. Returns the middle soul block and every block that
+/// makes up the structure.
+fn find_wither_structure(layer: &ChunkLayer, skull: BlockPos) -> Option<(BlockPos, Vec<BlockPos>)> {
+    let kind_at = |pos: BlockPos| layer.block(pos).map(|b| b.state.to_kind());
+    for [ax, az] in [[1, 0], [0, 1]] {
+        // The placed skull can be any of the three.
+        for offset in -1..=1 {
+            let center = BlockPos::new(skull.x - ax * offset, skull.y - 1, skull.z - az * offset);
+            let mut blocks = Vec::new();
+            let mut valid = true;
+            for side in -1..=1 {
+                let arm = BlockPos::new(center.x + ax * side, center.y, center.z + az * side);
+                let head = BlockPos::new(arm.x, arm.y + 1, arm.z);
+                valid &= kind_at(arm).is_some_and(is_soul_block) && kind_at(head).is_some_and(is_wither_skull);
+                blocks.extend([arm, head]);
+            }
+            let stem = BlockPos::new(center.x, center.y - 1, center.z);
+            valid &= kind_at(stem).is_some_and(is_soul_block);
+            blocks.push(stem);
+            if valid {
+                return Some((center, blocks));
+            }
+        }
+    }
+    None
+}
+
+// Building the wither structure replaces it with the boss
+pub fn spawn_withers(
+    mut commands: Commands,
+    mut events: EventReader<InteractBlockEvent>,
+    mut layers: Query<&mut ChunkLayer>,
+    clients: Query<(&Username, &VisibleChunkLayer)>,
+) {
+    for event in events.read() {
+        let Ok((username, visible_layer)) = clients.get(event.client) else {
+            continue;
+        };
+        let layer_entity = visible_layer.0;
+        let Ok(mut layer) = layers.get_mut(layer_entity) else {
+            continue;
+        };
+        let placed = event.position.get_in_direction(event.face);
+        if !layer.block(placed).is_some_and(|b| is_wither_skull(b.state.to_kind())) {
+            continue;
+        }
+        let Some((center, blocks)) = find_wither_structure(&layer, placed) else {
+            continue;
+        };
+        for pos in blocks {
+            layer.set_block(pos, BlockState::AIR);
+        }
+
+        let position = DVec3::new(center.x as f64 + 0.5, center.y as f64 - 0.5, center.z as f64 + 0.5);
+        let bar = commands
+            .spawn(BossBarBundle {
+                title: BossBarTitle(BossKind::Wither.name().into_text()),
+                health: BossBarHealth(1.0),
+                style: BossBarStyle {
+                    color: BossBarColor::Purple,
+                    division: BossBarDivision::NoDivision,
+                },
+                layer: EntityLayerId(layer_entity),
+                ..Default::default()
+            })
+            .id();
+        commands.spawn((
+            WitherEntityBundle {
+                layer: EntityLayerId(layer_entity),
+                position: Position(position),
+                living_health: Health(WITHER_MAX_HEALTH),
+                ..Default::default()
+            },
+            Boss {
+                kind: BossKind::Wither,
+                max_health: WITHER_MAX_HEALTH,
+                phase: BossPhase::Spawning {
+                    ticks_left: WITHER_SPAWN_TICKS,
+                },
+                attack_cooldown: 0,
+                bar,
+            },
+        ));
+
+        info!("[boss] {} summoned a wither at {center:?}", username.0);
+    }
+}
+
+/// Melee damage of whatever the attacker is holding.
+pub fn attack_damage(item: ItemKind) -> f32 {
+    match item {
+        ItemKind::WoodenSword | ItemKind::GoldenSword => 4.0,
+        ItemKind::StoneSword => 5.0,
+        ItemKind::IronSword => 6.0,
+        ItemKind::DiamondSword => 7.0,
+        ItemKind::NetheriteSword => 8.0,
+        ItemKind::WoodenAxe | ItemKind::GoldenAxe => 7.0,
+        ItemKind::StoneAxe | ItemKind::IronAxe | ItemKind::DiamondAxe => 9.0,
+        ItemKind::NetheriteAxe => 10.0,
+        _ => 1.0,
+    }
+}
+
+// Players hitting a boss
+pub fn damage_bosses(
+    mut events: EventReader<InteractEntityEvent>,
+    players: Query<(&Inventory, &HeldItem)>,
+    mut bosses: Query<(&Boss, &mut Health)>,
+) {
+    for event in events.read() {
+        if event.interact != EntityInteraction::Attack {
+            continue;
+        }
+        let Ok((boss, mut health)) = bosses.get_mut(event.entity) else {
+            continue;
+        };
+        if matches!(boss.phase, BossPhase::Spawning { .. }) {
+            continue;
+        }
+        let Ok((inventory, held)) = players.get(event.client) else {
+            continue;
+        };
+        let weapon = inventory.slot(held.slot());
+        let damage = attack_damage(weapon.item) + sharpness_bonus(enchantment::level(weapon, SHARPNESS));
+        health.0 = (health.0 - damage).max(0.0);
+    }
+}
+
+fn nearest_target(from: DVec3, layer: Entity, players: &ExplosionTargets) -> Option<DVec3> {
+    players
+        .iter()
+        .filter(|(_, _, mode, player_layer)| {
+            player_layer.0 == layer && matches!(mode, GameMode::Survival | GameMode::Adventure)
+        })
+        .map(|(pos, ..)| pos.0)
+        .filter(|pos| pos.distance(from) <= WITHER_TARGET_RANGE)
+        .min_by(|a, b| a.distance_squared(from).total_cmp(&b.distance_squared(from)))
+}
+
+fn fire_projectile(commands: &mut Commands, kind: BossKind, layer: EntityLayerId, from: DVec3, target: DVec3) {
+    let velocity = (target + DVec3::new(0.0, 1.0, 0.0) - from).normalize_or_zero() * SKULL_SPEED;
+    let projectile = BossProjectile {
+        velocity,
+        power: SKULL_EXPLOSION,
+        ticks_left: SKULL_LIFETIME_TICKS,
+    };
+    // Valence velocities are in blocks per second.
+    let sent_velocity = Velocity((velocity * 20.0).as_vec3());
+    match kind {
+        BossKind::Wither => commands.spawn((
+            WitherSkullEntityBundle {
+                layer,
+                position: Position(from),
+                velocity: sent_velocity,
+                ..Default::default()
+            },
+            projectile,
+        )),
+        BossKind::EnderDragon => commands.spawn((
+            DragonFireballEntityBundle {
+                layer,
+                position: Position(from),
+                velocity: sent_velocity,
+                ..Default::default()
+            },
+            projectile,
+        )),
+    };
+}
+
+// Phase changes and attacks
+pub fn tick_bosses(
+    mut commands: Commands,
+    mut layers: Query<&mut ChunkLayer>,
+    mut bosses: Query<(&mut Boss, &Position, &Health, &EntityLayerId), Without<Client>>,
+    mut targets: ExplosionTargets,
+    mut world_events: EventWriter<WorldEvent>,
+    mut pending: ResMut<PendingBlocks>,
+) {
+    for (mut boss, pos, health, layer_id) in &mut bosses {
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+        match boss.phase {
+            BossPhase::Spawning { ticks_left } if ticks_left > 1 => {
+                boss.phase = BossPhase::Spawning {
+                    ticks_left: ticks_left - 1,
+                };
+                continue;
+            }
+            BossPhase::Spawning { .. } => {
+                // Finishing the charge-up clears out the area around it.
+                explode(&mut layer, layer_id.0, pos.0, WITHER_SPAWN_EXPLOSION, &mut targets, &mut world_events, &mut pending);
+                boss.phase = BossPhase::Ranged;
+            }
+            BossPhase::Ranged if health.0 <= boss.max_health / 2.0 => {
+                boss.phase = BossPhase::Enraged;
+                let sound = match boss.kind {
+                    BossKind::Wither => Sound::EntityWitherAmbient,
+                    BossKind::EnderDragon => Sound::EntityEnderDragonGrowl,
+                };
+                layer.play_sound(sound, SoundCategory::Hostile, pos.0, 4.0, 0.5);
+            }
+            _ => {}
+        }
+
+        if boss.attack_cooldown > 0 {
+            boss.attack_cooldown -= 1;
+            continue;
+        }
+        boss.attack_cooldown = boss.attack_interval();
+
+        let head = pos.0 + DVec3::new(0.0, 3.0, 0.0);
+        let Some(target) = nearest_target(head, layer_id.0, &targets) else {
+            continue;
+        };
+        let volley = if boss.phase == BossPhase::Enraged { 3 } else { 1 };
+        for i in 0..volley {
+            let spread = DVec3::new((i as f64 - 1.0) * (volley - 1) as f64, 0.0, 0.0);
+            fire_projectile(&mut commands, boss.kind, *layer_id, head, target + spread);
+        }
+        let sound = match boss.kind {
+            BossKind::Wither => Sound::EntityWitherShoot,
+            BossKind::EnderDragon => Sound::EntityEnderDragonShoot,
+        };
+        layer.play_sound(sound, SoundCategory::Hostile, head, 1.0, 1.0);
+
+        // An enraged wither occasionally blows up whatever it's standing on.
+        if boss.kind == BossKind::Wither
+            && boss.phase == BossPhase::Enraged
+            && valence::rand::random::<f32>() < 0.1
+        {
+            explode(&mut layer, layer_id.0, pos.0, 2.0, &mut targets, &mut world_events, &mut pending);
+        }
+    }
+}
+
+// Moves boss projectiles and blows them up when they hit something
+pub fn tick_boss_projectiles(
+    mut commands: Commands,
+    mut layers: Query<&mut ChunkLayer>,
+    mut projectiles: Query<(Entity, &mut Position, &mut BossProjectile, &EntityLayerId), Without<Client>>,
+    mut targets: ExplosionTargets,
+    mut world_events: EventWriter<WorldEvent>,
+    mut pending: ResMut<PendingBlocks>,
+) {
+    for (entity, mut pos, mut projectile, layer_id) in &mut projectiles {
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+        let aabb = Aabb::from_feet(pos.0, PROJECTILE_SIZE, PROJECTILE_SIZE);
+        let (moved, collisions) = sweep(&layer, aabb, projectile.velocity);
+        pos.0 += moved;
+        projectile.ticks_left = projectile.ticks_left.saturating_sub(1);
+
+        let hit_block = collisions.any();
+        let hit_player = targets.iter().any(|(player, _, mode, player_layer)| {
+            player_layer == layer_id
+                && matches!(mode, GameMode::Survival | GameMode::Adventure)
+                && player.0.distance(pos.0) < 1.5
+        });
+        if hit_block || hit_player {
+            explode(&mut layer, layer_id.0, pos.0, projectile.power, &mut targets, &mut world_events, &mut pending);
+            commands.entity(entity).insert(Despawned);
+        } else if projectile.ticks_left == 0 {
+            commands.entity(entity).insert(Despawned);
+        }
+    }
+}
+
+// Keeps boss bars in sync and hands out loot when a boss dies
+pub fn update_bosses(
+    mut commands: Commands,
+    bosses: Query<(Entity, &Boss, &Health, &Position, &EntityLayerId), Changed<Health>>,
+    mut bars: Query<&mut BossBarHealth>,
+    mut clients: Query<&mut Client>,
+    mut defeated: EventWriter<BossDefeatedEvent>,
+) {
+    for (entity, boss, health, pos, layer) in &bosses {
+        if let Ok(mut bar) = bars.get_mut(boss.bar) {
+            bar.0 = (health.0 / boss.max_health).clamp(0.0, 1.0);
+        }
+        if health.0 > 0.0 {
+            continue;
+        }
+
+        if boss.kind == BossKind::Wither {
+            commands.spawn(ItemEntityBundle {
+                layer: *layer,
+                item_stack: Stack(ItemStack::new(ItemKind::NetherStar, 1, None)),
+                position: *pos,
+                velocity: Velocity(Vec3::new(0.0, 4.0, 0.0)),
+                ..Default::default()
+            });
+        }
+        commands.entity(boss.bar).insert(Despawned);
+        commands.entity(entity).insert(Despawned);
+        defeated.send(BossDefeatedEvent {
+            kind: boss.kind,
+            position: pos.0,
+            layer: layer.0,
+        });
+
+        let name = boss.kind.name();
+        info!("[boss] {name} was defeated");
+        for mut client in &mut clients {
+            client.send_chat_message(new_crystal_message(format!("The {name} has been defeated!")));
+        }
+    }
+}
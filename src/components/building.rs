@@ -1,44 +1,167 @@
-use valence::{entity::{item::{ItemEntityBundle, Stack}, Velocity}, interact_block::InteractBlockEvent, inventory::HeldItem, prelude::*};
+use valence::{entity::{item::{ItemEntityBundle, Stack}, Velocity}, inventory::HeldItem, op_level::OpLevel, prelude::*};
+
+use super::attempts::{Attempts, BlockBreakAttempt, BlockPlaceAttempt};
+use super::beacon::ActiveEffects;
+use super::block_rules::{revert_block, BlockRules};
+use super::experience::{block_experience, spawn_experience};
+use super::hud::HudMessage;
+use super::loot::{LootContext, LootTables};
+use super::mining::{break_ticks, can_harvest, finished_in_time, haste, DigStart};
+use crate::world::events::{WorldEvent, WorldEventKind};
+use crate::world::physics::PhysicsBody;
+use crate::world::BEDROCK_LAYERS;
+
+/// Keeps players from breaking the bedrock floor and from breaking or placing
+/// what the block rules don't allow.
+pub fn protect_blocks(
+    clients: Query<&OpLevel>,
+    layers: Query<&ChunkLayer>,
+    mut breaks: ResMut<Attempts<BlockBreakAttempt>>,
+    mut places: ResMut<Attempts<BlockPlaceAttempt>>,
+    rules: Res<BlockRules>,
+) {
+    for attempt in breaks.pending_mut() {
+        let (Ok(op_level), Ok(layer)) = (clients.get(attempt.event.player), layers.get(attempt.event.layer)) else {
+            continue;
+        };
+        let Some(block) = layer.block(attempt.event.pos) else {
+            continue;
+        };
+        let kind = block.state.to_kind();
+        // Not even creative players can dig through the bottom of the world
+        if kind == BlockKind::Bedrock && attempt.event.pos.y < layer.min_y() + BEDROCK_LAYERS {
+            attempt.cancel("You can't break the bedrock floor");
+        } else if !rules.can_break(op_level, kind) {
+            attempt.cancel(format!("You can't break {} here", kind.to_str()));
+        }
+    }
+    for attempt in places.pending_mut() {
+        let Ok(op_level) = clients.get(attempt.event.player) else {
+            continue;
+        };
+        if !rules.can_place(op_level, attempt.event.item) {
+            attempt.cancel(format!("You can't use {} here", attempt.event.item.to_str()));
+        }
+    }
+}
 
 pub fn digging(
     mut commands: Commands,
-    mut clients: Query<(&GameMode, &mut Client)>,
+    mut clients: Query<(
+        &mut Client,
+        &GameMode,
+        &Inventory,
+        &HeldItem,
+        Option<&DigStart>,
+        Option<&ActiveEffects>,
+    )>,
     mut layers: Query<&mut ChunkLayer>,
-    mut events: EventReader<DiggingEvent>,
-    entity_layers: Query<&EntityLayerId>
+    mut attempts: ResMut<Attempts<BlockBreakAttempt>>,
+    entity_layers: Query<&EntityLayerId>,
+    mut world_events: EventWriter<WorldEvent>,
+    mut hud_messages: EventWriter<HudMessage>,
+    loot: Res<LootTables>,
+    server: Res<Server>,
 ) {
-    // NOTE: use `layers.get(event.client)` inside [1] when adding other chunk layers
-    let mut layer = layers.single_mut();
-
-    for event in events.read() {
-        let Ok((game_mode, mut client)) = clients.get_mut(event.client) else {
+    for attempt in attempts.pending_mut() {
+        let event = attempt.event;
+        let Ok((mut client, game_mode, inventory, held_item, dig_start, effects)) = clients.get_mut(event.player) else {
+            continue;
+        };
+        // dig in whichever dimension the player is in
+        let Ok(mut layer) = layers.get_mut(event.layer) else {
             continue;
         };
 
-        // [1]
-        let entity_layer = entity_layers.get(event.client);
+        let entity_layer = entity_layers.get(event.player);
+        let tool = inventory.slot(held_item.slot());
+        let now = server.current_tick();
 
-        if (*game_mode == GameMode::Creative && event.state == DiggingState::Start)
-            || (*game_mode == GameMode::Survival && event.state == DiggingState::Stop)
-        {
-            let blockkind = layer.block(event.position).expect("digging... nothing??").state.to_kind();
-            
-            layer.set_block(event.position, BlockState::AIR);
-            if let Ok(entity_layer) = entity_layer && *game_mode == GameMode::Survival {
-                commands.spawn(ItemEntityBundle {
-                    layer: *entity_layer,
-                    item_stack: Stack(ItemStack::new(blockkind.to_item_kind(), 1, None)),
-                    position: Position(DVec3::new(
-                        event.position.x as f64 + 0.5,
-                        event.position.y as f64,
-                        event.position.z as f64 + 0.5
-                    )),
-                    velocity: Velocity(Vec3::new(0.0, 1.2, 0.0)),
-                    ..Default::default()
+        let breaks = match (*game_mode, event.state) {
+            (GameMode::Creative, DiggingState::Start) => true,
+            (GameMode::Survival, DiggingState::Start) => {
+                commands.entity(event.player).insert(DigStart {
+                    pos: event.pos,
+                    tick: now,
                 });
-            } else if let Err(ref error) = entity_layer {
-                client.send_action_bar_message(format!("failed to spawn item. {}", error).color(Color::RED));
+                // The client doesn't send a stop for blocks it breaks instantly
+                layer.block(event.pos).is_some_and(|block| {
+                    break_ticks(block.state, tool, haste(effects, now)) == Some(0)
+                })
+            }
+            (GameMode::Survival, DiggingState::Stop) => {
+                let ticks = layer
+                    .block(event.pos)
+                    .and_then(|block| break_ticks(block.state, tool, haste(effects, now)));
+                let in_time = match (ticks, dig_start) {
+                    (Some(ticks), Some(start)) => finished_in_time(*start, event.pos, now, ticks),
+                    _ => false,
+                };
+                if !in_time {
+                    revert_block(&mut client, &layer, event.pos);
+                }
+                in_time
             }
+            _ => false,
+        };
+        if !breaks {
+            continue;
+        }
+
+        let Some(state) = layer.block(event.pos).map(|block| block.state) else {
+            attempt.cancel("That block isn't loaded");
+            continue;
+        };
+        let blockkind = state.to_kind();
+        if blockkind == BlockKind::Air {
+            // already broken by something else
+            continue;
+        }
+
+        layer.set_block(event.pos, BlockState::AIR);
+        world_events.send(WorldEvent::at_block(
+            event.layer,
+            event.pos,
+            Some(event.player),
+            WorldEventKind::BlockBroken { state },
+        ));
+        if let Ok(entity_layer) = entity_layer && *game_mode == GameMode::Survival {
+            // Blocks mined with the wrong tool (or by hand) drop nothing
+            if !can_harvest(state, tool) {
+                continue;
+            }
+            let context = LootContext::with_tool(tool);
+            for stack in loot.block_drops(blockkind, &context) {
+                let velocity = Vec3::new(0.0, 1.2, 0.0);
+                commands.spawn((
+                    ItemEntityBundle {
+                        layer: *entity_layer,
+                        item_stack: Stack(stack),
+                        position: Position(DVec3::new(
+                            event.pos.x as f64 + 0.5,
+                            event.pos.y as f64,
+                            event.pos.z as f64 + 0.5
+                        )),
+                        velocity: Velocity(velocity),
+                        ..Default::default()
+                    },
+                    PhysicsBody::item(velocity),
+                ));
+            }
+            let xp = if context.silk_touch { 0 } else { block_experience(blockkind) };
+            if xp > 0 {
+                let center = DVec3::new(
+                    event.pos.x as f64 + 0.5,
+                    event.pos.y as f64 + 0.5,
+                    event.pos.z as f64 + 0.5,
+                );
+                spawn_experience(&mut commands, *entity_layer, center, xp);
+            }
+        } else if let Err(ref error) = entity_layer {
+            hud_messages.send(HudMessage::warning(
+                event.player,
+                format!("failed to spawn item. {}", error).color(Color::RED),
+            ));
         }
     }
 }
@@ -46,25 +169,23 @@ pub fn digging(
 pub fn place_blocks(
     mut clients: Query<(&mut Inventory, &GameMode, &HeldItem)>,
     mut layers: Query<&mut ChunkLayer>,
-    mut events: EventReader<InteractBlockEvent>,
+    attempts: Res<Attempts<BlockPlaceAttempt>>,
+    mut world_events: EventWriter<WorldEvent>,
 ) {
-    let mut layer = layers.single_mut();
-
-    for event in events.read() {
-        let Ok((mut inventory, game_mode, held)) = clients.get_mut(event.client) else {
+    for event in attempts.pending() {
+        let Ok((mut inventory, game_mode, held)) = clients.get_mut(event.player) else {
             continue;
         };
-        if event.hand != Hand::Main {
+        let Ok(mut layer) = layers.get_mut(event.layer) else {
             continue;
-        }
+        };
 
-        // get the held item
+        // get the held item, which may have changed since the attempt
         let slot_id = held.slot();
         let stack = inventory.slot(slot_id);
-        if stack.is_empty() {
-            // no item in the slot
+        if stack.item != event.item {
             continue;
-        };
+        }
 
         let Some(block_kind) = BlockKind::from_item_kind(stack.item) else {
             // can't place this item as a block
@@ -81,7 +202,6 @@ pub fn place_blocks(
                 inventory.set_slot(slot_id, ItemStack::EMPTY);
             }
         }
-        let real_pos = event.position.get_in_direction(event.face);
         let state = block_kind.to_state().set(
             PropName::Axis,
             match event.face {
@@ -90,6 +210,12 @@ pub fn place_blocks(
                 Direction::West | Direction::East => PropValue::X,
             },
         );
-        layer.set_block(real_pos, state);
+        layer.set_block(event.pos, state);
+        world_events.send(WorldEvent::at_block(
+            event.layer,
+            event.pos,
+            Some(event.player),
+            WorldEventKind::BlockPlaced { state },
+        ));
     }
 }
\ No newline at end of file
@@ -1,13 +1,108 @@
-use valence::{client::Client, message::ChatMessageEvent, prelude::EventReader, prelude::*};
+use serde::{Deserialize, Serialize};
+use tracing::{info, info_span, warn};
+use valence::{client::Client, command::CommandExecutionEvent, prelude::*};
 
-pub fn chat_message_event(mut events: EventReader<ChatMessageEvent>, mut clients: Query<(&mut Client, &Username)>) {
-    for event in events.read() {
-        let username = clients.get(event.client).unwrap().1.clone();
+use super::attempts::{Attempts, ChatAttempt};
+use super::client_settings::ClientPreferences;
+use super::logging::CHAT;
+use super::playerdata::PlayerData;
+
+/// Where a player's chat goes when they don't use a command.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatChannel {
+    #[default]
+    Global,
+    Party,
+    Team,
+}
+
+impl ChatChannel {
+    pub const ALL: [ChatChannel; 3] = [ChatChannel::Global, ChatChannel::Party, ChatChannel::Team];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChatChannel::Global => "global",
+            ChatChannel::Party => "party",
+            ChatChannel::Team => "team",
+        }
+    }
+
+    /// The command that sends to this channel, if it isn't global.
+    fn command(self) -> Option<&'static str> {
+        match self {
+            ChatChannel::Global => None,
+            ChatChannel::Party => Some("party chat"),
+            ChatChannel::Team => Some("team chat"),
+        }
+    }
+}
+
+pub fn chat_message_event(
+    attempts: Res<Attempts<ChatAttempt>>,
+    mut clients: Query<(&mut Client, &Username, Option<&ClientPreferences>)>,
+    channels: Query<&PlayerData>,
+    mut executions: EventWriter<CommandExecutionEvent>,
+) {
+    for event in attempts.pending() {
+        // The sender can disconnect in the same tick they chat
+        let Ok((_, username, _)) = clients.get(event.player) else {
+            warn!(target: CHAT, "dropping a chat message from {:?}, who is no longer connected", event.player);
+            continue;
+        };
+        let username = username.clone();
         let message = event.message.clone();
+        let _span = info_span!(target: CHAT, "chat", player = %username.0).entered();
+        info!(target: CHAT, "{message}");
+        // Party and team chat go through their commands, which know who's in
+        // them and tell the sender if they aren't
+        let channel = channels.get(event.player).map_or(ChatChannel::Global, |data| data.chat_channel);
+        if let Some(command) = channel.command() {
+            executions.send(CommandExecutionEvent { command: format!("{command} {message}"), executor: event.player });
+            continue;
+        }
         let username_text = ("<".to_owned() + &username.0 + "> ").color(Color::AQUA);
 
-        for (mut client, _) in clients.iter_mut() {
+        for (mut client, _, prefs) in clients.iter_mut() {
+            // players who hid chat in their settings don't get other people's messages
+            if prefs.is_some_and(|prefs| !prefs.wants_player_chat()) {
+                continue;
+            }
             client.send_chat_message(username_text.clone() + String::from(message.clone()).color(Color::WHITE));
         }
     }
-}
\ No newline at end of file
+}
+/// Sends `text` to everyone but `player` who hasn't hidden join messages.
+fn announce(clients: &mut Query<(Entity, &mut Client, Option<&PlayerData>)>, player: Entity, text: Text) {
+    for (entity, mut client, data) in clients.iter_mut() {
+        if entity != player && !data.is_some_and(|data| data.hide_join_messages) {
+            client.send_chat_message(text.clone());
+        }
+    }
+}
+
+pub fn announce_joins(
+    joined: Query<(Entity, &Username), Added<Client>>,
+    mut clients: Query<(Entity, &mut Client, Option<&PlayerData>)>,
+) {
+    for (player, username) in &joined {
+        let text = Text::translate("multiplayer.player.joined", [Text::from(username.0.clone())]).color(Color::YELLOW);
+        announce(&mut clients, player, text);
+    }
+}
+
+// The username is still there the tick the client is removed, before the
+// entity is despawned
+pub fn announce_leaves(
+    mut removed: RemovedComponents<Client>,
+    usernames: Query<&Username>,
+    mut clients: Query<(Entity, &mut Client, Option<&PlayerData>)>,
+) {
+    for player in removed.read() {
+        let Ok(username) = usernames.get(player) else {
+            continue;
+        };
+        let text = Text::translate("multiplayer.player.left", [Text::from(username.0.clone())]).color(Color::YELLOW);
+        announce(&mut clients, player, text);
+    }
+}
@@ -0,0 +1,77 @@
+use tracing::debug;
+use valence::{
+    entity::player::PlayerModelParts,
+    event_loop::PacketEvent,
+    protocol::packets::play::{client_settings_c2s::ChatMode, ClientSettingsC2s},
+    prelude::*,
+};
+
+use super::logging::NET;
+
+const DEFAULT_LOCALE: &str = "en_us";
+
+/// What the player picked in their client's options screens. Sent when they
+/// join and again whenever they change something.
+#[derive(Component, Clone, Debug)]
+pub struct ClientPreferences {
+    /// e.g. `en_us`, `de_de`.
+    pub locale: String,
+    pub chat_mode: ChatMode,
+    pub chat_colors: bool,
+}
+
+impl Default for ClientPreferences {
+    fn default() -> Self {
+        Self {
+            locale: DEFAULT_LOCALE.to_owned(),
+            chat_mode: ChatMode::Enabled,
+            chat_colors: true,
+        }
+    }
+}
+
+impl ClientPreferences {
+    /// Language part of the locale (`de` for `de_de`), for picking
+    /// translations.
+    pub fn language(&self) -> &str {
+        self.locale.split('_').next().unwrap_or(DEFAULT_LOCALE)
+    }
+
+    /// Whether other players' chat should be sent to this player. Command
+    /// feedback and system messages are sent regardless.
+    pub fn wants_player_chat(&self) -> bool {
+        matches!(self.chat_mode, ChatMode::Enabled)
+    }
+}
+
+// The first settings packet can arrive in the same tick the client spawns, so
+// preferences are inserted rather than updated in place.
+pub fn handle_client_settings(
+    mut commands: Commands,
+    mut packets: EventReader<PacketEvent>,
+    mut clients: Query<(&Username, Option<&ClientPreferences>, &mut PlayerModelParts)>,
+) {
+    for packet in packets.read() {
+        let Some(pkt) = packet.decode::<ClientSettingsC2s>() else {
+            continue;
+        };
+        let Ok((username, prefs, mut model_parts)) = clients.get_mut(packet.client) else {
+            continue;
+        };
+        let locale = pkt.locale.to_lowercase();
+        if prefs.is_none_or(|prefs| prefs.locale != locale) {
+            debug!(target: NET, player = %username.0, "locale set to {locale}");
+        }
+        commands.entity(packet.client).insert(ClientPreferences {
+            locale,
+            chat_mode: pkt.chat_mode,
+            chat_colors: pkt.chat_colors,
+        });
+
+        // Shows the hat/jacket/sleeve layers the player enabled to everyone else
+        let parts = u8::from(pkt.displayed_skin_parts) as i8;
+        if model_parts.0 != parts {
+            model_parts.0 = parts;
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// src/components/combat.rs
+//
+// Players hitting mobs and each other, and players taking fall damage. Melee
+// damage comes from the held weapon plus Sharpness; every hit knocks a mob
+// away from the attacker, further with Knockback. Players aren't knocked
+// back, since their client moves them. Nothing can be hurt again for a short
+// while after a hit, like vanilla's invulnerability frames.
+//
+// Falls are measured from the highest point since the player last stood on
+// the ground or swam; every block past the third costs half a heart, less
+// with Feather Falling boots.
+//
+// Both are collected as `DamageAttempt`s first and only dealt in the `Apply`
+// stage, so protection and minigames can cancel them. Teams without friendly
+// fire cancel hits between teammates.
+
+use valence::{
+    entity::{
+        living::{Health, LivingEntity},
+        Despawned, EntityLayerId,
+    },
+    interact_entity::{EntityInteraction, InteractEntityEvent},
+    inventory::HeldItem,
+    movement::MovementEvent,
+    prelude::*,
+};
+
+use super::attempts::{Attempts, DamageAttempt, DamageCause};
+use super::boss::{attack_damage, Boss};
+use super::decoration::Decoration;
+use super::enchantment::{self, sharpness_bonus, FEATHER_FALLING, KNOCKBACK, SHARPNESS};
+use super::experience::{mob_experience, spawn_experience};
+use super::loot::{entity_table, LootContext, LootTables};
+use super::trading::Trader;
+use crate::world::physics::PhysicsBody;
+
+/// Ticks a mob can't be hurt for after being hit.
+const HURT_COOLDOWN_TICKS: i64 = 10;
+/// Horizontal knockback speed in blocks per tick, before Knockback.
+const BASE_KNOCKBACK: f64 = 0.4;
+/// Falls up to this many blocks don't hurt.
+const SAFE_FALL_DISTANCE: f64 = 3.0;
+/// Player inventory slot holding boots.
+const BOOTS_SLOT: u16 = 8;
+
+/// The tick a mob or player was last hit.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LastHurt(pub i64);
+
+/// The highest point of the player's current fall, if they're in the air.
+#[derive(Component, Default, Debug)]
+pub struct FallTracker {
+    peak: Option<f64>,
+}
+
+type MobQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Health,
+        &'static Position,
+        &'static EntityKind,
+        &'static EntityLayerId,
+        Option<&'static mut PhysicsBody>,
+        Option<&'static LastHurt>,
+    ),
+    (
+        With<LivingEntity>,
+        Without<Client>,
+        Without<Boss>,
+        Without<Trader>,
+        Without<Decoration>,
+        Without<Despawned>,
+    ),
+>;
+
+// Players hitting mobs and other players with whatever they're holding
+pub fn collect_attacks(
+    mut events: EventReader<InteractEntityEvent>,
+    players: Query<(&Inventory, &HeldItem, &GameMode)>,
+    mobs: MobQuery,
+    victims: Query<(&GameMode, Option<&LastHurt>), With<Client>>,
+    mut attempts: ResMut<Attempts<DamageAttempt>>,
+    server: Res<Server>,
+) {
+    let now = server.current_tick();
+    for event in events.read() {
+        if event.interact != EntityInteraction::Attack {
+            continue;
+        }
+        let Ok((inventory, held, game_mode)) = players.get(event.client) else {
+            continue;
+        };
+        if *game_mode == GameMode::Spectator {
+            continue;
+        }
+        let last_hurt = match (mobs.get(event.entity), victims.get(event.entity)) {
+            (Ok((.., last_hurt)), _) => last_hurt,
+            // Creative and spectating players can't be hurt
+            (_, Ok((GameMode::Survival | GameMode::Adventure, last_hurt))) => last_hurt,
+            _ => continue,
+        };
+        if last_hurt.is_some_and(|last| now - last.0 < HURT_COOLDOWN_TICKS) {
+            continue;
+        }
+        let weapon = inventory.slot(held.slot());
+        attempts.push(DamageAttempt {
+            victim: event.entity,
+            attacker: Some(event.client),
+            amount: attack_damage(weapon.item) + sharpness_bonus(enchantment::level(weapon, SHARPNESS)),
+            cause: DamageCause::Attack,
+        });
+    }
+}
+
+pub fn attack_mobs(
+    mut commands: Commands,
+    attempts: Res<Attempts<DamageAttempt>>,
+    players: Query<(&Inventory, &HeldItem, &Position)>,
+    mut mobs: MobQuery,
+    loot: Res<LootTables>,
+    server: Res<Server>,
+) {
+    let now = server.current_tick();
+    for attempt in attempts.pending().filter(|attempt| attempt.cause == DamageCause::Attack) {
+        let Some(Ok((inventory, held, attacker))) = attempt.attacker.map(|attacker| players.get(attacker)) else {
+            continue;
+        };
+        let Ok((mut health, pos, kind, layer, body, _)) = mobs.get_mut(attempt.victim) else {
+            continue;
+        };
+        let weapon = inventory.slot(held.slot());
+        health.0 -= attempt.amount;
+        commands.entity(attempt.victim).insert(LastHurt(now));
+
+        let offset = pos.0 - attacker.0;
+        let away = DVec3::new(offset.x, 0.0, offset.z).normalize_or_zero();
+        let strength = BASE_KNOCKBACK + 0.5 * enchantment::level(weapon, KNOCKBACK) as f64;
+        let knockback = DVec3::new(away.x * strength, 0.4, away.z * strength);
+        match body {
+            Some(mut body) => body.velocity = body.velocity / 2.0 + knockback,
+            None => {
+                let mut body = PhysicsBody::mob();
+                body.velocity = knockback;
+                commands.entity(attempt.victim).insert(body);
+            }
+        }
+
+        if health.0 <= 0.0 {
+            commands.entity(attempt.victim).insert(Despawned);
+            if let Some(table) = entity_table(*kind) {
+                loot.drop_loot(&mut commands, &table, *layer, BlockPos::from(pos.0), &LootContext::with_tool(weapon));
+            }
+            let xp = mob_experience(*kind);
+            if xp > 0 {
+                spawn_experience(&mut commands, *layer, pos.0, xp);
+            }
+        }
+    }
+}
+
+pub fn attack_players(
+    mut commands: Commands,
+    attempts: Res<Attempts<DamageAttempt>>,
+    mut victims: Query<&mut Health, With<Client>>,
+    server: Res<Server>,
+) {
+    for attempt in attempts.pending().filter(|attempt| attempt.cause == DamageCause::Attack) {
+        if let Ok(mut health) = victims.get_mut(attempt.victim) {
+            health.0 = (health.0 - attempt.amount).max(0.0);
+            commands.entity(attempt.victim).insert(LastHurt(server.current_tick()));
+        }
+    }
+}
+
+pub fn init_fall_trackers(mut commands: Commands, clients: Query<Entity, Added<Client>>) {
+    for entity in &clients {
+        commands.entity(entity).insert(FallTracker::default());
+    }
+}
+
+pub fn fall_damage(
+    mut events: EventReader<MovementEvent>,
+    mut players: Query<(&mut FallTracker, &GameMode, &Inventory, &VisibleChunkLayer)>,
+    layers: Query<&ChunkLayer>,
+    mut attempts: ResMut<Attempts<DamageAttempt>>,
+) {
+    for event in events.read() {
+        let Ok((mut tracker, game_mode, inventory, visible_layer)) = players.get_mut(event.client) else {
+            continue;
+        };
+        let in_liquid = layers
+            .get(visible_layer.0)
+            .ok()
+            .and_then(|layer| layer.block(BlockPos::from(event.position)))
+            .is_some_and(|block| block.state.is_liquid());
+        if matches!(game_mode, GameMode::Creative | GameMode::Spectator) || in_liquid {
+            tracker.peak = None;
+            continue;
+        }
+        if !event.on_ground {
+            let peak = tracker.peak.unwrap_or(event.old_position.y).max(event.position.y);
+            tracker.peak = Some(peak);
+            continue;
+        }
+        let Some(peak) = tracker.peak.take() else {
+            continue;
+        };
+        let distance = peak - event.position.y - SAFE_FALL_DISTANCE;
+        if distance <= 0.0 {
+            continue;
+        }
+        let feather_falling = enchantment::level(inventory.slot(BOOTS_SLOT), FEATHER_FALLING);
+        let damage = (distance.ceil() as f32 * (1.0 - enchantment::feather_falling_reduction(feather_falling))).round();
+        if damage > 0.0 {
+            attempts.push(DamageAttempt {
+                victim: event.client,
+                attacker: None,
+                amount: damage,
+                cause: DamageCause::Fall,
+            });
+        }
+    }
+}
+
+pub fn take_fall_damage(attempts: Res<Attempts<DamageAttempt>>, mut players: Query<&mut Health, With<Client>>) {
+    for attempt in attempts.pending().filter(|attempt| attempt.cause == DamageCause::Fall) {
+        if let Ok(mut health) = players.get_mut(attempt.victim) {
+            health.0 = (health.0 - attempt.amount).max(0.0);
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tracing::info;
+use valence::{
+    command::{scopes::CommandScopes, CommandExecutionEvent, CommandProcessedEvent},
+    event_loop::PacketEvent,
+    nbt::{compound, Compound, Value},
+    op_level::OpLevel,
+    protocol::packets::play::{update_command_block_c2s, UpdateCommandBlockC2s},
+    prelude::*,
+};
+
+use super::config::load_config;
+use super::hud::HudMessage;
+use crate::world::storage::{ChunkRestored, ChunkSaver};
+use crate::world::Overworld;
+
+#[derive(Deserialize)]
+pub struct CommandBlockConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// How often repeating command blocks run.
+    #[serde(default = "default_interval")]
+    pub repeat_interval_ticks: u32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+impl Default for CommandBlockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            repeat_interval_ticks: default_interval(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandBlockMode {
+    Impulse,
+    Repeat,
+    Chain,
+}
+
+impl CommandBlockMode {
+    fn block(self) -> BlockState {
+        match self {
+            CommandBlockMode::Impulse => BlockState::COMMAND_BLOCK,
+            CommandBlockMode::Repeat => BlockState::REPEATING_COMMAND_BLOCK,
+            CommandBlockMode::Chain => BlockState::CHAIN_COMMAND_BLOCK,
+        }
+    }
+
+    fn from_kind(kind: BlockKind) -> Option<Self> {
+        match kind {
+            BlockKind::CommandBlock => Some(CommandBlockMode::Impulse),
+            BlockKind::RepeatingCommandBlock => Some(CommandBlockMode::Repeat),
+            BlockKind::ChainCommandBlock => Some(CommandBlockMode::Chain),
+            _ => None,
+        }
+    }
+}
+
+pub struct CommandBlock {
+    pub command: String,
+    pub mode: CommandBlockMode,
+    /// "Always active" in the UI. Without redstone this is the only way a
+    /// command block can trigger.
+    pub auto: bool,
+    /// Only runs if the command block behind it succeeded.
+    pub conditional: bool,
+    /// Impulse blocks only fire once per activation.
+    fired: bool,
+    /// Whether its last command went through.
+    succeeded: bool,
+}
+
+impl CommandBlock {
+    /// Reads a command block back from its block entity.
+    fn load(state: BlockState, nbt: &Compound) -> Option<Self> {
+        let mode = CommandBlockMode::from_kind(state.to_kind())?;
+        let Some(Value::String(command)) = nbt.get("Command") else {
+            return None;
+        };
+        Some(Self {
+            command: command.clone(),
+            mode,
+            auto: matches!(nbt.get("auto"), Some(Value::Byte(1))),
+            conditional: state.get(PropName::Conditional) == Some(PropValue::True),
+            // Like vanilla, impulse blocks don't fire again just because
+            // their chunk was loaded
+            fired: true,
+            succeeded: false,
+        })
+    }
+}
+
+/// A command a block sent, waiting to hear whether it went through.
+struct SentCommand {
+    pos: BlockPos,
+    command: String,
+    /// The chain continues into a conditional block once it's known.
+    resume_chain: bool,
+}
+
+/// Marks the entity that command blocks execute commands as.
+#[derive(Component)]
+pub struct CommandBlockExecutor;
+
+#[derive(Resource)]
+pub struct CommandBlocks {
+    pub blocks: HashMap<BlockPos, CommandBlock>,
+    pub executor: Entity,
+    pub config: CommandBlockConfig,
+    /// Commands sent last tick. The command system handles them before the
+    /// next tick's `run_command_blocks`, which is when their success is known.
+    sent: Vec<SentCommand>,
+}
+
+pub fn setup_command_blocks(mut commands: Commands) {
+    let config: CommandBlockConfig = load_config("command_blocks.json");
+    let mut scopes = CommandScopes::new();
+    scopes.add("crystal.admin");
+    let executor = commands.spawn((CommandBlockExecutor, scopes)).id();
+    commands.insert_resource(CommandBlocks {
+        blocks: HashMap::new(),
+        executor,
+        config,
+        sent: Vec::new(),
+    });
+}
+
+// Applies edits made in the command block screen
+pub fn update_command_blocks(
+    mut packets: EventReader<PacketEvent>,
+    mut blocks: ResMut<CommandBlocks>,
+    mut layers: Query<&mut ChunkLayer, With<Overworld>>,
+    clients: Query<(&GameMode, &OpLevel)>,
+    mut hud_messages: EventWriter<HudMessage>,
+    mut saver: ResMut<ChunkSaver>,
+) {
+    let Ok(mut layer) = layers.get_single_mut() else {
+        return;
+    };
+
+    for packet in packets.read() {
+        let Some(pkt) = packet.decode::<UpdateCommandBlockC2s>() else {
+            continue;
+        };
+        let Ok((game_mode, op_level)) = clients.get(packet.client) else {
+            continue;
+        };
+        // Same requirements as vanilla for editing command blocks.
+        if *game_mode != GameMode::Creative || op_level.get() < 2 {
+            hud_messages.send(HudMessage::warning(packet.client, "You can't edit command blocks".color(Color::RED)));
+            continue;
+        }
+        if !blocks.config.enabled {
+            hud_messages.send(HudMessage::warning(packet.client, "Command blocks are disabled".color(Color::RED)));
+            continue;
+        }
+        let Some(current) = layer.block(pkt.position).map(|b| b.state) else {
+            continue;
+        };
+        if CommandBlockMode::from_kind(current.to_kind()).is_none() {
+            continue;
+        }
+
+        let mode = match pkt.mode {
+            update_command_block_c2s::Mode::Sequence => CommandBlockMode::Chain,
+            update_command_block_c2s::Mode::Auto => CommandBlockMode::Repeat,
+            update_command_block_c2s::Mode::Redstone => CommandBlockMode::Impulse,
+        };
+        let command = pkt.command.trim_start_matches('/').to_owned();
+        let auto = pkt.flags.automatic();
+        let conditional = pkt.flags.conditional();
+
+        // Keep facing, swap the block for the selected mode and store the
+        // command in the block entity so it stays with the chunk.
+        let conditional_value = if conditional { PropValue::True } else { PropValue::False };
+        let mut state = mode.block().set(PropName::Conditional, conditional_value);
+        if let Some(facing) = current.get(PropName::Facing) {
+            state = state.set(PropName::Facing, facing);
+        }
+        layer.set_block(
+            pkt.position,
+            Block::new(
+                state,
+                Some(compound! {
+                    "Command" => command.clone(),
+                    "auto" => auto as i8,
+                    "TrackOutput" => pkt.flags.track_output() as i8,
+                }),
+            ),
+        );
+        saver.mark_block_dirty(pkt.position);
+
+        info!("[command_block] {:?} set to {mode:?} `{command}`", pkt.position);
+        hud_messages.send(HudMessage::new(
+            packet.client,
+            "Command set: ".color(Color::GOLD) + command.clone().color(Color::WHITE),
+        ));
+        blocks.blocks.insert(
+            pkt.position,
+            CommandBlock {
+                command,
+                mode,
+                auto,
+                conditional,
+                fired: false,
+                succeeded: false,
+            },
+        );
+    }
+}
+
+// Picks up command blocks in chunks loaded from disk
+pub fn restore_command_blocks(
+    mut events: EventReader<ChunkRestored>,
+    mut blocks: ResMut<CommandBlocks>,
+    layers: Query<&ChunkLayer, With<Overworld>>,
+) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        for pos in &event.block_entities {
+            let Some(block) = layer.block(*pos) else {
+                continue;
+            };
+            if let Some(command_block) = block.nbt.and_then(|nbt| CommandBlock::load(block.state, nbt)) {
+                blocks.blocks.entry(*pos).or_insert(command_block);
+            }
+        }
+    }
+}
+
+/// The command block a chain continues into from `pos`, if any.
+fn next_in_chain(layer: &ChunkLayer, blocks: &HashMap<BlockPos, CommandBlock>, pos: BlockPos) -> Option<BlockPos> {
+    let [x, y, z] = layer
+        .block(pos)
+        .and_then(|b| b.state.get(PropName::Facing))
+        .and_then(facing_offset)?;
+    let next = BlockPos::new(pos.x + x, pos.y + y, pos.z + z);
+    blocks
+        .get(&next)
+        .is_some_and(|block| block.mode == CommandBlockMode::Chain)
+        .then_some(next)
+}
+
+/// Whether the command block pointing into `pos` succeeded last time it ran.
+fn behind_succeeded(layer: &ChunkLayer, blocks: &HashMap<BlockPos, CommandBlock>, pos: BlockPos) -> bool {
+    let Some([x, y, z]) = layer
+        .block(pos)
+        .and_then(|b| b.state.get(PropName::Facing))
+        .and_then(facing_offset)
+    else {
+        return false;
+    };
+    blocks
+        .get(&BlockPos::new(pos.x - x, pos.y - y, pos.z - z))
+        .is_some_and(|block| block.succeeded)
+}
+
+// Runs active command blocks and whatever chain blocks they point into.
+// A conditional block in a chain waits a tick for the command before it to
+// be handled, and only runs if it went through.
+pub fn run_command_blocks(
+    mut blocks: ResMut<CommandBlocks>,
+    layers: Query<&ChunkLayer, With<Overworld>>,
+    server: Res<Server>,
+    mut processed: EventReader<CommandProcessedEvent>,
+    mut executions: EventWriter<CommandExecutionEvent>,
+) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    let executor = blocks.executor;
+    let mut went_through: Vec<String> = processed
+        .read()
+        .filter(|event| event.executor == executor)
+        .map(|event| event.command.clone())
+        .collect();
+    if !blocks.config.enabled || blocks.blocks.is_empty() {
+        blocks.sent.clear();
+        return;
+    }
+
+    // Forget command blocks that were broken or replaced.
+    blocks.blocks.retain(|pos, block| {
+        layer
+            .block(*pos)
+            .and_then(|b| CommandBlockMode::from_kind(b.state.to_kind()))
+            .is_some_and(|mode| mode == block.mode)
+    });
+
+    let mut to_run: Vec<BlockPos> = Vec::new();
+    for sent in std::mem::take(&mut blocks.sent) {
+        let succeeded = match went_through.iter().position(|command| *command == sent.command) {
+            Some(index) => {
+                went_through.remove(index);
+                true
+            }
+            None => false,
+        };
+        if let Some(block) = blocks.blocks.get_mut(&sent.pos) {
+            block.succeeded = succeeded;
+        }
+        if sent.resume_chain
+            && succeeded
+            && let Some(next) = next_in_chain(layer, &blocks.blocks, sent.pos)
+        {
+            to_run.push(next);
+        }
+    }
+
+    let interval = blocks.config.repeat_interval_ticks.max(1) as i64;
+    let run_repeating = server.current_tick() % interval == 0;
+    let mut starts: Vec<BlockPos> = Vec::new();
+    for (pos, block) in blocks.blocks.iter_mut() {
+        if !block.auto || block.command.is_empty() {
+            block.fired = false;
+            continue;
+        }
+        match block.mode {
+            CommandBlockMode::Impulse if !block.fired => {
+                block.fired = true;
+                starts.push(*pos);
+            }
+            CommandBlockMode::Repeat if run_repeating => starts.push(*pos),
+            _ => {}
+        }
+    }
+    starts.retain(|pos| !blocks.blocks[pos].conditional || behind_succeeded(layer, &blocks.blocks, *pos));
+    to_run.extend(starts);
+
+    let mut sent = Vec::new();
+    for start in to_run {
+        let mut pos = start;
+        // Chains can loop back on themselves, so cap the length.
+        for _ in 0..blocks.blocks.len() {
+            let Some(block) = blocks.blocks.get(&pos) else {
+                break;
+            };
+            executions.send(CommandExecutionEvent {
+                command: block.command.clone(),
+                executor,
+            });
+            let next = next_in_chain(layer, &blocks.blocks, pos);
+            let waits = next.is_some_and(|next| blocks.blocks[&next].conditional);
+            sent.push(SentCommand {
+                pos,
+                command: block.command.clone(),
+                resume_chain: waits,
+            });
+            match next {
+                Some(next) if !waits => pos = next,
+                _ => break,
+            }
+        }
+    }
+    blocks.sent = sent;
+}
+
+fn facing_offset(facing: PropValue) -> Option<[i32; 3]> {
+    match facing {
+        PropValue::North => Some([0, 0, -1]),
+        PropValue::South => Some([0, 0, 1]),
+        PropValue::East => Some([1, 0, 0]),
+        PropValue::West => Some([-1, 0, 0]),
+        PropValue::Up => Some([0, 1, 0]),
+        PropValue::Down => Some([0, -1, 0]),
+        _ => None,
+    }
+}
@@ -0,0 +1,30 @@
+use std::fs;
+
+use serde::de::DeserializeOwned;
+use tracing::{error, info};
+
+use super::logging::CONFIG;
+
+pub const CONFIG_DIR: &str = "config";
+
+/// Loads `config/<file>` as json, falling back to the default value if the
+/// file doesn't exist or can't be parsed.
+pub fn load_config<T: DeserializeOwned + Default>(file: &str) -> T {
+    let path = format!("{CONFIG_DIR}/{file}");
+    match fs::read_to_string(&path) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(config) => {
+                info!(target: CONFIG, "loaded {path}");
+                config
+            }
+            Err(e) => {
+                error!(target: CONFIG, "failed to parse {path}: {e}, using defaults");
+                T::default()
+            }
+        },
+        Err(_) => {
+            info!(target: CONFIG, "{path} not found, using defaults");
+            T::default()
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// src/components/confirm.rs
+//
+// Confirmation for commands that are hard to undo. The first time one of
+// them runs it only says what it would do and is remembered for the
+// executor; `/confirm` within `CONFIRM_WINDOW` runs the same command again,
+// and this time it goes through. A new dangerous command replaces whatever
+// was waiting, so `/confirm` always runs the last preview its executor saw.
+//
+// Commands opt in by calling `Confirmations::confirmed` with the command line
+// that would run them, before doing anything.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use valence::prelude::*;
+
+pub const CONFIRM_WINDOW: Duration = Duration::from_secs(30);
+
+struct Pending {
+    command: String,
+    expires: Instant,
+}
+
+#[derive(Resource, Default)]
+pub struct Confirmations {
+    pending: HashMap<Entity, Pending>,
+    /// Commands `/confirm` has sent back through, waiting for their handler.
+    confirmed: HashMap<Entity, String>,
+}
+
+impl Confirmations {
+    /// Whether `command` from `executor` was confirmed. If it wasn't, it's
+    /// kept for `/confirm` and the caller should only show a preview.
+    pub fn confirmed(&mut self, executor: Entity, command: &str) -> bool {
+        if self.confirmed.get(&executor).is_some_and(|confirmed| confirmed == command) {
+            self.confirmed.remove(&executor);
+            return true;
+        }
+        let now = Instant::now();
+        self.pending.retain(|_, pending| pending.expires > now);
+        self.pending.insert(
+            executor,
+            Pending {
+                command: command.to_owned(),
+                expires: now + CONFIRM_WINDOW,
+            },
+        );
+        false
+    }
+
+    /// Takes the command `executor` is confirming and lets it through next
+    /// time it runs. `None` if there's nothing waiting or it expired.
+    pub fn confirm(&mut self, executor: Entity) -> Option<String> {
+        let pending = self.pending.remove(&executor)?;
+        if pending.expires <= Instant::now() {
+            return None;
+        }
+        self.confirmed.insert(executor, pending.command.clone());
+        Some(pending.command)
+    }
+}
@@ -1,8 +1,17 @@
 use crossbeam_channel::Receiver;
 use tracing::{error, info};
-use valence::{client::DisconnectClient, command::scopes::CommandScopes, op_level::OpLevel, prelude::*};
+use valence::{
+    client::DisconnectClient,
+    command::{scopes::CommandScopes, CommandExecutionEvent},
+    op_level::OpLevel,
+    prelude::*,
+};
 
-use super::core::set_op_status;
+use super::core::{set_op_status, sudo};
+use super::logging::CONSOLE;
+use super::playerdata::{save_all_player_data, PlayerData};
+use crate::world::storage::ChunkSaver;
+use crate::world::{regression, Overworld};
 
 #[derive(Resource)]
 pub struct ConsoleCommandReceiver {
@@ -18,8 +27,12 @@ pub fn handle_console_command(
     // mut world: ResMut<World>,
     mut commands: Commands,
     mut events: EventReader<ConsoleCommandEvent>,
-    mut clients: Query<(Entity, &mut Client, &mut Username, &mut OpLevel, &mut CommandScopes), With<Client>>
+    mut executions: EventWriter<CommandExecutionEvent>,
+    mut clients: Query<(Entity, &mut Client, &mut Username, &mut OpLevel, &mut CommandScopes), With<Client>>,
     // mut clients: Query<&mut Client>,
+    players: Query<(&UniqueId, &PlayerData)>,
+    overworld: Query<&ChunkLayer, With<Overworld>>,
+    mut saver: Option<ResMut<ChunkSaver>>,
 ) {
     for event in events.read() {
         let cmd = event.raw.trim();
@@ -29,14 +42,20 @@ pub fn handle_console_command(
 
         match name {
             "stop" => {
-                info!("Stopping server...");
+                info!(target: CONSOLE, "Stopping server...");
                 for client in clients.iter() {
                     commands.add(DisconnectClient { client: client.0, reason: "Server closed".into() });
                 }
                 std::process::exit(0);
             },
+            "save-all" => {
+                info!(target: CONSOLE, "[save-all] saving...");
+                let chunks = saver.as_mut().map_or(0, |saver| saver.save_all(overworld.get_single().ok()));
+                let players = save_all_player_data(&players);
+                info!(target: CONSOLE, "[save-all] saved {chunks} chunks and {players} players");
+            },
             "players" => {
-                info!("Online players: {}", clients.iter().count());
+                info!(target: CONSOLE, "Online players: {}", clients.iter().count());
             },
             "op" => {
                 let player_name = args.get(0).unwrap_or(&"");
@@ -46,7 +65,46 @@ pub fn handle_console_command(
                     }
                 }
             },
-            _ => error!("unknown command")
+            "sudo" => {
+                let Some((player_name, command)) = args.split_first().filter(|(_, command)| !command.is_empty()) else {
+                    error!(target: CONSOLE, "usage: sudo <player> <command>");
+                    continue;
+                };
+                let Some((player, username, ..)) = clients.iter().find(|(_, _, username, ..)| username.0 == *player_name) else {
+                    error!(target: CONSOLE, "[sudo] could not find player {player_name}");
+                    continue;
+                };
+                match sudo(&mut executions, (player, username), &command.join(" "), "console") {
+                    Ok(()) => info!(target: CONSOLE, "[sudo] ran /{} as {player_name}", command.join(" ")),
+                    Err(reason) => error!(target: CONSOLE, "[sudo] {reason}"),
+                }
+            },
+            "worldgen" => match args.first().copied() {
+                Some("record") => match regression::record() {
+                    Ok(()) => info!(target: CONSOLE, "[worldgen] recorded generation fingerprints"),
+                    Err(e) => error!(target: CONSOLE, "[worldgen] failed to record fingerprints: {e}"),
+                },
+                Some("verify") => match regression::verify() {
+                    Ok(mismatches) if mismatches.is_empty() => info!(target: CONSOLE, "[worldgen] generation matches the recorded fingerprints"),
+                    Ok(mismatches) => {
+                        for mismatch in mismatches {
+                            error!(target: CONSOLE, "[worldgen] {mismatch}");
+                        }
+                    }
+                    Err(e) => error!(target: CONSOLE, "[worldgen] failed to verify fingerprints: {e}"),
+                },
+                Some("hashes") => match serde_json::to_string_pretty(&regression::test_chunk_hashes()) {
+                    Ok(json) => info!(target: CONSOLE, "[worldgen] src/world/regression_chunks.json:\n{json}"),
+                    Err(e) => error!(target: CONSOLE, "[worldgen] failed to print chunk hashes: {e}"),
+                },
+                Some("bench") => {
+                    for line in regression::bench() {
+                        info!(target: CONSOLE, "[worldgen] {line}");
+                    }
+                }
+                _ => error!(target: CONSOLE, "usage: worldgen <record|verify|hashes|bench>"),
+            },
+            _ => error!(target: CONSOLE, "unknown command")
         }
     }
 }
@@ -0,0 +1,148 @@
+// src/components/consumables.rs
+//
+// Items that get used up: thrown ender pearls and golden apples. Both start
+// from `ItemUseEvent`, so an item on cooldown does nothing.
+//
+// A pearl flies with the server's physics and teleports its thrower to where
+// it lands, if they're still in the same world. Eating takes 32 ticks like
+// vanilla and stops if the player lets go or switches away from the apple.
+// The server doesn't run regeneration, so golden apples heal straight away
+// instead of over time.
+
+use valence::{
+    entity::{ender_pearl::EnderPearlEntityBundle, living::Health, Despawned, EntityLayerId, Velocity},
+    event_loop::PacketEvent,
+    inventory::HeldItem,
+    protocol::{
+        packets::play::{player_action_c2s::PlayerAction, EntityStatusS2c, PlayerActionC2s},
+        WritePacket,
+    },
+    prelude::*,
+};
+
+use super::cooldown::ItemUseEvent;
+use super::decoration::take_one;
+use crate::world::physics::PhysicsBody;
+
+/// How fast pearls leave the hand, in blocks per second.
+const PEARL_SPEED: f32 = 30.0;
+const EYE_HEIGHT: f64 = 1.62;
+const EATING_TICKS: i64 = 32;
+/// Tells the client it's done using its item.
+const FINISH_USING_ITEM: u8 = 9;
+
+/// A pearl in flight and who threw it.
+#[derive(Component)]
+pub struct ThrownPearl {
+    thrower: Entity,
+}
+
+/// Put on a player while they're eating.
+#[derive(Component)]
+pub struct Eating {
+    item: ItemKind,
+    slot: u16,
+    done_at: i64,
+}
+
+/// Health a food gives back, for the foods that are handled.
+fn healing(item: ItemKind) -> Option<f32> {
+    match item {
+        ItemKind::GoldenApple => Some(4.0),
+        ItemKind::EnchantedGoldenApple => Some(20.0),
+        _ => None,
+    }
+}
+
+pub fn use_consumables(
+    mut commands: Commands,
+    mut uses: EventReader<ItemUseEvent>,
+    mut players: Query<(&mut Inventory, &HeldItem, &GameMode, &Position, &Look, &EntityLayerId)>,
+    server: Res<Server>,
+) {
+    for event in uses.read() {
+        let Ok((mut inventory, held, game_mode, pos, look, layer)) = players.get_mut(event.client) else {
+            continue;
+        };
+        let slot = match event.hand {
+            Hand::Main => held.slot(),
+            Hand::Off => 45,
+        };
+        if event.item == ItemKind::EnderPearl {
+            take_one(&mut inventory, slot, *game_mode);
+            let velocity = look.vec() * PEARL_SPEED;
+            commands.spawn((
+                EnderPearlEntityBundle {
+                    layer: *layer,
+                    position: Position(pos.0 + DVec3::new(0.0, EYE_HEIGHT - 0.1, 0.0)),
+                    velocity: Velocity(velocity),
+                    ..Default::default()
+                },
+                PhysicsBody::item(velocity),
+                ThrownPearl { thrower: event.client },
+            ));
+        } else if healing(event.item).is_some() {
+            commands.entity(event.client).insert(Eating {
+                item: event.item,
+                slot,
+                done_at: server.current_tick() + EATING_TICKS,
+            });
+        }
+    }
+}
+
+// Teleports throwers to where their pearls come down
+pub fn land_pearls(
+    mut commands: Commands,
+    pearls: Query<(Entity, &Position, &PhysicsBody, &EntityLayerId, &ThrownPearl), Without<Client>>,
+    mut throwers: Query<(&mut Position, &EntityLayerId), With<Client>>,
+) {
+    for (pearl, pos, body, layer, thrown) in &pearls {
+        // Hitting a wall stops it sideways, so it's landed once it's barely moving
+        if !body.on_ground && body.velocity.length_squared() > 1e-4 {
+            continue;
+        }
+        commands.entity(pearl).insert(Despawned);
+        if let Ok((mut thrower_pos, thrower_layer)) = throwers.get_mut(thrown.thrower)
+            && thrower_layer.0 == layer.0
+        {
+            thrower_pos.set(pos.0);
+        }
+    }
+}
+
+pub fn finish_eating(
+    mut commands: Commands,
+    mut packets: EventReader<PacketEvent>,
+    mut eaters: Query<(Entity, &mut Client, &mut Inventory, &mut Health, &HeldItem, &GameMode, &EntityId, &Eating)>,
+    server: Res<Server>,
+) {
+    for packet in packets.read() {
+        if packet
+            .decode::<PlayerActionC2s>()
+            .is_some_and(|pkt| pkt.action == PlayerAction::ReleaseUseItem)
+            && eaters.contains(packet.client)
+        {
+            commands.entity(packet.client).remove::<Eating>();
+        }
+    }
+
+    let now = server.current_tick();
+    for (entity, mut client, mut inventory, mut health, held, game_mode, entity_id, eating) in &mut eaters {
+        let still_holding = inventory.slot(eating.slot).item == eating.item && (eating.slot == 45 || eating.slot == held.slot());
+        if !still_holding {
+            commands.entity(entity).remove::<Eating>();
+            continue;
+        }
+        if now < eating.done_at {
+            continue;
+        }
+        take_one(&mut inventory, eating.slot, *game_mode);
+        health.0 = (health.0 + healing(eating.item).unwrap_or(0.0)).min(20.0);
+        client.write_packet(&EntityStatusS2c {
+            entity_id: entity_id.get(),
+            entity_status: FINISH_USING_ITEM,
+        });
+        commands.entity(entity).remove::<Eating>();
+    }
+}
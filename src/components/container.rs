@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use valence::{
+    entity::{
+        item::{ItemEntityBundle, Stack},
+        Velocity,
+    },
+    interact_block::InteractBlockEvent,
+    nbt::{compound, Compound, List, Value},
+    prelude::*,
+};
+
+use super::loot::{LootContext, LootTables};
+use crate::world::{in_overworld, Overworld};
+use crate::world::physics::PhysicsBody;
+use crate::world::storage::{ChunkRestored, ChunkSaver};
+
+const CLEANUP_INTERVAL_TICKS: i64 = 20;
+
+/// Which inventory a container block uses, or `None` if it isn't one.
+pub fn container_kind(block: BlockKind) -> Option<InventoryKind> {
+    match block {
+        BlockKind::Chest | BlockKind::TrappedChest | BlockKind::Barrel => Some(InventoryKind::Generic9x3),
+        BlockKind::Hopper => Some(InventoryKind::Hopper),
+        BlockKind::Furnace | BlockKind::BlastFurnace | BlockKind::Smoker => Some(InventoryKind::Furnace),
+        BlockKind::Dispenser | BlockKind::Dropper => Some(InventoryKind::Generic3x3),
+        _ => None,
+    }
+}
+
+fn container_title(block: BlockKind) -> &'static str {
+    match block {
+        BlockKind::Chest | BlockKind::TrappedChest => "Chest",
+        BlockKind::Barrel => "Barrel",
+        BlockKind::Hopper => "Item Hopper",
+        BlockKind::Furnace => "Furnace",
+        BlockKind::BlastFurnace => "Blast Furnace",
+        BlockKind::Smoker => "Smoker",
+        BlockKind::Dispenser => "Dispenser",
+        BlockKind::Dropper => "Dropper",
+        _ => "Container",
+    }
+}
+
+/// Marks the inventory entity backing a container block.
+#[derive(Component)]
+pub struct ContainerBlock {
+    pub pos: BlockPos,
+    pub kind: BlockKind,
+}
+
+/// Loot that's rolled the first time a container is opened or broken, like
+/// the chests in generated structures.
+#[derive(Clone, Debug)]
+pub struct PendingLoot {
+    pub table: String,
+    pub seed: u64,
+}
+
+/// The inventories of every container block that has been placed or opened,
+/// keyed by block position.
+#[derive(Resource, Default)]
+pub struct Containers {
+    pub inventories: HashMap<BlockPos, Entity>,
+    /// Containers that haven't been opened yet and get their items from a
+    /// loot table when they are.
+    pub loot: HashMap<BlockPos, PendingLoot>,
+}
+
+impl Containers {
+    /// Returns the container's inventory entity, spawning an empty one the
+    /// first time the container is used.
+    pub fn get_or_create(&mut self, commands: &mut Commands, pos: BlockPos, block: BlockKind) -> Option<Entity> {
+        if let Some(entity) = self.inventories.get(&pos) {
+            return Some(*entity);
+        }
+        self.create_with(commands, pos, block, [])
+    }
+
+    /// Spawns a container that already holds `items`, replacing whatever
+    /// inventory was at `pos` before.
+    pub fn create_with(
+        &mut self,
+        commands: &mut Commands,
+        pos: BlockPos,
+        block: BlockKind,
+        items: impl IntoIterator<Item = (u16, ItemStack)>,
+    ) -> Option<Entity> {
+        let kind = container_kind(block)?;
+        let mut inventory = Inventory::with_title(kind, container_title(block));
+        for (slot, stack) in items {
+            if slot < inventory.slot_count() {
+                inventory.set_slot(slot, stack);
+            }
+        }
+        let entity = commands.spawn((inventory, ContainerBlock { pos, kind: block })).id();
+        if let Some(old) = self.inventories.insert(pos, entity) {
+            commands.entity(old).despawn();
+        }
+        Some(entity)
+    }
+}
+
+/// A container's contents as its block entity's `Items` list, like vanilla.
+fn items_nbt(inventory: &Inventory) -> Compound {
+    let items = (0..inventory.slot_count())
+        .filter_map(|slot| {
+            let stack = inventory.slot(slot);
+            if stack.is_empty() {
+                return None;
+            }
+            let mut item = compound! {
+                "Slot" => slot as i8,
+                "id" => format!("minecraft:{}", stack.item.to_str()),
+                "Count" => stack.count,
+            };
+            if let Some(tag) = &stack.nbt {
+                item.insert("tag", tag.clone());
+            }
+            Some(item)
+        })
+        .collect();
+    compound! { "Items" => List::Compound(items) }
+}
+
+/// The items in a container block entity's NBT, by slot.
+fn items_from_nbt(nbt: &Compound) -> Vec<(u16, ItemStack)> {
+    let Some(Value::List(List::Compound(items))) = nbt.get("Items") else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let (Some(Value::Byte(slot)), Some(Value::String(id))) = (item.get("Slot"), item.get("id")) else {
+                return None;
+            };
+            let kind = ItemKind::from_str(id.trim_start_matches("minecraft:"))?;
+            let count = match item.get("Count") {
+                Some(Value::Byte(count)) => *count,
+                _ => 1,
+            };
+            let tag = match item.get("tag") {
+                Some(Value::Compound(tag)) => Some(tag.clone()),
+                _ => None,
+            };
+            Some((*slot as u16, ItemStack::new(kind, count, tag)))
+        })
+        .collect()
+}
+
+/// Adds as much of `stack` as fits into the given slots, returning what's left.
+pub fn insert_stack(inventory: &mut Inventory, slots: impl Iterator<Item = u16> + Clone, mut stack: ItemStack) -> ItemStack {
+    let max = stack.item.max_stack();
+    // Top up matching stacks first, then use empty slots.
+    for slot in slots.clone() {
+        let existing = inventory.slot(slot);
+        if existing.item == stack.item && existing.count < max && !existing.is_empty() {
+            let moved = (max - existing.count).min(stack.count);
+            inventory.set_slot_amount(slot, existing.count + moved);
+            stack.count -= moved;
+            if stack.count == 0 {
+                return ItemStack::EMPTY;
+            }
+        }
+    }
+    for slot in slots {
+        if inventory.slot(slot).is_empty() {
+            inventory.set_slot(slot, stack);
+            return ItemStack::EMPTY;
+        }
+    }
+    stack
+}
+
+pub fn drop_stack(commands: &mut Commands, layer: EntityLayerId, pos: BlockPos, stack: ItemStack) {
+    let velocity = Vec3::new(0.0, 1.0, 0.0);
+    commands.spawn((
+        ItemEntityBundle {
+            layer,
+            item_stack: Stack(stack),
+            position: Position(DVec3::new(pos.x as f64 + 0.5, pos.y as f64 + 0.5, pos.z as f64 + 0.5)),
+            velocity: Velocity(velocity),
+            ..Default::default()
+        },
+        PhysicsBody::item(velocity),
+    ));
+}
+
+// Right-clicking a container block opens its inventory
+pub fn open_containers(
+    mut commands: Commands,
+    mut events: EventReader<InteractBlockEvent>,
+    mut containers: ResMut<Containers>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    clients: Query<&VisibleChunkLayer>,
+    loot: Res<LootTables>,
+) {
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if !in_overworld(&clients, event.client, overworld) {
+            continue;
+        }
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Some(block) = layer.block(event.position) else {
+            continue;
+        };
+        let kind = block.state.to_kind();
+        let inventory = match containers.loot.remove(&event.position) {
+            Some(pending) => {
+                let slots = container_kind(kind).map_or(0, |k| k.slot_count() as u16);
+                let items = loot.fill_container(&pending.table, pending.seed, slots);
+                containers.create_with(&mut commands, event.position, kind, items)
+            }
+            None => containers.get_or_create(&mut commands, event.position, kind),
+        };
+        let Some(inventory) = inventory else {
+            continue;
+        };
+        commands.entity(event.client).insert(OpenInventory::new(inventory));
+    }
+}
+
+// Registers containers right after they're placed so hoppers etc. see them
+pub fn register_placed_containers(
+    mut commands: Commands,
+    mut events: EventReader<InteractBlockEvent>,
+    mut containers: ResMut<Containers>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    clients: Query<&VisibleChunkLayer>,
+) {
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if !in_overworld(&clients, event.client, overworld) {
+            continue;
+        }
+        let placed = event.position.get_in_direction(event.face);
+        if let Some(block) = layer.block(placed) {
+            containers.get_or_create(&mut commands, placed, block.state.to_kind());
+        }
+    }
+}
+
+// Drops the contents of containers whose block has been broken
+pub fn remove_broken_containers(
+    mut commands: Commands,
+    server: Res<Server>,
+    mut containers: ResMut<Containers>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    inventories: Query<(&Inventory, &ContainerBlock)>,
+    loot: Res<LootTables>,
+) {
+    if server.current_tick() % CLEANUP_INTERVAL_TICKS != 0 {
+        return;
+    }
+    let Ok((layer_entity, layer)) = layers.get_single() else {
+        return;
+    };
+
+    containers.inventories.retain(|pos, entity| {
+        let Ok((inventory, container)) = inventories.get(*entity) else {
+            return true;
+        };
+        // Chunks that aren't loaded keep their containers.
+        let Some(block) = layer.block(*pos) else {
+            return true;
+        };
+        if block.state.to_kind() == container.kind {
+            return true;
+        }
+        for stack in inventory.slots().filter(|s| !s.is_empty()) {
+            drop_stack(&mut commands, EntityLayerId(layer_entity), *pos, stack.clone());
+        }
+        commands.entity(*entity).despawn();
+        false
+    });
+    // Unopened loot containers still drop their loot when broken
+    containers.loot.retain(|pos, pending| {
+        let Some(block) = layer.block(*pos) else {
+            return true;
+        };
+        if container_kind(block.state.to_kind()).is_some() {
+            return true;
+        }
+        if let Some(table) = loot.get(&pending.table) {
+            for stack in table.roll(pending.seed, &LootContext::default()) {
+                drop_stack(&mut commands, EntityLayerId(layer_entity), *pos, stack);
+            }
+        }
+        false
+    });
+}
+
+// Copies changed container contents into their block entities, so they're
+// saved with the chunk
+pub fn store_container_items(
+    mut layers: Query<&mut ChunkLayer, With<Overworld>>,
+    inventories: Query<(&Inventory, &ContainerBlock), Changed<Inventory>>,
+    mut saver: ResMut<ChunkSaver>,
+) {
+    let Ok(mut layer) = layers.get_single_mut() else {
+        return;
+    };
+    for (inventory, container) in &inventories {
+        let Some(state) = layer.block(container.pos).map(|block| block.state) else {
+            continue;
+        };
+        if state.to_kind() != container.kind {
+            continue;
+        }
+        layer.set_block(container.pos, Block::new(state, Some(items_nbt(inventory))));
+        saver.mark_block_dirty(container.pos);
+    }
+}
+
+// Gives containers in chunks loaded from disk their saved contents back.
+// Ones still in memory from before the chunk unloaded are newer and kept.
+pub fn restore_containers(
+    mut commands: Commands,
+    mut events: EventReader<ChunkRestored>,
+    mut containers: ResMut<Containers>,
+    layers: Query<&ChunkLayer, With<Overworld>>,
+) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        for pos in &event.block_entities {
+            let Some(block) = layer.block(*pos) else {
+                continue;
+            };
+            let kind = block.state.to_kind();
+            if container_kind(kind).is_none() || containers.inventories.contains_key(pos) {
+                continue;
+            }
+            let items = block.nbt.map(items_from_nbt).unwrap_or_default();
+            containers.create_with(&mut commands, *pos, kind, items);
+        }
+    }
+}
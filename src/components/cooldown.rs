@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tracing::error;
+use valence::{
+    interact_item::InteractItemEvent,
+    inventory::HeldItem,
+    protocol::{packets::play::CooldownUpdateS2c, VarInt, WritePacket},
+    prelude::*,
+};
+
+use super::config::load_config;
+
+/// Cooldown lengths in ticks, keyed by item name (e.g. "ender_pearl").
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct CooldownConfig(pub HashMap<String, u32>);
+
+impl Default for CooldownConfig {
+    fn default() -> Self {
+        Self(HashMap::from([
+            ("ender_pearl".to_owned(), 20),
+            ("chorus_fruit".to_owned(), 20),
+            ("golden_apple".to_owned(), 40),
+            ("enchanted_golden_apple".to_owned(), 200),
+        ]))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ItemCooldownRules {
+    pub cooldowns: HashMap<ItemKind, u32>,
+}
+
+/// Per-player cooldowns, storing the tick each item becomes usable again.
+#[derive(Component, Default)]
+pub struct ItemCooldowns {
+    ready_at: HashMap<ItemKind, i64>,
+}
+
+impl ItemCooldowns {
+    pub fn is_cooling_down(&self, item: ItemKind, current_tick: i64) -> bool {
+        self.ready_at.get(&item).is_some_and(|ready| *ready > current_tick)
+    }
+
+    /// Starts a cooldown and shows it on the client's hotbar.
+    pub fn start(&mut self, client: &mut Client, item: ItemKind, ticks: u32, current_tick: i64) {
+        self.ready_at.insert(item, current_tick + ticks as i64);
+        client.write_packet(&CooldownUpdateS2c {
+            item_id: VarInt(item.to_raw() as i32),
+            cooldown_ticks: VarInt(ticks as i32),
+        });
+    }
+}
+
+/// Sent when a player uses an item that isn't on cooldown. Systems that give
+/// items behavior, like throwing pearls and eating in `consumables`, listen to
+/// this instead of `InteractItemEvent` so cooldowns are respected.
+#[derive(Event)]
+pub struct ItemUseEvent {
+    pub client: Entity,
+    pub item: ItemKind,
+    pub hand: Hand,
+}
+
+pub fn setup_item_cooldowns(mut commands: Commands) {
+    let config: CooldownConfig = load_config("cooldowns.json");
+    let mut rules = ItemCooldownRules::default();
+    for (name, ticks) in config.0 {
+        match ItemKind::from_str(name.trim_start_matches("minecraft:")) {
+            Some(kind) => {
+                rules.cooldowns.insert(kind, ticks);
+            }
+            None => error!("[cooldown] unknown item in cooldowns.json: {name}"),
+        }
+    }
+    commands.insert_resource(rules);
+}
+
+pub fn init_clients_cooldowns(mut commands: Commands, clients: Query<Entity, Added<Client>>) {
+    for entity in &clients {
+        commands.entity(entity).insert(ItemCooldowns::default());
+    }
+}
+
+pub fn enforce_item_cooldowns(
+    mut events: EventReader<InteractItemEvent>,
+    mut clients: Query<(&mut Client, &mut ItemCooldowns, &Inventory, &HeldItem)>,
+    rules: Res<ItemCooldownRules>,
+    server: Res<Server>,
+    mut uses: EventWriter<ItemUseEvent>,
+) {
+    let tick = server.current_tick();
+    for event in events.read() {
+        let Ok((mut client, mut cooldowns, inventory, held)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        let slot = match event.hand {
+            Hand::Main => held.slot(),
+            Hand::Off => 45,
+        };
+        let item = inventory.slot(slot).item;
+        if item == ItemKind::Air {
+            continue;
+        }
+        if cooldowns.is_cooling_down(item, tick) {
+            // The client shouldn't send this while the cooldown is displayed,
+            // so it's either lagging or cheating.
+            continue;
+        }
+        if let Some(ticks) = rules.cooldowns.get(&item).filter(|t| **t > 0) {
+            cooldowns.start(&mut client, item, *ticks, tick);
+        }
+        uses.send(ItemUseEvent {
+            client: event.client,
+            item,
+            hand: event.hand,
+        });
+    }
+}
@@ -1,5 +1,12 @@
-use tracing::info;
-use valence::{command::scopes::CommandScopes, op_level::OpLevel, prelude::*};
+use tracing::{info, warn};
+use valence::{
+    command::{scopes::CommandScopes, CommandExecutionEvent},
+    ecs::query::QueryEntityError,
+    op_level::OpLevel,
+    prelude::*,
+};
+
+use super::logging::{AUDIT, COMMANDS};
 
 #[derive(Resource)]
 #[allow(dead_code)]
@@ -13,6 +20,41 @@ pub fn set_op_status(client: &mut Client, username: &Username, which: &mut OpLev
     if level == 4 { client.send_chat_message(new_crystal_message(format!("Made {} a server operator", username.0).color(Color::GREEN))); }
 }
 
+/// Unwraps the lookup of whoever ran a command. They can disconnect between
+/// sending it and the handler running, so instead of panicking the command is
+/// dropped and logged under `what`.
+pub fn executor<T>(lookup: Result<T, QueryEntityError>, what: &str) -> Option<T> {
+    match lookup {
+        Ok(found) => Some(found),
+        Err(e) => {
+            warn!(target: COMMANDS, "[{what}] executor went away before the command ran: {e}");
+            None
+        }
+    }
+}
+
+/// Runs `command` as though `player` typed it, with their permissions, and
+/// audit-logs that `by` did so. Used by `/sudo` and the console's `sudo`.
+/// Returns why not if the command can't be run that way.
+pub fn sudo(
+    executions: &mut EventWriter<CommandExecutionEvent>,
+    player: (Entity, &Username),
+    command: &str,
+    by: &str,
+) -> Result<(), &'static str> {
+    let command = command.trim().trim_start_matches('/');
+    if command.is_empty() {
+        return Err("no command given");
+    }
+    // Nesting sudo would let the audit log lose track of who really ran it.
+    if command.split_ascii_whitespace().next() == Some("sudo") {
+        return Err("can't sudo a sudo");
+    }
+    info!(target: AUDIT, by, player = %player.1.0, "sudo /{command}");
+    executions.send(CommandExecutionEvent { command: command.to_owned(), executor: player.0 });
+    Ok(())
+}
+
 pub fn new_crystal_message(message: Text) -> Text {
     "[Crystal] ".color(Color::RED) + "".color(Color::GOLD) + message
 }
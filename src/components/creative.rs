@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use tracing::{error, info};
+use valence::{inventory::CreativeInventoryActionEvent, prelude::*};
+
+use super::config::load_config;
+use super::hud::HudMessage;
+
+#[derive(Deserialize, Clone)]
+pub struct KitItem {
+    pub item: String,
+    #[serde(default = "default_count")]
+    pub count: i8,
+}
+
+fn default_count() -> i8 {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct CreativeConfig {
+    /// Items creative players can't take from the creative menu.
+    #[serde(default)]
+    pub banned_items: Vec<String>,
+    /// Curated item sets handed out with `/kit <name>`, in slot order.
+    #[serde(default)]
+    pub kits: HashMap<String, Vec<KitItem>>,
+}
+
+impl Default for CreativeConfig {
+    fn default() -> Self {
+        Self {
+            banned_items: [
+                "bedrock",
+                "command_block",
+                "chain_command_block",
+                "repeating_command_block",
+                "command_block_minecart",
+                "barrier",
+                "structure_block",
+                "structure_void",
+                "jigsaw",
+                "debug_stick",
+            ]
+            .into_iter()
+            .map(str::to_owned)
+            .collect(),
+            kits: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct CreativeRules {
+    pub banned_items: HashSet<ItemKind>,
+    pub kits: HashMap<String, Vec<ItemStack>>,
+}
+
+fn parse_item(name: &str) -> Option<ItemKind> {
+    ItemKind::from_str(name.trim_start_matches("minecraft:"))
+}
+
+pub fn setup_creative_rules(mut commands: Commands) {
+    let config: CreativeConfig = load_config("creative.json");
+    let mut rules = CreativeRules::default();
+
+    for name in &config.banned_items {
+        match parse_item(name) {
+            Some(kind) => {
+                rules.banned_items.insert(kind);
+            }
+            None => error!("[creative] unknown banned item: {name}"),
+        }
+    }
+    for (kit, items) in config.kits {
+        let stacks = items
+            .iter()
+            .filter_map(|i| match parse_item(&i.item) {
+                Some(kind) => Some(ItemStack::new(kind, i.count, None)),
+                None => {
+                    error!("[creative] unknown item {} in kit {kit}", i.item);
+                    None
+                }
+            })
+            .collect();
+        rules.kits.insert(kit, stacks);
+    }
+    info!(
+        "[creative] {} banned items, {} kits",
+        rules.banned_items.len(),
+        rules.kits.len()
+    );
+    commands.insert_resource(rules);
+}
+
+// Removes banned items that creative players pulled out of the creative menu
+pub fn filter_creative_items(
+    mut events: EventReader<CreativeInventoryActionEvent>,
+    mut clients: Query<(&mut Inventory, &GameMode)>,
+    rules: Res<CreativeRules>,
+    mut hud_messages: EventWriter<HudMessage>,
+) {
+    for event in events.read() {
+        if !rules.banned_items.contains(&event.clicked_item.item) {
+            continue;
+        }
+        let Ok((mut inventory, game_mode)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        if *game_mode != GameMode::Creative || event.slot < 0 {
+            continue;
+        }
+        inventory.set_slot(event.slot as u16, ItemStack::EMPTY);
+        hud_messages.send(HudMessage::warning(
+            event.client,
+            format!("{:?} is not allowed on this server", event.clicked_item.item).color(Color::RED),
+        ));
+    }
+}
@@ -0,0 +1,798 @@
+// src/components/decoration.rs
+//
+// Item frames, paintings and armor stands. Frames hang on the face they're
+// placed against; right-clicking puts an item in or rotates it, hitting takes
+// the item out and then breaks the frame. Paintings pick a random motif among
+// the largest that fit the wall, and right-clicking one switches to the next
+// motif that fits. Both drop when the block they hang on is broken. Armor
+// stands take armor and held items by right-clicking the part of the stand
+// they go on, and sneak-right-click cycles through a few poses.
+//
+// Everything placed in the overworld is kept in `world/decorations.json` and
+// spawned again on startup.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use valence::{
+    entity::{
+        armor_stand::{self, ArmorStandEntityBundle},
+        item_frame::{self, ItemFrameEntityBundle},
+        painting::{self, PaintingEntityBundle},
+        Despawned, EntityLayerId, EulerAngle, ObjectData, PaintingKind,
+    },
+    equipment::Equipment,
+    interact_block::InteractBlockEvent,
+    interact_entity::{EntityInteraction, InteractEntityEvent},
+    inventory::HeldItem,
+    prelude::*,
+    rand::seq::SliceRandom,
+};
+
+use super::container::drop_stack;
+use super::hud::HudMessage;
+use super::logging::STORAGE;
+use crate::world::events::{WorldEvent, WorldEventKind};
+use crate::world::{in_overworld, Overworld};
+
+pub const DECORATIONS_FILE: &str = "world/decorations.json";
+
+/// Distance from the centre of the block a frame is in to the wall it's on.
+const FRAME_OFFSET: f64 = 0.46875;
+/// Armor stand flag that makes the arms visible.
+const SHOW_ARMS: u8 = 0x04;
+
+// `Equipment` slot indices
+const MAIN_HAND: usize = 0;
+const FEET: usize = 2;
+const LEGS: usize = 3;
+const CHEST: usize = 4;
+const HEAD: usize = 5;
+
+/// Marks an entity to be saved with the world.
+#[derive(Component)]
+pub enum Decoration {
+    ItemFrame { facing: Direction },
+    /// `variant` indexes `PAINTINGS`. Paintings are positioned in their anchor
+    /// block, see `painting_cells`.
+    Painting { facing: Direction, variant: usize },
+    ArmorStand { pose: usize },
+}
+
+/// Set whenever a decoration changes, so they're written out at the end of
+/// the tick.
+#[derive(Resource, Default)]
+pub struct DecorationsDirty(pub bool);
+
+struct StandPose {
+    name: &'static str,
+    /// Head, body, left arm, right arm, left leg, right leg as pitch/yaw/roll.
+    parts: [[f32; 3]; 6],
+}
+
+const POSES: &[StandPose] = &[
+    StandPose {
+        name: "default",
+        parts: [[0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [-10.0, 0.0, -10.0], [-15.0, 0.0, 10.0], [-1.0, 0.0, -1.0], [1.0, 0.0, 1.0]],
+    },
+    StandPose {
+        name: "wave",
+        parts: [[-5.0, 0.0, 0.0], [0.0, 0.0, 0.0], [-10.0, 0.0, -10.0], [-160.0, 0.0, -20.0], [-1.0, 0.0, -1.0], [1.0, 0.0, 1.0]],
+    },
+    StandPose {
+        name: "point",
+        parts: [[0.0, 20.0, 0.0], [0.0, 0.0, 0.0], [-10.0, 0.0, -10.0], [-90.0, 20.0, 0.0], [-1.0, 0.0, -1.0], [1.0, 0.0, 1.0]],
+    },
+    StandPose {
+        name: "salute",
+        parts: [[0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [-10.0, 0.0, -10.0], [-120.0, -40.0, 20.0], [-1.0, 0.0, -1.0], [1.0, 0.0, 1.0]],
+    },
+    StandPose {
+        name: "walk",
+        parts: [[0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [30.0, 0.0, -5.0], [-30.0, 0.0, 5.0], [-25.0, 0.0, 0.0], [25.0, 0.0, 0.0]],
+    },
+];
+
+/// Motifs that can be placed, with their size in blocks.
+const PAINTINGS: &[(PaintingKind, &str, i32, i32)] = &[
+    (PaintingKind::Kebab, "kebab", 1, 1),
+    (PaintingKind::Aztec, "aztec", 1, 1),
+    (PaintingKind::Alban, "alban", 1, 1),
+    (PaintingKind::Aztec2, "aztec2", 1, 1),
+    (PaintingKind::Bomb, "bomb", 1, 1),
+    (PaintingKind::Plant, "plant", 1, 1),
+    (PaintingKind::Wasteland, "wasteland", 1, 1),
+    (PaintingKind::Pool, "pool", 2, 1),
+    (PaintingKind::Courbet, "courbet", 2, 1),
+    (PaintingKind::Sea, "sea", 2, 1),
+    (PaintingKind::Sunset, "sunset", 2, 1),
+    (PaintingKind::Creebet, "creebet", 2, 1),
+    (PaintingKind::Wanderer, "wanderer", 1, 2),
+    (PaintingKind::Graham, "graham", 1, 2),
+    (PaintingKind::Match, "match", 2, 2),
+    (PaintingKind::Bust, "bust", 2, 2),
+    (PaintingKind::Stage, "stage", 2, 2),
+    (PaintingKind::Void, "void", 2, 2),
+    (PaintingKind::SkullAndRoses, "skull_and_roses", 2, 2),
+    (PaintingKind::Wither, "wither", 2, 2),
+    (PaintingKind::Fighters, "fighters", 4, 2),
+    (PaintingKind::Skeleton, "skeleton", 4, 3),
+    (PaintingKind::DonkeyKong, "donkey_kong", 4, 3),
+    (PaintingKind::Pointer, "pointer", 4, 4),
+    (PaintingKind::Pigscene, "pigscene", 4, 4),
+    (PaintingKind::BurningSkull, "burning_skull", 4, 4),
+];
+
+/// An item stack as stored on disk.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct SavedStack {
+    pub item: String,
+    pub count: i8,
+}
+
+impl SavedStack {
+    pub fn of(stack: &ItemStack) -> Option<Self> {
+        (!stack.is_empty()).then(|| Self {
+            item: stack.item.to_str().to_owned(),
+            count: stack.count,
+        })
+    }
+
+    pub fn stack(&self) -> ItemStack {
+        ItemKind::from_str(&self.item).map_or(ItemStack::EMPTY, |kind| ItemStack::new(kind, self.count, None))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SavedFrame {
+    pos: [i32; 3],
+    /// Same numbering as the entity's object data.
+    facing: i32,
+    item: Option<SavedStack>,
+    rotation: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SavedPainting {
+    pos: [i32; 3],
+    facing: i32,
+    variant: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SavedStand {
+    pos: [f64; 3],
+    yaw: f32,
+    pose: usize,
+    /// Main hand, off hand, feet, legs, chest, head.
+    equipment: Vec<Option<SavedStack>>,
+}
+
+/// A set of decorations as written to disk, either the whole world's or the
+/// ones inside a saved structure.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct SavedDecorations {
+    frames: Vec<SavedFrame>,
+    #[serde(default)]
+    paintings: Vec<SavedPainting>,
+    stands: Vec<SavedStand>,
+}
+
+pub type FrameData = (&'static Position, &'static Decoration, &'static item_frame::ItemStack, &'static item_frame::Rotation);
+pub type PaintingData = (&'static Position, &'static Decoration);
+pub type StandData = (&'static Position, &'static Look, &'static Decoration, &'static Equipment);
+
+impl SavedDecorations {
+    /// Copies every decoration whose position `include` accepts.
+    pub fn capture(
+        frames: &Query<FrameData, Without<Despawned>>,
+        paintings: &Query<PaintingData, (With<painting::Variant>, Without<Despawned>)>,
+        stands: &Query<StandData, Without<Despawned>>,
+        include: impl Fn(DVec3) -> bool,
+    ) -> Self {
+        let mut saved = Self::default();
+        for (pos, decoration, item, rotation) in frames {
+            if let Decoration::ItemFrame { facing } = decoration
+                && include(pos.0)
+            {
+                let block = BlockPos::from(pos.0);
+                saved.frames.push(SavedFrame {
+                    pos: [block.x, block.y, block.z],
+                    facing: direction_id(*facing),
+                    item: SavedStack::of(&item.0),
+                    rotation: rotation.0,
+                });
+            }
+        }
+        for (pos, decoration) in paintings {
+            if let Decoration::Painting { facing, variant } = decoration
+                && include(pos.0)
+            {
+                let anchor = BlockPos::from(pos.0);
+                saved.paintings.push(SavedPainting {
+                    pos: [anchor.x, anchor.y, anchor.z],
+                    facing: direction_id(*facing),
+                    variant: PAINTINGS[*variant].1.to_owned(),
+                });
+            }
+        }
+        for (pos, look, decoration, equipment) in stands {
+            if let Decoration::ArmorStand { pose } = decoration
+                && include(pos.0)
+            {
+                saved.stands.push(SavedStand {
+                    pos: pos.0.to_array(),
+                    yaw: look.yaw,
+                    pose: *pose,
+                    equipment: (0..6).map(|slot| SavedStack::of(equipment.slot(slot))).collect(),
+                });
+            }
+        }
+        saved
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len() + self.paintings.len() + self.stands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Moves every decoration by `offset` blocks.
+    pub fn translate(&mut self, [dx, dy, dz]: [i32; 3]) {
+        for pos in self.frames.iter_mut().map(|f| &mut f.pos).chain(self.paintings.iter_mut().map(|p| &mut p.pos)) {
+            *pos = [pos[0] + dx, pos[1] + dy, pos[2] + dz];
+        }
+        for stand in &mut self.stands {
+            stand.pos = [stand.pos[0] + dx as f64, stand.pos[1] + dy as f64, stand.pos[2] + dz as f64];
+        }
+    }
+
+    pub fn spawn(&self, commands: &mut Commands, layer: Entity) {
+        for frame in &self.frames {
+            let item = frame.item.as_ref().map_or(ItemStack::EMPTY, SavedStack::stack);
+            spawn_frame(commands, layer, BlockPos::from(frame.pos), direction_from_id(frame.facing), item, frame.rotation);
+        }
+        for saved_painting in &self.paintings {
+            let Some(variant) = PAINTINGS.iter().position(|(_, name, ..)| *name == saved_painting.variant) else {
+                error!(target: STORAGE, "unknown painting {}", saved_painting.variant);
+                continue;
+            };
+            let facing = direction_from_id(saved_painting.facing);
+            spawn_painting(commands, layer, BlockPos::from(saved_painting.pos), facing, variant);
+        }
+        for stand in &self.stands {
+            let mut equipment = Equipment::default();
+            for (slot, stack) in stand.equipment.iter().enumerate() {
+                if let Some(stack) = stack {
+                    equipment.set_slot(slot, stack.stack());
+                }
+            }
+            spawn_stand(commands, layer, DVec3::from(stand.pos), stand.yaw, stand.pose, equipment);
+        }
+    }
+}
+
+fn direction_id(direction: Direction) -> i32 {
+    match direction {
+        Direction::Down => 0,
+        Direction::Up => 1,
+        Direction::North => 2,
+        Direction::South => 3,
+        Direction::West => 4,
+        Direction::East => 5,
+    }
+}
+
+fn direction_from_id(id: i32) -> Direction {
+    match id {
+        0 => Direction::Down,
+        1 => Direction::Up,
+        2 => Direction::North,
+        3 => Direction::South,
+        4 => Direction::West,
+        _ => Direction::East,
+    }
+}
+
+fn frame_position(block: BlockPos, facing: Direction) -> DVec3 {
+    let [dx, dy, dz] = match facing {
+        Direction::Down => [0, -1, 0],
+        Direction::Up => [0, 1, 0],
+        Direction::North => [0, 0, -1],
+        Direction::South => [0, 0, 1],
+        Direction::West => [-1, 0, 0],
+        Direction::East => [1, 0, 0],
+    };
+    DVec3::new(
+        block.x as f64 + 0.5 - dx as f64 * FRAME_OFFSET,
+        block.y as f64 + 0.5 - dy as f64 * FRAME_OFFSET,
+        block.z as f64 + 0.5 - dz as f64 * FRAME_OFFSET,
+    )
+}
+
+fn counter_clockwise(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::West,
+        Direction::West => Direction::South,
+        Direction::South => Direction::East,
+        Direction::East => Direction::North,
+        vertical => vertical,
+    }
+}
+
+fn offset(pos: BlockPos, direction: Direction, amount: i32) -> BlockPos {
+    let [dx, dy, dz] = match direction {
+        Direction::Down => [0, -1, 0],
+        Direction::Up => [0, 1, 0],
+        Direction::North => [0, 0, -1],
+        Direction::South => [0, 0, 1],
+        Direction::West => [-1, 0, 0],
+        Direction::East => [1, 0, 0],
+    };
+    BlockPos::new(pos.x + dx * amount, pos.y + dy * amount, pos.z + dz * amount)
+}
+
+/// Blocks a painting covers. Like the client, sizes are centred on the anchor
+/// block, with even sizes leaning up and to the painting's left.
+fn painting_cells(anchor: BlockPos, facing: Direction, width: i32, height: i32) -> impl Iterator<Item = BlockPos> {
+    let side = counter_clockwise(facing);
+    (-(height - 1) / 2..=height / 2).flat_map(move |dy| {
+        (-(width - 1) / 2..=width / 2).map(move |dx| offset(offset(anchor, side, dx), Direction::Up, dy))
+    })
+}
+
+/// Blocks hung on by the decoration, as `(block, facing)` cells in front of
+/// them.
+fn decoration_cells(pos: &Position, decoration: &Decoration) -> Vec<(BlockPos, Direction)> {
+    let block = BlockPos::from(pos.0);
+    match *decoration {
+        Decoration::ItemFrame { facing } => vec![(block, facing)],
+        Decoration::Painting { facing, variant } => {
+            let (_, _, width, height) = PAINTINGS[variant];
+            painting_cells(block, facing, width, height).map(|cell| (cell, facing)).collect()
+        }
+        Decoration::ArmorStand { .. } => Vec::new(),
+    }
+}
+
+fn painting_fits(
+    layer: &ChunkLayer,
+    anchor: BlockPos,
+    facing: Direction,
+    variant: usize,
+    occupied: &HashSet<(BlockPos, i32)>,
+) -> bool {
+    let (_, _, width, height) = PAINTINGS[variant];
+    painting_cells(anchor, facing, width, height).all(|cell| {
+        layer.block(cell).is_some_and(|b| b.state.is_air())
+            && layer
+                .block(offset(cell, facing, -1))
+                .is_some_and(|b| b.state.blocks_motion())
+            && !occupied.contains(&(cell, direction_id(facing)))
+    })
+}
+
+fn occupied_cells<'a>(decorations: impl Iterator<Item = (&'a Position, &'a Decoration)>) -> HashSet<(BlockPos, i32)> {
+    decorations
+        .flat_map(|(pos, decoration)| decoration_cells(pos, decoration))
+        .map(|(cell, facing)| (cell, direction_id(facing)))
+        .collect()
+}
+
+fn spawn_painting(commands: &mut Commands, layer: Entity, anchor: BlockPos, facing: Direction, variant: usize) {
+    commands.spawn((
+        PaintingEntityBundle {
+            layer: EntityLayerId(layer),
+            position: Position(frame_position(anchor, facing)),
+            object_data: ObjectData(direction_id(facing)),
+            painting_variant: painting::Variant(PAINTINGS[variant].0),
+            ..Default::default()
+        },
+        Decoration::Painting { facing, variant },
+    ));
+}
+
+fn spawn_frame(commands: &mut Commands, layer: Entity, block: BlockPos, facing: Direction, item: ItemStack, rotation: i32) {
+    commands.spawn((
+        ItemFrameEntityBundle {
+            layer: EntityLayerId(layer),
+            position: Position(frame_position(block, facing)),
+            object_data: ObjectData(direction_id(facing)),
+            item_frame_item_stack: item_frame::ItemStack(item),
+            item_frame_rotation: item_frame::Rotation(rotation),
+            ..Default::default()
+        },
+        Decoration::ItemFrame { facing },
+    ));
+}
+
+fn angle([pitch, yaw, roll]: [f32; 3]) -> EulerAngle {
+    EulerAngle { pitch, yaw, roll }
+}
+
+fn spawn_stand(commands: &mut Commands, layer: Entity, pos: DVec3, yaw: f32, pose: usize, equipment: Equipment) {
+    let parts = POSES[pose % POSES.len()].parts;
+    commands.spawn((
+        ArmorStandEntityBundle {
+            layer: EntityLayerId(layer),
+            position: Position(pos),
+            look: Look::new(yaw, 0.0),
+            head_yaw: HeadYaw(yaw),
+            armor_stand_armor_stand_flags: armor_stand::ArmorStandFlags(SHOW_ARMS),
+            armor_stand_head_rotation: armor_stand::HeadRotation(angle(parts[0])),
+            armor_stand_body_rotation: armor_stand::BodyRotation(angle(parts[1])),
+            armor_stand_left_arm_rotation: armor_stand::LeftArmRotation(angle(parts[2])),
+            armor_stand_right_arm_rotation: armor_stand::RightArmRotation(angle(parts[3])),
+            armor_stand_left_leg_rotation: armor_stand::LeftLegRotation(angle(parts[4])),
+            armor_stand_right_leg_rotation: armor_stand::RightLegRotation(angle(parts[5])),
+            ..Default::default()
+        },
+        equipment,
+        Decoration::ArmorStand { pose },
+    ));
+}
+
+/// Which equipment slot an item goes in on an armor stand.
+fn stand_slot(item: ItemKind) -> usize {
+    let name = item.to_str();
+    if name.ends_with("_helmet") || name.ends_with("_head") || name.ends_with("_skull") || item == ItemKind::CarvedPumpkin {
+        HEAD
+    } else if name.ends_with("_chestplate") || item == ItemKind::Elytra {
+        CHEST
+    } else if name.ends_with("_leggings") {
+        LEGS
+    } else if name.ends_with("_boots") {
+        FEET
+    } else {
+        MAIN_HAND
+    }
+}
+
+/// The slot for the part of the stand that was clicked, `y` being the height
+/// above its feet. Same split as vanilla.
+fn clicked_slot(y: f32) -> usize {
+    match y {
+        y if y >= 1.6 => HEAD,
+        y if y >= 0.9 => CHEST,
+        y if y >= 0.4 => LEGS,
+        _ => FEET,
+    }
+}
+
+pub fn take_one(inventory: &mut Inventory, slot: u16, game_mode: GameMode) -> ItemStack {
+    let stack = inventory.slot(slot).clone();
+    if game_mode == GameMode::Survival {
+        if stack.count > 1 {
+            inventory.set_slot_amount(slot, stack.count - 1);
+        } else {
+            inventory.set_slot(slot, ItemStack::EMPTY);
+        }
+    }
+    stack.with_count(1)
+}
+
+pub fn load_decorations(mut commands: Commands, layers: Query<Entity, Added<Overworld>>) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    let Ok(contents) = fs::read_to_string(DECORATIONS_FILE) else {
+        return;
+    };
+    let saved: SavedDecorations = match serde_json::from_str(&contents) {
+        Ok(saved) => saved,
+        Err(e) => {
+            error!(target: STORAGE, "failed to parse {DECORATIONS_FILE}: {e}");
+            return;
+        }
+    };
+    saved.spawn(&mut commands, layer);
+    info!(
+        target: STORAGE,
+        "loaded {} item frames, {} paintings and {} armor stands",
+        saved.frames.len(),
+        saved.paintings.len(),
+        saved.stands.len()
+    );
+}
+
+pub fn place_decorations(
+    mut commands: Commands,
+    mut events: EventReader<InteractBlockEvent>,
+    mut clients: Query<(&mut Inventory, &GameMode, &HeldItem, &Look)>,
+    visible_layers: Query<&VisibleChunkLayer>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    decorations: Query<(&Position, &Decoration), Without<Despawned>>,
+    mut dirty: ResMut<DecorationsDirty>,
+) {
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if event.hand != Hand::Main || !in_overworld(&visible_layers, event.client, overworld) {
+            continue;
+        }
+        let Ok((mut inventory, game_mode, held, look)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        let item = inventory.slot(held.slot()).item;
+        let block = event.position.get_in_direction(event.face);
+        if !layer.block(block).is_some_and(|b| b.state.is_air()) {
+            continue;
+        }
+        match item {
+            ItemKind::ItemFrame => {
+                let pos = frame_position(block, event.face);
+                let taken = decorations.iter().any(|(other, decoration)| {
+                    matches!(decoration, Decoration::ItemFrame { .. }) && other.0.distance_squared(pos) < 0.01
+                });
+                if taken {
+                    continue;
+                }
+                take_one(&mut inventory, held.slot(), *game_mode);
+                spawn_frame(&mut commands, overworld, block, event.face, ItemStack::EMPTY, 0);
+            }
+            ItemKind::Painting if !matches!(event.face, Direction::Up | Direction::Down) => {
+                let occupied = occupied_cells(decorations.iter());
+                let fitting: Vec<usize> = (0..PAINTINGS.len())
+                    .filter(|variant| painting_fits(layer, block, event.face, *variant, &occupied))
+                    .collect();
+                // Like vanilla, only the biggest motifs that fit are picked from
+                let largest = fitting.iter().map(|v| PAINTINGS[*v].2 * PAINTINGS[*v].3).max();
+                let candidates: Vec<usize> =
+                    fitting.into_iter().filter(|v| Some(PAINTINGS[*v].2 * PAINTINGS[*v].3) == largest).collect();
+                let Some(variant) = candidates.choose(&mut valence::rand::thread_rng()) else {
+                    continue;
+                };
+                take_one(&mut inventory, held.slot(), *game_mode);
+                spawn_painting(&mut commands, overworld, block, event.face, *variant);
+            }
+            ItemKind::ArmorStand if event.face == Direction::Up => {
+                // Face the player, snapped to 45 degrees like vanilla
+                let yaw = ((look.yaw + 180.0) / 45.0).round() * 45.0;
+                let pos = DVec3::new(block.x as f64 + 0.5, block.y as f64, block.z as f64 + 0.5);
+                take_one(&mut inventory, held.slot(), *game_mode);
+                spawn_stand(&mut commands, overworld, pos, yaw, 0, Equipment::default());
+            }
+            _ => continue,
+        }
+        dirty.0 = true;
+    }
+}
+
+pub fn interact_decorations(
+    mut commands: Commands,
+    mut events: EventReader<InteractEntityEvent>,
+    mut clients: Query<(&mut Inventory, &GameMode, &HeldItem), With<Client>>,
+    mut frames: Query<(&Position, &EntityLayerId, &mut item_frame::ItemStack, &mut item_frame::Rotation)>,
+    mut stands: Query<(&Position, &EntityLayerId, &mut Decoration, &mut Equipment), Without<Client>>,
+    mut poses: Query<(
+        &mut armor_stand::HeadRotation,
+        &mut armor_stand::BodyRotation,
+        &mut armor_stand::LeftArmRotation,
+        &mut armor_stand::RightArmRotation,
+        &mut armor_stand::LeftLegRotation,
+        &mut armor_stand::RightLegRotation,
+    )>,
+    mut dirty: ResMut<DecorationsDirty>,
+    mut hud_messages: EventWriter<HudMessage>,
+) {
+    for event in events.read() {
+        let Ok((mut inventory, game_mode, held)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        let survival = *game_mode == GameMode::Survival;
+
+        if let Ok((pos, &layer, mut frame_item, mut rotation)) = frames.get_mut(event.entity) {
+            let block = BlockPos::from(pos.0);
+            match event.interact {
+                EntityInteraction::Interact(Hand::Main) if frame_item.0.is_empty() => {
+                    if inventory.slot(held.slot()).is_empty() {
+                        continue;
+                    }
+                    frame_item.0 = take_one(&mut inventory, held.slot(), *game_mode);
+                    rotation.0 = 0;
+                }
+                EntityInteraction::Interact(Hand::Main) => rotation.0 = (rotation.0 + 1) % 8,
+                EntityInteraction::Attack if !frame_item.0.is_empty() => {
+                    let item = std::mem::replace(&mut frame_item.0, ItemStack::EMPTY);
+                    if survival {
+                        drop_stack(&mut commands, layer, block, item);
+                    }
+                }
+                EntityInteraction::Attack => {
+                    if survival {
+                        drop_stack(&mut commands, layer, block, ItemStack::new(ItemKind::ItemFrame, 1, None));
+                    }
+                    commands.entity(event.entity).insert(Despawned);
+                }
+                _ => continue,
+            }
+            dirty.0 = true;
+            continue;
+        }
+
+        let Ok((pos, &layer, mut decoration, mut equipment)) = stands.get_mut(event.entity) else {
+            continue;
+        };
+        let Decoration::ArmorStand { pose } = &mut *decoration else {
+            continue;
+        };
+        let block = BlockPos::from(pos.0);
+        match event.interact {
+            // Vanilla clients send both; only the one with the position is used
+            EntityInteraction::InteractAt { target, hand: Hand::Main } => {
+                let held_stack = inventory.slot(held.slot()).clone();
+                if event.sneaking {
+                    *pose = (*pose + 1) % POSES.len();
+                    if let Ok((mut head, mut body, mut left_arm, mut right_arm, mut left_leg, mut right_leg)) =
+                        poses.get_mut(event.entity)
+                    {
+                        let parts = POSES[*pose].parts;
+                        head.0 = angle(parts[0]);
+                        body.0 = angle(parts[1]);
+                        left_arm.0 = angle(parts[2]);
+                        right_arm.0 = angle(parts[3]);
+                        left_leg.0 = angle(parts[4]);
+                        right_leg.0 = angle(parts[5]);
+                    }
+                    hud_messages.send(HudMessage::new(event.client, format!("Pose: {}", POSES[*pose].name)));
+                } else if held_stack.is_empty() {
+                    // Take whatever is on the clicked part, or the held item
+                    let slot = match clicked_slot(target.y) {
+                        slot if !equipment.slot(slot).is_empty() => slot,
+                        _ => MAIN_HAND,
+                    };
+                    let taken = equipment.slot(slot).clone();
+                    if taken.is_empty() {
+                        continue;
+                    }
+                    equipment.set_slot(slot, ItemStack::EMPTY);
+                    inventory.set_slot(held.slot(), taken);
+                } else {
+                    let slot = stand_slot(held_stack.item);
+                    let previous = equipment.slot(slot).clone();
+                    if !previous.is_empty() && held_stack.count > 1 {
+                        continue;
+                    }
+                    equipment.set_slot(slot, held_stack.with_count(1));
+                    if previous.is_empty() {
+                        take_one(&mut inventory, held.slot(), *game_mode);
+                    } else {
+                        inventory.set_slot(held.slot(), previous);
+                    }
+                }
+            }
+            EntityInteraction::Attack => {
+                if survival {
+                    for slot in 0..6 {
+                        drop_stack(&mut commands, layer, block, equipment.slot(slot).clone());
+                    }
+                    drop_stack(&mut commands, layer, block, ItemStack::new(ItemKind::ArmorStand, 1, None));
+                }
+                commands.entity(event.entity).insert(Despawned);
+            }
+            _ => continue,
+        }
+        dirty.0 = true;
+    }
+}
+
+pub fn use_paintings(
+    mut commands: Commands,
+    mut events: EventReader<InteractEntityEvent>,
+    clients: Query<&GameMode, With<Client>>,
+    layers: Query<&ChunkLayer, With<Overworld>>,
+    mut decorations: Query<(Entity, &Position, &EntityLayerId, &mut Decoration, Option<&mut painting::Variant>), Without<Despawned>>,
+    mut dirty: ResMut<DecorationsDirty>,
+    mut hud_messages: EventWriter<HudMessage>,
+) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        let Ok(game_mode) = clients.get(event.client) else {
+            continue;
+        };
+        let Ok((_, pos, &entity_layer, decoration, _)) = decorations.get(event.entity) else {
+            continue;
+        };
+        let Decoration::Painting { facing, variant } = *decoration else {
+            continue;
+        };
+        let anchor = BlockPos::from(pos.0);
+        match event.interact {
+            EntityInteraction::Interact(Hand::Main) => {
+                let occupied = occupied_cells(
+                    decorations
+                        .iter()
+                        .filter(|(entity, ..)| *entity != event.entity)
+                        .map(|(_, pos, _, decoration, _)| (pos, decoration)),
+                );
+                let Some(next) = (1..PAINTINGS.len())
+                    .map(|step| (variant + step) % PAINTINGS.len())
+                    .find(|next| painting_fits(layer, anchor, facing, *next, &occupied))
+                else {
+                    continue;
+                };
+                if let Ok((_, _, _, mut decoration, Some(mut painting_variant))) = decorations.get_mut(event.entity) {
+                    *decoration = Decoration::Painting { facing, variant: next };
+                    painting_variant.0 = PAINTINGS[next].0;
+                }
+                hud_messages.send(HudMessage::new(event.client, format!("Painting: {}", PAINTINGS[next].1)));
+            }
+            EntityInteraction::Attack => {
+                if *game_mode == GameMode::Survival {
+                    drop_stack(&mut commands, entity_layer, anchor, ItemStack::new(ItemKind::Painting, 1, None));
+                }
+                commands.entity(event.entity).insert(Despawned);
+            }
+            _ => continue,
+        }
+        dirty.0 = true;
+    }
+}
+
+// Frames and paintings fall off when the block behind them is broken
+pub fn drop_unsupported_decorations(
+    mut commands: Commands,
+    mut world_events: EventReader<WorldEvent>,
+    layers: Query<Entity, With<Overworld>>,
+    decorations: Query<(Entity, &Position, &EntityLayerId, &Decoration, Option<&item_frame::ItemStack>), Without<Despawned>>,
+    mut dirty: ResMut<DecorationsDirty>,
+) {
+    let Ok(overworld) = layers.get_single() else {
+        return;
+    };
+    for event in world_events.read() {
+        if event.layer != overworld || !matches!(event.kind, WorldEventKind::BlockBroken { .. }) {
+            continue;
+        }
+        let broken = BlockPos::from(event.pos);
+        for (entity, pos, &layer, decoration, frame_item) in &decorations {
+            let unsupported = decoration_cells(pos, decoration)
+                .into_iter()
+                .any(|(cell, facing)| offset(cell, facing, -1) == broken);
+            if !unsupported {
+                continue;
+            }
+            let block = BlockPos::from(pos.0);
+            let item = match decoration {
+                Decoration::Painting { .. } => ItemKind::Painting,
+                _ => ItemKind::ItemFrame,
+            };
+            drop_stack(&mut commands, layer, block, ItemStack::new(item, 1, None));
+            if let Some(frame_item) = frame_item
+                && !frame_item.0.is_empty()
+            {
+                drop_stack(&mut commands, layer, block, frame_item.0.clone());
+            }
+            commands.entity(entity).insert(Despawned);
+            dirty.0 = true;
+        }
+    }
+}
+
+pub fn save_decorations(
+    mut dirty: ResMut<DecorationsDirty>,
+    frames: Query<FrameData, Without<Despawned>>,
+    paintings: Query<PaintingData, (With<painting::Variant>, Without<Despawned>)>,
+    stands: Query<StandData, Without<Despawned>>,
+) {
+    if !dirty.0 {
+        return;
+    }
+    dirty.0 = false;
+
+    let saved = SavedDecorations::capture(&frames, &paintings, &stands, |_| true);
+    let result = fs::create_dir_all(Path::new(DECORATIONS_FILE).parent().unwrap_or(Path::new(".")))
+        .and_then(|_| serde_json::to_string(&saved).map_err(std::io::Error::other))
+        .and_then(|json| fs::write(DECORATIONS_FILE, json));
+    if let Err(e) = result {
+        error!(target: STORAGE, "failed to save {DECORATIONS_FILE}: {e}");
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+
+use valence::{
+    entity::{
+        arrow::ArrowEntityBundle,
+        item::{ItemEntityBundle, Stack},
+        Velocity,
+    },
+    prelude::*,
+};
+
+use super::container::{insert_stack, ContainerBlock, Containers};
+use super::redstone::is_powered;
+use crate::world::physics::PhysicsBody;
+use crate::world::storage::ChunkSaver;
+use crate::world::Overworld;
+
+const EJECT_SPEED: f32 = 4.0;
+const ARROW_SPEED: f32 = 24.0;
+
+/// Dispensers and droppers that were powered last tick, so they only fire on
+/// the rising edge.
+#[derive(Resource, Default)]
+pub struct PoweredDispensers {
+    powered: HashSet<BlockPos>,
+}
+
+fn facing_vec(state: BlockState) -> [i32; 3] {
+    match state.get(PropName::Facing) {
+        Some(PropValue::North) => [0, 0, -1],
+        Some(PropValue::South) => [0, 0, 1],
+        Some(PropValue::East) => [1, 0, 0],
+        Some(PropValue::West) => [-1, 0, 0],
+        Some(PropValue::Up) => [0, 1, 0],
+        _ => [0, -1, 0],
+    }
+}
+
+/// What a dispenser does with the item it picked.
+enum DispenseAction {
+    Eject,
+    ShootArrow,
+    PlaceFluid(BlockState, ItemKind),
+    PickUpFluid(ItemKind),
+}
+
+fn dispense_action(item: ItemKind, front: BlockState) -> DispenseAction {
+    match item {
+        ItemKind::Arrow => DispenseAction::ShootArrow,
+        ItemKind::WaterBucket if front.is_air() => DispenseAction::PlaceFluid(BlockState::WATER, ItemKind::Bucket),
+        ItemKind::LavaBucket if front.is_air() => DispenseAction::PlaceFluid(BlockState::LAVA, ItemKind::Bucket),
+        ItemKind::Bucket if front == BlockState::WATER => DispenseAction::PickUpFluid(ItemKind::WaterBucket),
+        ItemKind::Bucket if front == BlockState::LAVA => DispenseAction::PickUpFluid(ItemKind::LavaBucket),
+        _ => DispenseAction::Eject,
+    }
+}
+
+pub fn trigger_dispensers(
+    mut commands: Commands,
+    mut powered: ResMut<PoweredDispensers>,
+    containers: Res<Containers>,
+    mut layers: Query<(Entity, &mut ChunkLayer), With<Overworld>>,
+    mut inventories: Query<(&mut Inventory, &ContainerBlock), Without<Client>>,
+    mut saver: ResMut<ChunkSaver>,
+) {
+    let Ok((layer_entity, mut layer)) = layers.get_single_mut() else {
+        return;
+    };
+
+    for (pos, entity) in &containers.inventories {
+        let Ok((mut inventory, container)) = inventories.get_mut(*entity) else {
+            continue;
+        };
+        if !matches!(container.kind, BlockKind::Dispenser | BlockKind::Dropper) {
+            continue;
+        }
+
+        let now_powered = is_powered(&layer, *pos);
+        let rising_edge = now_powered && powered.powered.insert(*pos);
+        if !now_powered {
+            powered.powered.remove(pos);
+        }
+        if !rising_edge {
+            continue;
+        }
+
+        // Vanilla picks a random non-empty slot.
+        let filled: Vec<u16> = (0..9).filter(|slot| !inventory.slot(*slot).is_empty()).collect();
+        let Some(&slot) = filled.get(valence::rand::random::<usize>() % filled.len().max(1)) else {
+            continue;
+        };
+        let Some(state) = layer.block(*pos).map(|b| b.state) else {
+            continue;
+        };
+        let [fx, fy, fz] = facing_vec(state);
+        let front = BlockPos::new(pos.x + fx, pos.y + fy, pos.z + fz);
+        let front_state = layer.block(front).map_or(BlockState::AIR, |b| b.state);
+        let stack = inventory.slot(slot).clone();
+        let spawn_pos = DVec3::new(
+            pos.x as f64 + 0.5 + fx as f64 * 0.7,
+            pos.y as f64 + 0.5 + fy as f64 * 0.7,
+            pos.z as f64 + 0.5 + fz as f64 * 0.7,
+        );
+        let direction = Vec3::new(fx as f32, fy as f32, fz as f32);
+
+        let action = if container.kind == BlockKind::Dropper {
+            DispenseAction::Eject
+        } else {
+            dispense_action(stack.item, front_state)
+        };
+
+        match action {
+            DispenseAction::Eject => {
+                commands.spawn((
+                    ItemEntityBundle {
+                        layer: EntityLayerId(layer_entity),
+                        item_stack: Stack(ItemStack::new(stack.item, 1, stack.nbt.clone())),
+                        position: Position(spawn_pos),
+                        velocity: Velocity(direction * EJECT_SPEED),
+                        ..Default::default()
+                    },
+                    PhysicsBody::item(direction * EJECT_SPEED),
+                ));
+            }
+            DispenseAction::ShootArrow => {
+                let velocity = direction * ARROW_SPEED + Vec3::new(0.0, 2.0, 0.0);
+                commands.spawn((
+                    ArrowEntityBundle {
+                        layer: EntityLayerId(layer_entity),
+                        position: Position(spawn_pos),
+                        velocity: Velocity(velocity),
+                        ..Default::default()
+                    },
+                    PhysicsBody::arrow(velocity),
+                ));
+            }
+            DispenseAction::PlaceFluid(fluid, empty) => {
+                layer.set_block(front, fluid);
+                saver.mark_block_dirty(front);
+                inventory.set_slot(slot, ItemStack::new(empty, 1, None));
+                continue;
+            }
+            DispenseAction::PickUpFluid(filled_bucket) => {
+                layer.set_block(front, BlockState::AIR);
+                saver.mark_block_dirty(front);
+                if stack.count > 1 {
+                    inventory.set_slot_amount(slot, stack.count - 1);
+                    let left = insert_stack(&mut inventory, 0..9, ItemStack::new(filled_bucket, 1, None));
+                    if !left.is_empty() {
+                        commands.spawn(ItemEntityBundle {
+                            layer: EntityLayerId(layer_entity),
+                            item_stack: Stack(left),
+                            position: Position(spawn_pos),
+                            ..Default::default()
+                        });
+                    }
+                } else {
+                    inventory.set_slot(slot, ItemStack::new(filled_bucket, 1, None));
+                }
+                continue;
+            }
+        }
+
+        // Ejected items and arrows use up one item.
+        if stack.count > 1 {
+            inventory.set_slot_amount(slot, stack.count - 1);
+        } else {
+            inventory.set_slot(slot, ItemStack::EMPTY);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// src/components/enchantment.rs
+//
+// Reading enchantments off items. They're stored the vanilla way, in the
+// item's `Enchantments` list of `{ id, lvl }` compounds, so enchanted items
+// from creative, anvils or imported worlds all work the same. The effects
+// themselves live where they apply: mining speed in `mining`, drops in `loot`
+// and damage in `combat`.
+
+use valence::{
+    nbt::{List, Value},
+    prelude::*,
+};
+
+pub const EFFICIENCY: &str = "efficiency";
+pub const SILK_TOUCH: &str = "silk_touch";
+pub const FORTUNE: &str = "fortune";
+pub const SHARPNESS: &str = "sharpness";
+pub const KNOCKBACK: &str = "knockback";
+pub const FEATHER_FALLING: &str = "feather_falling";
+
+/// The level of enchantment `id` on `stack`, or 0 if it doesn't have it.
+/// `id` can leave out the `minecraft:` namespace.
+pub fn level(stack: &ItemStack, id: &str) -> u32 {
+    let Some(Value::List(List::Compound(enchantments))) = stack.nbt.as_ref().and_then(|nbt| nbt.get("Enchantments"))
+    else {
+        return 0;
+    };
+    enchantments
+        .iter()
+        .find(|enchantment| match enchantment.get("id") {
+            Some(Value::String(name)) => name.trim_start_matches("minecraft:") == id.trim_start_matches("minecraft:"),
+            _ => false,
+        })
+        .and_then(|enchantment| match enchantment.get("lvl")? {
+            Value::Short(lvl) => Some(*lvl as i32),
+            Value::Int(lvl) => Some(*lvl),
+            Value::Byte(lvl) => Some(*lvl as i32),
+            _ => None,
+        })
+        .map_or(0, |lvl| lvl.max(0) as u32)
+}
+
+/// Extra melee damage from Sharpness.
+pub fn sharpness_bonus(level: u32) -> f32 {
+    if level == 0 { 0.0 } else { 0.5 * level as f32 + 0.5 }
+}
+
+/// How much of the fall damage Feather Falling takes away, from 0 to 0.8.
+pub fn feather_falling_reduction(level: u32) -> f32 {
+    (3 * level).min(20) as f32 / 25.0
+}
@@ -0,0 +1,503 @@
+use std::f64::consts::TAU;
+
+use noise::{NoiseFn, SuperSimplex};
+use tracing::info;
+use valence::{
+    boss_bar::{BossBarBundle, BossBarColor, BossBarDivision, BossBarStyle, BossBarTitle},
+    entity::{
+        end_crystal::EndCrystalEntityBundle,
+        ender_dragon::{self, EnderDragonEntityBundle},
+        living::Health,
+        Despawned, EntityLayerId,
+    },
+    interact_block::InteractBlockEvent,
+    interact_entity::{EntityInteraction, InteractEntityEvent},
+    inventory::HeldItem,
+    message::SendMessage,
+    prelude::*,
+};
+
+use super::boss::{Boss, BossDefeatedEvent, BossKind, BossPhase, DRAGON_MAX_HEALTH};
+use super::core::new_crystal_message;
+use super::explosion::{explode, ExplosionTargets};
+use crate::world::deferred::PendingBlocks;
+use crate::world::events::WorldEvent;
+use crate::world::{send_to_layer, Overworld, WorldSpawn};
+
+/// Where players arrive in the End, on the obsidian platform.
+pub const END_SPAWN_POS: DVec3 = DVec3::new(100.5, 49.0, 0.5);
+const END_HEIGHT: u32 = 256;
+/// Chunks around the origin generated at startup. Everything past this is void.
+const PREGEN_RADIUS: i32 = 8;
+const ISLAND_RADIUS: f64 = 80.0;
+const ISLAND_TOP: i32 = 60;
+const PILLAR_COUNT: usize = 10;
+const PILLAR_RING_RADIUS: f64 = 42.0;
+const VOID_Y: f64 = -64.0;
+const DRAGON_CIRCLE_RADIUS: f64 = 50.0;
+const DRAGON_HEAL_INTERVAL_TICKS: i64 = 10;
+const CRYSTAL_EXPLOSION: f32 = 6.0;
+
+/// Marks the End's layer.
+#[derive(Component)]
+pub struct TheEnd;
+
+/// State of the dragon fight. There's only ever one End, so only one fight.
+#[derive(Resource, Default)]
+pub struct DragonFight {
+    pub dragon: Option<Entity>,
+    pub crystals: Vec<Entity>,
+    pub defeated: bool,
+}
+
+struct Pillar {
+    x: i32,
+    z: i32,
+    radius: i32,
+    /// Y of the bedrock block the crystal sits on.
+    height: i32,
+}
+
+/// The obsidian pillars around the main island, evenly spaced with heights
+/// growing by three blocks per pillar like vanilla's.
+fn pillars() -> Vec<Pillar> {
+    (0..PILLAR_COUNT)
+        .map(|i| {
+            let angle = TAU * i as f64 / PILLAR_COUNT as f64;
+            // Alternate heights so neighbouring pillars differ.
+            let size = (i * 3) % PILLAR_COUNT;
+            Pillar {
+                x: (angle.cos() * PILLAR_RING_RADIUS).round() as i32,
+                z: (angle.sin() * PILLAR_RING_RADIUS).round() as i32,
+                radius: 2 + size as i32 / 3,
+                height: 76 + size as i32 * 3,
+            }
+        })
+        .collect()
+}
+
+fn generate_end_chunk(pos: ChunkPos, edge_noise: &SuperSimplex, pillars: &[Pillar], biome: BiomeId) -> UnloadedChunk {
+    let mut chunk = UnloadedChunk::with_height(END_HEIGHT);
+    chunk.fill_biomes(biome);
+
+    for z in 0..16 {
+        for x in 0..16 {
+            let world_x = pos.x * 16 + x as i32;
+            let world_z = pos.z * 16 + z as i32;
+            let distance = ((world_x * world_x + world_z * world_z) as f64).sqrt();
+
+            // Main island: a flattened dome whose edge is roughened by noise.
+            let edge = ISLAND_RADIUS + edge_noise.get([world_x as f64 / 30.0, world_z as f64 / 30.0]) * 12.0;
+            if distance < edge {
+                let t = distance / edge;
+                let top = ISLAND_TOP + ((1.0 - t) * 4.0).round() as i32;
+                let depth = ((1.0 - t * t).sqrt() * 40.0) as i32 + 1;
+                for y in (top - depth).max(0)..=top {
+                    chunk.set_block_state(x, y as u32, z, BlockState::END_STONE);
+                }
+            }
+
+            for pillar in pillars {
+                let dx = world_x - pillar.x;
+                let dz = world_z - pillar.z;
+                if dx * dx + dz * dz > pillar.radius * pillar.radius {
+                    continue;
+                }
+                for y in ISLAND_TOP - 10..pillar.height {
+                    chunk.set_block_state(x, y as u32, z, BlockState::OBSIDIAN);
+                }
+                if dx == 0 && dz == 0 {
+                    chunk.set_block_state(x, pillar.height as u32, z, BlockState::BEDROCK);
+                }
+            }
+        }
+    }
+    chunk
+}
+
+/// Builds the bedrock exit portal in the middle of the island. Once the dragon
+/// is dead the bowl is filled with portal blocks.
+fn build_exit_portal(layer: &mut ChunkLayer, active: bool) {
+    let base = ISLAND_TOP + 4;
+    for x in -3i32..=3 {
+        for z in -3i32..=3 {
+            let distance_sq = x * x + z * z;
+            if distance_sq > 12 {
+                continue;
+            }
+            layer.set_block([x, base - 1, z], BlockState::BEDROCK);
+            let rim = distance_sq > 6;
+            let block = if rim {
+                BlockState::BEDROCK
+            } else if active {
+                BlockState::END_PORTAL
+            } else {
+                BlockState::AIR
+            };
+            layer.set_block([x, base, z], block);
+        }
+    }
+    // Centre pillar with torches, as in vanilla.
+    for y in base..base + 4 {
+        layer.set_block([0, y, 0], BlockState::BEDROCK);
+    }
+    for (x, z, facing) in [
+        (1, 0, PropValue::East),
+        (-1, 0, PropValue::West),
+        (0, 1, PropValue::South),
+        (0, -1, PropValue::North),
+    ] {
+        layer.set_block(
+            [x, base + 2, z],
+            BlockState::WALL_TORCH.set(PropName::Facing, facing),
+        );
+    }
+}
+
+fn build_spawn_platform(layer: &mut ChunkLayer) {
+    let center = BlockPos::from(END_SPAWN_POS);
+    for x in -2..=2 {
+        for z in -2..=2 {
+            layer.set_block([center.x + x, center.y - 1, center.z + z], BlockState::OBSIDIAN);
+            for y in 0..3 {
+                layer.set_block([center.x + x, center.y + y, center.z + z], BlockState::AIR);
+            }
+        }
+    }
+}
+
+pub fn setup_end(
+    mut commands: Commands,
+    server: Res<Server>,
+    dimensions: Res<DimensionTypeRegistry>,
+    biomes: Res<BiomeRegistry>,
+) {
+    let mut layer = LayerBundle::new(ident!("the_end"), &dimensions, &biomes, &server);
+    let biome = biomes.index_of(ident!("the_end")).unwrap_or_default();
+    let edge_noise = SuperSimplex::new(0);
+    let pillars = pillars();
+
+    for cz in -PREGEN_RADIUS..=PREGEN_RADIUS {
+        for cx in -PREGEN_RADIUS..=PREGEN_RADIUS {
+            let pos = ChunkPos::new(cx, cz);
+            layer
+                .chunk
+                .insert_chunk(pos, generate_end_chunk(pos, &edge_noise, &pillars, biome));
+        }
+    }
+    build_exit_portal(&mut layer.chunk, false);
+    build_spawn_platform(&mut layer.chunk);
+
+    commands.spawn((layer, TheEnd));
+    info!("[end] generated the End ({} chunks)", (PREGEN_RADIUS * 2 + 1).pow(2));
+}
+
+fn is_portal_frame_with_eye(layer: &ChunkLayer, pos: BlockPos) -> bool {
+    layer.block(pos).is_some_and(|block| {
+        block.state.to_kind() == BlockKind::EndPortalFrame && block.state.get(PropName::Eye) == Some(PropValue::True)
+    })
+}
+
+/// The 12 frame positions around a 3x3 portal centred on `center`.
+fn frame_positions(center: BlockPos) -> impl Iterator<Item = BlockPos> {
+    (-1..=1).flat_map(move |i| {
+        [
+            BlockPos::new(center.x + i, center.y, center.z - 2),
+            BlockPos::new(center.x + i, center.y, center.z + 2),
+            BlockPos::new(center.x - 2, center.y, center.z + i),
+            BlockPos::new(center.x + 2, center.y, center.z + i),
+        ]
+    })
+}
+
+// Putting eyes of ender into a complete frame ring opens an End portal
+pub fn activate_end_portals(
+    mut events: EventReader<InteractBlockEvent>,
+    mut layers: Query<(Entity, &mut ChunkLayer), With<Overworld>>,
+    mut clients: Query<(&mut Inventory, &HeldItem, &GameMode, &VisibleChunkLayer)>,
+) {
+    let Ok((overworld, mut layer)) = layers.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Ok((mut inventory, held, game_mode, visible_layer)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        if visible_layer.0 != overworld {
+            continue;
+        }
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+        let stack = inventory.slot(held.slot()).clone();
+        if state.to_kind() != BlockKind::EndPortalFrame
+            || state.get(PropName::Eye) == Some(PropValue::True)
+            || stack.item != ItemKind::EnderEye
+        {
+            continue;
+        }
+
+        layer.set_block(event.position, state.set(PropName::Eye, PropValue::True));
+        if *game_mode != GameMode::Creative {
+            if stack.count > 1 {
+                inventory.set_slot_amount(held.slot(), stack.count - 1);
+            } else {
+                inventory.set_slot(held.slot(), ItemStack::EMPTY);
+            }
+        }
+
+        // The frame could be on any side of the portal.
+        for dx in -2..=2 {
+            for dz in -2..=2 {
+                let center = BlockPos::new(event.position.x + dx, event.position.y, event.position.z + dz);
+                if !frame_positions(center).all(|pos| is_portal_frame_with_eye(&layer, pos)) {
+                    continue;
+                }
+                for x in -1..=1 {
+                    for z in -1..=1 {
+                        layer.set_block([center.x + x, center.y, center.z + z], BlockState::END_PORTAL);
+                    }
+                }
+                layer.play_sound(
+                    Sound::BlockEndPortalSpawn,
+                    SoundCategory::Block,
+                    DVec3::new(center.x as f64 + 0.5, center.y as f64, center.z as f64 + 0.5),
+                    1.0,
+                    1.0,
+                );
+                info!("[end] end portal opened at {center:?}");
+            }
+        }
+    }
+}
+
+// Moves players through End portals, and out of the void
+pub fn use_end_portals(
+    spawn: Res<WorldSpawn>,
+    overworld: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    end: Query<(Entity, &ChunkLayer), With<TheEnd>>,
+    mut clients: Query<(
+        &mut Client,
+        &mut EntityLayerId,
+        &mut VisibleChunkLayer,
+        &mut VisibleEntityLayers,
+        &mut Position,
+    )>,
+) {
+    let (Ok((overworld_entity, overworld_layer)), Ok((end_entity, end_layer))) =
+        (overworld.get_single(), end.get_single())
+    else {
+        return;
+    };
+
+    for (mut client, mut layer_id, mut visible_chunk, mut visible_entities, mut pos) in &mut clients {
+        let in_end = visible_chunk.0 == end_entity;
+        let layer = if in_end { end_layer } else { overworld_layer };
+        let in_portal = layer
+            .block(BlockPos::from(pos.0))
+            .is_some_and(|block| block.state.to_kind() == BlockKind::EndPortal);
+
+        if in_end && (in_portal || pos.0.y < VOID_Y) {
+            if !in_portal {
+                client.send_chat_message(new_crystal_message("You fell out of the world.".into()));
+            }
+            send_to_layer(
+                overworld_entity,
+                spawn.0,
+                &mut layer_id,
+                &mut visible_chunk,
+                &mut visible_entities,
+                &mut pos,
+            );
+        } else if !in_end && in_portal {
+            send_to_layer(
+                end_entity,
+                END_SPAWN_POS,
+                &mut layer_id,
+                &mut visible_chunk,
+                &mut visible_entities,
+                &mut pos,
+            );
+        }
+    }
+}
+
+// Spawns the dragon and its crystals the first time someone enters the End
+pub fn start_dragon_fight(
+    mut commands: Commands,
+    mut fight: ResMut<DragonFight>,
+    end: Query<Entity, With<TheEnd>>,
+    mut clients: Query<(&mut Client, &VisibleChunkLayer)>,
+) {
+    if fight.dragon.is_some() || fight.defeated {
+        return;
+    }
+    let Ok(end_entity) = end.get_single() else {
+        return;
+    };
+    if !clients.iter().any(|(_, layer)| layer.0 == end_entity) {
+        return;
+    }
+
+    let layer = EntityLayerId(end_entity);
+    fight.crystals = pillars()
+        .into_iter()
+        .map(|pillar| {
+            commands
+                .spawn(EndCrystalEntityBundle {
+                    layer,
+                    position: Position(DVec3::new(
+                        pillar.x as f64 + 0.5,
+                        pillar.height as f64 + 1.0,
+                        pillar.z as f64 + 0.5,
+                    )),
+                    ..Default::default()
+                })
+                .id()
+        })
+        .collect();
+
+    let bar = commands
+        .spawn(BossBarBundle {
+            title: BossBarTitle(BossKind::EnderDragon.name().into_text()),
+            style: BossBarStyle {
+                color: BossBarColor::Pink,
+                division: BossBarDivision::NoDivision,
+            },
+            layer,
+            ..Default::default()
+        })
+        .id();
+    let dragon = commands
+        .spawn((
+            EnderDragonEntityBundle {
+                layer,
+                position: Position(DVec3::new(DRAGON_CIRCLE_RADIUS, 75.0, 0.0)),
+                living_health: Health(DRAGON_MAX_HEALTH),
+                // Circling
+                ender_dragon_phase: ender_dragon::Phase(0),
+                ..Default::default()
+            },
+            Boss {
+                kind: BossKind::EnderDragon,
+                max_health: DRAGON_MAX_HEALTH,
+                phase: BossPhase::Ranged,
+                attack_cooldown: 100,
+                bar,
+            },
+        ))
+        .id();
+    fight.dragon = Some(dragon);
+
+    for (mut client, visible_layer) in &mut clients {
+        if visible_layer.0 == end_entity {
+            client.send_chat_message(new_crystal_message("The Ender Dragon has awoken!".into()));
+        }
+    }
+    info!("[end] dragon fight started");
+}
+
+// Flies the dragon in a circle around the pillars
+pub fn move_dragon(
+    server: Res<Server>,
+    fight: Res<DragonFight>,
+    mut dragons: Query<(&mut Position, &mut Look, &mut HeadYaw, &Boss)>,
+) {
+    let Some(dragon) = fight.dragon else {
+        return;
+    };
+    let Ok((mut pos, mut look, mut head_yaw, boss)) = dragons.get_mut(dragon) else {
+        return;
+    };
+    // Faster when enraged.
+    let speed = if boss.phase == BossPhase::Enraged { 0.006 } else { 0.004 };
+    let angle = server.current_tick() as f64 * speed * TAU;
+    let next = DVec3::new(
+        angle.cos() * DRAGON_CIRCLE_RADIUS,
+        75.0 + (angle * 4.0).sin() * 6.0,
+        angle.sin() * DRAGON_CIRCLE_RADIUS,
+    );
+    let direction = next - pos.0;
+    // The dragon model faces backwards compared to other mobs.
+    let yaw = (-direction.x.atan2(direction.z)).to_degrees() as f32 + 180.0;
+    pos.0 = next;
+    look.yaw = yaw;
+    head_yaw.0 = yaw;
+}
+
+// End crystals heal the dragon while any are left
+pub fn heal_dragon(
+    server: Res<Server>,
+    mut fight: ResMut<DragonFight>,
+    crystals: Query<(), (With<Position>, Without<Despawned>)>,
+    mut dragons: Query<(&mut Health, &Boss)>,
+) {
+    if server.current_tick() % DRAGON_HEAL_INTERVAL_TICKS != 0 {
+        return;
+    }
+    fight.crystals.retain(|crystal| crystals.contains(*crystal));
+    let Some(dragon) = fight.dragon else {
+        return;
+    };
+    let Ok((mut health, boss)) = dragons.get_mut(dragon) else {
+        return;
+    };
+    if !fight.crystals.is_empty() && health.0 > 0.0 && health.0 < boss.max_health {
+        health.0 = (health.0 + 1.0).min(boss.max_health);
+    }
+}
+
+// Hitting an end crystal blows it up
+pub fn destroy_end_crystals(
+    mut commands: Commands,
+    mut events: EventReader<InteractEntityEvent>,
+    mut fight: ResMut<DragonFight>,
+    mut layers: Query<(Entity, &mut ChunkLayer), With<TheEnd>>,
+    crystals: Query<&Position, Without<Client>>,
+    mut targets: ExplosionTargets,
+    mut world_events: EventWriter<WorldEvent>,
+    mut pending: ResMut<PendingBlocks>,
+) {
+    let Ok((end_entity, mut layer)) = layers.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        if event.interact != EntityInteraction::Attack || !fight.crystals.contains(&event.entity) {
+            continue;
+        }
+        let Ok(pos) = crystals.get(event.entity) else {
+            continue;
+        };
+        explode(&mut layer, end_entity, pos.0, CRYSTAL_EXPLOSION, &mut targets, &mut world_events, &mut pending);
+        commands.entity(event.entity).insert(Despawned);
+        fight.crystals.retain(|crystal| *crystal != event.entity);
+    }
+}
+
+// Opens the exit portal and places the egg once the dragon dies
+pub fn finish_dragon_fight(
+    mut commands: Commands,
+    mut events: EventReader<BossDefeatedEvent>,
+    mut fight: ResMut<DragonFight>,
+    mut layers: Query<&mut ChunkLayer, With<TheEnd>>,
+) {
+    let Ok(mut layer) = layers.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        if event.kind != BossKind::EnderDragon {
+            continue;
+        }
+        fight.dragon = None;
+        fight.defeated = true;
+        for crystal in fight.crystals.drain(..) {
+            commands.entity(crystal).insert(Despawned);
+        }
+        build_exit_portal(&mut layer, true);
+        layer.set_block([0, ISLAND_TOP + 8, 0], BlockState::DRAGON_EGG);
+        info!("[end] dragon defeated, exit portal opened");
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use valence::{
+    entity::{
+        item::ItemEntity,
+        living::{Health, LivingEntity},
+        Despawned,
+    },
+    prelude::*,
+};
+
+use super::boss::Boss;
+use super::config::load_config;
+use super::decoration::Decoration;
+use super::end::TheEnd;
+use super::trading::Trader;
+use crate::world::nether::TheNether;
+use crate::world::worlds::NamedWorld;
+use crate::world::Overworld;
+
+#[derive(Deserialize, Clone)]
+pub struct WorldEntityRules {
+    /// Ticks before dropped items disappear.
+    #[serde(default = "default_item_despawn")]
+    pub item_despawn_ticks: u32,
+    /// Mobs further than this from every player are removed.
+    #[serde(default = "default_mob_despawn_distance")]
+    pub mob_despawn_distance: f64,
+    /// More living entities than this in one block take cramming damage.
+    /// 0 disables cramming.
+    #[serde(default = "default_max_cramming")]
+    pub max_entity_cramming: usize,
+}
+
+fn default_item_despawn() -> u32 {
+    6000
+}
+
+fn default_mob_despawn_distance() -> f64 {
+    128.0
+}
+
+fn default_max_cramming() -> usize {
+    24
+}
+
+impl Default for WorldEntityRules {
+    fn default() -> Self {
+        Self {
+            item_despawn_ticks: default_item_despawn(),
+            mob_despawn_distance: default_mob_despawn_distance(),
+            max_entity_cramming: default_max_cramming(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct EntityRulesConfig {
+    /// Rules used by worlds without their own entry.
+    #[serde(default)]
+    pub global: WorldEntityRules,
+    /// Per-world overrides, keyed by world name: "overworld", "the_nether",
+    /// "the_end" or the name of an extra world.
+    #[serde(default)]
+    pub worlds: HashMap<String, WorldEntityRules>,
+}
+
+#[derive(Resource, Default)]
+pub struct EntityRules(pub EntityRulesConfig);
+
+impl EntityRules {
+    pub fn for_world(&self, world: &str) -> &WorldEntityRules {
+        self.0.worlds.get(world).unwrap_or(&self.0.global)
+    }
+}
+
+/// Marks mobs the server spawned on its own, which go away again when no
+/// player is near. Traders, bosses and decorations never have it.
+#[derive(Component)]
+pub struct NaturalMob;
+
+/// How many ticks a non-player entity has existed.
+#[derive(Component, Default)]
+pub struct EntityAge(pub u32);
+
+const CRAMMING_DAMAGE: f32 = 6.0;
+const CRAMMING_INTERVAL_TICKS: i64 = 10;
+
+type WorldQuery<'w, 's> = Query<'w, 's, (Has<Overworld>, Has<TheNether>, Has<TheEnd>, Option<&'static NamedWorld>)>;
+
+/// The name a layer's world has in `entities.json`.
+fn world_name<'a>(worlds: &'a WorldQuery, layer: Entity) -> Option<&'a str> {
+    match worlds.get(layer).ok()? {
+        (true, ..) => Some("overworld"),
+        (_, true, ..) => Some("the_nether"),
+        (_, _, true, _) => Some("the_end"),
+        (.., Some(world)) => Some(&world.name),
+        _ => None,
+    }
+}
+
+impl EntityRules {
+    /// The rules for whichever world `layer` is.
+    fn for_layer(&self, worlds: &WorldQuery, layer: Entity) -> &WorldEntityRules {
+        world_name(worlds, layer).map_or(&self.0.global, |world| self.for_world(world))
+    }
+}
+
+pub fn setup_entity_rules(mut commands: Commands) {
+    commands.insert_resource(EntityRules(load_config("entities.json")));
+}
+
+pub fn track_entity_age(
+    mut commands: Commands,
+    added: Query<Entity, (Added<EntityKind>, Without<Client>)>,
+    mut ages: Query<&mut EntityAge>,
+) {
+    for entity in &added {
+        commands.entity(entity).insert(EntityAge::default());
+    }
+    for mut age in &mut ages {
+        age.0 = age.0.saturating_add(1);
+    }
+}
+
+pub fn despawn_expired_entities(
+    mut commands: Commands,
+    rules: Res<EntityRules>,
+    worlds: WorldQuery,
+    items: Query<(Entity, &EntityAge, &EntityLayerId), With<ItemEntity>>,
+    mobs: Query<
+        (Entity, &Position, &EntityLayerId),
+        (With<NaturalMob>, Without<Client>, Without<Trader>, Without<Decoration>, Without<Boss>),
+    >,
+    players: Query<(&Position, &EntityLayerId), With<Client>>,
+) {
+    for (entity, age, layer) in &items {
+        if age.0 >= rules.for_layer(&worlds, layer.0).item_despawn_ticks {
+            commands.entity(entity).insert(Despawned);
+        }
+    }
+
+    for (entity, pos, layer) in &mobs {
+        let max_distance = rules.for_layer(&worlds, layer.0).mob_despawn_distance;
+        let near_player = players.iter().any(|(player, player_layer)| {
+            player_layer.0 == layer.0 && player.0.distance_squared(pos.0) <= max_distance * max_distance
+        });
+        if !near_player {
+            commands.entity(entity).insert(Despawned);
+        }
+    }
+}
+
+pub fn entity_cramming(
+    mut commands: Commands,
+    rules: Res<EntityRules>,
+    server: Res<Server>,
+    worlds: WorldQuery,
+    mut living: Query<(Entity, &Position, &EntityLayerId, &mut Health, Option<&GameMode>), With<LivingEntity>>,
+) {
+    if server.current_tick() % CRAMMING_INTERVAL_TICKS != 0 {
+        return;
+    }
+
+    let mut per_block: HashMap<(Entity, BlockPos), Vec<Entity>> = HashMap::new();
+    for (entity, pos, layer, _, game_mode) in &living {
+        if game_mode == Some(&GameMode::Spectator) {
+            continue;
+        }
+        per_block
+            .entry((layer.0, BlockPos::from(pos.0)))
+            .or_default()
+            .push(entity);
+    }
+
+    for (&(layer, _), entities) in &per_block {
+        let max = rules.for_layer(&worlds, layer).max_entity_cramming;
+        if max == 0 || entities.len() <= max {
+            continue;
+        }
+        for entity in entities {
+            let Ok((_, _, _, mut health, game_mode)) = living.get_mut(*entity) else {
+                continue;
+            };
+            if game_mode == Some(&GameMode::Creative) {
+                continue;
+            }
+            health.0 = (health.0 - CRAMMING_DAMAGE).max(0.0);
+            if health.0 <= 0.0 && game_mode.is_none() {
+                commands.entity(*entity).insert(Despawned);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+// src/components/experience.rs
+//
+// Player experience and the orbs that carry it. A player's total points are
+// kept in their `PlayerData`; the level and bar shown to the client are
+// worked out from that with vanilla's level curve.
+//
+// Orbs are spawned by `spawn_experience`, fall like items, drift towards the
+// nearest player within `ATTRACT_RANGE` and are absorbed on touch. Orbs lying
+// close together are merged every so often so mob farms and big mining trips
+// don't leave hundreds of entities behind.
+
+use valence::{
+    entity::{experience_orb::ExperienceOrbEntityBundle, Despawned, EntityLayerId},
+    protocol::{packets::play::ExperienceBarUpdateS2c, VarInt, WritePacket},
+    prelude::*,
+    rand::Rng,
+};
+
+use super::boss::{BossDefeatedEvent, BossKind};
+use super::mob_behavior::is_hostile;
+use super::playerdata::PlayerData;
+use crate::world::physics::PhysicsBody;
+
+/// Players this close pull orbs towards them.
+const ATTRACT_RANGE: f64 = 8.0;
+/// Orbs this close to a player's feet are picked up.
+const PICKUP_RANGE: f64 = 1.0;
+/// Orbs this close to each other are merged.
+const MERGE_RANGE: f64 = 1.0;
+const MERGE_INTERVAL_TICKS: i64 = 20;
+/// Orbs nobody picks up disappear after five minutes, like vanilla.
+const ORB_LIFETIME_TICKS: u32 = 20 * 60 * 5;
+/// Vanilla's orb sizes. Bigger amounts are split into as few orbs as
+/// possible.
+const ORB_SIZES: [u32; 11] = [2477, 1237, 617, 307, 149, 73, 37, 17, 7, 3, 1];
+
+#[derive(Component, Debug)]
+pub struct ExperienceOrb {
+    pub value: u32,
+    age: u32,
+}
+
+/// Points needed to go from `level` to the next one.
+pub fn points_for_level(level: u32) -> u32 {
+    match level {
+        0..=15 => 2 * level + 7,
+        16..=30 => 5 * level - 38,
+        _ => 9 * level - 158,
+    }
+}
+
+/// The level and progress towards the next one (0 to 1) for a total number
+/// of points.
+pub fn level_progress(mut total: u32) -> (u32, f32) {
+    let mut level = 0;
+    while total >= points_for_level(level) {
+        total -= points_for_level(level);
+        level += 1;
+    }
+    (level, total as f32 / points_for_level(level) as f32)
+}
+
+/// Experience dropped for breaking a block, if any.
+pub fn block_experience(kind: BlockKind) -> u32 {
+    let (min, max) = match kind {
+        BlockKind::CoalOre | BlockKind::DeepslateCoalOre => (0, 2),
+        BlockKind::NetherGoldOre => (0, 1),
+        BlockKind::DiamondOre
+        | BlockKind::DeepslateDiamondOre
+        | BlockKind::EmeraldOre
+        | BlockKind::DeepslateEmeraldOre => (3, 7),
+        BlockKind::LapisOre | BlockKind::DeepslateLapisOre | BlockKind::NetherQuartzOre => (2, 5),
+        BlockKind::RedstoneOre | BlockKind::DeepslateRedstoneOre => (1, 5),
+        BlockKind::Spawner => (15, 43),
+        _ => return 0,
+    };
+    valence::rand::thread_rng().gen_range(min..=max)
+}
+
+/// Experience dropped when a player kills a mob.
+pub fn mob_experience(kind: EntityKind) -> u32 {
+    match kind {
+        EntityKind::BLAZE => 10,
+        EntityKind::VILLAGER | EntityKind::WANDERING_TRADER | EntityKind::ARMOR_STAND => 0,
+        _ if is_hostile(kind) => 5,
+        _ => valence::rand::thread_rng().gen_range(1..=3),
+    }
+}
+
+/// Spawns orbs worth `amount` points in total at `pos`.
+pub fn spawn_experience(commands: &mut Commands, layer: EntityLayerId, pos: DVec3, mut amount: u32) {
+    let mut rng = valence::rand::thread_rng();
+    while amount > 0 {
+        let value = ORB_SIZES.into_iter().find(|size| *size <= amount).unwrap_or(1);
+        amount -= value;
+        let velocity = Vec3::new(rng.gen_range(-2.0..2.0), rng.gen_range(0.0..4.0), rng.gen_range(-2.0..2.0));
+        commands.spawn((
+            ExperienceOrbEntityBundle {
+                layer,
+                position: Position(pos),
+                ..Default::default()
+            },
+            PhysicsBody::experience_orb(velocity),
+            ExperienceOrb { value, age: 0 },
+        ));
+    }
+}
+
+// Orbs drift towards the closest player in range, like vanilla
+pub fn attract_experience_orbs(
+    mut commands: Commands,
+    mut orbs: Query<(Entity, &Position, &EntityLayerId, &mut PhysicsBody, &mut ExperienceOrb), Without<Despawned>>,
+    players: Query<(&Position, &EntityLayerId, &GameMode), With<Client>>,
+) {
+    for (entity, pos, layer, mut body, mut orb) in &mut orbs {
+        orb.age += 1;
+        if orb.age >= ORB_LIFETIME_TICKS {
+            commands.entity(entity).insert(Despawned);
+            continue;
+        }
+        let target = players
+            .iter()
+            .filter(|(_, player_layer, mode)| player_layer.0 == layer.0 && **mode != GameMode::Spectator)
+            .map(|(player, _, _)| player.0 + DVec3::new(0.0, 1.62 / 2.0, 0.0))
+            .min_by(|a, b| a.distance_squared(pos.0).total_cmp(&b.distance_squared(pos.0)));
+        let Some(target) = target else {
+            continue;
+        };
+        let offset = (target - pos.0) / ATTRACT_RANGE;
+        let pull = 1.0 - offset.length();
+        if pull > 0.0 {
+            body.velocity += offset.normalize_or_zero() * pull * pull * 0.1;
+        }
+    }
+}
+
+pub fn merge_experience_orbs(
+    mut commands: Commands,
+    server: Res<Server>,
+    mut orbs: Query<(Entity, &Position, &EntityLayerId, &mut ExperienceOrb), Without<Despawned>>,
+) {
+    if server.current_tick() % MERGE_INTERVAL_TICKS != 0 {
+        return;
+    }
+    let snapshot: Vec<(Entity, DVec3, Entity)> = orbs.iter().map(|(e, pos, layer, _)| (e, pos.0, layer.0)).collect();
+    let mut merged = Vec::new();
+    for (i, (keep, pos, layer)) in snapshot.iter().enumerate() {
+        if merged.contains(keep) {
+            continue;
+        }
+        let mut gained = 0;
+        for (other, other_pos, other_layer) in &snapshot[i + 1..] {
+            if other_layer != layer || merged.contains(other) || pos.distance(*other_pos) > MERGE_RANGE {
+                continue;
+            }
+            if let Ok((_, _, _, orb)) = orbs.get(*other) {
+                gained += orb.value;
+            }
+            merged.push(*other);
+            commands.entity(*other).insert(Despawned);
+        }
+        if gained > 0
+            && let Ok((_, _, _, mut orb)) = orbs.get_mut(*keep)
+        {
+            orb.value += gained;
+            orb.age = 0;
+        }
+    }
+}
+
+pub fn pickup_experience_orbs(
+    mut commands: Commands,
+    orbs: Query<(Entity, &Position, &EntityLayerId, &ExperienceOrb), Without<Despawned>>,
+    mut players: Query<(&Position, &EntityLayerId, &GameMode, &mut PlayerData, &mut Client)>,
+) {
+    for (entity, pos, layer, orb) in &orbs {
+        let Some((player_pos, _, _, mut data, mut client)) = players.iter_mut().find(|(player, player_layer, mode, ..)| {
+            player_layer.0 == layer.0 && **mode != GameMode::Spectator && player.0.distance(pos.0) <= PICKUP_RANGE + 0.5
+        }) else {
+            continue;
+        };
+        data.experience = data.experience.saturating_add(orb.value);
+        let pitch = valence::rand::thread_rng().gen_range(0.55..1.25);
+        client.play_sound(Sound::EntityExperienceOrbPickup, SoundCategory::Player, player_pos.0, 0.1, pitch);
+        commands.entity(entity).insert(Despawned);
+    }
+}
+
+// Keeps the client's experience bar in line with the player's points
+pub fn sync_experience_bar(mut players: Query<(&mut Client, &PlayerData), Changed<PlayerData>>) {
+    for (mut client, data) in &mut players {
+        let (level, bar) = level_progress(data.experience);
+        client.write_packet(&ExperienceBarUpdateS2c {
+            bar,
+            level: VarInt(level as i32),
+            total_xp: VarInt(data.experience.min(i32::MAX as u32) as i32),
+        });
+    }
+}
+
+pub fn drop_boss_experience(mut commands: Commands, mut events: EventReader<BossDefeatedEvent>) {
+    for event in events.read() {
+        let amount = match event.kind {
+            BossKind::Wither => 50,
+            BossKind::EnderDragon => 12_000,
+        };
+        spawn_experience(&mut commands, EntityLayerId(event.layer), event.position, amount);
+    }
+}
@@ -0,0 +1,94 @@
+use valence::{entity::living::Health, prelude::*};
+
+use crate::world::batch::BlockBatch;
+use crate::world::deferred::PendingBlocks;
+use crate::world::events::{WorldEvent, WorldEventKind};
+
+/// Players an explosion can hurt.
+pub type ExplosionTargets<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Position, &'static mut Health, &'static GameMode, &'static EntityLayerId),
+    With<Client>,
+>;
+
+/// Blocks explosions can't break.
+fn is_blast_proof(kind: BlockKind) -> bool {
+    matches!(
+        kind,
+        BlockKind::Bedrock
+            | BlockKind::Obsidian
+            | BlockKind::CryingObsidian
+            | BlockKind::EndPortalFrame
+            | BlockKind::Barrier
+            | BlockKind::CommandBlock
+    )
+}
+
+/// Blows up a sphere of blocks around `center` and damages players in the
+/// same layer within twice the radius, falling off with distance. Returns how
+/// many blocks were destroyed. Blocks in chunks that aren't loaded are
+/// destroyed when they load.
+pub fn explode(
+    layer: &mut ChunkLayer,
+    layer_entity: Entity,
+    center: DVec3,
+    power: f32,
+    players: &mut ExplosionTargets,
+    events: &mut EventWriter<WorldEvent>,
+    pending: &mut PendingBlocks,
+) -> usize {
+    let radius = power as f64;
+    let r = radius.ceil() as i32;
+    let origin = BlockPos::from(center);
+    let mut destroyed = 0;
+    let mut batch = BlockBatch::default();
+
+    for x in -r..=r {
+        for y in -r..=r {
+            for z in -r..=r {
+                let pos = BlockPos::new(origin.x + x, origin.y + y, origin.z + z);
+                let offset = DVec3::new(pos.x as f64 + 0.5, pos.y as f64 + 0.5, pos.z as f64 + 0.5) - center;
+                if offset.length() > radius {
+                    continue;
+                }
+                let Some(block) = layer.block(pos) else {
+                    pending.replace_block_deferred(layer_entity, layer, pos, BlockState::AIR, |state| {
+                        !state.is_air() && !is_blast_proof(state.to_kind())
+                    });
+                    continue;
+                };
+                let kind = block.state.to_kind();
+                if block.state.is_air() || is_blast_proof(kind) {
+                    continue;
+                }
+                batch.set(pos, BlockState::AIR);
+                destroyed += 1;
+            }
+        }
+    }
+    batch.apply(layer);
+
+    events.send(WorldEvent {
+        layer: layer_entity,
+        pos: center,
+        source: None,
+        kind: WorldEventKind::Explosion { power },
+    });
+
+    let reach = radius * 2.0;
+    for (pos, mut health, game_mode, player_layer) in players.iter_mut() {
+        if player_layer.0 != layer_entity || matches!(game_mode, GameMode::Creative | GameMode::Spectator) {
+            continue;
+        }
+        let distance = pos.0.distance(center);
+        if distance > reach {
+            continue;
+        }
+        let exposure = 1.0 - distance / reach;
+        let damage = ((exposure * exposure + exposure) / 2.0 * 7.0 * reach + 1.0) as f32;
+        health.0 = (health.0 - damage).max(0.0);
+    }
+
+    destroyed
+}
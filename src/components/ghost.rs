@@ -0,0 +1,125 @@
+// src/components/ghost.rs
+//
+// Ghost blocks: blocks shown to a single player that aren't in the world,
+// for selection outlines, dry-run previews and minigame effects. Only that
+// player's client is told about them, so the world, everyone else and the
+// server's own block checks never see them.
+//
+// Each ghost lasts until it expires, its owner clears them, or its chunk goes
+// out of the owner's view, and expired or cleared ghosts are swapped back for
+// the real block. A real block change at the same spot replaces the ghost on
+// the client right away.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use valence::{
+    prelude::*,
+    protocol::{packets::play::BlockUpdateS2c, WritePacket},
+};
+
+/// Most ghost blocks one player can have, so a big preview can't flood them.
+pub const MAX_GHOSTS: usize = 8192;
+
+struct Ghost {
+    state: BlockState,
+    expires: Instant,
+    sent: bool,
+}
+
+#[derive(Default)]
+struct PlayerGhosts {
+    /// The layer the sent ghosts are in.
+    layer: Option<Entity>,
+    blocks: HashMap<BlockPos, Ghost>,
+    /// Sent ghosts that were cleared and need the real block back.
+    reverting: Vec<BlockPos>,
+}
+
+#[derive(Resource, Default)]
+pub struct GhostBlocks {
+    players: HashMap<Entity, PlayerGhosts>,
+}
+
+impl GhostBlocks {
+    /// Shows `blocks` to `player` for `lifetime`, replacing any ghosts they
+    /// already have at the same spots. Returns how many were shown; the rest
+    /// didn't fit under `MAX_GHOSTS`.
+    pub fn show(
+        &mut self,
+        player: Entity,
+        blocks: impl IntoIterator<Item = (BlockPos, BlockState)>,
+        lifetime: Duration,
+    ) -> usize {
+        let ghosts = self.players.entry(player).or_default();
+        let expires = Instant::now() + lifetime;
+        let mut shown = 0;
+        for (pos, state) in blocks {
+            if ghosts.blocks.len() >= MAX_GHOSTS && !ghosts.blocks.contains_key(&pos) {
+                break;
+            }
+            ghosts.blocks.insert(pos, Ghost { state, expires, sent: false });
+            shown += 1;
+        }
+        shown
+    }
+
+    /// Takes all of `player`'s ghosts away again.
+    pub fn clear(&mut self, player: Entity) {
+        if let Some(ghosts) = self.players.get_mut(&player) {
+            let sent = ghosts.blocks.drain().filter(|(_, ghost)| ghost.sent).map(|(pos, _)| pos);
+            ghosts.reverting.extend(sent);
+        }
+    }
+}
+
+// Sends new ghost blocks and puts the real blocks back for the ones that ended
+pub fn update_ghost_blocks(
+    mut ghosts: ResMut<GhostBlocks>,
+    mut clients: Query<(&mut Client, &VisibleChunkLayer, View)>,
+    layers: Query<&ChunkLayer>,
+) {
+    let now = Instant::now();
+    ghosts.players.retain(|player, ghosts| {
+        let Ok((mut client, visible_layer, view)) = clients.get_mut(*player) else {
+            return false;
+        };
+        let Ok(layer) = layers.get(visible_layer.0) else {
+            return true;
+        };
+        // The client threw the old dimension's blocks away with it
+        if ghosts.layer.is_some_and(|sent_in| sent_in != visible_layer.0) {
+            ghosts.blocks.retain(|_, ghost| !ghost.sent);
+            ghosts.reverting.clear();
+        }
+        ghosts.layer = Some(visible_layer.0);
+
+        let view = view.get();
+        let revert = |client: &mut Client, pos: BlockPos| {
+            if let Some(block) = layer.block(pos) {
+                client.write_packet(&BlockUpdateS2c { position: pos, block_id: block.state });
+            }
+        };
+        for pos in ghosts.reverting.drain(..) {
+            revert(&mut *client, pos);
+        }
+        ghosts.blocks.retain(|pos, ghost| {
+            // Chunks out of view are gone from the client, ghosts and all
+            if !view.contains(ChunkPos::from(*pos)) {
+                return false;
+            }
+            if ghost.expires <= now {
+                if ghost.sent {
+                    revert(&mut *client, *pos);
+                }
+                return false;
+            }
+            if !ghost.sent {
+                client.write_packet(&BlockUpdateS2c { position: *pos, block_id: ghost.state });
+                ghost.sent = true;
+            }
+            true
+        });
+        !ghosts.blocks.is_empty()
+    });
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use valence::{
+    entity::{
+        item::{ItemEntity, Stack},
+        Despawned,
+    },
+    prelude::*,
+};
+
+use super::container::{insert_stack, ContainerBlock, Containers};
+use crate::world::Overworld;
+
+/// Ticks a hopper waits after moving an item, same as vanilla.
+pub const HOPPER_COOLDOWN_TICKS: u32 = 8;
+
+const HOPPER_SLOTS: Range<u16> = 0..5;
+const FURNACE_INPUT: u16 = 0;
+const FURNACE_FUEL: u16 = 1;
+const FURNACE_OUTPUT: u16 = 2;
+
+/// Per-hopper transfer cooldowns. Hoppers without an entry are ready.
+#[derive(Resource, Default)]
+pub struct HopperScheduler {
+    cooldowns: HashMap<BlockPos, u32>,
+}
+
+fn is_furnace(kind: BlockKind) -> bool {
+    matches!(kind, BlockKind::Furnace | BlockKind::BlastFurnace | BlockKind::Smoker)
+}
+
+fn all_slots(inventory: &Inventory) -> Range<u16> {
+    0..inventory.slot_count()
+}
+
+/// Slots a hopper may take items out of. Furnaces only give up their output.
+fn extract_slots(kind: BlockKind, inventory: &Inventory) -> Range<u16> {
+    if is_furnace(kind) {
+        FURNACE_OUTPUT..FURNACE_OUTPUT + 1
+    } else {
+        all_slots(inventory)
+    }
+}
+
+/// Slots a hopper may put items into. Furnaces take input from above and fuel
+/// from the sides.
+fn insert_slots(kind: BlockKind, inventory: &Inventory, from_above: bool) -> Range<u16> {
+    if is_furnace(kind) {
+        if from_above {
+            FURNACE_INPUT..FURNACE_INPUT + 1
+        } else {
+            FURNACE_FUEL..FURNACE_FUEL + 1
+        }
+    } else {
+        all_slots(inventory)
+    }
+}
+
+/// Moves a single item from one inventory to another. Returns whether
+/// anything moved.
+fn transfer_one(from: &mut Inventory, from_slots: Range<u16>, to: &mut Inventory, to_slots: Range<u16>) -> bool {
+    for slot in from_slots {
+        let stack = from.slot(slot).clone();
+        if stack.is_empty() {
+            continue;
+        }
+        let single = ItemStack::new(stack.item, 1, stack.nbt.clone());
+        if insert_stack(to, to_slots.clone(), single).is_empty() {
+            if stack.count > 1 {
+                from.set_slot_amount(slot, stack.count - 1);
+            } else {
+                from.set_slot(slot, ItemStack::EMPTY);
+            }
+            return true;
+        }
+    }
+    false
+}
+
+fn facing_target(pos: BlockPos, facing: Option<PropValue>) -> BlockPos {
+    match facing {
+        Some(PropValue::North) => BlockPos::new(pos.x, pos.y, pos.z - 1),
+        Some(PropValue::South) => BlockPos::new(pos.x, pos.y, pos.z + 1),
+        Some(PropValue::East) => BlockPos::new(pos.x + 1, pos.y, pos.z),
+        Some(PropValue::West) => BlockPos::new(pos.x - 1, pos.y, pos.z),
+        _ => BlockPos::new(pos.x, pos.y - 1, pos.z),
+    }
+}
+
+// Pushes into the container a hopper faces and pulls from the one above it
+pub fn tick_hoppers(
+    mut scheduler: ResMut<HopperScheduler>,
+    containers: Res<Containers>,
+    layers: Query<&ChunkLayer, With<Overworld>>,
+    mut inventories: Query<(&mut Inventory, &ContainerBlock), Without<Client>>,
+) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+
+    let hoppers: Vec<(BlockPos, Entity)> = containers
+        .inventories
+        .iter()
+        .filter(|(_, entity)| {
+            inventories
+                .get(**entity)
+                .is_ok_and(|(_, container)| container.kind == BlockKind::Hopper)
+        })
+        .map(|(pos, entity)| (*pos, *entity))
+        .collect();
+
+    scheduler.cooldowns.retain(|pos, _| containers.inventories.contains_key(pos));
+
+    for (pos, hopper) in hoppers {
+        let cooldown = scheduler.cooldowns.entry(pos).or_insert(0);
+        if *cooldown > 0 {
+            *cooldown -= 1;
+            continue;
+        }
+        let Some(state) = layer.block(pos).map(|b| b.state) else {
+            continue;
+        };
+        // Powered hoppers are locked.
+        if state.get(PropName::Enabled) == Some(PropValue::False) {
+            continue;
+        }
+
+        let mut moved = false;
+
+        // Push
+        let facing = state.get(PropName::Facing);
+        let target_pos = facing_target(pos, facing);
+        if let Some(target) = containers.inventories.get(&target_pos) {
+            if let Ok([(mut from, _), (mut to, to_block)]) = inventories.get_many_mut([hopper, *target]) {
+                let slots = insert_slots(to_block.kind, &to, target_pos.y < pos.y);
+                moved |= transfer_one(&mut from, HOPPER_SLOTS, &mut to, slots);
+            }
+        }
+
+        // Pull
+        let above = BlockPos::new(pos.x, pos.y + 1, pos.z);
+        if let Some(source) = containers.inventories.get(&above) {
+            if let Ok([(mut from, from_block), (mut to, _)]) = inventories.get_many_mut([*source, hopper]) {
+                let slots = extract_slots(from_block.kind, &from);
+                moved |= transfer_one(&mut from, slots, &mut to, HOPPER_SLOTS);
+            }
+        }
+
+        if moved {
+            *cooldown = HOPPER_COOLDOWN_TICKS;
+        }
+    }
+}
+
+// Hoppers swallow item entities lying in the block space above them
+pub fn hopper_pickup_items(
+    mut commands: Commands,
+    containers: Res<Containers>,
+    mut items: Query<(Entity, &Position, &mut Stack), (With<ItemEntity>, Without<Despawned>)>,
+    mut inventories: Query<(&mut Inventory, &ContainerBlock), Without<Client>>,
+) {
+    for (entity, pos, mut stack) in &mut items {
+        let below = BlockPos::from(pos.0 - DVec3::new(0.0, 1.0, 0.0));
+        let Some(hopper) = containers.inventories.get(&below) else {
+            continue;
+        };
+        let Ok((mut inventory, container)) = inventories.get_mut(*hopper) else {
+            continue;
+        };
+        if container.kind != BlockKind::Hopper {
+            continue;
+        }
+        let left = insert_stack(&mut inventory, HOPPER_SLOTS, stack.0.clone());
+        if left.is_empty() {
+            commands.entity(entity).insert(Despawned);
+        } else if left.count != stack.0.count {
+            stack.0 = left;
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// src/components/hud.rs
+//
+// The action bar as a shared display. Providers fill in named elements of a
+// player's `Hud` (coordinates, facing, TPS...) and players pick which ones
+// they see with `/hud toggle`. Anything that wants to briefly tell a player
+// something sends a `HudMessage` instead of writing to the action bar
+// directly; while it's showing it replaces the persistent line, and a message
+// only replaces another one of equal or higher priority.
+
+use valence::prelude::*;
+
+use super::metrics::current_tps;
+use super::playerdata::PlayerData;
+use crate::world::throttle::ChunkThrottle;
+
+pub const COORDS: &str = "coords";
+pub const FACING: &str = "facing";
+pub const TPS: &str = "tps";
+
+/// The client fades the action bar out after about three seconds, so the
+/// line is sent again before that even if it hasn't changed.
+const REFRESH_TICKS: i64 = 40;
+const DEFAULT_MESSAGE_TICKS: i64 = 60;
+
+/// Priorities for `HudMessage`. The persistent line is below all of them.
+pub const PRIORITY_INFO: i32 = 0;
+pub const PRIORITY_WARNING: i32 = 10;
+
+/// Every element players can toggle. Providers outside this module register
+/// theirs at startup.
+#[derive(Resource)]
+pub struct HudElements(pub Vec<&'static str>);
+
+impl Default for HudElements {
+    fn default() -> Self {
+        Self(vec![COORDS, FACING, TPS])
+    }
+}
+
+impl HudElements {
+    pub fn find(&self, name: &str) -> Option<&'static str> {
+        self.0.iter().copied().find(|element| element.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A short-lived action bar message.
+#[derive(Event, Clone, Debug)]
+pub struct HudMessage {
+    pub client: Entity,
+    pub text: Text,
+    pub priority: i32,
+    pub ticks: i64,
+}
+
+impl HudMessage {
+    pub fn new(client: Entity, text: impl Into<Text>) -> Self {
+        Self {
+            client,
+            text: text.into(),
+            priority: PRIORITY_INFO,
+            ticks: DEFAULT_MESSAGE_TICKS,
+        }
+    }
+
+    pub fn warning(client: Entity, text: impl Into<Text>) -> Self {
+        Self {
+            priority: PRIORITY_WARNING,
+            ..Self::new(client, text)
+        }
+    }
+}
+
+struct ActiveMessage {
+    text: Text,
+    priority: i32,
+    until: i64,
+}
+
+#[derive(Component, Default)]
+pub struct Hud {
+    /// Latest text of each element, in the order providers first set them.
+    elements: Vec<(&'static str, Text)>,
+    message: Option<ActiveMessage>,
+    last_sent: Option<(Text, i64)>,
+}
+
+impl Hud {
+    pub fn set(&mut self, element: &'static str, text: impl Into<Text>) {
+        let text = text.into();
+        match self.elements.iter_mut().find(|(name, _)| *name == element) {
+            Some((_, current)) => *current = text,
+            None => self.elements.push((element, text)),
+        }
+    }
+
+    fn line(&self, enabled: &[String]) -> Option<Text> {
+        let mut line: Option<Text> = None;
+        for (_, text) in self.elements.iter().filter(|(name, _)| enabled.iter().any(|e| e == name)) {
+            line = Some(match line {
+                Some(line) => line + "  |  ".color(Color::DARK_GRAY) + text.clone(),
+                None => text.clone(),
+            });
+        }
+        line
+    }
+}
+
+/// Compass direction for a yaw, with the axis it points along.
+pub fn facing(yaw: f32) -> &'static str {
+    match (yaw.rem_euclid(360.0) / 90.0).round() as i32 % 4 {
+        0 => "south (+z)",
+        1 => "west (-x)",
+        2 => "north (-z)",
+        _ => "east (+x)",
+    }
+}
+
+pub fn init_clients_hud(mut commands: Commands, clients: Query<Entity, Added<Client>>) {
+    for entity in &clients {
+        commands.entity(entity).insert(Hud::default());
+    }
+}
+
+pub fn update_builtin_hud_elements(
+    mut huds: Query<(&mut Hud, &PlayerData, &Position, &Look)>,
+    throttle: Option<Res<ChunkThrottle>>,
+) {
+    let tps = current_tps(throttle.as_deref());
+    for (mut hud, data, pos, look) in &mut huds {
+        if data.hud_elements.is_empty() {
+            continue;
+        }
+        let BlockPos { x, y, z } = BlockPos::from(pos.0);
+        hud.set(COORDS, format!("{x} {y} {z}").color(Color::WHITE));
+        hud.set(FACING, facing(look.yaw).color(Color::AQUA));
+        let color = match tps {
+            t if t >= 18.0 => Color::GREEN,
+            t if t >= 15.0 => Color::YELLOW,
+            _ => Color::RED,
+        };
+        hud.set(TPS, format!("{tps:.1} TPS").color(color));
+    }
+}
+
+pub fn receive_hud_messages(mut messages: EventReader<HudMessage>, mut huds: Query<&mut Hud>, server: Res<Server>) {
+    let now = server.current_tick();
+    for message in messages.read() {
+        let Ok(mut hud) = huds.get_mut(message.client) else {
+            continue;
+        };
+        let outranked = hud
+            .message
+            .as_ref()
+            .is_some_and(|active| active.until > now && active.priority > message.priority);
+        if !outranked {
+            hud.message = Some(ActiveMessage {
+                text: message.text.clone(),
+                priority: message.priority,
+                until: now + message.ticks,
+            });
+        }
+    }
+}
+
+pub fn render_huds(mut clients: Query<(&mut Client, &mut Hud, &PlayerData)>, server: Res<Server>) {
+    let now = server.current_tick();
+    for (mut client, mut hud, data) in &mut clients {
+        if hud.message.as_ref().is_some_and(|active| active.until <= now) {
+            hud.message = None;
+        }
+        let text = match &hud.message {
+            Some(active) => Some(active.text.clone()),
+            None => hud.line(&data.hud_elements),
+        };
+        // Nothing to show; whatever was there fades out by itself
+        let Some(text) = text else {
+            hud.last_sent = None;
+            continue;
+        };
+        let unchanged = hud
+            .last_sent
+            .as_ref()
+            .is_some_and(|(sent, at)| *sent == text && now - at < REFRESH_TICKS);
+        if !unchanged {
+            client.send_action_bar_message(text.clone());
+            hud.last_sent = Some((text, now));
+        }
+    }
+}
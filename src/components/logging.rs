@@ -0,0 +1,187 @@
+// src/components/logging.rs
+//
+// Log targets per subsystem, so logs can be filtered by what they're about
+// rather than by module path. Use them as `info!(target: WORLDGEN, ...)`; the
+// target shows up in place of the old `[worldgen]`-style prefixes.
+//
+// `config/logging.json` sets the default level and per-target overrides:
+// `{ "level": "info", "targets": { "worldgen": "warn", "chat": "debug" } }`
+//
+// Everything that passes the filter is also appended to `logs/latest.log` and
+// kept in memory (the last `RECENT_CAPACITY` lines) for `/logs tail`.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use valence::log::tracing_subscriber::layer::Context;
+use valence::log::tracing_subscriber::Layer;
+use valence::log::{BoxedLayer, Level, LogPlugin};
+use valence::prelude::App;
+
+use super::config::load_config;
+
+pub const WORLDGEN: &str = "worldgen";
+pub const STORAGE: &str = "storage";
+pub const CHAT: &str = "chat";
+pub const COMMANDS: &str = "commands";
+pub const CONSOLE: &str = "console";
+pub const NET: &str = "net";
+pub const CONFIG: &str = "config";
+pub const PLAYERDATA: &str = "playerdata";
+/// Who did what and when: sessions, moderation, admin actions.
+pub const AUDIT: &str = "audit";
+/// Periodic memory and queue size reports.
+pub const MEMORY: &str = "memory";
+
+pub const LOG_DIR: &str = "logs";
+const RECENT_CAPACITY: usize = 1000;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub level: String,
+    /// Target -> level, e.g. `"worldgen": "warn"`.
+    pub targets: BTreeMap<String, String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_owned(),
+            targets: BTreeMap::new(),
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn level(&self) -> Level {
+        self.level.parse().unwrap_or(Level::INFO)
+    }
+
+    /// An `EnvFilter` directive string, e.g. `wgpu=error,worldgen=warn`.
+    fn filter(&self) -> String {
+        // Bevy's own noisy defaults, then ours
+        let mut directives = vec!["wgpu=error".to_owned(), "naga=warn".to_owned()];
+        directives.extend(self.targets.iter().map(|(target, level)| format!("{target}={level}")));
+        directives.join(",")
+    }
+}
+
+/// The log plugin configured from `logging.json`. The config is read before
+/// logging starts, so problems with it are only reported by falling back to
+/// the defaults.
+pub fn log_plugin() -> LogPlugin {
+    let config: LoggingConfig = load_config("logging.json");
+    LogPlugin {
+        level: config.level(),
+        filter: config.filter(),
+        custom_layer: capture_layer,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    /// Seconds since midnight UTC.
+    pub time: u64,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogLine {
+    pub fn timestamp(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.time / 3600 % 24, self.time / 60 % 60, self.time % 60)
+    }
+}
+
+static RECENT_LOGS: LazyLock<Mutex<VecDeque<LogLine>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::with_capacity(RECENT_CAPACITY)));
+
+/// The most recent `count` log lines at `min_level` or more severe, oldest
+/// first.
+pub fn recent_logs(count: usize, min_level: Level) -> Vec<LogLine> {
+    let recent = RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut lines: Vec<LogLine> = recent
+        .iter()
+        .rev()
+        .filter(|line| line.level <= min_level)
+        .take(count)
+        .cloned()
+        .collect();
+    lines.reverse();
+    lines
+}
+
+/// Collects an event's message and fields into one line.
+#[derive(Default)]
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+}
+
+struct CaptureLayer {
+    file: Option<Mutex<File>>,
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        let line = LogLine {
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_owned(),
+            message: visitor.message + &visitor.fields,
+        };
+
+        if let Some(file) = &self.file
+            && let Ok(mut file) = file.lock()
+        {
+            let _ = writeln!(file, "[{}] {:>5} {}: {}", line.timestamp(), line.level, line.target, line.message);
+        }
+        let mut recent = RECENT_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+}
+
+fn capture_layer(_app: &mut App) -> Option<BoxedLayer> {
+    let file = fs::create_dir_all(LOG_DIR)
+        .and_then(|_| {
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(format!("{LOG_DIR}/latest.log"))
+        })
+        .ok()
+        .map(Mutex::new);
+    Some(Box::new(CaptureLayer { file }))
+}
@@ -0,0 +1,403 @@
+// src/components/loot.rs
+//
+// Data-driven drops. A loot table is a list of pools; each pool is rolled a
+// number of times and every roll picks one entry by weight. Entries and pools
+// can have conditions, so e.g. gravel only drops flint sometimes and glass
+// only drops with the right tool.
+//
+// Tables are looked up by name: `blocks/<block>` when a block is broken,
+// `entities/<mob>` when a mob dies, `chests/<name>` for structure chests and
+// `gameplay/fishing` for fishing. Blocks without a table drop themselves.
+//
+// Silk Touch makes blocks drop themselves instead of rolling their table.
+// Entries marked `"fortune": true` drop more with Fortune, the way ores do.
+//
+// Rolls are seeded, so the same seed and table always give the same items;
+// structure chests rely on that to fill the same way wherever they're opened.
+//
+// `config/loot_tables.json`:
+// `{ "tables": { "blocks/gravel": { "pools": [ { "rolls": 1, "entries": [
+//     { "item": "gravel", "weight": 9 }, { "item": "flint", "weight": 1 } ] } ] } } }`
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tracing::{error, info};
+use valence::{
+    entity::EntityLayerId,
+    prelude::*,
+    rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng},
+};
+
+use super::config::load_config;
+use super::container::drop_stack;
+use super::enchantment::{self, FORTUNE, SILK_TOUCH};
+use super::logging::CONFIG;
+
+pub const FISHING: &str = "gameplay/fishing";
+/// Used for chests in structures that don't have their own
+/// `chests/<structure>` table.
+pub const STRUCTURE_CHESTS: &str = "chests/structure";
+
+/// Used when there's no `config/loot_tables.json`.
+const DEFAULT_TABLES: &str = r#"{
+    "blocks/grass_block": { "pools": [ { "entries": [ { "item": "dirt" } ] } ] },
+    "blocks/stone": { "pools": [ { "entries": [ { "item": "cobblestone" } ] } ] },
+    "blocks/deepslate": { "pools": [ { "entries": [ { "item": "cobbled_deepslate" } ] } ] },
+    "blocks/coal_ore": { "pools": [ { "entries": [ { "item": "coal", "fortune": true } ] } ] },
+    "blocks/deepslate_coal_ore": { "pools": [ { "entries": [ { "item": "coal", "fortune": true } ] } ] },
+    "blocks/iron_ore": { "pools": [ { "entries": [ { "item": "raw_iron", "fortune": true } ] } ] },
+    "blocks/deepslate_iron_ore": { "pools": [ { "entries": [ { "item": "raw_iron", "fortune": true } ] } ] },
+    "blocks/copper_ore": { "pools": [ { "entries": [ { "item": "raw_copper", "count": { "min": 2, "max": 5 }, "fortune": true } ] } ] },
+    "blocks/deepslate_copper_ore": { "pools": [ { "entries": [ { "item": "raw_copper", "count": { "min": 2, "max": 5 }, "fortune": true } ] } ] },
+    "blocks/gold_ore": { "pools": [ { "entries": [ { "item": "raw_gold", "fortune": true } ] } ] },
+    "blocks/deepslate_gold_ore": { "pools": [ { "entries": [ { "item": "raw_gold", "fortune": true } ] } ] },
+    "blocks/redstone_ore": { "pools": [ { "entries": [ { "item": "redstone", "count": { "min": 4, "max": 5 }, "fortune": true } ] } ] },
+    "blocks/deepslate_redstone_ore": { "pools": [ { "entries": [ { "item": "redstone", "count": { "min": 4, "max": 5 }, "fortune": true } ] } ] },
+    "blocks/lapis_ore": { "pools": [ { "entries": [ { "item": "lapis_lazuli", "count": { "min": 4, "max": 9 }, "fortune": true } ] } ] },
+    "blocks/deepslate_lapis_ore": { "pools": [ { "entries": [ { "item": "lapis_lazuli", "count": { "min": 4, "max": 9 }, "fortune": true } ] } ] },
+    "blocks/diamond_ore": { "pools": [ { "entries": [ { "item": "diamond", "fortune": true } ] } ] },
+    "blocks/deepslate_diamond_ore": { "pools": [ { "entries": [ { "item": "diamond", "fortune": true } ] } ] },
+    "blocks/emerald_ore": { "pools": [ { "entries": [ { "item": "emerald", "fortune": true } ] } ] },
+    "blocks/deepslate_emerald_ore": { "pools": [ { "entries": [ { "item": "emerald", "fortune": true } ] } ] },
+    "blocks/gravel": { "pools": [ { "entries": [
+        { "item": "gravel", "weight": 9 },
+        { "item": "flint", "weight": 1 }
+    ] } ] },
+    "blocks/glass": { "pools": [ { "conditions": [ { "condition": "match_tool", "items": ["shears"] } ],
+        "entries": [ { "item": "glass" } ] } ] },
+    "blocks/spawner": { "pools": [] },
+    "blocks/tall_grass": { "pools": [ { "entries": [
+        { "item": "wheat_seeds", "weight": 1 },
+        { "weight": 7 }
+    ] } ] },
+    "blocks/grass": { "pools": [ { "entries": [
+        { "item": "wheat_seeds", "weight": 1 },
+        { "weight": 7 }
+    ] } ] },
+    "entities/zombie": { "pools": [ { "entries": [ { "item": "rotten_flesh", "count": { "min": 0, "max": 2 } } ] } ] },
+    "entities/skeleton": { "pools": [
+        { "entries": [ { "item": "bone", "count": { "min": 0, "max": 2 } } ] },
+        { "entries": [ { "item": "arrow", "count": { "min": 0, "max": 2 } } ] }
+    ] },
+    "entities/spider": { "pools": [ { "entries": [ { "item": "string", "count": { "min": 0, "max": 2 } } ] } ] },
+    "entities/creeper": { "pools": [ { "entries": [ { "item": "gunpowder", "count": { "min": 0, "max": 2 } } ] } ] },
+    "chests/structure": { "pools": [
+        { "rolls": { "min": 2, "max": 5 }, "entries": [
+            { "item": "bread", "weight": 15, "count": { "min": 1, "max": 4 } },
+            { "item": "apple", "weight": 15, "count": { "min": 1, "max": 3 } },
+            { "item": "iron_ingot", "weight": 10, "count": { "min": 1, "max": 5 } },
+            { "item": "gold_ingot", "weight": 5, "count": { "min": 1, "max": 3 } },
+            { "item": "saddle", "weight": 3 },
+            { "item": "diamond", "weight": 1, "count": { "min": 1, "max": 2 } }
+        ] }
+    ] },
+    "gameplay/fishing": { "pools": [ { "entries": [
+        { "item": "cod", "weight": 60 },
+        { "item": "salmon", "weight": 25 },
+        { "item": "pufferfish", "weight": 13 },
+        { "item": "tropical_fish", "weight": 2 }
+    ] } ] }
+}"#;
+
+/// Either a fixed number or an inclusive range.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(untagged)]
+pub enum Amount {
+    Exact(u32),
+    Range { min: u32, max: u32 },
+}
+
+impl Default for Amount {
+    fn default() -> Self {
+        Amount::Exact(1)
+    }
+}
+
+impl Amount {
+    fn roll(self, rng: &mut impl Rng) -> u32 {
+        match self {
+            Amount::Exact(n) => n,
+            Amount::Range { min, max } => rng.gen_range(min..=max.max(min)),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum LootCondition {
+    RandomChance { chance: f32 },
+    /// Passes if the tool used is one of `items`.
+    MatchTool { items: Vec<String> },
+}
+
+/// What's known about how the loot came to be dropped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LootContext {
+    /// The item held by whoever broke the block or killed the mob.
+    pub tool: Option<ItemKind>,
+    pub silk_touch: bool,
+    /// Fortune level of the tool.
+    pub fortune: u32,
+}
+
+impl LootContext {
+    pub fn with_tool(stack: &ItemStack) -> Self {
+        Self {
+            tool: Some(stack.item),
+            silk_touch: enchantment::level(stack, SILK_TOUCH) > 0,
+            fortune: enchantment::level(stack, FORTUNE),
+        }
+    }
+}
+
+/// Blocks that drop nothing even with Silk Touch.
+fn resists_silk_touch(block: BlockKind) -> bool {
+    matches!(block, BlockKind::Spawner | BlockKind::BuddingAmethyst | BlockKind::ReinforcedDeepslate)
+}
+
+impl LootCondition {
+    fn test(&self, rng: &mut impl Rng, context: &LootContext) -> bool {
+        match self {
+            LootCondition::RandomChance { chance } => rng.r#gen::<f32>() < *chance,
+            LootCondition::MatchTool { items } => context
+                .tool
+                .is_some_and(|tool| items.iter().any(|item| item.trim_start_matches("minecraft:") == tool.to_str())),
+        }
+    }
+}
+
+fn all_pass(conditions: &[LootCondition], rng: &mut impl Rng, context: &LootContext) -> bool {
+    conditions.iter().all(|condition| condition.test(rng, context))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LootEntryConfig {
+    /// Leave out for an entry that drops nothing.
+    pub item: Option<String>,
+    pub weight: u32,
+    pub count: Amount,
+    /// Whether Fortune multiplies the count.
+    pub fortune: bool,
+    pub conditions: Vec<LootCondition>,
+}
+
+impl Default for LootEntryConfig {
+    fn default() -> Self {
+        Self {
+            item: None,
+            weight: 1,
+            count: Amount::default(),
+            fortune: false,
+            conditions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LootPoolConfig {
+    pub rolls: Amount,
+    pub conditions: Vec<LootCondition>,
+    pub entries: Vec<LootEntryConfig>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LootTableConfig {
+    pub pools: Vec<LootPoolConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct LootConfig {
+    pub tables: HashMap<String, LootTableConfig>,
+}
+
+impl Default for LootConfig {
+    fn default() -> Self {
+        Self {
+            tables: serde_json::from_str(DEFAULT_TABLES).unwrap_or_default(),
+        }
+    }
+}
+
+struct LootEntry {
+    item: Option<ItemKind>,
+    weight: u32,
+    count: Amount,
+    fortune: bool,
+    conditions: Vec<LootCondition>,
+}
+
+struct LootPool {
+    rolls: Amount,
+    conditions: Vec<LootCondition>,
+    entries: Vec<LootEntry>,
+}
+
+impl LootPool {
+    fn roll(&self, rng: &mut impl Rng, context: &LootContext, drops: &mut Vec<ItemStack>) {
+        if !all_pass(&self.conditions, rng, context) {
+            return;
+        }
+        for _ in 0..self.rolls.roll(rng) {
+            let candidates: Vec<&LootEntry> = self
+                .entries
+                .iter()
+                .filter(|entry| all_pass(&entry.conditions, rng, context))
+                .collect();
+            let total: u32 = candidates.iter().map(|entry| entry.weight).sum();
+            if total == 0 {
+                return;
+            }
+            let mut pick = rng.gen_range(0..total);
+            let Some(entry) = candidates.into_iter().find(|entry| {
+                if pick < entry.weight {
+                    return true;
+                }
+                pick -= entry.weight;
+                false
+            }) else {
+                continue;
+            };
+            let Some(item) = entry.item else {
+                continue;
+            };
+            let mut count = entry.count.roll(rng);
+            if entry.fortune && context.fortune > 0 {
+                // Vanilla's ore bonus: multiplied by 1 to fortune + 1, with 1
+                // twice as likely as any other
+                count *= rng.gen_range(0..context.fortune + 2).max(1);
+            }
+            while count > 0 {
+                let amount = count.min(item.max_stack() as u32);
+                drops.push(ItemStack::new(item, amount as i8, None));
+                count -= amount;
+            }
+        }
+    }
+}
+
+pub struct LootTable {
+    pools: Vec<LootPool>,
+}
+
+impl LootTable {
+    fn parse(name: &str, config: &LootTableConfig) -> Self {
+        let pools = config
+            .pools
+            .iter()
+            .map(|pool| LootPool {
+                rolls: pool.rolls,
+                conditions: pool.conditions.clone(),
+                entries: pool
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        let item = entry.item.as_deref().and_then(|item| {
+                            let kind = ItemKind::from_str(item.trim_start_matches("minecraft:"));
+                            if kind.is_none() {
+                                error!(target: CONFIG, "unknown item {item} in loot table {name}");
+                            }
+                            kind
+                        });
+                        LootEntry {
+                            item,
+                            weight: entry.weight,
+                            count: entry.count,
+                            fortune: entry.fortune,
+                            conditions: entry.conditions.clone(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { pools }
+    }
+
+    /// Rolls every pool. The same seed and context always give the same
+    /// items.
+    pub fn roll(&self, seed: u64, context: &LootContext) -> Vec<ItemStack> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut drops = Vec::new();
+        for pool in &self.pools {
+            pool.roll(&mut rng, context, &mut drops);
+        }
+        drops
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LootTables {
+    tables: HashMap<String, LootTable>,
+}
+
+impl LootTables {
+    pub fn get(&self, name: &str) -> Option<&LootTable> {
+        self.tables.get(name)
+    }
+
+    /// What breaking a block drops: its loot table, or the block itself if it
+    /// doesn't have one or was mined with Silk Touch.
+    pub fn block_drops(&self, block: BlockKind, context: &LootContext) -> Vec<ItemStack> {
+        if context.silk_touch && !resists_silk_touch(block) && block.to_item_kind() != ItemKind::Air {
+            return vec![ItemStack::new(block.to_item_kind(), 1, None)];
+        }
+        match self.get(&block_table(block)) {
+            Some(table) => table.roll(valence::rand::random(), context),
+            None if block.to_item_kind() != ItemKind::Air => vec![ItemStack::new(block.to_item_kind(), 1, None)],
+            None => Vec::new(),
+        }
+    }
+
+    /// Rolls a table and spreads the items over random slots of a container
+    /// with `slots` slots. Items that don't fit are lost.
+    pub fn fill_container(&self, table: &str, seed: u64, slots: u16) -> Vec<(u16, ItemStack)> {
+        let Some(table) = self.get(table) else {
+            return Vec::new();
+        };
+        let mut order: Vec<u16> = (0..slots).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(seed.rotate_left(17)));
+        order.into_iter().zip(table.roll(seed, &LootContext::default())).collect()
+    }
+
+    /// Rolls a table and drops the items at `pos`. Does nothing if the table
+    /// doesn't exist.
+    pub fn drop_loot(
+        &self,
+        commands: &mut Commands,
+        table: &str,
+        layer: EntityLayerId,
+        pos: BlockPos,
+        context: &LootContext,
+    ) {
+        let Some(table) = self.get(table) else {
+            return;
+        };
+        for stack in table.roll(valence::rand::random(), context) {
+            drop_stack(commands, layer, pos, stack);
+        }
+    }
+}
+
+pub fn block_table(block: BlockKind) -> String {
+    format!("blocks/{}", block.to_str())
+}
+
+pub fn entity_table(kind: EntityKind) -> Option<String> {
+    let name = kind.translation_key()?.strip_prefix("entity.minecraft.")?;
+    Some(format!("entities/{name}"))
+}
+
+pub fn setup_loot_tables(mut commands: Commands) {
+    let config: LootConfig = load_config("loot_tables.json");
+    let tables: HashMap<String, LootTable> = config
+        .tables
+        .iter()
+        .map(|(name, table)| (name.clone(), LootTable::parse(name, table)))
+        .collect();
+    info!(target: CONFIG, "loaded {} loot tables", tables.len());
+    commands.insert_resource(LootTables { tables });
+}
@@ -0,0 +1,143 @@
+// src/components/memory.rs
+//
+// What the server is holding in memory: process RSS, loaded chunks, entities
+// and the work queues that can grow under load. It's logged every
+// `log_interval_secs` and shown by `/mem`, with a warning whenever one of the
+// soft limits is passed, to help pick how much memory a deployment needs.
+//
+// `config/memory.json`:
+// `{ "log_interval_secs": 300, "rss_soft_limit_mb": 2048,
+//    "chunk_soft_limit": 20000, "entity_soft_limit": 5000 }`
+// Limits are off unless set.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tracing::{info, warn};
+use valence::prelude::*;
+
+use super::config::load_config;
+use super::logging::MEMORY;
+use super::metrics::resident_memory_bytes;
+use crate::world::deferred::PendingBlocks;
+use crate::world::storage::ChunkSaver;
+use crate::world::GameState;
+
+#[derive(Deserialize, Resource, Clone, Debug)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// 0 turns the periodic log line off.
+    pub log_interval_secs: u64,
+    pub rss_soft_limit_mb: Option<u64>,
+    pub chunk_soft_limit: Option<usize>,
+    pub entity_soft_limit: Option<usize>,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            log_interval_secs: 300,
+            rss_soft_limit_mb: None,
+            chunk_soft_limit: None,
+            entity_soft_limit: None,
+        }
+    }
+}
+
+pub struct MemoryReport {
+    pub rss_bytes: Option<u64>,
+    /// Loaded chunks across every layer.
+    pub chunks: usize,
+    pub entities: usize,
+    /// The most common entity kinds, most common first.
+    pub top_entities: Vec<(EntityKind, usize)>,
+    pub queued_chunks: usize,
+    pub queued_blocks: usize,
+    pub unsaved_chunks: usize,
+}
+
+impl MemoryReport {
+    pub fn gather(
+        layers: &Query<&ChunkLayer>,
+        entities: &Query<&EntityKind>,
+        state: Option<&GameState>,
+        pending: Option<&PendingBlocks>,
+        saver: Option<&ChunkSaver>,
+    ) -> Self {
+        let mut by_kind: HashMap<EntityKind, usize> = HashMap::new();
+        for kind in entities {
+            *by_kind.entry(*kind).or_default() += 1;
+        }
+        let mut top_entities: Vec<(EntityKind, usize)> = by_kind.into_iter().collect();
+        top_entities.sort_by(|a, b| b.1.cmp(&a.1));
+        top_entities.truncate(5);
+
+        Self {
+            rss_bytes: resident_memory_bytes(),
+            chunks: layers.iter().map(|layer| layer.chunks().count()).sum(),
+            entities: entities.iter().count(),
+            top_entities,
+            queued_chunks: state.map_or(0, GameState::queued_chunks),
+            queued_blocks: pending.map_or(0, PendingBlocks::queued_changes),
+            unsaved_chunks: saver.map_or(0, ChunkSaver::dirty_count),
+        }
+    }
+
+    pub fn rss_text(&self) -> String {
+        self.rss_bytes
+            .map_or_else(|| "unknown".to_owned(), |bytes| format!("{} MB", bytes / 1024 / 1024))
+    }
+
+    /// One line per soft limit that's been passed.
+    pub fn warnings(&self, config: &MemoryConfig) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let (Some(limit), Some(bytes)) = (config.rss_soft_limit_mb, self.rss_bytes)
+            && bytes / 1024 / 1024 > limit
+        {
+            warnings.push(format!("memory use {} is over the soft limit of {limit} MB", self.rss_text()));
+        }
+        if let Some(limit) = config.chunk_soft_limit
+            && self.chunks > limit
+        {
+            warnings.push(format!("{} chunks loaded, over the soft limit of {limit}", self.chunks));
+        }
+        if let Some(limit) = config.entity_soft_limit
+            && self.entities > limit
+        {
+            warnings.push(format!("{} entities, over the soft limit of {limit}", self.entities));
+        }
+        warnings
+    }
+}
+
+pub fn setup_memory_reports(mut commands: Commands) {
+    commands.insert_resource(load_config::<MemoryConfig>("memory.json"));
+}
+
+pub fn log_memory_usage(
+    config: Res<MemoryConfig>,
+    server: Res<Server>,
+    layers: Query<&ChunkLayer>,
+    entities: Query<&EntityKind>,
+    state: Option<Res<GameState>>,
+    pending: Option<Res<PendingBlocks>>,
+    saver: Option<Res<ChunkSaver>>,
+) {
+    if config.log_interval_secs == 0 || server.current_tick() % (config.log_interval_secs * 20) as i64 != 0 {
+        return;
+    }
+    let report = MemoryReport::gather(&layers, &entities, state.as_deref(), pending.as_deref(), saver.as_deref());
+    info!(
+        target: MEMORY,
+        "rss {}, {} chunks, {} entities, {} chunks queued, {} block changes queued, {} unsaved chunks",
+        report.rss_text(),
+        report.chunks,
+        report.entities,
+        report.queued_chunks,
+        report.queued_blocks,
+        report.unsaved_chunks,
+    );
+    for warning in report.warnings(&config) {
+        warn!(target: MEMORY, "{warning}");
+    }
+}
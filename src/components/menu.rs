@@ -0,0 +1,227 @@
+// src/components/menu.rs
+//
+// Chest menus: a virtual chest full of item icons, some of which are buttons.
+// The chest is read-only, so icons can't be taken out, and clicking a button
+// either runs a command as the player, with their own permissions, or calls
+// back into the server with the world and the player who clicked.
+//
+// Anything can open one with `open_menu`, from a system through
+// `commands.add(move |world: &mut World| open_menu(world, player, menu))`.
+// Long lists go through `Menu::paged`, which splits them over pages with
+// arrows to flip between them.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use valence::{
+    command::CommandExecutionEvent,
+    inventory::{ClickMode, ClickSlotEvent},
+    nbt::{compound, List},
+    prelude::*,
+};
+
+pub const MAX_ROWS: u8 = 6;
+const ROW: u16 = 9;
+
+/// Called with the world and the player who clicked.
+pub type MenuCallback = Arc<dyn Fn(&mut World, Entity) + Send + Sync>;
+
+#[derive(Clone)]
+pub enum MenuAction {
+    /// Runs a command as the player, without the leading `/`.
+    Command(String),
+    Callback(MenuCallback),
+    Close,
+}
+
+impl MenuAction {
+    pub fn callback(callback: impl Fn(&mut World, Entity) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(callback))
+    }
+}
+
+/// An icon, and what clicking it does. Icons without an action are just
+/// for show.
+#[derive(Clone)]
+pub struct MenuButton {
+    pub icon: ItemStack,
+    pub action: Option<MenuAction>,
+}
+
+impl MenuButton {
+    pub fn new(icon: ItemStack, action: MenuAction) -> Self {
+        Self { icon, action: Some(action) }
+    }
+}
+
+pub struct Menu {
+    title: Text,
+    rows: u8,
+    buttons: HashMap<u16, MenuButton>,
+}
+
+impl Menu {
+    /// An empty chest menu, between 1 and `MAX_ROWS` rows tall.
+    pub fn new(title: impl Into<Text>, rows: u8) -> Self {
+        Self {
+            title: title.into(),
+            rows: rows.clamp(1, MAX_ROWS),
+            buttons: HashMap::new(),
+        }
+    }
+
+    pub fn slots(&self) -> u16 {
+        u16::from(self.rows) * ROW
+    }
+
+    /// Puts a button in `slot`, counted from the top left. Slots outside
+    /// the menu are ignored.
+    pub fn button(mut self, slot: u16, button: MenuButton) -> Self {
+        if slot < self.slots() {
+            self.buttons.insert(slot, button);
+        }
+        self
+    }
+
+    pub fn icon(self, slot: u16, icon: ItemStack) -> Self {
+        self.button(slot, MenuButton { icon, action: None })
+    }
+
+    /// `page` of `buttons`, filling every row but the bottom one, which has
+    /// arrows to the previous and next pages when there are any.
+    pub fn paged(title: impl Into<Text>, rows: u8, buttons: Vec<MenuButton>, page: usize) -> Self {
+        paged_menu(title.into(), rows.clamp(2, MAX_ROWS), Arc::new(buttons), page)
+    }
+
+    fn inventory_kind(&self) -> InventoryKind {
+        match self.rows {
+            1 => InventoryKind::Generic9x1,
+            2 => InventoryKind::Generic9x2,
+            3 => InventoryKind::Generic9x3,
+            4 => InventoryKind::Generic9x4,
+            5 => InventoryKind::Generic9x5,
+            _ => InventoryKind::Generic9x6,
+        }
+    }
+}
+
+fn paged_menu(title: Text, rows: u8, buttons: Arc<Vec<MenuButton>>, page: usize) -> Menu {
+    let per_page = usize::from(rows - 1) * usize::from(ROW);
+    let pages = buttons.len().div_ceil(per_page).max(1);
+    let page = page.min(pages - 1);
+    let mut menu = Menu::new(title.clone() + format!(" ({}/{pages})", page + 1), rows);
+    for (slot, button) in buttons.iter().skip(page * per_page).take(per_page).enumerate() {
+        menu = menu.button(slot as u16, button.clone());
+    }
+
+    let bottom = menu.slots() - ROW;
+    let arrow = |to: usize, name: &str| {
+        let (title, buttons) = (title.clone(), buttons.clone());
+        MenuButton::new(
+            icon(ItemKind::Arrow, name, []),
+            MenuAction::callback(move |world, player| {
+                open_menu(world, player, paged_menu(title.clone(), rows, buttons.clone(), to));
+            }),
+        )
+    };
+    if page > 0 {
+        menu = menu.button(bottom, arrow(page - 1, "Previous page"));
+    }
+    if page + 1 < pages {
+        menu = menu.button(bottom + ROW - 1, arrow(page + 1, "Next page"));
+    }
+    menu
+}
+
+/// An item with a name and lines of lore under it, for menu icons.
+pub fn icon(item: ItemKind, name: impl Into<Text>, lore: impl IntoIterator<Item = Text>) -> ItemStack {
+    // Names and lore are italic unless they say otherwise
+    let json = |text: Text| serde_json::to_string(&text.not_italic()).unwrap_or_default();
+    let lore: Vec<String> = lore.into_iter().map(json).collect();
+    let mut display = compound! { "Name" => json(name.into()) };
+    if !lore.is_empty() {
+        display.insert("Lore", List::String(lore));
+    }
+    ItemStack::new(item, 1, Some(compound! { "display" => display }))
+}
+
+/// Put on a client while they have a menu open.
+#[derive(Component)]
+pub struct ViewingMenu {
+    inventory: Entity,
+    buttons: HashMap<u16, MenuButton>,
+}
+
+/// Opens `menu` for `player`, replacing whatever they had open.
+pub fn open_menu(world: &mut World, player: Entity, menu: Menu) {
+    if world.get::<Client>(player).is_none() {
+        return;
+    }
+    let mut inventory = Inventory::with_title(menu.inventory_kind(), menu.title.clone());
+    for (slot, button) in &menu.buttons {
+        inventory.set_slot(*slot, button.icon.clone());
+    }
+    inventory.readonly = true;
+    let inventory = world.spawn(inventory).id();
+
+    if let Some(old) = world.get::<ViewingMenu>(player).map(|viewing| viewing.inventory) {
+        world.despawn(old);
+    }
+    world.entity_mut(player).insert((
+        OpenInventory::new(inventory),
+        ViewingMenu {
+            inventory,
+            buttons: menu.buttons,
+        },
+    ));
+}
+
+pub fn click_menus(
+    mut commands: Commands,
+    mut events: EventReader<ClickSlotEvent>,
+    clients: Query<(&ViewingMenu, &OpenInventory)>,
+    mut executions: EventWriter<CommandExecutionEvent>,
+) {
+    for event in events.read() {
+        // Double clicks and drags come in as well as the clicks they start
+        // with, which would run a button twice
+        if !matches!(event.mode, ClickMode::Click | ClickMode::ShiftClick) || event.window_id == 0 {
+            continue;
+        }
+        let Ok((viewing, open)) = clients.get(event.client) else {
+            continue;
+        };
+        if open.entity != viewing.inventory {
+            continue;
+        }
+        let Some(action) = u16::try_from(event.slot_id)
+            .ok()
+            .and_then(|slot| viewing.buttons.get(&slot))
+            .and_then(|button| button.action.clone())
+        else {
+            continue;
+        };
+
+        let player = event.client;
+        match action {
+            MenuAction::Command(command) => {
+                executions.send(CommandExecutionEvent { command, executor: player });
+            }
+            MenuAction::Callback(callback) => commands.add(move |world: &mut World| callback(world, player)),
+            MenuAction::Close => {
+                commands.entity(player).remove::<OpenInventory>();
+            }
+        }
+    }
+}
+
+// Cleans up after menus that were closed, or replaced by another screen
+pub fn close_menus(mut commands: Commands, clients: Query<(Entity, &ViewingMenu, Option<&OpenInventory>)>) {
+    for (entity, viewing, open) in &clients {
+        if open.is_some_and(|open| open.entity == viewing.inventory) {
+            continue;
+        }
+        commands.entity(viewing.inventory).despawn();
+        commands.entity(entity).remove::<ViewingMenu>();
+    }
+}
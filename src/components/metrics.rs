@@ -0,0 +1,212 @@
+// src/components/metrics.rs
+//
+// Server health over time. Every `sample_interval_secs` the player count, TPS
+// and memory use are recorded into a ring buffer holding the last `capacity`
+// samples, which is written to `world/metrics.json` every so often so the
+// history survives restarts.
+//
+// The history is served as json over a tiny HTTP server so dashboards can
+// graph it directly:
+//   GET /api/metrics         every sample, oldest first
+//   GET /api/metrics/latest  the newest sample
+//
+// `config/metrics.json`:
+// `{ "http_bind": "127.0.0.1:25580", "sample_interval_secs": 60,
+//    "capacity": 1440, "save_every_samples": 5 }`
+// An empty `http_bind` turns the HTTP server off; samples are still recorded.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use valence::prelude::*;
+
+use super::config::load_config;
+use super::logging::{NET, STORAGE};
+use crate::world::throttle::ChunkThrottle;
+
+pub const METRICS_FILE: &str = "world/metrics.json";
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Address the HTTP API listens on, empty to disable it.
+    pub http_bind: String,
+    pub sample_interval_secs: u64,
+    /// Samples kept; older ones are dropped. The default is a day's worth.
+    pub capacity: usize,
+    /// The history is written to disk after this many new samples.
+    pub save_every_samples: u32,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            http_bind: "127.0.0.1:25580".to_owned(),
+            sample_interval_secs: 60,
+            capacity: 1440,
+            save_every_samples: 5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct MetricsSample {
+    /// Unix time in seconds.
+    pub time: u64,
+    pub players: usize,
+    pub tps: f32,
+    /// Resident memory of the process, if the platform reports it.
+    pub memory_bytes: Option<u64>,
+}
+
+#[derive(Resource)]
+pub struct MetricsHistory {
+    config: MetricsConfig,
+    /// Shared with the HTTP thread.
+    samples: Arc<Mutex<VecDeque<MetricsSample>>>,
+    unsaved: u32,
+}
+
+/// Resident memory of the server process in bytes. Only Linux reports it.
+pub fn resident_memory_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Ticks per second, worked out from the smoothed tick time. Ticks that finish
+/// early still wait for the next one, so this caps at 20.
+pub fn current_tps(throttle: Option<&ChunkThrottle>) -> f32 {
+    throttle.map_or(20.0, |t| 1000.0 / t.mspt.max(50.0))
+}
+
+fn load_history(capacity: usize) -> VecDeque<MetricsSample> {
+    let Ok(contents) = fs::read_to_string(METRICS_FILE) else {
+        return VecDeque::new();
+    };
+    match serde_json::from_str::<VecDeque<MetricsSample>>(&contents) {
+        Ok(mut samples) => {
+            while samples.len() > capacity {
+                samples.pop_front();
+            }
+            info!(target: STORAGE, "loaded {} metrics samples", samples.len());
+            samples
+        }
+        Err(e) => {
+            error!(target: STORAGE, "failed to parse {METRICS_FILE}: {e}");
+            VecDeque::new()
+        }
+    }
+}
+
+fn save_history(samples: &VecDeque<MetricsSample>) {
+    let result = fs::create_dir_all(Path::new(METRICS_FILE).parent().unwrap_or(Path::new(".")))
+        .and_then(|_| serde_json::to_string(samples).map_err(std::io::Error::other))
+        .and_then(|json| fs::write(METRICS_FILE, json));
+    if let Err(e) = result {
+        error!(target: STORAGE, "failed to save {METRICS_FILE}: {e}");
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn handle_request(mut stream: TcpStream, samples: &Mutex<VecDeque<MetricsSample>>, interval_secs: u64) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", r#"{"error":"only GET is supported"}"#);
+    }
+    // Query strings are ignored
+    let path = path.split('?').next().unwrap_or("");
+    let body = {
+        let samples = samples.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match path {
+            "/api/metrics" => serde_json::json!({ "interval_secs": interval_secs, "samples": &*samples }),
+            "/api/metrics/latest" => serde_json::json!(samples.back()),
+            _ => return respond(&mut stream, "404 Not Found", r#"{"error":"not found"}"#),
+        }
+    };
+    respond(&mut stream, "200 OK", &body.to_string())
+}
+
+fn start_http_server(bind: &str, samples: Arc<Mutex<VecDeque<MetricsSample>>>, interval_secs: u64) {
+    let listener = match TcpListener::bind(bind) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(target: NET, "failed to start the metrics API on {bind}: {e}");
+            return;
+        }
+    };
+    info!(target: NET, "metrics API listening on http://{bind}/api/metrics");
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| handle_request(stream, &samples, interval_secs));
+            if let Err(e) = result {
+                warn!(target: NET, "metrics API request failed: {e}");
+            }
+        }
+    });
+}
+
+pub fn setup_metrics(mut commands: Commands) {
+    let config = load_config::<MetricsConfig>("metrics.json");
+    let samples = Arc::new(Mutex::new(load_history(config.capacity)));
+    if !config.http_bind.is_empty() {
+        start_http_server(&config.http_bind, samples.clone(), config.sample_interval_secs);
+    }
+    commands.insert_resource(MetricsHistory {
+        config,
+        samples,
+        unsaved: 0,
+    });
+}
+
+pub fn record_metrics(
+    mut history: ResMut<MetricsHistory>,
+    server: Res<Server>,
+    clients: Query<(), With<Client>>,
+    throttle: Option<Res<ChunkThrottle>>,
+) {
+    let interval_ticks = (history.config.sample_interval_secs.max(1) * 20) as i64;
+    if server.current_tick() % interval_ticks != 0 {
+        return;
+    }
+    let sample = MetricsSample {
+        time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        players: clients.iter().count(),
+        tps: current_tps(throttle.as_deref()),
+        memory_bytes: resident_memory_bytes(),
+    };
+
+    let capacity = history.config.capacity.max(1);
+    let mut samples = history.samples.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    samples.push_back(sample);
+    while samples.len() > capacity {
+        samples.pop_front();
+    }
+    drop(samples);
+
+    history.unsaved += 1;
+    if history.unsaved >= history.config.save_every_samples {
+        history.unsaved = 0;
+        save_history(&history.samples.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    }
+}
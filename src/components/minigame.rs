@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tracing::info;
+use valence::prelude::*;
+
+use super::config::load_config;
+use super::core::new_crystal_message;
+use crate::world::WorldSpawn;
+
+const TICKS_PER_SECOND: u32 = 20;
+const ENDING_TICKS: u32 = 5 * TICKS_PER_SECOND;
+
+fn default_countdown() -> u32 {
+    10
+}
+
+// --- Arena definitions ---
+
+/// An arena as defined in `config/arenas.json`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ArenaDefinition {
+    pub name: String,
+    /// Which game mode runs in this arena (e.g. "spleef").
+    pub mode: String,
+    /// Two opposite corners of the playable region.
+    pub region: [[i32; 3]; 2],
+    /// Where players wait before the game starts.
+    pub lobby: [f64; 3],
+    pub spawn_points: Vec<[f64; 3]>,
+    pub min_players: usize,
+    pub max_players: usize,
+    #[serde(default = "default_countdown")]
+    pub countdown_seconds: u32,
+}
+
+impl ArenaDefinition {
+    pub fn region_min(&self) -> BlockPos {
+        let [a, b] = self.region;
+        BlockPos::new(a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2]))
+    }
+
+    pub fn region_max(&self) -> BlockPos {
+        let [a, b] = self.region;
+        BlockPos::new(a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2]))
+    }
+
+    pub fn contains(&self, pos: BlockPos) -> bool {
+        let (min, max) = (self.region_min(), self.region_max());
+        (min.x..=max.x).contains(&pos.x)
+            && (min.y..=max.y).contains(&pos.y)
+            && (min.z..=max.z).contains(&pos.z)
+    }
+
+    pub fn lobby_pos(&self) -> DVec3 {
+        DVec3::from_array(self.lobby)
+    }
+
+    /// Spawn point for the n-th player, wrapping around if there are more
+    /// players than spawn points.
+    pub fn spawn_pos(&self, n: usize) -> DVec3 {
+        self.spawn_points
+            .get(n % self.spawn_points.len().max(1))
+            .map(|p| DVec3::from_array(*p))
+            .unwrap_or_else(|| self.lobby_pos())
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct ArenaConfig {
+    #[serde(default)]
+    pub arenas: Vec<ArenaDefinition>,
+}
+
+// --- Game state ---
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStage {
+    /// Waiting for enough players.
+    Lobby,
+    Countdown { ticks_left: u32 },
+    InGame,
+    Ending { ticks_left: u32 },
+}
+
+impl GameStage {
+    pub fn is_joinable(&self) -> bool {
+        matches!(self, GameStage::Lobby | GameStage::Countdown { .. })
+    }
+}
+
+pub struct Game {
+    pub arena: ArenaDefinition,
+    pub stage: GameStage,
+    pub players: Vec<Entity>,
+}
+
+/// Every arena and the game currently running in it, keyed by arena name.
+#[derive(Resource, Default)]
+pub struct Minigames {
+    pub games: HashMap<String, Game>,
+}
+
+impl Minigames {
+    /// Returns the name of the arena the player is currently in.
+    pub fn arena_of(&self, player: Entity) -> Option<&str> {
+        self.games
+            .iter()
+            .find(|(_, game)| game.players.contains(&player))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+// --- Events ---
+
+/// Sent to put a player into an arena (by `/minigame join` or another system).
+#[derive(Event)]
+pub struct JoinMinigameRequest {
+    pub player: Entity,
+    pub arena: String,
+}
+
+#[derive(Event)]
+pub struct LeaveMinigameRequest {
+    pub player: Entity,
+}
+
+/// Sent by a game mode when its game is decided.
+#[derive(Event)]
+pub struct EndMinigameRequest {
+    pub arena: String,
+    pub winners: Vec<Entity>,
+}
+
+/// Fired whenever a game moves to a new stage. Game modes should filter on
+/// `mode` and set up / tear down their state from here.
+#[derive(Event, Clone)]
+pub struct GameStageChangeEvent {
+    pub arena: String,
+    pub mode: String,
+    pub from: GameStage,
+    pub to: GameStage,
+}
+
+#[derive(Event, Clone)]
+pub struct PlayerJoinedGameEvent {
+    pub arena: String,
+    pub player: Entity,
+}
+
+#[derive(Event, Clone)]
+pub struct PlayerLeftGameEvent {
+    pub arena: String,
+    pub player: Entity,
+}
+
+fn set_stage(
+    name: &str,
+    game: &mut Game,
+    to: GameStage,
+    stage_events: &mut EventWriter<GameStageChangeEvent>,
+) {
+    let from = game.stage;
+    game.stage = to;
+    // Only announce actual transitions, not countdown ticks.
+    if std::mem::discriminant(&from) != std::mem::discriminant(&to) {
+        info!("[minigame] {name}: {from:?} -> {to:?}");
+        stage_events.send(GameStageChangeEvent {
+            arena: name.to_owned(),
+            mode: game.arena.mode.clone(),
+            from,
+            to,
+        });
+    }
+}
+
+fn broadcast(game: &Game, clients: &mut Query<(&mut Client, &mut Position)>, message: Text) {
+    for player in &game.players {
+        if let Ok((mut client, _)) = clients.get_mut(*player) {
+            client.send_chat_message(new_crystal_message(message.clone()));
+        }
+    }
+}
+
+// --- Systems ---
+
+pub fn setup_minigames(mut commands: Commands) {
+    let config: ArenaConfig = load_config("arenas.json");
+    let mut minigames = Minigames::default();
+    for arena in config.arenas {
+        info!("[minigame] registered arena {} ({})", arena.name, arena.mode);
+        minigames.games.insert(
+            arena.name.clone(),
+            Game {
+                arena,
+                stage: GameStage::Lobby,
+                players: Vec::new(),
+            },
+        );
+    }
+    commands.insert_resource(minigames);
+}
+
+pub fn handle_minigame_joins(
+    mut requests: EventReader<JoinMinigameRequest>,
+    mut minigames: ResMut<Minigames>,
+    mut clients: Query<(&mut Client, &mut Position)>,
+    mut joined: EventWriter<PlayerJoinedGameEvent>,
+) {
+    for request in requests.read() {
+        let current = minigames.arena_of(request.player).map(str::to_owned);
+        let Ok((mut client, mut pos)) = clients.get_mut(request.player) else {
+            continue;
+        };
+        if let Some(current) = current {
+            client.send_chat_message(
+                format!("[minigame] you are already in {current}").color(Color::RED),
+            );
+            continue;
+        }
+        let Some(game) = minigames.games.get_mut(&request.arena) else {
+            client.send_chat_message(
+                format!("[minigame] unknown arena: {}", request.arena).color(Color::RED),
+            );
+            continue;
+        };
+        if !game.stage.is_joinable() {
+            client.send_chat_message("[minigame] that game is already running".color(Color::RED));
+            continue;
+        }
+        if game.players.len() >= game.arena.max_players {
+            client.send_chat_message("[minigame] that arena is full".color(Color::RED));
+            continue;
+        }
+
+        game.players.push(request.player);
+        pos.set(game.arena.lobby_pos());
+        joined.send(PlayerJoinedGameEvent {
+            arena: request.arena.clone(),
+            player: request.player,
+        });
+
+        let message = format!(
+            "A player joined {} ({}/{})",
+            game.arena.name,
+            game.players.len(),
+            game.arena.max_players
+        )
+        .color(Color::GREEN);
+        broadcast(game, &mut clients, message);
+    }
+}
+
+pub fn handle_minigame_leaves(
+    mut requests: EventReader<LeaveMinigameRequest>,
+    mut disconnected: RemovedComponents<Client>,
+    mut minigames: ResMut<Minigames>,
+    mut clients: Query<(&mut Client, &mut Position)>,
+    mut left: EventWriter<PlayerLeftGameEvent>,
+    spawn: Res<WorldSpawn>,
+) {
+    let leaving: Vec<Entity> = requests
+        .read()
+        .map(|request| request.player)
+        .chain(disconnected.read())
+        .collect();
+
+    for player in leaving {
+        let Some(name) = minigames.arena_of(player).map(str::to_owned) else {
+            if let Ok((mut client, _)) = clients.get_mut(player) {
+                client.send_chat_message("[minigame] you are not in a game".color(Color::RED));
+            }
+            continue;
+        };
+        let game = minigames.games.get_mut(&name).unwrap();
+        game.players.retain(|p| *p != player);
+
+        if let Ok((mut client, mut pos)) = clients.get_mut(player) {
+            pos.set(spawn.0);
+            client.send_chat_message(new_crystal_message(
+                format!("You left {name}").color(Color::GOLD),
+            ));
+        }
+        left.send(PlayerLeftGameEvent {
+            arena: name,
+            player,
+        });
+    }
+}
+
+pub fn handle_minigame_ends(
+    mut requests: EventReader<EndMinigameRequest>,
+    mut minigames: ResMut<Minigames>,
+    mut clients: Query<(&mut Client, &mut Position)>,
+    usernames: Query<&Username>,
+    mut stage_events: EventWriter<GameStageChangeEvent>,
+) {
+    for request in requests.read() {
+        let Some(game) = minigames.games.get_mut(&request.arena) else {
+            continue;
+        };
+        if game.stage != GameStage::InGame {
+            continue;
+        }
+
+        let winners: Vec<String> = request
+            .winners
+            .iter()
+            .filter_map(|w| usernames.get(*w).ok())
+            .map(|u| u.0.clone())
+            .collect();
+        let message = if winners.is_empty() {
+            "Game over! Nobody won.".color(Color::GOLD)
+        } else {
+            format!("Game over! Winner: {}", winners.join(", ")).color(Color::GOLD)
+        };
+        broadcast(game, &mut clients, message);
+
+        set_stage(
+            &request.arena,
+            game,
+            GameStage::Ending {
+                ticks_left: ENDING_TICKS,
+            },
+            &mut stage_events,
+        );
+    }
+}
+
+// Advances every game's lifecycle by one tick
+pub fn tick_minigames(
+    mut minigames: ResMut<Minigames>,
+    mut clients: Query<(&mut Client, &mut Position)>,
+    mut stage_events: EventWriter<GameStageChangeEvent>,
+    spawn: Res<WorldSpawn>,
+) {
+    for (name, game) in minigames.games.iter_mut() {
+        match game.stage {
+            GameStage::Lobby => {
+                if game.players.len() >= game.arena.min_players && !game.players.is_empty() {
+                    let ticks_left = game.arena.countdown_seconds * TICKS_PER_SECOND;
+                    set_stage(name, game, GameStage::Countdown { ticks_left }, &mut stage_events);
+                }
+            }
+            GameStage::Countdown { ticks_left } => {
+                if game.players.len() < game.arena.min_players || game.players.is_empty() {
+                    broadcast(game, &mut clients, "Not enough players, countdown stopped.".color(Color::RED));
+                    set_stage(name, game, GameStage::Lobby, &mut stage_events);
+                } else if ticks_left == 0 {
+                    for (n, player) in game.players.iter().enumerate() {
+                        if let Ok((_, mut pos)) = clients.get_mut(*player) {
+                            pos.set(game.arena.spawn_pos(n));
+                        }
+                    }
+                    broadcast(game, &mut clients, "Go!".color(Color::GREEN));
+                    set_stage(name, game, GameStage::InGame, &mut stage_events);
+                } else {
+                    if ticks_left % TICKS_PER_SECOND == 0 {
+                        let seconds = ticks_left / TICKS_PER_SECOND;
+                        if seconds <= 5 || seconds % 10 == 0 {
+                            broadcast(game, &mut clients, format!("Starting in {seconds}...").color(Color::YELLOW));
+                        }
+                    }
+                    game.stage = GameStage::Countdown { ticks_left: ticks_left - 1 };
+                }
+            }
+            GameStage::InGame => {
+                // Game modes decide the winner; this only catches abandoned games.
+                if game.players.is_empty() {
+                    set_stage(name, game, GameStage::Ending { ticks_left: 0 }, &mut stage_events);
+                }
+            }
+            GameStage::Ending { ticks_left } => {
+                if ticks_left == 0 {
+                    for player in game.players.drain(..) {
+                        if let Ok((_, mut pos)) = clients.get_mut(player) {
+                            pos.set(spawn.0);
+                        }
+                    }
+                    set_stage(name, game, GameStage::Lobby, &mut stage_events);
+                } else {
+                    game.stage = GameStage::Ending { ticks_left: ticks_left - 1 };
+                }
+            }
+        }
+    }
+}
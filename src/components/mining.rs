@@ -0,0 +1,210 @@
+// src/components/mining.rs
+//
+// How long blocks take to break in survival. The client times digging itself
+// and says when it's done, so the server only has to check it didn't finish
+// impossibly fast, and to break blocks the client mines instantly (it never
+// sends a "finished" for those).
+//
+// Times follow vanilla's formula: the tool's speed (plus Efficiency) times
+// Haste, against the block's hardness. Hardness comes from a table of block
+// families rather than every block, and unknown blocks count as stone.
+
+use valence::prelude::*;
+
+use super::beacon::{ActiveEffects, HASTE};
+use super::enchantment::{self, EFFICIENCY};
+
+/// Digging may finish this much sooner than expected before it's rejected,
+/// to allow for lag between the start and stop packets.
+const LENIENCY: f64 = 0.7;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToolKind {
+    Pickaxe,
+    Axe,
+    Shovel,
+    Hoe,
+    Sword,
+    Shears,
+}
+
+/// Where and when a player started digging.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DigStart {
+    pub pos: BlockPos,
+    pub tick: i64,
+}
+
+/// What kind of tool an item is, its mining speed and its tier (wood 0 up to
+/// netherite 4).
+fn tool(item: ItemKind) -> Option<(ToolKind, f32, u32)> {
+    let name = item.to_str();
+    if item == ItemKind::Shears {
+        return Some((ToolKind::Shears, 2.0, 0));
+    }
+    let kind = match name.rsplit('_').next()? {
+        "pickaxe" => ToolKind::Pickaxe,
+        "axe" => ToolKind::Axe,
+        "shovel" => ToolKind::Shovel,
+        "hoe" => ToolKind::Hoe,
+        "sword" => ToolKind::Sword,
+        _ => return None,
+    };
+    let (speed, tier) = match name.split('_').next()? {
+        "wooden" => (2.0, 0),
+        "stone" => (4.0, 1),
+        "iron" => (6.0, 2),
+        "diamond" => (8.0, 3),
+        "netherite" => (9.0, 4),
+        "golden" => (12.0, 0),
+        _ => return None,
+    };
+    Some((kind, speed, tier))
+}
+
+/// A block's hardness, the tool that mines it fastest, and the tier needed
+/// for it to drop anything (`None` if any tool or a bare hand works).
+/// Negative hardness means it can't be broken.
+fn block_info(state: BlockState) -> (f32, Option<ToolKind>, Option<u32>) {
+    use ToolKind::*;
+    let kind = state.to_kind();
+    let name = kind.to_str();
+    match kind {
+        BlockKind::Bedrock
+        | BlockKind::Barrier
+        | BlockKind::EndPortal
+        | BlockKind::EndPortalFrame
+        | BlockKind::EndGateway
+        | BlockKind::CommandBlock
+        | BlockKind::ChainCommandBlock
+        | BlockKind::RepeatingCommandBlock
+        | BlockKind::StructureBlock
+        | BlockKind::Jigsaw
+        | BlockKind::NetherPortal => return (-1.0, None, None),
+        BlockKind::Obsidian | BlockKind::CryingObsidian | BlockKind::RespawnAnchor => return (50.0, Some(Pickaxe), Some(3)),
+        BlockKind::AncientDebris => return (30.0, Some(Pickaxe), Some(3)),
+        BlockKind::Cobweb => return (4.0, Some(Sword), None),
+        BlockKind::Spawner => return (5.0, Some(Pickaxe), Some(0)),
+        BlockKind::GrassBlock | BlockKind::Farmland | BlockKind::Gravel | BlockKind::Clay | BlockKind::Mycelium | BlockKind::Podzol => {
+            return (0.6, Some(Shovel), None);
+        }
+        BlockKind::Dirt | BlockKind::CoarseDirt | BlockKind::Sand | BlockKind::RedSand | BlockKind::SoulSand | BlockKind::SoulSoil => {
+            return (0.5, Some(Shovel), None);
+        }
+        BlockKind::SnowBlock => return (0.2, Some(Shovel), Some(0)),
+        BlockKind::Snow => return (0.1, Some(Shovel), Some(0)),
+        BlockKind::Ice | BlockKind::PackedIce | BlockKind::BlueIce => return (0.5, Some(Pickaxe), None),
+        BlockKind::Netherrack => return (0.4, Some(Pickaxe), Some(0)),
+        BlockKind::EndStone => return (3.0, Some(Pickaxe), Some(0)),
+        BlockKind::Cobblestone | BlockKind::MossyCobblestone => return (2.0, Some(Pickaxe), Some(0)),
+        BlockKind::Deepslate => return (3.0, Some(Pickaxe), Some(0)),
+        BlockKind::IronBlock | BlockKind::DiamondBlock | BlockKind::EmeraldBlock | BlockKind::NetheriteBlock => {
+            return (5.0, Some(Pickaxe), Some(1));
+        }
+        BlockKind::GoldBlock => return (3.0, Some(Pickaxe), Some(2)),
+        BlockKind::Anvil | BlockKind::ChippedAnvil | BlockKind::DamagedAnvil => return (5.0, Some(Pickaxe), Some(0)),
+        BlockKind::Chest | BlockKind::TrappedChest | BlockKind::Barrel | BlockKind::CraftingTable => {
+            return (2.5, Some(Axe), None);
+        }
+        BlockKind::Furnace | BlockKind::BlastFurnace | BlockKind::Smoker | BlockKind::Dispenser | BlockKind::Dropper => {
+            return (3.5, Some(Pickaxe), Some(0));
+        }
+        _ => {}
+    }
+    if name.ends_with("_ore") {
+        let tier = match name.trim_start_matches("deepslate_") {
+            "coal_ore" | "nether_gold_ore" | "nether_quartz_ore" => 0,
+            "iron_ore" | "copper_ore" | "lapis_ore" => 1,
+            _ => 2,
+        };
+        let hardness = if name.starts_with("deepslate_") { 4.5 } else { 3.0 };
+        return (hardness, Some(Pickaxe), Some(tier));
+    }
+    if name.ends_with("_leaves") {
+        return (0.2, Some(Hoe), None);
+    }
+    if name.ends_with("_wool") {
+        return (0.8, Some(Shears), None);
+    }
+    if name.ends_with("glass") || name.ends_with("glass_pane") {
+        return (0.3, None, None);
+    }
+    if name.ends_with("_log") || name.ends_with("_wood") || name.ends_with("_stem") || name.ends_with("_hyphae") {
+        return (2.0, Some(Axe), None);
+    }
+    if name.ends_with("_planks") || name.ends_with("_fence") || name.ends_with("_fence_gate") {
+        return (2.0, Some(Axe), None);
+    }
+    if name.ends_with("_door") || name.ends_with("_trapdoor") {
+        return if name.starts_with("iron_") { (5.0, Some(Pickaxe), Some(0)) } else { (3.0, Some(Axe), None) };
+    }
+    if name.contains("concrete_powder") {
+        return (0.5, Some(Shovel), None);
+    }
+    if name.ends_with("_concrete") {
+        return (1.8, Some(Pickaxe), Some(0));
+    }
+    if name.ends_with("terracotta") {
+        return (1.25, Some(Pickaxe), Some(0));
+    }
+    if name.contains("deepslate") || name.contains("blackstone") || name.contains("basalt") {
+        return (3.5, Some(Pickaxe), Some(0));
+    }
+    // Plants, torches, rails and anything else without a collision box break
+    // instantly, liquids can't be broken at all.
+    if state.is_liquid() {
+        return (-1.0, None, None);
+    }
+    if !state.blocks_motion() {
+        return (0.0, None, None);
+    }
+    (1.5, Some(Pickaxe), Some(0))
+}
+
+/// Whether a tool is good enough for the block to drop anything.
+pub fn can_harvest(state: BlockState, tool_stack: &ItemStack) -> bool {
+    let (_, best, tier) = block_info(state);
+    let Some(tier) = tier else {
+        return true;
+    };
+    tool(tool_stack.item).is_some_and(|(kind, _, tool_tier)| Some(kind) == best && tool_tier >= tier)
+}
+
+/// Ticks it takes to break `state` with `tool_stack`, or `None` if it can't
+/// be broken. 0 means it breaks instantly.
+pub fn break_ticks(state: BlockState, tool_stack: &ItemStack, haste: Option<u8>) -> Option<u32> {
+    let (hardness, best, _) = block_info(state);
+    if hardness < 0.0 {
+        return None;
+    }
+    if hardness == 0.0 {
+        return Some(0);
+    }
+    let mut speed = match tool(tool_stack.item) {
+        Some((ToolKind::Sword, _, _)) if best == Some(ToolKind::Sword) => 15.0,
+        Some((kind, speed, _)) if Some(kind) == best => speed,
+        _ => 1.0,
+    };
+    let efficiency = enchantment::level(tool_stack, EFFICIENCY);
+    if efficiency > 0 && speed > 1.0 {
+        speed += (efficiency * efficiency + 1) as f32;
+    }
+    if let Some(amplifier) = haste {
+        speed *= 1.0 + 0.2 * (amplifier as f32 + 1.0);
+    }
+    let per_tick = speed / hardness / if can_harvest(state, tool_stack) { 30.0 } else { 100.0 };
+    if per_tick >= 1.0 {
+        return Some(0);
+    }
+    Some((1.0 / per_tick).ceil() as u32)
+}
+
+/// The player's Haste amplifier right now, if they have it.
+pub fn haste(effects: Option<&ActiveEffects>, now: i64) -> Option<u8> {
+    effects.and_then(|effects| effects.amplifier(HASTE, now))
+}
+
+/// Whether digging that started at `start` could have finished by `now`.
+pub fn finished_in_time(start: DigStart, pos: BlockPos, now: i64, ticks: u32) -> bool {
+    start.pos == pos && (now - start.tick) as f64 >= ticks as f64 * LENIENCY
+}
@@ -0,0 +1,127 @@
+use valence::{
+    entity::{
+        entity::Flags,
+        living::{Health, LivingEntity},
+        Despawned, EntityLayerId,
+    },
+    prelude::*,
+};
+
+use super::loot::{entity_table, LootContext, LootTables};
+use super::time::WorldTime;
+use crate::world::Overworld;
+
+const SUNLIGHT_CHECK_INTERVAL_TICKS: i64 = 20;
+const SUNLIGHT_DAMAGE: f32 = 1.0;
+
+/// Whether a mob currently attacks players on sight. AI should check this
+/// before targeting anyone.
+#[derive(Component, Debug)]
+pub struct MobAggression {
+    pub hostile: bool,
+}
+
+fn is_undead(kind: EntityKind) -> bool {
+    matches!(
+        kind,
+        EntityKind::ZOMBIE
+            | EntityKind::ZOMBIE_VILLAGER
+            | EntityKind::SKELETON
+            | EntityKind::STRAY
+            | EntityKind::PHANTOM
+            | EntityKind::DROWNED
+    )
+}
+
+pub fn is_hostile(kind: EntityKind) -> bool {
+    is_undead(kind)
+        || matches!(
+            kind,
+            EntityKind::SPIDER
+                | EntityKind::CAVE_SPIDER
+                | EntityKind::CREEPER
+                | EntityKind::WITCH
+                | EntityKind::HUSK
+                | EntityKind::SLIME
+        )
+}
+
+/// How much the hostile spawn rate should be scaled by tonight's moon, from
+/// 0.5 on a new moon up to 1.5 on a full moon. Spawners and natural spawning
+/// multiply their chance by this.
+pub fn hostile_spawn_multiplier(time: &WorldTime) -> f64 {
+    if time.is_day() {
+        return 0.0;
+    }
+    0.5 + time.moon_brightness()
+}
+
+pub fn init_mob_aggression(
+    mut commands: Commands,
+    mobs: Query<(Entity, &EntityKind), (Added<EntityKind>, With<LivingEntity>, Without<Client>)>,
+) {
+    for (entity, kind) in &mobs {
+        if is_hostile(*kind) {
+            commands.entity(entity).insert(MobAggression { hostile: true });
+        }
+    }
+}
+
+// Spiders only attack at night unless provoked
+pub fn update_spider_aggression(
+    time: Res<WorldTime>,
+    mut mobs: Query<(&EntityKind, &mut MobAggression)>,
+) {
+    let hostile = !time.is_day();
+    for (kind, mut aggression) in &mut mobs {
+        if matches!(*kind, EntityKind::SPIDER | EntityKind::CAVE_SPIDER) && aggression.hostile != hostile {
+            aggression.hostile = hostile;
+        }
+    }
+}
+
+fn sees_sky(layer: &ChunkLayer, pos: DVec3) -> bool {
+    let block_pos = BlockPos::from(pos);
+    let top = layer.min_y() + layer.height() as i32;
+    ((block_pos.y + 1)..top).all(|y| {
+        layer
+            .block(BlockPos::new(block_pos.x, y, block_pos.z))
+            .map_or(true, |block| !block.state.blocks_motion())
+    })
+}
+
+// Sets undead mobs standing in daylight on fire
+pub fn burn_undead_in_sunlight(
+    mut commands: Commands,
+    time: Res<WorldTime>,
+    server: Res<Server>,
+    layers: Query<&ChunkLayer, With<Overworld>>,
+    mut mobs: Query<(Entity, &EntityKind, &Position, &EntityLayerId, &mut Flags, &mut Health), Without<Client>>,
+    loot: Res<LootTables>,
+) {
+    if server.current_tick() % SUNLIGHT_CHECK_INTERVAL_TICKS != 0 {
+        return;
+    }
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+
+    for (entity, kind, pos, entity_layer, mut flags, mut health) in &mut mobs {
+        if !is_undead(*kind) {
+            continue;
+        }
+        let burning = time.is_day() && sees_sky(layer, pos.0);
+        if flags.on_fire() != burning {
+            flags.set_on_fire(burning);
+        }
+        if burning {
+            health.0 -= SUNLIGHT_DAMAGE;
+            if health.0 <= 0.0 {
+                commands.entity(entity).insert(Despawned);
+                if let Some(table) = entity_table(*kind) {
+                    loot.drop_loot(&mut commands, &table, *entity_layer, BlockPos::from(pos.0), &LootContext::default());
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,54 @@
 pub mod core;
 pub mod console;
 pub mod chat;
+pub mod client_settings;
+pub mod combat;
+pub mod command_block;
+pub mod attempts;
+pub mod beacon;
+pub mod block_rules;
+pub mod boss;
 pub mod building;
+pub mod config;
+pub mod confirm;
+pub mod consumables;
+pub mod container;
+pub mod cooldown;
+pub mod creative;
+pub mod decoration;
+pub mod dispenser;
+pub mod enchantment;
+pub mod end;
+pub mod entity_rules;
+pub mod experience;
+pub mod explosion;
+pub mod ghost;
+pub mod hopper;
+pub mod hud;
+pub mod logging;
+pub mod loot;
+pub mod memory;
+pub mod menu;
+pub mod metrics;
+pub mod minigame;
+pub mod mining;
+pub mod mob_behavior;
+pub mod party;
+pub mod permissions;
+pub mod playerdata;
+pub mod playtime;
+pub mod protocol;
+pub mod recipe;
+pub mod redstone;
+pub mod replay;
+pub mod schematic;
+pub mod sleep;
+pub mod spawner;
+pub mod spectate;
+pub mod spleef;
+pub mod team;
+pub mod time;
+pub mod trading;
+pub mod warps;
+pub mod weather;
 // pub mod maps;
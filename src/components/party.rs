@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use valence::{player_list::DisplayName, prelude::*};
+
+use super::minigame::JoinMinigameRequest;
+
+const INVITE_TIMEOUT_TICKS: u32 = 60 * 20;
+
+pub type PartyId = u64;
+
+pub struct Party {
+    pub leader: Entity,
+    /// Includes the leader.
+    pub members: Vec<Entity>,
+}
+
+pub struct PartyInvite {
+    pub party: PartyId,
+    pub from: Entity,
+    ticks_left: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct Parties {
+    pub parties: HashMap<PartyId, Party>,
+    /// Pending invites, keyed by the invited player.
+    pub invites: HashMap<Entity, PartyInvite>,
+    next_id: PartyId,
+}
+
+impl Parties {
+    pub fn party_of(&self, player: Entity) -> Option<PartyId> {
+        self.parties
+            .iter()
+            .find(|(_, party)| party.members.contains(&player))
+            .map(|(id, _)| *id)
+    }
+
+    /// Everyone in the player's party, or just the player if they're not in one.
+    pub fn members_of(&self, player: Entity) -> Vec<Entity> {
+        self.party_of(player)
+            .map(|id| self.parties[&id].members.clone())
+            .unwrap_or_else(|| vec![player])
+    }
+
+    pub fn create(&mut self, leader: Entity) -> Option<PartyId> {
+        if self.party_of(leader).is_some() {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.parties.insert(
+            id,
+            Party {
+                leader,
+                members: vec![leader],
+            },
+        );
+        Some(id)
+    }
+
+    pub fn invite(&mut self, party: PartyId, from: Entity, to: Entity) {
+        self.invites.insert(
+            to,
+            PartyInvite {
+                party,
+                from,
+                ticks_left: INVITE_TIMEOUT_TICKS,
+            },
+        );
+    }
+
+    /// Accepts the player's pending invite, returning the party they joined.
+    pub fn accept(&mut self, player: Entity) -> Option<PartyId> {
+        let invite = self.invites.remove(&player)?;
+        if self.party_of(player).is_some() {
+            return None;
+        }
+        let party = self.parties.get_mut(&invite.party)?;
+        party.members.push(player);
+        Some(invite.party)
+    }
+
+    /// Removes the player from their party, handing leadership to the next
+    /// member or disbanding the party if nobody is left.
+    pub fn leave(&mut self, player: Entity) -> Option<PartyId> {
+        let id = self.party_of(player)?;
+        let party = self.parties.get_mut(&id)?;
+        party.members.retain(|m| *m != player);
+        if party.members.is_empty() {
+            self.parties.remove(&id);
+            self.invites.retain(|_, invite| invite.party != id);
+        } else if party.leader == player {
+            party.leader = party.members[0];
+        }
+        Some(id)
+    }
+
+    /// Builds join requests for the player's whole party so minigames can
+    /// queue them together. Only the leader may queue a party.
+    pub fn queue_for_minigame(&self, player: Entity, arena: &str) -> Vec<JoinMinigameRequest> {
+        match self.party_of(player) {
+            Some(id) if self.parties[&id].leader != player => vec![],
+            _ => self
+                .members_of(player)
+                .into_iter()
+                .map(|member| JoinMinigameRequest {
+                    player: member,
+                    arena: arena.to_owned(),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub fn tick_party_invites(mut parties: ResMut<Parties>, mut clients: Query<&mut Client>) {
+    if parties.invites.is_empty() {
+        return;
+    }
+    parties.invites.retain(|invited, invite| {
+        invite.ticks_left = invite.ticks_left.saturating_sub(1);
+        if invite.ticks_left == 0 {
+            if let Ok(mut client) = clients.get_mut(*invited) {
+                client.send_chat_message("[party] your party invite expired".color(Color::GRAY));
+            }
+            false
+        } else {
+            true
+        }
+    });
+}
+
+pub fn party_disconnects(mut removed: RemovedComponents<Client>, mut parties: ResMut<Parties>) {
+    for entity in removed.read() {
+        parties.leave(entity);
+        parties.invites.remove(&entity);
+    }
+}
+
+// Groups party members together in the tab list by prefixing their names
+pub fn update_party_display_names(
+    parties: Res<Parties>,
+    mut players: Query<(Entity, &Username, &mut DisplayName)>,
+    usernames: Query<&Username>,
+) {
+    if !parties.is_changed() {
+        return;
+    }
+    for (entity, username, mut display_name) in &mut players {
+        let name = parties.party_of(entity).and_then(|id| {
+            let leader = usernames.get(parties.parties[&id].leader).ok()?;
+            Some(
+                format!("[{}] ", leader.0).color(Color::LIGHT_PURPLE)
+                    + username.0.clone().color(Color::WHITE),
+            )
+        });
+        if display_name.0 != name {
+            display_name.0 = name;
+        }
+    }
+}
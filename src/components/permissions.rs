@@ -0,0 +1,130 @@
+// src/components/permissions.rs
+//
+// Permission groups: named sets of scopes given to players by name, on top of
+// what `/op` gives them. A group lists scope patterns instead of every scope:
+// `*` at the end of a pattern matches every scope under it, and a pattern
+// starting with `-` takes scopes away again. Negations always win, whatever
+// order they're listed in.
+//
+// Patterns are matched against the scope of every registered command, read
+// from the command registry once all commands are in, and the group's scope (`crystal.group.<name>`) is linked to the ones it grants,
+// so the command layer's own checks pick them up.
+//
+// `config/permissions.json`:
+// `{ "groups": { "helper": ["crystal.command.*", "-crystal.command.op", "-crystal.command.sudo"] },
+//    "players": { "Steve": ["helper"] } }`
+// Everyone is in the `default` group, if there is one.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tracing::{info, warn};
+use valence::{
+    command::{scopes::CommandScopes, CommandRegistry, CommandScopeRegistry},
+    prelude::*,
+};
+
+use super::config::load_config;
+use super::logging::CONFIG;
+
+/// The group every player is in.
+const DEFAULT_GROUP: &str = "default";
+
+#[derive(Deserialize, Resource, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PermissionGroups {
+    /// Scope patterns of each group.
+    pub groups: HashMap<String, Vec<String>>,
+    /// Groups of each player, by username.
+    pub players: HashMap<String, Vec<String>>,
+}
+
+/// Whether `pattern` (without a leading `-`) covers `scope`.
+fn matches(pattern: &str, scope: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => scope.starts_with(prefix),
+        None => pattern == scope,
+    }
+}
+
+impl PermissionGroups {
+    pub fn group_scope(group: &str) -> String {
+        format!("crystal.group.{group}")
+    }
+
+    /// Whether `group` grants `scope`.
+    pub fn grants(&self, group: &str, scope: &str) -> bool {
+        let Some(patterns) = self.groups.get(group) else {
+            return false;
+        };
+        let (denied, allowed): (Vec<&String>, Vec<&String>) = patterns.iter().partition(|p| p.starts_with('-'));
+        allowed.iter().any(|p| matches(p, scope)) && !denied.iter().any(|p| matches(&p[1..], scope))
+    }
+
+    /// Links every group to the scopes in `scopes` it grants.
+    pub fn link(&self, registry: &mut CommandScopeRegistry, scopes: &[&str]) {
+        for group in self.groups.keys() {
+            let granted: Vec<&str> = scopes.iter().copied().filter(|scope| self.grants(group, scope)).collect();
+            if granted.is_empty() {
+                warn!(target: CONFIG, "permission group {group} doesn't grant any commands");
+            }
+            for scope in &granted {
+                registry.link(&Self::group_scope(group), scope);
+            }
+            info!(target: CONFIG, "permission group {group}: {}", granted.join(", "));
+        }
+        for (player, groups) in &self.players {
+            for group in groups.iter().filter(|group| !self.groups.contains_key(*group)) {
+                warn!(target: CONFIG, "{player} is in permission group {group}, which doesn't exist");
+            }
+        }
+    }
+
+    /// The groups a player is in, `default` included.
+    pub fn groups_of<'a>(&'a self, username: &str) -> impl Iterator<Item = &'a str> + 'a {
+        let listed = self
+            .players
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(username))
+            .map(|(_, groups)| groups.as_slice())
+            .unwrap_or_default();
+        self.groups
+            .contains_key(DEFAULT_GROUP)
+            .then_some(DEFAULT_GROUP)
+            .into_iter()
+            .chain(listed.iter().map(String::as_str))
+    }
+}
+
+/// The scope of every registered command, from the `#[scopes]` on each.
+pub fn registered_command_scopes(registry: &CommandRegistry) -> Vec<String> {
+    let mut scopes: Vec<String> = registry
+        .graph
+        .graph
+        .node_weights()
+        .flat_map(|node| node.scopes.iter())
+        .filter(|scope| scope.starts_with("crystal.command."))
+        .cloned()
+        .collect();
+    scopes.sort_unstable();
+    scopes.dedup();
+    scopes
+}
+
+/// Loads the groups and links them to `scopes`, the scopes of every command.
+pub fn setup_permission_groups(commands: &mut Commands, registry: &mut CommandScopeRegistry, scopes: &[&str]) {
+    let groups = load_config::<PermissionGroups>("permissions.json");
+    groups.link(registry, scopes);
+    commands.insert_resource(groups);
+}
+
+pub fn init_clients_permissions(
+    mut clients: Query<(&Username, &mut CommandScopes), Added<Client>>,
+    groups: Res<PermissionGroups>,
+) {
+    for (username, mut scopes) in &mut clients {
+        for group in groups.groups_of(&username.0) {
+            scopes.add(&PermissionGroups::group_scope(group));
+        }
+    }
+}
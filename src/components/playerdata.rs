@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use valence::prelude::*;
+
+use super::chat::ChatChannel;
+use super::logging::PLAYERDATA;
+
+pub const PLAYER_DATA_DIR: &str = "playerdata";
+
+/// Everything we remember about a player between sessions, stored in
+/// `playerdata/<uuid>.json`.
+#[derive(Component, Serialize, Deserialize, Default, Clone, Debug)]
+pub struct PlayerData {
+    #[serde(default)]
+    pub unlocked_recipes: Vec<String>,
+    /// Name the player last joined with, for looking up offline players.
+    #[serde(default)]
+    pub last_username: String,
+    /// Total seconds played, not counting the current session.
+    #[serde(default)]
+    pub playtime_secs: u64,
+    /// Unix time the player last left.
+    #[serde(default)]
+    pub last_seen: Option<u64>,
+    /// Action bar HUD elements the player turned on.
+    #[serde(default)]
+    pub hud_elements: Vec<String>,
+    /// Total experience points collected.
+    #[serde(default)]
+    pub experience: u32,
+    /// Overworld spots set with `/sethome`, by name.
+    #[serde(default)]
+    pub homes: BTreeMap<String, [f64; 3]>,
+    /// Other players joining and leaving isn't shown in chat.
+    #[serde(default)]
+    pub hide_join_messages: bool,
+    /// Where chat without a command goes.
+    #[serde(default)]
+    pub chat_channel: ChatChannel,
+}
+
+fn player_data_path(uuid: &UniqueId) -> String {
+    format!("{PLAYER_DATA_DIR}/{}.json", uuid.0)
+}
+
+pub fn load_player_data(uuid: &UniqueId) -> PlayerData {
+    let Ok(contents) = fs::read_to_string(player_data_path(uuid)) else {
+        return PlayerData::default();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        error!(target: PLAYERDATA, "failed to parse data for {}: {e}", uuid.0);
+        PlayerData::default()
+    })
+}
+
+/// Finds a player who isn't online by the name they last joined with.
+pub fn find_offline_player_data(username: &str) -> Option<PlayerData> {
+    fs::read_dir(PLAYER_DATA_DIR)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| serde_json::from_str::<PlayerData>(&fs::read_to_string(entry.path()).ok()?).ok())
+        .find(|data| data.last_username.eq_ignore_ascii_case(username))
+}
+
+pub fn save_player_data(uuid: &UniqueId, data: &PlayerData) {
+    let result = fs::create_dir_all(PLAYER_DATA_DIR)
+        .and_then(|_| {
+            serde_json::to_string_pretty(data)
+                .map_err(std::io::Error::other)
+        })
+        .and_then(|json| fs::write(player_data_path(uuid), json));
+    if let Err(e) = result {
+        error!(target: PLAYERDATA, "failed to save data for {}: {e}", uuid.0);
+    }
+}
+
+/// Writes every given player's data right away. Returns how many players
+/// that was.
+pub fn save_all_player_data<'a>(players: impl IntoIterator<Item = (&'a UniqueId, &'a PlayerData)>) -> usize {
+    players.into_iter().map(|(uuid, data)| save_player_data(uuid, data)).count()
+}
+
+pub fn init_clients_player_data(
+    mut commands: Commands,
+    clients: Query<(Entity, &UniqueId), Added<Client>>,
+) {
+    for (entity, uuid) in &clients {
+        commands.entity(entity).insert(load_player_data(uuid));
+    }
+}
+
+// Writes player data back to disk whenever a system modifies it
+pub fn save_changed_player_data(
+    players: Query<(&UniqueId, Ref<PlayerData>), Changed<PlayerData>>,
+) {
+    for (uuid, data) in &players {
+        if data.is_added() {
+            continue;
+        }
+        save_player_data(uuid, &data);
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::{error, info};
+use valence::prelude::*;
+
+use super::logging::{AUDIT, LOG_DIR};
+use super::playerdata::{save_player_data, PlayerData};
+
+/// When the player's current session started.
+#[derive(Component)]
+pub struct Session {
+    pub started: Instant,
+}
+
+impl Session {
+    pub fn elapsed_secs(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+}
+
+/// One line of `logs/sessions.jsonl`.
+#[derive(Serialize)]
+struct SessionRecord<'a> {
+    event: &'a str,
+    uuid: String,
+    username: &'a str,
+    time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_secs: Option<u64>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn record_session(record: SessionRecord) {
+    info!(
+        target: AUDIT,
+        player = record.username,
+        uuid = %record.uuid,
+        duration_secs = record.duration_secs,
+        "session {}",
+        record.event
+    );
+    let result = fs::create_dir_all(LOG_DIR)
+        .and_then(|_| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(format!("{LOG_DIR}/sessions.jsonl"))
+        })
+        .and_then(|mut file| {
+            let json = serde_json::to_string(&record).map_err(std::io::Error::other)?;
+            writeln!(file, "{json}")
+        });
+    if let Err(e) = result {
+        error!(target: AUDIT, "failed to record session: {e}");
+    }
+}
+
+/// Formats seconds as e.g. `3d 4h 12m`.
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    match (days, hours) {
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h {minutes}m"),
+    }
+}
+
+// Runs after player data is loaded, so the username can be stored with it
+pub fn start_sessions(
+    mut commands: Commands,
+    mut clients: Query<(Entity, &UniqueId, &Username, &mut PlayerData), Added<PlayerData>>,
+) {
+    for (entity, uuid, username, mut data) in &mut clients {
+        commands.entity(entity).insert(Session { started: Instant::now() });
+        if data.last_username != username.0 {
+            data.last_username = username.0.clone();
+        }
+        record_session(SessionRecord {
+            event: "join",
+            uuid: uuid.0.to_string(),
+            username: &username.0,
+            time: unix_now(),
+            duration_secs: None,
+        });
+    }
+}
+
+// Disconnected clients lose their `Client` component a tick before they're
+// despawned, which is when their session gets added to their total.
+pub fn end_sessions(
+    mut removed: RemovedComponents<Client>,
+    mut players: Query<(&UniqueId, &Username, &Session, &mut PlayerData)>,
+) {
+    for entity in removed.read() {
+        let Ok((uuid, username, session, mut data)) = players.get_mut(entity) else {
+            continue;
+        };
+        let duration = session.elapsed_secs();
+        data.playtime_secs += duration;
+        data.last_seen = Some(unix_now());
+        save_player_data(uuid, &data);
+        record_session(SessionRecord {
+            event: "leave",
+            uuid: uuid.0.to_string(),
+            username: &username.0,
+            time: unix_now(),
+            duration_secs: Some(duration),
+        });
+    }
+}
@@ -0,0 +1,253 @@
+// src/components/protocol.rs
+//
+// Telling clients on the wrong version which one to use. The server list
+// entry says so in the status ping. Joining is turned away by valence before
+// any of our callbacks run, and without a message. With `version_gate` on,
+// players connect to a small gate in front of valence instead, which reads
+// each connection's handshake, disconnects logins from another version with
+// the same message, and passes everything else through to valence on a
+// loopback address.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{debug, info};
+use valence::network::{
+    HandshakeData, NetworkCallbacks, ServerListLegacyPing, ServerListLegacyPingPayload, ServerListLegacyPingResponse,
+    ServerListPing, SharedNetworkState,
+};
+use valence::prelude::*;
+use valence::{MINECRAFT_VERSION, PROTOCOL_VERSION};
+
+use super::config::load_config;
+use super::logging::NET;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ProtocolConfig {
+    /// Shown in the server list to clients on the right version.
+    pub motd: String,
+    /// Replaces the built-in (client-translated) outdated message if set.
+    /// `{version}` is replaced with the supported version.
+    pub outdated_message: Option<String>,
+    /// Where players connect.
+    pub address: SocketAddr,
+    /// Puts the gate on `address` to kick logins from other versions with a
+    /// message. Off by default: valence then only ever sees the gate's
+    /// loopback address, and each player costs two more threads.
+    pub version_gate: bool,
+    /// Where valence listens behind the gate. Only the gate should reach it.
+    pub internal_address: SocketAddr,
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> Self {
+        Self {
+            motd: "A Crystal server".to_owned(),
+            outdated_message: None,
+            address: SocketAddr::from(([0, 0, 0, 0], 25565)),
+            version_gate: false,
+            internal_address: SocketAddr::from(([127, 0, 0, 1], 25566)),
+        }
+    }
+}
+
+impl ProtocolConfig {
+    /// "Please use version X", translated by the client unless overridden.
+    fn outdated_message(&self, client_protocol: i32) -> Text {
+        if let Some(message) = &self.outdated_message {
+            return message.replace("{version}", MINECRAFT_VERSION).color(Color::RED);
+        }
+        let key = if client_protocol < PROTOCOL_VERSION {
+            "multiplayer.disconnect.outdated_client"
+        } else {
+            "multiplayer.disconnect.incompatible"
+        };
+        Text::translate(key, [MINECRAFT_VERSION.into_text()]).color(Color::RED)
+    }
+}
+
+pub struct CrystalCallbacks {
+    config: ProtocolConfig,
+}
+
+impl CrystalCallbacks {
+    pub fn load() -> Self {
+        Self {
+            config: load_config("protocol.json"),
+        }
+    }
+
+    /// Returns where valence should listen, starting the gate in front of it
+    /// if it's enabled. A gate that can't listen stops startup, since nobody
+    /// could join otherwise.
+    pub fn start_gate(&self) -> SocketAddr {
+        let config = self.config.clone();
+        if !config.version_gate {
+            return config.address;
+        }
+        let internal = config.internal_address;
+        let listener = TcpListener::bind(config.address)
+            .unwrap_or_else(|e| panic!("can't listen on {} for the version gate: {e}", config.address));
+        info!(target: NET, "accepting players on {} through the version gate", config.address);
+        thread::spawn(move || run_gate(listener, config));
+        internal
+    }
+}
+
+/// Handshakes are tiny, so a client taking this long isn't coming.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longer than any real handshake, whose server address is at most 255 characters.
+const MAX_HANDSHAKE_LEN: i32 = 1024;
+/// The handshake's next state when the client wants to join.
+const NEXT_STATE_LOGIN: i32 = 2;
+/// Pre-1.7 server list pings start with this byte instead of a handshake.
+const LEGACY_PING: u8 = 0xfe;
+
+fn run_gate(listener: TcpListener, config: ProtocolConfig) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let config = config.clone();
+        thread::spawn(move || {
+            if let Err(e) = gate_connection(stream, &config) {
+                debug!(target: NET, "gate connection ended: {e}");
+            }
+        });
+    }
+}
+
+fn read_var_int(reader: &mut impl Read) -> io::Result<i32> {
+    let mut value = 0;
+    for i in 0..5 {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as i32) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "VarInt too long"))
+}
+
+fn write_var_int(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value < 0x80 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+}
+
+/// Protocol version and next state from a handshake packet's body.
+fn parse_handshake(mut body: &[u8]) -> io::Result<(i32, i32)> {
+    let _packet_id = read_var_int(&mut body)?;
+    let protocol = read_var_int(&mut body)?;
+    let address_len = read_var_int(&mut body)? as usize;
+    body = body.get(address_len + 2..).ok_or(io::ErrorKind::UnexpectedEof)?;
+    Ok((protocol, read_var_int(&mut body)?))
+}
+
+fn gate_connection(mut client: TcpStream, config: &ProtocolConfig) -> io::Result<()> {
+    client.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut forwarded = Vec::new();
+    let mut first = [0];
+    client.peek(&mut first)?;
+    if first[0] != LEGACY_PING {
+        let len = read_var_int(&mut client)?;
+        if !(0..=MAX_HANDSHAKE_LEN).contains(&len) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a handshake"));
+        }
+        let mut body = vec![0; len as usize];
+        client.read_exact(&mut body)?;
+        let (protocol, next_state) = parse_handshake(&body)?;
+
+        if next_state >= NEXT_STATE_LOGIN && protocol != PROTOCOL_VERSION {
+            let remote = client.peer_addr()?;
+            info!(target: NET, %remote, protocol, "turned away a client on another version");
+            let reason = serde_json::to_string(&config.outdated_message(protocol)).map_err(io::Error::other)?;
+            // Login disconnect: packet 0 with the reason as json
+            let mut packet = vec![0];
+            write_var_int(&mut packet, reason.len() as i32);
+            packet.extend_from_slice(reason.as_bytes());
+            let mut framed = Vec::new();
+            write_var_int(&mut framed, packet.len() as i32);
+            framed.extend_from_slice(&packet);
+            client.write_all(&framed)?;
+            return client.shutdown(Shutdown::Both);
+        }
+        // Valence only sees the gate, so this is the one record of who joined from where
+        if next_state >= NEXT_STATE_LOGIN {
+            info!(target: NET, remote = %client.peer_addr()?, "login through the version gate");
+        }
+        write_var_int(&mut forwarded, len);
+        forwarded.extend_from_slice(&body);
+    }
+    client.set_read_timeout(None)?;
+
+    let mut server = TcpStream::connect(config.internal_address)?;
+    server.write_all(&forwarded)?;
+    let (mut client_read, mut server_write) = (client.try_clone()?, server.try_clone()?);
+    thread::spawn(move || {
+        let _ = io::copy(&mut client_read, &mut server_write);
+        let _ = server_write.shutdown(Shutdown::Both);
+    });
+    let _ = io::copy(&mut server, &mut client);
+    client.shutdown(Shutdown::Both)
+}
+
+fn online_players(shared: &SharedNetworkState) -> i32 {
+    shared.player_count().load(Ordering::Relaxed) as i32
+}
+
+#[async_trait]
+impl NetworkCallbacks for CrystalCallbacks {
+    async fn server_list_ping(
+        &self,
+        shared: &SharedNetworkState,
+        remote_addr: SocketAddr,
+        handshake_data: &HandshakeData,
+    ) -> ServerListPing {
+        debug!(target: NET, %remote_addr, protocol = handshake_data.protocol_version, "server list ping");
+        let description = if handshake_data.protocol_version == PROTOCOL_VERSION {
+            self.config.motd.clone().into_text()
+        } else {
+            self.config.outdated_message(handshake_data.protocol_version)
+        };
+
+        // A protocol that doesn't match makes the client show `version_name`
+        // in red in place of the player count.
+        ServerListPing::Respond {
+            online_players: online_players(shared),
+            max_players: shared.max_players() as i32,
+            player_sample: vec![],
+            description,
+            favicon_png: &[],
+            version_name: format!("Crystal {MINECRAFT_VERSION}"),
+            protocol: PROTOCOL_VERSION,
+        }
+    }
+
+    // Pre-1.7 clients can't join at all; their list entry says which version to use
+    async fn server_list_legacy_ping(
+        &self,
+        shared: &SharedNetworkState,
+        _remote_addr: SocketAddr,
+        _payload: ServerListLegacyPingPayload,
+    ) -> ServerListLegacyPing {
+        ServerListLegacyPing::Respond(
+            ServerListLegacyPingResponse::new(PROTOCOL_VERSION, online_players(shared), shared.max_players() as i32)
+                .version(format!("Use {MINECRAFT_VERSION}"))
+                .description(self.config.motd.clone()),
+        )
+    }
+}
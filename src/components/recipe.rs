@@ -0,0 +1,197 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+use tracing::{error, info};
+use valence::{
+    ident::Ident,
+    protocol::{
+        packets::play::{
+            synchronize_recipes_s2c::{CraftingCategory, Recipe},
+            unlock_recipes_s2c::UpdateRecipeBookAction,
+            SynchronizeRecipesS2c, UnlockRecipesS2c,
+        },
+        WritePacket,
+    },
+    prelude::*,
+};
+
+use super::config::load_config;
+use super::playerdata::PlayerData;
+
+#[derive(Deserialize, Clone)]
+pub struct RecipeDefinition {
+    pub id: String,
+    /// Item names, one per ingredient slot.
+    pub ingredients: Vec<String>,
+    pub result: String,
+    #[serde(default = "default_count")]
+    pub count: i8,
+}
+
+fn default_count() -> i8 {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct RecipeConfig {
+    pub recipes: Vec<RecipeDefinition>,
+}
+
+impl Default for RecipeConfig {
+    fn default() -> Self {
+        let recipe = |id: &str, ingredients: &[&str], result: &str, count| RecipeDefinition {
+            id: format!("crystal:{id}"),
+            ingredients: ingredients.iter().map(|i| i.to_string()).collect(),
+            result: result.to_owned(),
+            count,
+        };
+        Self {
+            recipes: vec![
+                recipe("oak_planks", &["oak_log"], "oak_planks", 4),
+                recipe("birch_planks", &["birch_log"], "birch_planks", 4),
+                recipe("stick", &["oak_planks", "oak_planks"], "stick", 4),
+                recipe(
+                    "crafting_table",
+                    &["oak_planks", "oak_planks", "oak_planks", "oak_planks"],
+                    "crafting_table",
+                    1,
+                ),
+                recipe("torch", &["coal", "stick"], "torch", 4),
+            ],
+        }
+    }
+}
+
+/// A recipe with its item names resolved.
+pub struct LoadedRecipe {
+    pub id: Ident<String>,
+    pub ingredients: Vec<ItemKind>,
+    pub result: ItemStack,
+}
+
+#[derive(Resource, Default)]
+pub struct RecipeRegistry {
+    pub recipes: Vec<LoadedRecipe>,
+}
+
+fn parse_item(name: &str) -> Option<ItemKind> {
+    ItemKind::from_str(name.trim_start_matches("minecraft:"))
+}
+
+pub fn setup_recipes(mut commands: Commands) {
+    let config: RecipeConfig = load_config("recipes.json");
+    let mut registry = RecipeRegistry::default();
+    for recipe in config.recipes {
+        let ingredients: Option<Vec<ItemKind>> =
+            recipe.ingredients.iter().map(|i| parse_item(i)).collect();
+        let (Ok(id), Some(ingredients), Some(result)) = (
+            Ident::new(recipe.id.clone()),
+            ingredients,
+            parse_item(&recipe.result),
+        ) else {
+            error!("[recipe] skipping invalid recipe {}", recipe.id);
+            continue;
+        };
+        registry.recipes.push(LoadedRecipe {
+            id,
+            ingredients,
+            result: ItemStack::new(result, recipe.count, None),
+        });
+    }
+    info!("[recipe] loaded {} recipes", registry.recipes.len());
+    commands.insert_resource(registry);
+}
+
+fn unlock_packet(action: UpdateRecipeBookAction, recipe_ids: Vec<Ident<Cow<str>>>) -> UnlockRecipesS2c {
+    UnlockRecipesS2c {
+        action,
+        crafting_recipe_book_open: false,
+        crafting_recipe_book_filter_active: false,
+        smelting_recipe_book_open: false,
+        smelting_recipe_book_filter_active: false,
+        blast_furnace_recipe_book_open: false,
+        blast_furnace_recipe_book_filter_active: false,
+        smoker_recipe_book_open: false,
+        smoker_recipe_book_filter_active: false,
+        recipe_ids,
+    }
+}
+
+// Sends the recipe registry and previously unlocked recipes once player data is loaded
+pub fn init_clients_recipes(
+    registry: Res<RecipeRegistry>,
+    mut clients: Query<(&mut Client, &PlayerData), Added<PlayerData>>,
+) {
+    for (mut client, data) in &mut clients {
+        // Each ingredient slot accepts exactly one item kind.
+        let ingredients: Vec<Vec<Vec<ItemStack>>> = registry
+            .recipes
+            .iter()
+            .map(|recipe| {
+                recipe
+                    .ingredients
+                    .iter()
+                    .map(|item| vec![ItemStack::new(*item, 1, None)])
+                    .collect()
+            })
+            .collect();
+        let recipes = registry
+            .recipes
+            .iter()
+            .zip(&ingredients)
+            .map(|(recipe, slots)| Recipe::CraftingShapeless {
+                recipe_id: recipe.id.as_str_ident().into(),
+                group: "",
+                category: CraftingCategory::Misc,
+                ingredients: slots
+                    .iter()
+                    .map(|stacks| Cow::Borrowed(stacks.as_slice()))
+                    .collect(),
+                result: recipe.result.clone(),
+            })
+            .collect();
+        client.write_packet(&SynchronizeRecipesS2c { recipes });
+
+        let unlocked = data
+            .unlocked_recipes
+            .iter()
+            .filter_map(|id| Ident::new(Cow::Borrowed(id.as_str())).ok())
+            .collect::<Vec<_>>();
+        client.write_packet(&unlock_packet(
+            UpdateRecipeBookAction::Init {
+                recipe_ids: unlocked.clone(),
+            },
+            unlocked,
+        ));
+    }
+}
+
+// Unlocks every recipe the player holds at least one ingredient for
+pub fn unlock_recipes(
+    registry: Res<RecipeRegistry>,
+    mut clients: Query<(&mut Client, &Inventory, &mut PlayerData), Changed<Inventory>>,
+) {
+    for (mut client, inventory, mut data) in &mut clients {
+        let newly_unlocked: Vec<&LoadedRecipe> = registry
+            .recipes
+            .iter()
+            .filter(|recipe| !data.unlocked_recipes.iter().any(|id| id == recipe.id.as_str()))
+            .filter(|recipe| {
+                inventory
+                    .slots()
+                    .any(|stack| recipe.ingredients.contains(&stack.item))
+            })
+            .collect();
+        if newly_unlocked.is_empty() {
+            continue;
+        }
+
+        let ids = newly_unlocked
+            .iter()
+            .map(|recipe| recipe.id.as_str_ident().into())
+            .collect();
+        client.write_packet(&unlock_packet(UpdateRecipeBookAction::Add, ids));
+        data.unlocked_recipes
+            .extend(newly_unlocked.iter().map(|recipe| recipe.id.to_string()));
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use valence::{interact_block::InteractBlockEvent, prelude::*};
+
+use crate::world::events::{WorldEvent, WorldEventKind};
+use crate::world::{in_overworld, Overworld};
+
+const BUTTON_PRESS_TICKS: u32 = 20;
+
+/// Blocks players can switch on and off by clicking them.
+pub fn is_redstone_input(kind: BlockKind) -> bool {
+    is_button(kind) || kind == BlockKind::Lever
+}
+
+fn is_button(kind: BlockKind) -> bool {
+    kind.to_str().ends_with("_button")
+}
+
+/// Doors, trapdoors and fence gates players can open by hand (not iron ones).
+pub fn is_openable(kind: BlockKind) -> bool {
+    let name = kind.to_str();
+    !name.starts_with("iron_")
+        && (name.ends_with("_door") || name.ends_with("_trapdoor") || name.ends_with("_fence_gate"))
+}
+
+/// Buttons that are currently pressed and the ticks until they pop back out.
+#[derive(Resource, Default)]
+pub struct PressedButtons {
+    buttons: HashMap<BlockPos, u32>,
+}
+
+/// Whether a block emits power into its neighbours.
+fn emits_power(state: BlockState) -> bool {
+    state.to_kind() == BlockKind::RedstoneBlock || state.get(PropName::Powered) == Some(PropValue::True)
+}
+
+/// There's no wire simulation yet, so a block counts as powered when a lever,
+/// button or redstone block is directly next to it.
+pub fn is_powered(layer: &ChunkLayer, pos: BlockPos) -> bool {
+    [
+        [1, 0, 0],
+        [-1, 0, 0],
+        [0, 1, 0],
+        [0, -1, 0],
+        [0, 0, 1],
+        [0, 0, -1],
+    ]
+    .iter()
+    .any(|[x, y, z]| {
+        layer
+            .block(BlockPos::new(pos.x + x, pos.y + y, pos.z + z))
+            .is_some_and(|block| emits_power(block.state))
+    })
+}
+
+// Flips levers, presses buttons and opens doors on right click
+pub fn toggle_redstone_inputs(
+    mut events: EventReader<InteractBlockEvent>,
+    mut layers: Query<(Entity, &mut ChunkLayer), With<Overworld>>,
+    clients: Query<&VisibleChunkLayer>,
+    mut pressed: ResMut<PressedButtons>,
+    mut world_events: EventWriter<WorldEvent>,
+) {
+    let Ok((overworld, mut layer)) = layers.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        if !in_overworld(&clients, event.client, overworld) {
+            continue;
+        }
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+        let kind = state.to_kind();
+        let on = if kind == BlockKind::Lever {
+            let powered = state.get(PropName::Powered) == Some(PropValue::True);
+            let value = if powered { PropValue::False } else { PropValue::True };
+            layer.set_block(event.position, state.set(PropName::Powered, value));
+            !powered
+        } else if is_button(kind) && !pressed.buttons.contains_key(&event.position) {
+            layer.set_block(event.position, state.set(PropName::Powered, PropValue::True));
+            pressed.buttons.insert(event.position, BUTTON_PRESS_TICKS);
+            true
+        } else if is_openable(kind) {
+            let open = state.get(PropName::Open) != Some(PropValue::True);
+            let value = if open { PropValue::True } else { PropValue::False };
+            layer.set_block(event.position, state.set(PropName::Open, value));
+            // Doors are two blocks tall and both halves have to agree
+            let other_half = match state.get(PropName::Half) {
+                Some(PropValue::Lower) if kind.to_str().ends_with("_door") => Some(event.position.get_in_direction(Direction::Up)),
+                Some(PropValue::Upper) if kind.to_str().ends_with("_door") => Some(event.position.get_in_direction(Direction::Down)),
+                _ => None,
+            };
+            if let Some(other) = other_half
+                && let Some(other_state) = layer.block(other).map(|b| b.state)
+                && other_state.to_kind() == kind
+            {
+                layer.set_block(other, other_state.set(PropName::Open, value));
+            }
+            open
+        } else {
+            continue;
+        };
+        world_events.send(WorldEvent::at_block(
+            overworld,
+            event.position,
+            Some(event.client),
+            WorldEventKind::BlockToggled { state, on },
+        ));
+    }
+}
+
+pub fn release_buttons(
+    mut layers: Query<(Entity, &mut ChunkLayer), With<Overworld>>,
+    mut pressed: ResMut<PressedButtons>,
+    mut world_events: EventWriter<WorldEvent>,
+) {
+    let Ok((overworld, mut layer)) = layers.get_single_mut() else {
+        return;
+    };
+    pressed.buttons.retain(|pos, ticks| {
+        *ticks -= 1;
+        if *ticks > 0 {
+            return true;
+        }
+        if let Some(state) = layer.block(*pos).map(|b| b.state) {
+            if is_button(state.to_kind()) {
+                layer.set_block(*pos, state.set(PropName::Powered, PropValue::False));
+                world_events.send(WorldEvent::at_block(
+                    overworld,
+                    *pos,
+                    None,
+                    WorldEventKind::BlockToggled { state, on: false },
+                ));
+            }
+        }
+        false
+    });
+}
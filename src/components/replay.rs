@@ -0,0 +1,364 @@
+// src/components/replay.rs
+//
+// Recording flagged players for moderation. While a flagged player is online,
+// their movement and the blocks they break and place are appended to a log
+// in `recordings/`, one short line per frame:
+//   `m <tick> <x> <y> <z> <yaw> <pitch>`, `b <tick> <x> <y> <z>` or
+//   `p <tick> <x> <y> <z> <block state id>`
+// with ticks counted from the start of the session. Movement is only written
+// when it's changed noticeably, so standing still costs nothing.
+//
+// A recording plays back as an armor stand with the player's name in an
+// entity layer only the viewer can see, and the block changes show up as
+// ghost blocks for them alone. Playback happens in the viewer's own world
+// at the recorded coordinates, so view it from the world it was made in.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::{error, info};
+use valence::{
+    entity::{
+        armor_stand::ArmorStandEntityBundle,
+        entity::{CustomName, NameVisible},
+        EntityLayerId, HeadYaw, Look,
+    },
+    movement::MovementEvent,
+    prelude::*,
+};
+
+use super::ghost::GhostBlocks;
+use super::logging::STORAGE;
+use crate::world::events::{WorldEvent, WorldEventKind};
+
+const RECORDINGS_DIR: &str = "recordings";
+/// Usernames being recorded, one per line.
+const FLAGGED_FILE: &str = "recordings/flagged.txt";
+/// How often recorded frames are written out.
+const FLUSH_TICKS: i64 = 200;
+/// Smaller moves and turns than these aren't recorded.
+const MIN_MOVE: f64 = 0.05;
+const MIN_TURN: f32 = 2.0;
+/// How long replayed block changes stay visible after they happen.
+const REPLAY_GHOST_TIME: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Copy, Debug)]
+pub enum Frame {
+    Move { pos: DVec3, yaw: f32, pitch: f32 },
+    Break { pos: BlockPos },
+    Place { pos: BlockPos, state: BlockState },
+}
+
+impl Frame {
+    fn to_line(self, tick: i64) -> String {
+        match self {
+            Frame::Move { pos, yaw, pitch } => {
+                format!("m {tick} {:.2} {:.2} {:.2} {yaw:.0} {pitch:.0}", pos.x, pos.y, pos.z)
+            }
+            Frame::Break { pos } => format!("b {tick} {} {} {}", pos.x, pos.y, pos.z),
+            Frame::Place { pos, state } => format!("p {tick} {} {} {} {}", pos.x, pos.y, pos.z, state.to_raw()),
+        }
+    }
+
+    fn parse(line: &str) -> Option<(i64, Frame)> {
+        let mut parts = line.split_whitespace();
+        let kind = parts.next()?;
+        let tick = parts.next()?.parse().ok()?;
+        let mut number = || parts.next()?.parse::<f64>().ok();
+        let frame = match kind {
+            "m" => Frame::Move {
+                pos: DVec3::new(number()?, number()?, number()?),
+                yaw: number()? as f32,
+                pitch: number()? as f32,
+            },
+            "b" => Frame::Break { pos: BlockPos::new(number()? as i32, number()? as i32, number()? as i32) },
+            "p" => Frame::Place {
+                pos: BlockPos::new(number()? as i32, number()? as i32, number()? as i32),
+                state: BlockState::from_raw(number()? as u16)?,
+            },
+            _ => return None,
+        };
+        Some((tick, frame))
+    }
+}
+
+struct Session {
+    path: PathBuf,
+    started: i64,
+    last_flush: i64,
+    pending: Vec<String>,
+    last_move: Option<(DVec3, f32, f32)>,
+}
+
+impl Session {
+    fn record(&mut self, now: i64, frame: Frame) {
+        self.pending.push(frame.to_line(now - self.started));
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(RECORDINGS_DIR)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        for line in self.pending.drain(..) {
+            writeln!(file, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+pub struct Recorder {
+    /// Lowercase usernames.
+    flagged: BTreeSet<String>,
+    sessions: HashMap<Entity, Session>,
+}
+
+impl Recorder {
+    pub fn load() -> Self {
+        let flagged = fs::read_to_string(FLAGGED_FILE)
+            .map(|text| text.lines().map(|line| line.trim().to_lowercase()).filter(|line| !line.is_empty()).collect())
+            .unwrap_or_default();
+        Self { flagged, sessions: HashMap::new() }
+    }
+
+    pub fn is_flagged(&self, username: &str) -> bool {
+        self.flagged.contains(&username.to_lowercase())
+    }
+
+    /// Starts or stops recording `username`. Takes effect next tick.
+    pub fn set_flagged(&mut self, username: &str, flagged: bool) -> io::Result<()> {
+        if flagged {
+            self.flagged.insert(username.to_lowercase());
+        } else {
+            self.flagged.remove(&username.to_lowercase());
+        }
+        fs::create_dir_all(RECORDINGS_DIR)?;
+        let lines: Vec<&str> = self.flagged.iter().map(String::as_str).collect();
+        fs::write(FLAGGED_FILE, lines.join("\n"))
+    }
+
+    /// Names of the saved recordings, optionally only `username`'s, oldest
+    /// first.
+    pub fn list(username: Option<&str>) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(RECORDINGS_DIR) else {
+            return Vec::new();
+        };
+        let prefix = username.map(|name| format!("{}-", name.to_lowercase()));
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "log").then(|| path.file_stem()?.to_str().map(str::to_owned))?
+            })
+            .filter(|name| prefix.as_ref().is_none_or(|prefix| name.starts_with(prefix.as_str())))
+            .collect();
+        // Names end in the start time, so this is oldest first per player
+        names.sort_unstable();
+        names
+    }
+
+    /// The frames of a saved recording.
+    pub fn read(name: &str) -> io::Result<Vec<(i64, Frame)>> {
+        let text = fs::read_to_string(Path::new(RECORDINGS_DIR).join(format!("{name}.log")))?;
+        Ok(text.lines().filter_map(Frame::parse).collect())
+    }
+}
+
+fn end_session(username: Option<&str>, mut session: Session) {
+    if let Err(e) = session.flush() {
+        error!(target: STORAGE, "couldn't write recording {}: {e}", session.path.display());
+    }
+    info!(target: STORAGE, "finished recording {}", username.unwrap_or("a player who left"));
+}
+
+// Starts and ends sessions as flagged players come and go, and writes out
+// what's been recorded every so often
+pub fn update_recordings(mut recorder: ResMut<Recorder>, clients: Query<(Entity, &Username)>, server: Res<Server>) {
+    let now = server.current_tick();
+    let recorder = &mut *recorder;
+    for (player, username) in &clients {
+        if recorder.is_flagged(&username.0) && !recorder.sessions.contains_key(&player) {
+            let started_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            let path = Path::new(RECORDINGS_DIR).join(format!("{}-{started_at}.log", username.0.to_lowercase()));
+            info!(target: STORAGE, "recording {} to {}", username.0, path.display());
+            recorder.sessions.insert(
+                player,
+                Session { path, started: now, last_flush: now, pending: Vec::new(), last_move: None },
+            );
+        }
+    }
+
+    let ended: Vec<Entity> = recorder
+        .sessions
+        .keys()
+        .copied()
+        .filter(|player| !clients.get(*player).is_ok_and(|(_, username)| recorder.is_flagged(&username.0)))
+        .collect();
+    for player in ended {
+        if let Some(session) = recorder.sessions.remove(&player) {
+            end_session(clients.get(player).ok().map(|(_, username)| username.0.as_str()), session);
+        }
+    }
+
+    for session in recorder.sessions.values_mut() {
+        if now - session.last_flush >= FLUSH_TICKS {
+            session.last_flush = now;
+            if let Err(e) = session.flush() {
+                error!(target: STORAGE, "couldn't write recording {}: {e}", session.path.display());
+            }
+        }
+    }
+}
+
+pub fn record_movement(mut events: EventReader<MovementEvent>, mut recorder: ResMut<Recorder>, server: Res<Server>) {
+    for event in events.read() {
+        let Some(session) = recorder.sessions.get_mut(&event.client) else {
+            continue;
+        };
+        let (pos, yaw, pitch) = (event.position, event.look.yaw, event.look.pitch);
+        let moved = session.last_move.is_none_or(|(last_pos, last_yaw, last_pitch)| {
+            last_pos.distance(pos) >= MIN_MOVE
+                || (last_yaw - yaw).abs() >= MIN_TURN
+                || (last_pitch - pitch).abs() >= MIN_TURN
+        });
+        if moved {
+            session.last_move = Some((pos, yaw, pitch));
+            session.record(server.current_tick(), Frame::Move { pos, yaw, pitch });
+        }
+    }
+}
+
+pub fn record_block_changes(mut events: EventReader<WorldEvent>, mut recorder: ResMut<Recorder>, server: Res<Server>) {
+    for event in events.read() {
+        let Some(session) = event.source.and_then(|source| recorder.sessions.get_mut(&source)) else {
+            continue;
+        };
+        let pos = BlockPos::from(event.pos);
+        let frame = match event.kind {
+            WorldEventKind::BlockBroken { .. } => Frame::Break { pos },
+            WorldEventKind::BlockPlaced { state } => Frame::Place { pos, state },
+            _ => continue,
+        };
+        session.record(server.current_tick(), frame);
+    }
+}
+
+struct Playback {
+    /// The entity layer only the viewer sees, and the stand in it.
+    layer: Entity,
+    stand: Entity,
+    frames: Vec<(i64, Frame)>,
+    started: i64,
+    next: usize,
+}
+
+/// Recordings being played back, by viewer.
+#[derive(Resource, Default)]
+pub struct Replays {
+    playing: HashMap<Entity, Playback>,
+}
+
+impl Replays {
+    /// Plays `frames` to `viewer`, replacing whatever they were watching.
+    pub fn start(
+        &mut self,
+        commands: &mut Commands,
+        server: &Server,
+        viewer: Entity,
+        visible: &mut VisibleEntityLayers,
+        name: &str,
+        frames: Vec<(i64, Frame)>,
+    ) {
+        self.stop(commands, viewer, visible);
+        let start = frames
+            .iter()
+            .find_map(|(_, frame)| match frame {
+                Frame::Move { pos, .. } => Some(*pos),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let layer = commands.spawn(EntityLayer::new(server)).id();
+        let stand = commands
+            .spawn(ArmorStandEntityBundle {
+                layer: EntityLayerId(layer),
+                position: Position(start),
+                entity_custom_name: CustomName(Some(format!("{name} (replay)").color(Color::GOLD))),
+                entity_name_visible: NameVisible(true),
+                ..Default::default()
+            })
+            .id();
+        visible.0.insert(layer);
+        self.playing.insert(viewer, Playback { layer, stand, frames, started: server.current_tick(), next: 0 });
+    }
+
+    /// Ends `viewer`'s playback, if they're watching one.
+    pub fn stop(&mut self, commands: &mut Commands, viewer: Entity, visible: &mut VisibleEntityLayers) -> bool {
+        let Some(playback) = self.playing.remove(&viewer) else {
+            return false;
+        };
+        visible.0.remove(&playback.layer);
+        commands.entity(playback.stand).insert(Despawned);
+        commands.entity(playback.layer).insert(Despawned);
+        true
+    }
+}
+
+// Moves replay stands along their recordings and shows their block changes
+pub fn play_replays(
+    mut commands: Commands,
+    mut replays: ResMut<Replays>,
+    mut viewers: Query<(&mut Client, &mut VisibleEntityLayers)>,
+    mut stands: Query<(&mut Position, &mut Look, &mut HeadYaw)>,
+    mut ghosts: ResMut<GhostBlocks>,
+    server: Res<Server>,
+) {
+    let now = server.current_tick();
+    let mut finished = Vec::new();
+    for (viewer, playback) in &mut replays.playing {
+        if !viewers.contains(*viewer) {
+            finished.push(*viewer);
+            continue;
+        }
+        let elapsed = now - playback.started;
+        while let Some((tick, frame)) = playback.frames.get(playback.next).copied()
+            && tick <= elapsed
+        {
+            playback.next += 1;
+            match frame {
+                Frame::Move { pos, yaw, pitch } => {
+                    if let Ok((mut position, mut look, mut head_yaw)) = stands.get_mut(playback.stand) {
+                        position.set(pos);
+                        *look = Look::new(yaw, pitch);
+                        head_yaw.0 = yaw;
+                    }
+                }
+                Frame::Break { pos } => {
+                    ghosts.show(*viewer, [(pos, BlockState::AIR)], REPLAY_GHOST_TIME);
+                }
+                Frame::Place { pos, state } => {
+                    ghosts.show(*viewer, [(pos, state)], REPLAY_GHOST_TIME);
+                }
+            }
+        }
+        if playback.next >= playback.frames.len() {
+            finished.push(*viewer);
+        }
+    }
+
+    for viewer in finished {
+        let Ok((mut client, mut visible)) = viewers.get_mut(viewer) else {
+            // The viewer left, so only the replay's own entities need cleaning up
+            if let Some(playback) = replays.playing.remove(&viewer) {
+                commands.entity(playback.stand).insert(Despawned);
+                commands.entity(playback.layer).insert(Despawned);
+            }
+            continue;
+        };
+        replays.stop(&mut commands, viewer, &mut visible);
+        client.send_chat_message("[replay] finished, block changes stay visible for a few minutes".color(Color::GRAY));
+    }
+}
@@ -0,0 +1,216 @@
+// src/components/schematic.rs
+//
+// Copies of boxes of blocks. Spleef uses them to reset arenas; `/structure`
+// saves them to `structures/<name>.json` so they can be pasted again or
+// generated into new terrain.
+//
+// Structure voids are kept as-is and never pasted, so they leave whatever is
+// already there alone. A jigsaw block marks the anchor, the block that ends
+// up on the ground when the structure is placed, and is saved as air.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use valence::prelude::*;
+
+use super::decoration::{SavedDecorations, SavedStack};
+use crate::world::batch::BlockBatch;
+
+pub const STRUCTURES_DIR: &str = "structures";
+
+/// The contents of a container block inside a schematic.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedContainer {
+    /// Relative to the schematic's minimum corner.
+    pub offset: [i32; 3],
+    pub items: Vec<(u16, SavedStack)>,
+}
+
+/// A copy of a box of blocks that can be pasted back into a layer later.
+#[derive(Clone, Debug)]
+pub struct Schematic {
+    pub size: [i32; 3],
+    /// Stored x-fastest, then z, then y.
+    blocks: Vec<BlockState>,
+    /// The block placed on the surface when generated into terrain, relative
+    /// to the minimum corner. Defaults to the middle of the bottom layer.
+    pub anchor: [i32; 3],
+    pub containers: Vec<SavedContainer>,
+    /// Positions are relative to the minimum corner.
+    pub decorations: SavedDecorations,
+}
+
+/// On-disk form of a schematic. Blocks are indices into `palette`, which
+/// holds raw block state ids.
+#[derive(Serialize, Deserialize)]
+struct SchematicFile {
+    size: [i32; 3],
+    anchor: [i32; 3],
+    palette: Vec<u16>,
+    blocks: Vec<u16>,
+    #[serde(default)]
+    containers: Vec<SavedContainer>,
+    #[serde(default)]
+    decorations: SavedDecorations,
+}
+
+impl Schematic {
+    /// Copies every block between `min` and `max` (inclusive). Blocks in
+    /// unloaded chunks are stored as air.
+    pub fn capture(layer: &ChunkLayer, min: BlockPos, max: BlockPos) -> Self {
+        let size = [max.x - min.x + 1, max.y - min.y + 1, max.z - min.z + 1];
+        let mut blocks = Vec::with_capacity((size[0] * size[1] * size[2]).max(0) as usize);
+        let mut anchor = [size[0] / 2, 0, size[2] / 2];
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let state = layer
+                        .block(BlockPos::new(x, y, z))
+                        .map(|block| block.state)
+                        .unwrap_or(BlockState::AIR);
+                    if state.to_kind() == BlockKind::Jigsaw {
+                        anchor = [x - min.x, y - min.y, z - min.z];
+                        blocks.push(BlockState::AIR);
+                    } else {
+                        blocks.push(state);
+                    }
+                }
+            }
+        }
+        Self {
+            size,
+            blocks,
+            anchor,
+            containers: Vec::new(),
+            decorations: SavedDecorations::default(),
+        }
+    }
+
+    pub fn volume(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Iterates over `(offset, state)` pairs relative to the schematic origin.
+    pub fn iter(&self) -> impl Iterator<Item = ([i32; 3], BlockState)> + '_ {
+        let [sx, _, sz] = self.size;
+        self.blocks.iter().enumerate().map(move |(i, state)| {
+            let i = i as i32;
+            ([i % sx, i / (sx * sz), (i / sx) % sz], *state)
+        })
+    }
+
+    /// The block at `offset`, if it's inside the schematic.
+    pub fn get(&self, [x, y, z]: [i32; 3]) -> Option<BlockState> {
+        let [sx, sy, sz] = self.size;
+        if !(0..sx).contains(&x) || !(0..sy).contains(&y) || !(0..sz).contains(&z) {
+            return None;
+        }
+        Some(self.blocks[(x + z * sx + y * sx * sz) as usize])
+    }
+
+    /// Where the minimum corner goes so the anchor ends up at `pos`.
+    pub fn origin_for_anchor(&self, pos: BlockPos) -> BlockPos {
+        BlockPos::new(pos.x - self.anchor[0], pos.y - self.anchor[1], pos.z - self.anchor[2])
+    }
+
+    /// The blocks pasting at `origin` would change, with what they'd become.
+    pub fn changes<'a>(&'a self, layer: &'a ChunkLayer, origin: BlockPos) -> impl Iterator<Item = (BlockPos, BlockState)> + 'a {
+        self.iter().filter_map(move |([x, y, z], state)| {
+            let pos = BlockPos::new(origin.x + x, origin.y + y, origin.z + z);
+            (state != BlockState::STRUCTURE_VOID && layer.block(pos).is_some_and(|block| block.state != state))
+                .then_some((pos, state))
+        })
+    }
+
+    /// Pastes the schematic with its minimum corner at `origin`, returning how
+    /// many blocks were actually changed. Structure voids are skipped.
+    pub fn paste(&self, layer: &mut ChunkLayer, origin: BlockPos) -> usize {
+        let mut batch = BlockBatch::default();
+        for ([x, y, z], state) in self.iter().filter(|(_, state)| *state != BlockState::STRUCTURE_VOID) {
+            batch.set(BlockPos::new(origin.x + x, origin.y + y, origin.z + z), state);
+        }
+        batch.apply(layer)
+    }
+
+    pub fn path(name: &str) -> PathBuf {
+        Path::new(STRUCTURES_DIR).join(format!("{name}.json"))
+    }
+
+    /// Whether `name` is usable as a file name.
+    pub fn valid_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    pub fn save(&self, name: &str) -> std::io::Result<()> {
+        let mut palette: Vec<u16> = Vec::new();
+        let mut index_of: HashMap<u16, u16> = HashMap::new();
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|state| {
+                *index_of.entry(state.to_raw()).or_insert_with(|| {
+                    palette.push(state.to_raw());
+                    (palette.len() - 1) as u16
+                })
+            })
+            .collect();
+        let file = SchematicFile {
+            size: self.size,
+            anchor: self.anchor,
+            palette,
+            blocks,
+            containers: self.containers.clone(),
+            decorations: self.decorations.clone(),
+        };
+        fs::create_dir_all(STRUCTURES_DIR)?;
+        let json = serde_json::to_string(&file).map_err(std::io::Error::other)?;
+        fs::write(Self::path(name), json)
+    }
+
+    pub fn load(name: &str) -> std::io::Result<Self> {
+        if !Self::valid_name(name) {
+            return Err(std::io::Error::other("invalid structure name"));
+        }
+        let contents = fs::read_to_string(Self::path(name))?;
+        let file: SchematicFile = serde_json::from_str(&contents).map_err(std::io::Error::other)?;
+        let volume = (file.size[0] * file.size[1] * file.size[2]).max(0) as usize;
+        if file.blocks.len() != volume {
+            return Err(std::io::Error::other(format!("expected {volume} blocks, found {}", file.blocks.len())));
+        }
+        let blocks = file
+            .blocks
+            .iter()
+            .map(|index| {
+                file.palette
+                    .get(*index as usize)
+                    .and_then(|raw| BlockState::from_raw(*raw))
+                    .unwrap_or(BlockState::AIR)
+            })
+            .collect();
+        Ok(Self {
+            size: file.size,
+            blocks,
+            anchor: file.anchor,
+            containers: file.containers,
+            decorations: file.decorations,
+        })
+    }
+
+    /// Names of every saved structure.
+    pub fn list() -> Vec<String> {
+        let Ok(entries) = fs::read_dir(STRUCTURES_DIR) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "json").then_some(())?;
+                path.file_stem()?.to_str().map(str::to_owned)
+            })
+            .collect();
+        names.sort();
+        names
+    }
+}
@@ -0,0 +1,235 @@
+// src/components/sleep.rs
+//
+// Beds and skipping the night. Like vanilla's playersSleepingPercentage, only
+// a share of the players in the overworld have to be in bed; everyone is
+// told in chat how many are sleeping and how many are needed.
+//
+// `config/sleep.json`: `{ "players_sleeping_percentage": 50 }`
+
+use serde::Deserialize;
+use tracing::info;
+use valence::{
+    client_command::LeaveBedEvent,
+    entity::{entity::Pose as PoseComponent, living::SleepingPosition, Pose},
+    interact_block::InteractBlockEvent,
+    protocol::WritePacket,
+    prelude::*,
+};
+
+use super::config::load_config;
+use super::hud::HudMessage;
+use super::logging::AUDIT;
+use super::time::WorldTime;
+use crate::world::{in_overworld, Overworld};
+
+/// Vanilla makes players lie down for this long before the night is skipped.
+const FALL_ASLEEP_TICKS: i64 = 100;
+/// How close a player has to be to use a bed.
+const BED_REACH: f64 = 3.0;
+
+#[derive(Deserialize, Resource, Clone, Debug)]
+#[serde(default)]
+pub struct SleepConfig {
+    /// Percentage of overworld players that have to sleep to skip the night.
+    /// 0 means one is enough.
+    pub players_sleeping_percentage: u32,
+}
+
+impl Default for SleepConfig {
+    fn default() -> Self {
+        Self {
+            players_sleeping_percentage: 100,
+        }
+    }
+}
+
+impl SleepConfig {
+    pub fn needed(&self, players: usize) -> usize {
+        let percentage = self.players_sleeping_percentage.min(100) as usize;
+        (players * percentage).div_ceil(100).max(1)
+    }
+}
+
+#[derive(Component)]
+pub struct Sleeping {
+    pub bed: BlockPos,
+    pub since: i64,
+}
+
+pub fn is_bed(kind: BlockKind) -> bool {
+    kind.to_str().ends_with("_bed")
+}
+
+/// Beds are two blocks; players lie in the head half.
+fn bed_head(pos: BlockPos, state: BlockState) -> BlockPos {
+    if state.get(PropName::Part) == Some(PropValue::Head) {
+        return pos;
+    }
+    match state.get(PropName::Facing) {
+        Some(PropValue::North) => BlockPos::new(pos.x, pos.y, pos.z - 1),
+        Some(PropValue::South) => BlockPos::new(pos.x, pos.y, pos.z + 1),
+        Some(PropValue::West) => BlockPos::new(pos.x - 1, pos.y, pos.z),
+        Some(PropValue::East) => BlockPos::new(pos.x + 1, pos.y, pos.z),
+        _ => pos,
+    }
+}
+
+/// Players who count towards the sleep vote. Spectators can't sleep, so
+/// they're left out.
+fn overworld_players(players: &Query<(&VisibleChunkLayer, &GameMode), With<Client>>, overworld: Entity) -> usize {
+    players
+        .iter()
+        .filter(|(layer, game_mode)| layer.0 == overworld && **game_mode != GameMode::Spectator)
+        .count()
+}
+
+fn broadcast(clients: &mut Query<&mut Client>, message: Text) {
+    for mut client in clients {
+        client.send_chat_message(message.clone());
+    }
+}
+
+pub fn setup_sleep(mut commands: Commands) {
+    commands.insert_resource(load_config::<SleepConfig>("sleep.json"));
+}
+
+pub fn enter_beds(
+    mut commands: Commands,
+    mut events: EventReader<InteractBlockEvent>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    visible_layers: Query<&VisibleChunkLayer>,
+    mut sleepers: Query<(&Username, &GameMode, &mut Position, &mut PoseComponent, &mut SleepingPosition), Without<Sleeping>>,
+    sleeping: Query<&Sleeping>,
+    time: Res<WorldTime>,
+    server: Res<Server>,
+    mut hud_messages: EventWriter<HudMessage>,
+) {
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+        if !is_bed(state.to_kind()) || !in_overworld(&visible_layers, event.client, overworld) {
+            continue;
+        }
+        let Ok((username, game_mode, mut pos, mut pose, mut sleeping_pos)) = sleepers.get_mut(event.client) else {
+            continue;
+        };
+        if matches!(game_mode, GameMode::Spectator) {
+            continue;
+        }
+        let head = bed_head(event.position, state);
+        let bed_center = DVec3::new(head.x as f64 + 0.5, head.y as f64 + 0.5625, head.z as f64 + 0.5);
+        if pos.0.distance(bed_center) > BED_REACH {
+            hud_messages.send(HudMessage::new(event.client, "You may not rest now; the bed is too far away"));
+            continue;
+        }
+        if time.is_day() {
+            hud_messages.send(HudMessage::new(event.client, "You can sleep only at night"));
+            continue;
+        }
+        if sleeping.iter().any(|s| s.bed == head) {
+            hud_messages.send(HudMessage::new(event.client, "This bed is occupied"));
+            continue;
+        }
+
+        pos.set(bed_center);
+        pose.0 = Pose::Sleeping;
+        sleeping_pos.0 = Some(head);
+        commands.entity(event.client).insert(Sleeping {
+            bed: head,
+            since: server.current_tick(),
+        });
+        info!(target: AUDIT, player = %username.0, "went to bed at {head:?}");
+    }
+}
+
+fn wake(pose: &mut PoseComponent, sleeping_pos: &mut SleepingPosition) {
+    pose.0 = Pose::Standing;
+    sleeping_pos.0 = None;
+}
+
+pub fn leave_beds(
+    mut commands: Commands,
+    mut events: EventReader<LeaveBedEvent>,
+    mut sleepers: Query<(&mut PoseComponent, &mut SleepingPosition), With<Sleeping>>,
+) {
+    for event in events.read() {
+        if let Ok((mut pose, mut sleeping_pos)) = sleepers.get_mut(event.client) {
+            wake(&mut pose, &mut sleeping_pos);
+            commands.entity(event.client).remove::<Sleeping>();
+        }
+    }
+}
+
+/// Tells everyone when the number of sleeping players changes.
+pub fn announce_sleepers(
+    added: Query<&Username, Added<Sleeping>>,
+    mut removed: RemovedComponents<Sleeping>,
+    sleeping: Query<(), With<Sleeping>>,
+    players: Query<(&VisibleChunkLayer, &GameMode), With<Client>>,
+    layers: Query<Entity, With<Overworld>>,
+    mut clients: Query<&mut Client>,
+    config: Res<SleepConfig>,
+) {
+    let woke = removed.read().count();
+    if added.is_empty() && woke == 0 {
+        return;
+    }
+    let Ok(overworld) = layers.get_single() else {
+        return;
+    };
+    let total = overworld_players(&players, overworld);
+    let count = sleeping.iter().count();
+    let needed = config.needed(total);
+    for username in &added {
+        broadcast(
+            &mut clients,
+            format!("[sleep] {} is sleeping ({count}/{needed} needed)", username.0).color(Color::GRAY),
+        );
+    }
+    if added.is_empty() && count > 0 {
+        broadcast(&mut clients, format!("[sleep] {count}/{needed} players sleeping").color(Color::GRAY));
+    }
+}
+
+pub fn skip_night(
+    mut commands: Commands,
+    mut sleepers: Query<(Entity, &Sleeping, &mut PoseComponent, &mut SleepingPosition)>,
+    players: Query<(&VisibleChunkLayer, &GameMode), With<Client>>,
+    layers: Query<Entity, With<Overworld>>,
+    mut clients: Query<&mut Client>,
+    mut time: ResMut<WorldTime>,
+    config: Res<SleepConfig>,
+    server: Res<Server>,
+) {
+    if sleepers.is_empty() {
+        return;
+    }
+    let Ok(overworld) = layers.get_single() else {
+        return;
+    };
+    let total = overworld_players(&players, overworld);
+    let now = server.current_tick();
+    let asleep = sleepers.iter().filter(|(_, s, ..)| now - s.since >= FALL_ASLEEP_TICKS).count();
+    if asleep < config.needed(total) {
+        return;
+    }
+
+    time.skip_to_morning();
+    let packet = time.packet();
+    for mut client in &mut clients {
+        client.write_packet(&packet);
+    }
+    for (entity, _, mut pose, mut sleeping_pos) in &mut sleepers {
+        wake(&mut pose, &mut sleeping_pos);
+        commands.entity(entity).remove::<Sleeping>();
+    }
+    broadcast(&mut clients, "[sleep] the night was skipped".color(Color::GOLD));
+    info!(target: AUDIT, "night skipped with {asleep}/{total} players asleep");
+}
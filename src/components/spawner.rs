@@ -0,0 +1,300 @@
+// src/components/spawner.rs
+//
+// Mob spawners. Every spawner block has a mob and a countdown that only runs
+// while a player is within `player_range`; when it runs out, a few mobs
+// appear on free spots around the spawner unless there are already too many
+// of them nearby. Placed spawners and spawners in generated structures spawn
+// pigs until an op looks at one and runs `/setspawner <mob>`. Broken spawners drop nothing (see the `blocks/spawner`
+// loot table).
+//
+// Spawners are kept in `world/spawners.json`.
+//
+// `config/spawners.json`:
+// `{ "min_delay": 200, "max_delay": 800, "spawn_count": 4, "spawn_range": 4,
+//    "player_range": 16.0, "max_nearby": 6 }`
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use valence::{
+    entity::{
+        blaze::BlazeEntityBundle, cave_spider::CaveSpiderEntityBundle, chicken::ChickenEntityBundle,
+        cow::CowEntityBundle, creeper::CreeperEntityBundle, husk::HuskEntityBundle, pig::PigEntityBundle,
+        sheep::SheepEntityBundle, silverfish::SilverfishEntityBundle, skeleton::SkeletonEntityBundle,
+        spider::SpiderEntityBundle, stray::StrayEntityBundle, witch::WitchEntityBundle, zombie::ZombieEntityBundle,
+        EntityLayerId,
+    },
+    interact_block::InteractBlockEvent,
+    prelude::*,
+    rand::Rng,
+};
+
+use super::config::load_config;
+use super::entity_rules::NaturalMob;
+use super::logging::STORAGE;
+use crate::world::{in_overworld, Overworld};
+
+pub const SPAWNERS_FILE: &str = "world/spawners.json";
+const DEFAULT_MOB: &str = "pig";
+
+/// Mobs a spawner can be set to.
+pub const SPAWNER_MOBS: &[(&str, EntityKind)] = &[
+    ("blaze", EntityKind::BLAZE),
+    ("cave_spider", EntityKind::CAVE_SPIDER),
+    ("chicken", EntityKind::CHICKEN),
+    ("cow", EntityKind::COW),
+    ("creeper", EntityKind::CREEPER),
+    ("husk", EntityKind::HUSK),
+    ("pig", EntityKind::PIG),
+    ("sheep", EntityKind::SHEEP),
+    ("silverfish", EntityKind::SILVERFISH),
+    ("skeleton", EntityKind::SKELETON),
+    ("spider", EntityKind::SPIDER),
+    ("stray", EntityKind::STRAY),
+    ("witch", EntityKind::WITCH),
+    ("zombie", EntityKind::ZOMBIE),
+];
+
+#[derive(Deserialize, Resource, Clone, Debug)]
+#[serde(default)]
+pub struct SpawnerConfig {
+    /// Ticks between spawns are picked between these.
+    pub min_delay: u32,
+    pub max_delay: u32,
+    /// Mobs spawned at once, at most.
+    pub spawn_count: u32,
+    /// How far from the spawner mobs can appear, horizontally.
+    pub spawn_range: i32,
+    /// Spawners only run with a player this close.
+    pub player_range: f64,
+    /// No more spawns while this many of the spawner's mob are near it.
+    pub max_nearby: usize,
+}
+
+impl Default for SpawnerConfig {
+    fn default() -> Self {
+        Self {
+            min_delay: 200,
+            max_delay: 800,
+            spawn_count: 4,
+            spawn_range: 4,
+            player_range: 16.0,
+            max_nearby: 6,
+        }
+    }
+}
+
+impl SpawnerConfig {
+    fn next_delay(&self) -> u32 {
+        valence::rand::thread_rng().gen_range(self.min_delay..=self.max_delay.max(self.min_delay))
+    }
+}
+
+pub struct Spawner {
+    /// One of `SPAWNER_MOBS`.
+    pub mob: &'static str,
+    /// Ticks until the next spawn.
+    pub delay: u32,
+}
+
+/// Every spawner in the overworld, keyed by block position.
+#[derive(Resource, Default)]
+pub struct Spawners {
+    pub spawners: HashMap<BlockPos, Spawner>,
+    pub dirty: bool,
+}
+
+impl Spawners {
+    /// Starts tracking a spawner block with the default mob, unless it's
+    /// already known.
+    pub fn track(&mut self, pos: BlockPos, config: &SpawnerConfig) {
+        if !self.spawners.contains_key(&pos) {
+            self.spawners.insert(
+                pos,
+                Spawner {
+                    mob: DEFAULT_MOB,
+                    delay: config.next_delay(),
+                },
+            );
+            self.dirty = true;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSpawner {
+    pos: [i32; 3],
+    mob: String,
+}
+
+pub fn find_mob(name: &str) -> Option<(&'static str, EntityKind)> {
+    let name = name.trim_start_matches("minecraft:");
+    SPAWNER_MOBS.iter().find(|(mob, _)| *mob == name).copied()
+}
+
+fn spawn_mob(commands: &mut Commands, mob: &str, layer: Entity, pos: DVec3) {
+    let layer = EntityLayerId(layer);
+    let position = Position(pos);
+    let yaw = valence::rand::thread_rng().gen_range(-180.0..180.0);
+    let (look, head_yaw) = (Look::new(yaw, 0.0), HeadYaw(yaw));
+    macro_rules! spawn {
+        ($bundle:ident) => {
+            commands.spawn((
+                $bundle {
+                    layer,
+                    position,
+                    look,
+                    head_yaw,
+                    ..Default::default()
+                },
+                NaturalMob,
+            ))
+        };
+    }
+    match mob {
+        "blaze" => spawn!(BlazeEntityBundle),
+        "cave_spider" => spawn!(CaveSpiderEntityBundle),
+        "chicken" => spawn!(ChickenEntityBundle),
+        "cow" => spawn!(CowEntityBundle),
+        "creeper" => spawn!(CreeperEntityBundle),
+        "husk" => spawn!(HuskEntityBundle),
+        "sheep" => spawn!(SheepEntityBundle),
+        "silverfish" => spawn!(SilverfishEntityBundle),
+        "skeleton" => spawn!(SkeletonEntityBundle),
+        "spider" => spawn!(SpiderEntityBundle),
+        "stray" => spawn!(StrayEntityBundle),
+        "witch" => spawn!(WitchEntityBundle),
+        "zombie" => spawn!(ZombieEntityBundle),
+        _ => spawn!(PigEntityBundle),
+    };
+}
+
+/// A random spot near the spawner with room for a mob to stand.
+fn spawn_spot(layer: &ChunkLayer, spawner: BlockPos, range: i32) -> Option<DVec3> {
+    let mut rng = valence::rand::thread_rng();
+    let x = spawner.x + rng.gen_range(-range..=range);
+    let y = spawner.y + rng.gen_range(-1..=1);
+    let z = spawner.z + rng.gen_range(-range..=range);
+    let free = |y: i32| layer.block([x, y, z]).is_some_and(|b| !b.state.blocks_motion() && !b.state.is_liquid());
+    let floor = layer.block([x, y - 1, z]).is_some_and(|b| b.state.blocks_motion());
+    (floor && free(y) && free(y + 1)).then(|| DVec3::new(x as f64 + 0.5, y as f64, z as f64 + 0.5))
+}
+
+pub fn setup_spawners(mut commands: Commands) {
+    commands.insert_resource(load_config::<SpawnerConfig>("spawners.json"));
+
+    let mut spawners = Spawners::default();
+    if let Ok(contents) = fs::read_to_string(SPAWNERS_FILE) {
+        match serde_json::from_str::<Vec<SavedSpawner>>(&contents) {
+            Ok(saved) => {
+                for spawner in saved {
+                    let mob = find_mob(&spawner.mob).map_or(DEFAULT_MOB, |(mob, _)| mob);
+                    spawners.spawners.insert(BlockPos::from(spawner.pos), Spawner { mob, delay: 20 });
+                }
+                info!(target: STORAGE, "loaded {} spawners", spawners.spawners.len());
+            }
+            Err(e) => error!(target: STORAGE, "failed to parse {SPAWNERS_FILE}: {e}"),
+        }
+    }
+    commands.insert_resource(spawners);
+}
+
+// Keeps track of spawners players place
+pub fn register_placed_spawners(
+    mut events: EventReader<InteractBlockEvent>,
+    mut spawners: ResMut<Spawners>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    clients: Query<&VisibleChunkLayer>,
+    config: Res<SpawnerConfig>,
+) {
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if !in_overworld(&clients, event.client, overworld) {
+            continue;
+        }
+        let placed = event.position.get_in_direction(event.face);
+        if layer.block(placed).is_some_and(|b| b.state.to_kind() == BlockKind::Spawner) {
+            spawners.track(placed, &config);
+        }
+    }
+}
+
+pub fn tick_spawners(
+    mut commands: Commands,
+    mut spawners: ResMut<Spawners>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    players: Query<(&Position, &GameMode), With<Client>>,
+    mobs: Query<(&EntityKind, &Position), Without<Client>>,
+    config: Res<SpawnerConfig>,
+) {
+    let Ok((overworld, layer)) = layers.get_single() else {
+        return;
+    };
+    let spawners = &mut *spawners;
+    let mut removed = false;
+    spawners.spawners.retain(|pos, spawner| {
+        // Spawners in unloaded chunks are kept but don't run.
+        let Some(block) = layer.block(*pos) else {
+            return true;
+        };
+        if block.state.to_kind() != BlockKind::Spawner {
+            removed = true;
+            return false;
+        }
+        let center = DVec3::new(pos.x as f64 + 0.5, pos.y as f64 + 0.5, pos.z as f64 + 0.5);
+        let player_near = players
+            .iter()
+            .any(|(p, mode)| *mode != GameMode::Spectator && p.0.distance(center) <= config.player_range);
+        if !player_near {
+            return true;
+        }
+        if spawner.delay > 0 {
+            spawner.delay -= 1;
+            return true;
+        }
+        spawner.delay = config.next_delay();
+
+        let Some((_, kind)) = find_mob(spawner.mob) else {
+            return true;
+        };
+        let reach = (config.spawn_range * 2) as f64;
+        let nearby = mobs
+            .iter()
+            .filter(|(k, p)| **k == kind && p.0.distance(center) <= reach)
+            .count();
+        let room = config.max_nearby.saturating_sub(nearby).min(config.spawn_count as usize);
+        for _ in 0..room {
+            if let Some(spot) = spawn_spot(layer, *pos, config.spawn_range) {
+                spawn_mob(&mut commands, spawner.mob, overworld, spot);
+            }
+        }
+        true
+    });
+    spawners.dirty |= removed;
+}
+
+pub fn save_spawners(mut spawners: ResMut<Spawners>) {
+    if !spawners.dirty {
+        return;
+    }
+    spawners.dirty = false;
+    let saved: Vec<SavedSpawner> = spawners
+        .spawners
+        .iter()
+        .map(|(pos, spawner)| SavedSpawner {
+            pos: [pos.x, pos.y, pos.z],
+            mob: spawner.mob.to_owned(),
+        })
+        .collect();
+    let result = fs::create_dir_all(Path::new(SPAWNERS_FILE).parent().unwrap_or(Path::new(".")))
+        .and_then(|_| serde_json::to_string(&saved).map_err(std::io::Error::other))
+        .and_then(|json| fs::write(SPAWNERS_FILE, json));
+    if let Err(e) = result {
+        error!(target: STORAGE, "failed to save {SPAWNERS_FILE}: {e}");
+    }
+}
@@ -0,0 +1,111 @@
+// src/components/spectate.rs
+//
+// Staff spectating: `/spectate <player>` puts the executor in spectator mode
+// with their camera on the target, and `/spectate` on its own puts them back
+// where they were, in the game mode they had. The spectator's own position
+// follows the target around so the chunks and entities near it stay loaded
+// for them, including when the target changes dimension.
+
+use tracing::info;
+use valence::{
+    entity::{EntityId, EntityLayerId},
+    prelude::*,
+    protocol::{packets::play::SetCameraEntityS2c, VarInt, WritePacket},
+};
+
+use super::logging::AUDIT;
+use crate::world::send_to_layer;
+
+/// A spectator further than this from their target is moved back to it.
+const FOLLOW_DISTANCE: f64 = 16.0;
+
+/// On a player who's spectating someone, with what to restore afterwards.
+#[derive(Component)]
+pub struct Spectating {
+    pub target: Entity,
+    game_mode: GameMode,
+    position: DVec3,
+    layer: Entity,
+    /// Whether the client's camera is on the target yet. It can only be once
+    /// the client has the target loaded.
+    attached: bool,
+}
+
+impl Spectating {
+    pub fn new(target: Entity, game_mode: GameMode, position: DVec3, layer: Entity) -> Self {
+        Self { target, game_mode, position, layer, attached: false }
+    }
+
+    /// Switches to another target, keeping what to restore.
+    pub fn retarget(&mut self, target: Entity) {
+        self.target = target;
+        self.attached = false;
+    }
+}
+
+pub type SpectatorData = (
+    &'static mut Client,
+    &'static mut GameMode,
+    &'static mut Position,
+    &'static mut EntityLayerId,
+    &'static mut VisibleChunkLayer,
+    &'static mut VisibleEntityLayers,
+    &'static EntityId,
+);
+
+/// Puts a spectator back how they were before `/spectate`.
+pub fn stop_spectating(commands: &mut Commands, clients: &mut Query<SpectatorData>, spectator: Entity, spectating: &Spectating) {
+    let Ok((mut client, mut game_mode, mut position, mut layer_id, mut visible_chunk, mut visible_entities, entity_id)) =
+        clients.get_mut(spectator)
+    else {
+        return;
+    };
+    client.write_packet(&SetCameraEntityS2c { entity_id: VarInt(entity_id.get()) });
+    send_to_layer(
+        spectating.layer,
+        spectating.position,
+        &mut layer_id,
+        &mut visible_chunk,
+        &mut visible_entities,
+        &mut position,
+    );
+    *game_mode = spectating.game_mode;
+    commands.entity(spectator).remove::<Spectating>();
+}
+
+// Keeps spectators with their targets, and ends spectating when the target leaves
+pub fn update_spectators(
+    mut commands: Commands,
+    mut spectators: Query<(Entity, &mut Spectating, &Username)>,
+    mut clients: Query<SpectatorData>,
+) {
+    for (spectator, mut spectating, username) in &mut spectators {
+        let target = clients.get(spectating.target).map(|(_, _, pos, layer, _, _, id)| (pos.0, layer.0, id.get()));
+        let Ok((target_pos, target_layer, target_id)) = target else {
+            stop_spectating(&mut commands, &mut clients, spectator, &spectating);
+            if let Ok((mut client, ..)) = clients.get_mut(spectator) {
+                client.send_chat_message("[spectate] the player you were watching left".color(Color::GOLD));
+            }
+            info!(target: AUDIT, player = %username.0, "stopped spectating, the target left");
+            continue;
+        };
+        let Ok((mut client, _, mut position, mut layer_id, mut visible_chunk, mut visible_entities, _)) =
+            clients.get_mut(spectator)
+        else {
+            continue;
+        };
+
+        if layer_id.0 != target_layer {
+            send_to_layer(target_layer, target_pos, &mut layer_id, &mut visible_chunk, &mut visible_entities, &mut position);
+            spectating.attached = false;
+            continue;
+        }
+        if position.0.distance(target_pos) > FOLLOW_DISTANCE {
+            position.set(target_pos);
+        }
+        if !spectating.attached {
+            client.write_packet(&SetCameraEntityS2c { entity_id: VarInt(target_id) });
+            spectating.attached = true;
+        }
+    }
+}
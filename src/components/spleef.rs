@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use tracing::info;
+use valence::prelude::*;
+
+use super::attempts::{Attempts, BlockBreakAttempt};
+use super::core::new_crystal_message;
+use super::minigame::{
+    EndMinigameRequest, GameStage, GameStageChangeEvent, Minigames, PlayerJoinedGameEvent, PlayerLeftGameEvent,
+};
+use super::schematic::Schematic;
+use crate::world::Overworld;
+
+pub const SPLEEF_MODE: &str = "spleef";
+
+/// Per-arena spleef state.
+#[derive(Default)]
+pub struct SpleefState {
+    /// The arena as it was before the first game, restored after every game.
+    pub snapshot: Option<Schematic>,
+    pub alive: Vec<Entity>,
+    pub scores: HashMap<Entity, u32>,
+    /// Each player's game mode from before they joined, given back when they
+    /// leave or the game finishes.
+    previous_modes: HashMap<Entity, GameMode>,
+    started_with: usize,
+    finished: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct SpleefGames {
+    pub games: HashMap<String, SpleefState>,
+}
+
+fn is_spleef_block(state: BlockState) -> bool {
+    matches!(state.to_kind(), BlockKind::SnowBlock | BlockKind::Snow)
+}
+
+pub fn spleef_stage_changes(
+    mut events: EventReader<GameStageChangeEvent>,
+    minigames: Res<Minigames>,
+    mut spleef: ResMut<SpleefGames>,
+    mut layers: Query<&mut ChunkLayer, With<Overworld>>,
+    mut players: Query<&mut GameMode>,
+) {
+    let Ok(mut layer) = layers.get_single_mut() else {
+        return;
+    };
+
+    for event in events.read().filter(|e| e.mode == SPLEEF_MODE) {
+        let Some(game) = minigames.games.get(&event.arena) else {
+            continue;
+        };
+        let state = spleef.games.entry(event.arena.clone()).or_default();
+
+        match event.to {
+            GameStage::Countdown { .. } => {
+                if state.snapshot.is_none() {
+                    let schematic = Schematic::capture(
+                        &layer,
+                        game.arena.region_min(),
+                        game.arena.region_max(),
+                    );
+                    info!(
+                        "[spleef] saved {} blocks of arena {}",
+                        schematic.volume(),
+                        event.arena
+                    );
+                    state.snapshot = Some(schematic);
+                }
+            }
+            GameStage::InGame => {
+                state.alive = game.players.clone();
+                state.started_with = state.alive.len();
+                state.finished = false;
+                state.scores.clear();
+                for player in &state.alive {
+                    if let Ok(mut game_mode) = players.get_mut(*player) {
+                        *game_mode = GameMode::Survival;
+                    }
+                }
+            }
+            GameStage::Lobby => {
+                if let Some(snapshot) = &state.snapshot {
+                    let changed = snapshot.paste(&mut layer, game.arena.region_min());
+                    info!("[spleef] reset arena {} ({changed} blocks)", event.arena);
+                }
+                state.alive.clear();
+                // A finished game has sent everyone back to spawn
+                state.previous_modes.retain(|player, previous| {
+                    if game.players.contains(player) {
+                        return true;
+                    }
+                    if let Ok(mut game_mode) = players.get_mut(*player) {
+                        *game_mode = *previous;
+                    }
+                    false
+                });
+            }
+            GameStage::Ending { .. } => {}
+        }
+    }
+}
+
+// Remembers each player's game mode, since the game puts them in survival
+pub fn spleef_joins(
+    mut joined: EventReader<PlayerJoinedGameEvent>,
+    minigames: Res<Minigames>,
+    mut spleef: ResMut<SpleefGames>,
+    players: Query<&GameMode>,
+) {
+    for event in joined.read() {
+        if !minigames.games.get(&event.arena).is_some_and(|game| game.arena.mode == SPLEEF_MODE) {
+            continue;
+        }
+        if let Ok(game_mode) = players.get(event.player) {
+            let state = spleef.games.entry(event.arena.clone()).or_default();
+            state.previous_modes.insert(event.player, *game_mode);
+        }
+    }
+}
+
+// Spleef blocks break instantly for players still in, whatever the block
+// rules say, since the arena is the game's
+pub fn spleef_digging(
+    mut attempts: ResMut<Attempts<BlockBreakAttempt>>,
+    minigames: Res<Minigames>,
+    mut spleef: ResMut<SpleefGames>,
+    mut layers: Query<&mut ChunkLayer, With<Overworld>>,
+) {
+    let Ok(mut layer) = layers.get_single_mut() else {
+        return;
+    };
+
+    for attempt in attempts.all_mut() {
+        let event = attempt.event;
+        if event.state != DiggingState::Start {
+            continue;
+        }
+        let Some(arena) = minigames.arena_of(event.player) else {
+            continue;
+        };
+        let game = &minigames.games[arena];
+        if game.arena.mode != SPLEEF_MODE
+            || game.stage != GameStage::InGame
+            || !game.arena.contains(event.pos)
+        {
+            continue;
+        }
+        let Some(state) = spleef.games.get_mut(arena) else {
+            continue;
+        };
+        if !state.alive.contains(&event.player) {
+            continue;
+        }
+
+        // Snow breaks instantly in spleef regardless of tool.
+        if layer
+            .block(event.pos)
+            .is_some_and(|block| is_spleef_block(block.state))
+        {
+            layer.set_block(event.pos, BlockState::AIR);
+            *state.scores.entry(event.player).or_default() += 1;
+            attempt.take_over();
+        }
+    }
+}
+
+pub fn spleef_eliminations(
+    minigames: Res<Minigames>,
+    mut spleef: ResMut<SpleefGames>,
+    mut left: EventReader<PlayerLeftGameEvent>,
+    mut players: Query<(&mut Client, &mut Position, &Username)>,
+    mut game_modes: Query<&mut GameMode>,
+    mut end: EventWriter<EndMinigameRequest>,
+) {
+    for event in left.read() {
+        if let Some(state) = spleef.games.get_mut(&event.arena) {
+            state.alive.retain(|p| *p != event.player);
+            if let Some(previous) = state.previous_modes.remove(&event.player)
+                && let Ok(mut game_mode) = game_modes.get_mut(event.player)
+            {
+                *game_mode = previous;
+            }
+        }
+    }
+
+    for (name, state) in spleef.games.iter_mut() {
+        let Some(game) = minigames.games.get(name) else {
+            continue;
+        };
+        if game.stage != GameStage::InGame || state.finished {
+            continue;
+        }
+
+        // Anyone below the arena floor has fallen out.
+        let floor = game.arena.region_min().y as f64;
+        let fallen: Vec<(Entity, String)> = state
+            .alive
+            .iter()
+            .filter_map(|p| players.get(*p).ok().map(|(_, pos, username)| (*p, pos, username)))
+            .filter(|(_, pos, _)| pos.0.y < floor)
+            .map(|(p, _, username)| (p, username.0.clone()))
+            .collect();
+
+        for (player, username) in fallen {
+            state.alive.retain(|p| *p != player);
+            if let Ok((_, mut pos, _)) = players.get_mut(player) {
+                pos.set(game.arena.lobby_pos());
+            }
+            let message = format!(
+                "{username} fell! {} players left.",
+                state.alive.len()
+            )
+            .color(Color::RED);
+            for p in &game.players {
+                if let Ok((mut client, ..)) = players.get_mut(*p) {
+                    client.send_chat_message(new_crystal_message(message.clone()));
+                }
+            }
+        }
+
+        let decided = if state.started_with > 1 {
+            state.alive.len() <= 1
+        } else {
+            state.alive.is_empty()
+        };
+        if decided {
+            let mut scores: Vec<(String, u32)> = state
+                .scores
+                .iter()
+                .filter_map(|(p, score)| players.get(*p).ok().map(|(.., u)| (u.0.clone(), *score)))
+                .collect();
+            scores.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+            for p in &game.players {
+                if let Ok((mut client, ..)) = players.get_mut(*p) {
+                    client.send_chat_message("[spleef] blocks broken:".color(Color::GOLD));
+                    for (username, score) in &scores {
+                        client.send_chat_message(
+                            format!(" - {username}: ").color(Color::GOLD) + (*score).color(Color::RED),
+                        );
+                    }
+                }
+            }
+
+            end.send(EndMinigameRequest {
+                arena: name.clone(),
+                winners: state.alive.clone(),
+            });
+            state.finished = true;
+        }
+    }
+}
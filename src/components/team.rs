@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use valence::{
+    protocol::{
+        packets::play::team_s2c::{CollisionRule, Mode, NameTagVisibility, TeamColor, TeamFlags},
+        packets::play::TeamS2c,
+        WritePacket,
+    },
+    prelude::*,
+};
+
+use super::attempts::{Attempts, DamageAttempt};
+
+pub struct Team {
+    pub color: TeamColor,
+    pub friendly_fire: bool,
+    /// Usernames of the members, since that's what the team packets use.
+    pub members: Vec<String>,
+}
+
+impl Team {
+    pub fn text_color(&self) -> Color {
+        team_color_to_color(self.color)
+    }
+
+    fn flags(&self) -> TeamFlags {
+        TeamFlags::new()
+            .with_friendly_fire(self.friendly_fire)
+            .with_see_invisible_teammates(true)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Teams {
+    pub teams: HashMap<String, Team>,
+}
+
+impl Teams {
+    pub fn team_of(&self, username: &str) -> Option<&str> {
+        self.teams
+            .iter()
+            .find(|(_, team)| team.members.iter().any(|m| m == username))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Whether `attacker` is allowed to hurt `victim`.
+    pub fn can_damage(&self, attacker: &str, victim: &str) -> bool {
+        match (self.team_of(attacker), self.team_of(victim)) {
+            (Some(a), Some(b)) if a == b => self.teams[a].friendly_fire,
+            _ => true,
+        }
+    }
+}
+
+pub const COLOR_NAMES: [(&str, TeamColor); 16] = [
+    ("black", TeamColor::Black),
+    ("dark_blue", TeamColor::DarkBlue),
+    ("dark_green", TeamColor::DarkGreen),
+    ("dark_aqua", TeamColor::DarkCyan),
+    ("dark_red", TeamColor::DarkRed),
+    ("dark_purple", TeamColor::Purple),
+    ("gold", TeamColor::Gold),
+    ("gray", TeamColor::Gray),
+    ("dark_gray", TeamColor::DarkGray),
+    ("blue", TeamColor::Blue),
+    ("green", TeamColor::BrightGreen),
+    ("aqua", TeamColor::Cyan),
+    ("red", TeamColor::BrightRed),
+    ("light_purple", TeamColor::Pink),
+    ("yellow", TeamColor::Yellow),
+    ("white", TeamColor::White),
+];
+
+pub fn parse_team_color(name: &str) -> Option<TeamColor> {
+    COLOR_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, color)| *color)
+}
+
+fn team_color_to_color(color: TeamColor) -> Color {
+    match color {
+        TeamColor::Black => Color::BLACK,
+        TeamColor::DarkBlue => Color::DARK_BLUE,
+        TeamColor::DarkGreen => Color::DARK_GREEN,
+        TeamColor::DarkCyan => Color::DARK_AQUA,
+        TeamColor::DarkRed => Color::DARK_RED,
+        TeamColor::Purple => Color::DARK_PURPLE,
+        TeamColor::Gold => Color::GOLD,
+        TeamColor::Gray => Color::GRAY,
+        TeamColor::DarkGray => Color::DARK_GRAY,
+        TeamColor::Blue => Color::BLUE,
+        TeamColor::BrightGreen => Color::GREEN,
+        TeamColor::Cyan => Color::AQUA,
+        TeamColor::BrightRed => Color::RED,
+        TeamColor::Pink => Color::LIGHT_PURPLE,
+        TeamColor::Yellow => Color::YELLOW,
+        _ => Color::WHITE,
+    }
+}
+
+// --- Packets ---
+
+pub fn write_create_team(client: &mut Client, name: &str, team: &Team) {
+    client.write_packet(&TeamS2c {
+        team_name: name,
+        mode: Mode::CreateTeam {
+            team_display_name: Cow::Owned(name.to_owned().into_text()),
+            friendly_flags: team.flags(),
+            name_tag_visibility: NameTagVisibility::Always,
+            collision_rule: CollisionRule::Always,
+            team_color: team.color,
+            team_prefix: Cow::Owned(Text::default()),
+            team_suffix: Cow::Owned(Text::default()),
+            entities: team.members.iter().map(String::as_str).collect(),
+        },
+    });
+}
+
+pub fn write_update_team(client: &mut Client, name: &str, team: &Team) {
+    client.write_packet(&TeamS2c {
+        team_name: name,
+        mode: Mode::UpdateTeamInfo {
+            team_display_name: Cow::Owned(name.to_owned().into_text()),
+            friendly_flags: team.flags(),
+            name_tag_visibility: NameTagVisibility::Always,
+            collision_rule: CollisionRule::Always,
+            team_color: team.color,
+            team_prefix: Cow::Owned(Text::default()),
+            team_suffix: Cow::Owned(Text::default()),
+        },
+    });
+}
+
+pub fn write_remove_team(client: &mut Client, name: &str) {
+    client.write_packet(&TeamS2c {
+        team_name: name,
+        mode: Mode::RemoveTeam,
+    });
+}
+
+pub fn write_team_members(client: &mut Client, name: &str, members: &[&str], added: bool) {
+    let entities = members.to_vec();
+    client.write_packet(&TeamS2c {
+        team_name: name,
+        mode: if added {
+            Mode::AddEntities { entities }
+        } else {
+            Mode::RemoveEntities { entities }
+        },
+    });
+}
+
+// Sends every existing team to newly joined clients
+pub fn init_clients_teams(teams: Res<Teams>, mut clients: Query<&mut Client, Added<Client>>) {
+    for mut client in &mut clients {
+        for (name, team) in &teams.teams {
+            write_create_team(&mut client, name, team);
+        }
+    }
+}
+
+pub fn team_disconnects(
+    mut removed: RemovedComponents<Client>,
+    usernames: Query<&Username>,
+    mut teams: ResMut<Teams>,
+    mut clients: Query<&mut Client>,
+) {
+    // The username is already gone by the time the client is removed, so drop
+    // any members that no longer match an online player.
+    if removed.read().count() == 0 {
+        return;
+    }
+    let online: Vec<&str> = usernames.iter().map(|u| u.0.as_str()).collect();
+    for (name, team) in teams.teams.iter_mut() {
+        let gone: Vec<String> = team
+            .members
+            .iter()
+            .filter(|m| !online.contains(&m.as_str()))
+            .cloned()
+            .collect();
+        if gone.is_empty() {
+            continue;
+        }
+        team.members.retain(|m| !gone.contains(m));
+        let gone: Vec<&str> = gone.iter().map(String::as_str).collect();
+        for mut client in &mut clients {
+            write_team_members(&mut client, name, &gone, false);
+        }
+    }
+}
+
+// Stops teammates hurting each other unless their team has friendly fire on
+pub fn protect_teammates(mut attempts: ResMut<Attempts<DamageAttempt>>, usernames: Query<&Username>, teams: Res<Teams>) {
+    for attempt in attempts.pending_mut() {
+        let Some(attacker) = attempt.event.attacker else {
+            continue;
+        };
+        let (Ok(attacker), Ok(victim)) = (usernames.get(attacker), usernames.get(attempt.event.victim)) else {
+            continue;
+        };
+        if !teams.can_damage(&attacker.0, &victim.0) {
+            attempt.cancel("You can't hurt your teammates");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// src/components/time.rs
+//
+// The world clock and the day/night cycle. Time moves on by one every tick
+// and clients are told where it's at every second; in between they move the
+// sun along on their own. With the cycle off the time of day stays put, like
+// vanilla's doDaylightCycle, though the world keeps aging.
+//
+// `config/time.json`: `{ "daylight_cycle": true }`
+
+use serde::Deserialize;
+use valence::{
+    protocol::{packets::play::WorldTimeUpdateS2c, WritePacket},
+    prelude::*,
+};
+
+use super::config::load_config;
+
+pub const TICKS_PER_DAY: i64 = 24000;
+/// Vanilla resends the time every second so clients don't drift.
+const SYNC_TICKS: i64 = 20;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TimeConfig {
+    /// Whether the time of day moves on. Off keeps it wherever it starts.
+    pub daylight_cycle: bool,
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self { daylight_cycle: true }
+    }
+}
+
+/// The world clock. `world_age` only ever counts up, `time_of_day` is what
+/// the sun position is derived from.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct WorldTime {
+    pub world_age: i64,
+    pub time_of_day: i64,
+    /// The time of day stays put.
+    pub frozen: bool,
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self {
+            world_age: 0,
+            // Start at morning
+            time_of_day: 1000,
+            frozen: false,
+        }
+    }
+}
+
+impl WorldTime {
+    /// Time within the current day, 0..24000.
+    pub fn day_time(&self) -> i64 {
+        self.time_of_day.rem_euclid(TICKS_PER_DAY)
+    }
+
+    pub fn is_day(&self) -> bool {
+        // Same window vanilla uses for undead burning and bed use.
+        !(12542..23460).contains(&self.day_time())
+    }
+
+    /// Jumps forward to the start of the next day, like sleeping does.
+    pub fn skip_to_morning(&mut self) {
+        let skipped = TICKS_PER_DAY - self.day_time();
+        self.time_of_day += skipped;
+        self.world_age += skipped;
+    }
+
+    /// 0 is full moon, 4 is new moon.
+    pub fn moon_phase(&self) -> i64 {
+        (self.time_of_day / TICKS_PER_DAY).rem_euclid(8)
+    }
+
+    /// How bright the moon is, from 0.0 (new moon) to 1.0 (full moon).
+    pub fn moon_brightness(&self) -> f64 {
+        const BRIGHTNESS: [f64; 8] = [1.0, 0.75, 0.5, 0.25, 0.0, 0.25, 0.5, 0.75];
+        BRIGHTNESS[self.moon_phase() as usize]
+    }
+
+    /// The packet telling clients the time. A negative time of day tells
+    /// them not to move the sun themselves, which is how a frozen clock is
+    /// sent; 0 can't be negated, so that goes out as -1.
+    pub fn packet(&self) -> WorldTimeUpdateS2c {
+        let time_of_day = match self.frozen {
+            true if self.time_of_day == 0 => -1,
+            true => -self.time_of_day,
+            false => self.time_of_day,
+        };
+        WorldTimeUpdateS2c {
+            world_age: self.world_age,
+            time_of_day,
+        }
+    }
+}
+
+pub fn setup_time(mut commands: Commands) {
+    let config = load_config::<TimeConfig>("time.json");
+    commands.insert_resource(WorldTime {
+        frozen: !config.daylight_cycle,
+        ..default()
+    });
+}
+
+/// Moves the clock on a tick and keeps clients in step with it.
+pub fn advance_time(
+    mut time: ResMut<WorldTime>,
+    mut clients: Query<&mut Client>,
+    mut joined: Query<&mut Client, Added<Client>>,
+) {
+    time.world_age += 1;
+    if !time.frozen {
+        time.time_of_day += 1;
+    }
+
+    let packet = time.packet();
+    if time.world_age % SYNC_TICKS == 0 {
+        for mut client in &mut clients {
+            client.write_packet(&packet);
+        }
+    } else {
+        for mut client in &mut joined {
+            client.write_packet(&packet);
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use valence::{
+    entity::{villager::VillagerEntityBundle, Despawned, EntityLayerId},
+    event_loop::PacketEvent,
+    interact_entity::{EntityInteraction, InteractEntityEvent},
+    inventory::{ClickMode, ClickSlotEvent, ClientInventoryState},
+    protocol::{
+        packets::play::{set_trade_offers_s2c::TradeOffer, SelectMerchantTradeC2s, SetTradeOffersS2c},
+        VarInt, WritePacket,
+    },
+    prelude::*,
+};
+
+use super::config::load_config;
+use super::hud::HudMessage;
+use super::logging::STORAGE;
+use crate::world::Overworld;
+
+/// Traders in the overworld, with their progress, so they come back on startup.
+pub const TRADERS_FILE: &str = "world/traders.json";
+/// The merchant screen's output slot. Taking from it buys the selected trade.
+const RESULT_SLOT: u16 = 2;
+
+#[derive(Deserialize, Clone)]
+pub struct TradeItem {
+    pub item: String,
+    #[serde(default = "default_count")]
+    pub count: i8,
+}
+
+fn default_count() -> i8 {
+    1
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TradeDefinition {
+    pub buy: TradeItem,
+    #[serde(default)]
+    pub buy2: Option<TradeItem>,
+    pub sell: TradeItem,
+    /// Trader level needed before this trade shows up (1-5).
+    #[serde(default = "default_level")]
+    pub level: u32,
+    /// Trader experience gained per trade.
+    #[serde(default)]
+    pub xp: u32,
+    /// 0 means unlimited.
+    #[serde(default)]
+    pub max_uses: u32,
+}
+
+fn default_level() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TraderDefinition {
+    pub name: String,
+    pub trades: Vec<TradeDefinition>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct TradeConfig {
+    #[serde(default)]
+    pub traders: HashMap<String, TraderDefinition>,
+}
+
+/// A trade with its items resolved.
+#[derive(Clone)]
+pub struct Trade {
+    pub buy: ItemStack,
+    pub buy2: ItemStack,
+    pub sell: ItemStack,
+    pub level: u32,
+    pub xp: u32,
+    pub max_uses: u32,
+}
+
+pub struct TraderType {
+    pub name: String,
+    pub trades: Vec<Trade>,
+}
+
+#[derive(Resource, Default)]
+pub struct TraderTypes {
+    pub types: HashMap<String, TraderType>,
+}
+
+/// Experience needed to reach each trader level, like vanilla villagers.
+const LEVEL_XP: [u32; 5] = [0, 10, 70, 150, 250];
+
+/// An NPC that opens the merchant screen when interacted with.
+#[derive(Component)]
+pub struct Trader {
+    pub kind: String,
+    pub xp: u32,
+    /// How often each trade (by index) has been used.
+    pub uses: HashMap<usize, u32>,
+}
+
+impl Trader {
+    pub fn new(kind: String) -> Self {
+        Self {
+            kind,
+            xp: 0,
+            uses: HashMap::new(),
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        LEVEL_XP.iter().filter(|xp| self.xp >= **xp).count() as u32
+    }
+
+    /// Indices of the trades this trader offers at its current level.
+    fn unlocked_trades<'a>(&self, kind: &'a TraderType) -> Vec<(usize, &'a Trade)> {
+        kind.trades
+            .iter()
+            .enumerate()
+            .filter(|(_, trade)| trade.level <= self.level())
+            .collect()
+    }
+}
+
+/// Put on a client while they have a trader's screen open.
+#[derive(Component)]
+pub struct TradingWith {
+    pub trader: Entity,
+    /// The merchant screen's inventory.
+    inventory: Entity,
+    offers_sent: bool,
+    /// Index of the chosen trade among all of the trader's trades.
+    selected: Option<usize>,
+}
+
+/// A trader as written to disk.
+#[derive(Serialize, Deserialize)]
+struct SavedTrader {
+    kind: String,
+    pos: [f64; 3],
+    yaw: f32,
+    xp: u32,
+    #[serde(default)]
+    uses: HashMap<usize, u32>,
+}
+
+/// Spawns a villager that trades as `trader`.
+pub fn spawn_trader(commands: &mut Commands, layer: EntityLayerId, pos: DVec3, yaw: f32, trader: Trader) {
+    commands.spawn((
+        VillagerEntityBundle {
+            layer,
+            position: Position(pos),
+            look: Look::new(yaw, 0.0),
+            head_yaw: HeadYaw(yaw),
+            ..Default::default()
+        },
+        trader,
+    ));
+}
+
+fn parse_stack(item: &TradeItem) -> Option<ItemStack> {
+    let kind = ItemKind::from_str(item.item.trim_start_matches("minecraft:"))?;
+    Some(ItemStack::new(kind, item.count, None))
+}
+
+pub fn setup_traders(mut commands: Commands) {
+    let config: TradeConfig = load_config("trades.json");
+    let mut types = TraderTypes::default();
+    for (id, definition) in config.traders {
+        let trades: Option<Vec<Trade>> = definition
+            .trades
+            .iter()
+            .map(|t| {
+                Some(Trade {
+                    buy: parse_stack(&t.buy)?,
+                    buy2: match &t.buy2 {
+                        Some(item) => parse_stack(item)?,
+                        None => ItemStack::EMPTY,
+                    },
+                    sell: parse_stack(&t.sell)?,
+                    level: t.level,
+                    xp: t.xp,
+                    max_uses: t.max_uses,
+                })
+            })
+            .collect();
+        match trades {
+            Some(trades) => {
+                types.types.insert(
+                    id,
+                    TraderType {
+                        name: definition.name,
+                        trades,
+                    },
+                );
+            }
+            None => error!("[trading] trader {id} has an unknown item, skipping"),
+        }
+    }
+    info!("[trading] loaded {} trader types", types.types.len());
+    commands.insert_resource(types);
+}
+
+pub fn load_traders(mut commands: Commands, layers: Query<Entity, Added<Overworld>>) {
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    let Ok(contents) = fs::read_to_string(TRADERS_FILE) else {
+        return;
+    };
+    let saved: Vec<SavedTrader> = match serde_json::from_str(&contents) {
+        Ok(saved) => saved,
+        Err(e) => {
+            error!(target: STORAGE, "failed to parse {TRADERS_FILE}: {e}");
+            return;
+        }
+    };
+    info!(target: STORAGE, "loaded {} traders", saved.len());
+    for trader in saved {
+        let [x, y, z] = trader.pos;
+        let progress = Trader {
+            kind: trader.kind,
+            xp: trader.xp,
+            uses: trader.uses,
+        };
+        spawn_trader(&mut commands, EntityLayerId(layer), DVec3::new(x, y, z), trader.yaw, progress);
+    }
+}
+
+// Rewrites the traders file whenever a trader is added, traded with or removed
+pub fn save_traders(
+    changed: Query<(), Changed<Trader>>,
+    mut removed: RemovedComponents<Trader>,
+    traders: Query<(&Trader, &Position, &Look, &EntityLayerId), Without<Despawned>>,
+    overworld: Query<Entity, With<Overworld>>,
+) {
+    let removed = removed.read().count() > 0;
+    if changed.is_empty() && !removed {
+        return;
+    }
+    let Ok(overworld) = overworld.get_single() else {
+        return;
+    };
+    let saved: Vec<SavedTrader> = traders
+        .iter()
+        .filter(|(.., layer)| layer.0 == overworld)
+        .map(|(trader, pos, look, _)| SavedTrader {
+            kind: trader.kind.clone(),
+            pos: pos.0.to_array(),
+            yaw: look.yaw,
+            xp: trader.xp,
+            uses: trader.uses.clone(),
+        })
+        .collect();
+    let result = fs::create_dir_all(Path::new(TRADERS_FILE).parent().unwrap_or(Path::new(".")))
+        .and_then(|_| serde_json::to_string_pretty(&saved).map_err(std::io::Error::other))
+        .and_then(|json| fs::write(TRADERS_FILE, json));
+    if let Err(e) = result {
+        error!(target: STORAGE, "failed to save {TRADERS_FILE}: {e}");
+    }
+}
+
+// Opens the merchant screen when a player right-clicks a trader
+pub fn open_trader_menus(
+    mut commands: Commands,
+    mut events: EventReader<InteractEntityEvent>,
+    traders: Query<&Trader>,
+    types: Res<TraderTypes>,
+) {
+    for event in events.read() {
+        if !matches!(event.interact, EntityInteraction::Interact(Hand::Main)) {
+            continue;
+        }
+        let Ok(trader) = traders.get(event.entity) else {
+            continue;
+        };
+        let Some(kind) = types.types.get(&trader.kind) else {
+            continue;
+        };
+        let mut inventory = Inventory::with_title(InventoryKind::Merchant, kind.name.clone().color(Color::DARK_GREEN));
+        // Trades are paid for from the player's inventory, never these slots
+        inventory.readonly = true;
+        let inventory = commands.spawn(inventory).id();
+        commands.entity(event.client).insert((
+            OpenInventory::new(inventory),
+            TradingWith {
+                trader: event.entity,
+                inventory,
+                offers_sent: false,
+                selected: None,
+            },
+        ));
+    }
+}
+
+fn trade_offers(trader: &Trader, kind: &TraderType) -> Vec<TradeOffer> {
+    trader
+        .unlocked_trades(kind)
+        .into_iter()
+        .map(|(index, trade)| {
+            let uses = trader.uses.get(&index).copied().unwrap_or(0);
+            TradeOffer {
+                input_one: trade.buy.clone(),
+                output_item: trade.sell.clone(),
+                input_two: trade.buy2.clone(),
+                trade_disabled: trade.max_uses > 0 && uses >= trade.max_uses,
+                number_of_trade_uses: uses as i32,
+                max_trade_uses: if trade.max_uses == 0 { i32::MAX } else { trade.max_uses as i32 },
+                xp: trade.xp as i32,
+                special_price: 0,
+                price_multiplier: 0.0,
+                demand: 0,
+            }
+        })
+        .collect()
+}
+
+fn send_offers(client: &mut Client, window_id: u8, trader: &Trader, kind: &TraderType) {
+    client.write_packet(&SetTradeOffersS2c {
+        window_id: VarInt(window_id as i32),
+        trades: trade_offers(trader, kind),
+        villager_level: VarInt(trader.level() as i32),
+        experience: VarInt(trader.xp as i32),
+        is_regular_villager: true,
+        can_restock: false,
+    });
+}
+
+// The offers can only be sent once the client knows about the window
+pub fn send_trade_offers(
+    mut clients: Query<(&mut Client, &ClientInventoryState, &mut TradingWith), With<OpenInventory>>,
+    traders: Query<&Trader>,
+    types: Res<TraderTypes>,
+) {
+    for (mut client, inv_state, mut trading) in &mut clients {
+        if trading.offers_sent || inv_state.window_id() == 0 {
+            continue;
+        }
+        let Ok(trader) = traders.get(trading.trader) else {
+            continue;
+        };
+        let Some(kind) = types.types.get(&trader.kind) else {
+            continue;
+        };
+        send_offers(&mut client, inv_state.window_id(), trader, kind);
+        trading.offers_sent = true;
+    }
+}
+
+pub fn close_trader_menus(
+    mut commands: Commands,
+    clients: Query<(Entity, &TradingWith), Without<OpenInventory>>,
+) {
+    for (entity, trading) in &clients {
+        commands.entity(trading.inventory).despawn();
+        commands.entity(entity).remove::<TradingWith>();
+    }
+}
+
+/// What `trade` costs per item, so two inputs of the same item add up.
+fn trade_cost(trade: &Trade) -> HashMap<ItemKind, i32> {
+    let mut cost = HashMap::new();
+    for stack in [&trade.buy, &trade.buy2] {
+        if !stack.is_empty() {
+            *cost.entry(stack.item).or_default() += stack.count as i32;
+        }
+    }
+    cost
+}
+
+fn count_items(inventory: &Inventory, item: ItemKind) -> i32 {
+    (9..=44)
+        .map(|slot| inventory.slot(slot))
+        .filter(|stack| stack.item == item)
+        .map(|stack| stack.count as i32)
+        .sum()
+}
+
+fn remove_items(inventory: &mut Inventory, item: ItemKind, mut count: i32) {
+    for slot in 9..=44 {
+        if count == 0 {
+            break;
+        }
+        let stack = inventory.slot(slot);
+        if stack.item != item {
+            continue;
+        }
+        let taken = count.min(stack.count as i32);
+        let left = stack.count as i32 - taken;
+        if left == 0 {
+            inventory.set_slot(slot, ItemStack::EMPTY);
+        } else {
+            inventory.set_slot_amount(slot, left as i8);
+        }
+        count -= taken;
+    }
+}
+
+/// Puts a stack into the first empty main inventory slot, returning false if
+/// the inventory is full.
+fn give_item(inventory: &mut Inventory, stack: ItemStack) -> bool {
+    match (9..=44).find(|slot| inventory.slot(*slot).is_empty()) {
+        Some(slot) => {
+            inventory.set_slot(slot, stack);
+            true
+        }
+        None => false,
+    }
+}
+
+// Clicking a trade in the list only shows it in the merchant slots, like
+// vanilla. Nothing is bought until the result is taken.
+pub fn handle_trade_selection(
+    mut packets: EventReader<PacketEvent>,
+    mut clients: Query<&mut TradingWith>,
+    mut merchants: Query<&mut Inventory, Without<Client>>,
+    traders: Query<&Trader>,
+    types: Res<TraderTypes>,
+) {
+    for packet in packets.read() {
+        let Some(pkt) = packet.decode::<SelectMerchantTradeC2s>() else {
+            continue;
+        };
+        let Ok(mut trading) = clients.get_mut(packet.client) else {
+            continue;
+        };
+        let Ok(trader) = traders.get(trading.trader) else {
+            continue;
+        };
+        let Some(kind) = types.types.get(&trader.kind) else {
+            continue;
+        };
+        let Some((index, trade)) = trader.unlocked_trades(kind).get(pkt.selected_slot.0 as usize).copied() else {
+            continue;
+        };
+        trading.selected = Some(index);
+        if let Ok(mut merchant) = merchants.get_mut(trading.inventory) {
+            merchant.set_slot(0, trade.buy.clone());
+            merchant.set_slot(1, trade.buy2.clone());
+            merchant.set_slot(RESULT_SLOT, trade.sell.clone());
+        }
+    }
+}
+
+// Taking the result of the selected trade buys it straight from the player's
+// inventory, with everything checked on the server. Each click buys once.
+pub fn buy_selected_trades(
+    mut events: EventReader<ClickSlotEvent>,
+    mut clients: Query<(&mut Client, &mut Inventory, &ClientInventoryState, &TradingWith)>,
+    mut traders: Query<&mut Trader>,
+    types: Res<TraderTypes>,
+    mut hud_messages: EventWriter<HudMessage>,
+) {
+    for event in events.read() {
+        // Double clicks and drags come in as well as the clicks they start
+        // with, which would buy twice
+        if !matches!(event.mode, ClickMode::Click | ClickMode::ShiftClick)
+            || event.window_id == 0
+            || u16::try_from(event.slot_id) != Ok(RESULT_SLOT)
+        {
+            continue;
+        }
+        let Ok((mut client, mut inventory, inv_state, trading)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        let Some(index) = trading.selected else {
+            continue;
+        };
+        let Ok(mut trader) = traders.get_mut(trading.trader) else {
+            continue;
+        };
+        let Some(kind) = types.types.get(&trader.kind) else {
+            continue;
+        };
+        let Some(trade) = kind.trades.get(index) else {
+            continue;
+        };
+
+        let uses = trader.uses.get(&index).copied().unwrap_or(0);
+        if trade.max_uses > 0 && uses >= trade.max_uses {
+            hud_messages.send(HudMessage::warning(event.client, "This trade is sold out".color(Color::RED)));
+            continue;
+        }
+        let cost = trade_cost(trade);
+        if !cost.iter().all(|(item, count)| count_items(&inventory, *item) >= *count) {
+            hud_messages.send(HudMessage::warning(event.client, "You can't afford this trade".color(Color::RED)));
+            continue;
+        }
+        if !(9..=44).any(|slot| inventory.slot(slot).is_empty()) {
+            hud_messages.send(HudMessage::warning(event.client, "Your inventory is full".color(Color::RED)));
+            continue;
+        }
+
+        for (item, count) in cost {
+            remove_items(&mut inventory, item, count);
+        }
+        give_item(&mut inventory, trade.sell.clone());
+
+        let old_level = trader.level();
+        *trader.uses.entry(index).or_default() += 1;
+        trader.xp += trade.xp;
+        if trader.level() > old_level {
+            client.send_chat_message(
+                format!("[trade] {} reached level {}!", kind.name, trader.level()).color(Color::GREEN),
+            );
+        }
+        // Refresh uses and any newly unlocked trades.
+        send_offers(&mut client, inv_state.window_id(), &trader, kind);
+    }
+}
@@ -0,0 +1,70 @@
+// src/components/warps.rs
+//
+// Warps and homes: named places in the overworld to teleport back to. Warps
+// are shared and set by staff with `/setwarp`, homes are each player's own and
+// kept in their player data. Both can be picked from a chest menu as well as
+// by name, and the menu's buttons just run `/warp <name>` or `/home <name>`,
+// so they go through the same checks.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use tracing::{error, info};
+use valence::prelude::*;
+
+use super::logging::STORAGE;
+use super::menu::{icon, Menu, MenuAction, MenuButton};
+
+pub const WARPS_FILE: &str = "world/warps.json";
+/// How many homes each player can set.
+pub const MAX_HOMES: usize = 5;
+/// The home `/home` and `/sethome` use when no name is given.
+pub const DEFAULT_HOME: &str = "home";
+const MENU_ROWS: u8 = 6;
+
+/// Every warp by name, with the spot it goes to.
+#[derive(Resource, Default)]
+pub struct Warps(pub BTreeMap<String, [f64; 3]>);
+
+impl Warps {
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(WARPS_FILE) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&contents) {
+            Ok(warps) => Self(warps),
+            Err(e) => {
+                error!(target: STORAGE, "failed to parse {WARPS_FILE}: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        fs::create_dir_all(Path::new(WARPS_FILE).parent().unwrap_or(Path::new(".")))?;
+        let json = serde_json::to_string_pretty(&self.0).map_err(std::io::Error::other)?;
+        fs::write(WARPS_FILE, json)
+    }
+}
+
+pub fn setup_warps(mut commands: Commands) {
+    let warps = Warps::load();
+    info!(target: STORAGE, "loaded {} warps", warps.0.len());
+    commands.insert_resource(warps);
+}
+
+/// A menu of `places`, each teleporting with `/<command> <name>` when clicked.
+pub fn places_menu(title: &str, command: &str, item: ItemKind, places: &BTreeMap<String, [f64; 3]>) -> Menu {
+    let buttons = places
+        .iter()
+        .map(|(name, [x, y, z])| {
+            let lore = [
+                format!("{x:.0} {y:.0} {z:.0}").color(Color::GRAY),
+                "Click to teleport".color(Color::YELLOW),
+            ];
+            MenuButton::new(icon(item, name.clone().color(Color::GOLD), lore), MenuAction::Command(format!("{command} {name}")))
+        })
+        .collect();
+    Menu::paged(title.color(Color::DARK_PURPLE), MENU_ROWS, buttons, 0)
+}
@@ -0,0 +1,164 @@
+// src/components/weather.rs
+//
+// Overworld weather. It's clear, raining or thundering for a random while,
+// then changes: clear skies turn to rain, sometimes straight to a storm, and
+// rain and storms clear up again. Rain fades in and out over a few seconds
+// instead of switching on at once, like vanilla. Clients are told through
+// the overworld layer's `Rain` and `Thunder` levels. Where generated terrain
+// is cold enough for snow, chunks carry snowy biomes, so rain falls there as
+// snow.
+//
+// `config/weather.json`: `{ "weather_cycle": true }`. With the cycle off the
+// weather only changes with `/weather`.
+
+use std::fmt;
+use std::ops::Range;
+
+use serde::Deserialize;
+use valence::{
+    prelude::*,
+    rand::{thread_rng, Rng},
+    weather::{Rain, Thunder},
+};
+
+use super::config::load_config;
+use crate::world::Overworld;
+
+/// How long each kind of weather lasts, in ticks, same as vanilla.
+const CLEAR_TICKS: Range<i64> = 12_000..180_000;
+const RAIN_TICKS: Range<i64> = 12_000..24_000;
+const THUNDER_TICKS: Range<i64> = 3_600..15_600;
+/// One in this many spells of rain is a thunderstorm.
+const THUNDER_CHANCE: u32 = 3;
+/// How much the rain and thunder levels change each tick while fading.
+const FADE_PER_TICK: f32 = 0.01;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WeatherConfig {
+    /// Whether the weather changes on its own.
+    pub weather_cycle: bool,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self { weather_cycle: true }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Thunder,
+}
+
+impl WeatherKind {
+    /// A random length for a spell of this weather.
+    pub fn random_ticks(self) -> i64 {
+        let range = match self {
+            WeatherKind::Clear => CLEAR_TICKS,
+            WeatherKind::Rain => RAIN_TICKS,
+            WeatherKind::Thunder => THUNDER_TICKS,
+        };
+        thread_rng().gen_range(range)
+    }
+
+    /// The rain and thunder levels this weather fades towards.
+    fn levels(self) -> (f32, f32) {
+        match self {
+            WeatherKind::Clear => (0.0, 0.0),
+            WeatherKind::Rain => (1.0, 0.0),
+            WeatherKind::Thunder => (1.0, 1.0),
+        }
+    }
+}
+
+impl fmt::Display for WeatherKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WeatherKind::Clear => "clear",
+            WeatherKind::Rain => "rain",
+            WeatherKind::Thunder => "thunder",
+        })
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    /// Ticks until the weather changes.
+    pub remaining: i64,
+    /// The weather only changes through `set`.
+    pub frozen: bool,
+}
+
+impl Weather {
+    /// Switches to `kind` for `ticks`, or for a random while.
+    pub fn set(&mut self, kind: WeatherKind, ticks: Option<i64>) {
+        self.kind = kind;
+        self.remaining = ticks.unwrap_or_else(|| kind.random_ticks());
+    }
+
+    /// What comes after the current weather.
+    fn next(&self) -> WeatherKind {
+        match self.kind {
+            WeatherKind::Clear if thread_rng().gen_ratio(1, THUNDER_CHANCE) => WeatherKind::Thunder,
+            WeatherKind::Clear => WeatherKind::Rain,
+            WeatherKind::Rain | WeatherKind::Thunder => WeatherKind::Clear,
+        }
+    }
+}
+
+pub fn setup_weather(mut commands: Commands) {
+    let config = load_config::<WeatherConfig>("weather.json");
+    commands.insert_resource(Weather {
+        kind: WeatherKind::Clear,
+        remaining: WeatherKind::Clear.random_ticks(),
+        frozen: !config.weather_cycle,
+    });
+}
+
+fn fade(level: f32, target: f32) -> f32 {
+    if level < target {
+        (level + FADE_PER_TICK).min(target)
+    } else {
+        (level - FADE_PER_TICK).max(target)
+    }
+}
+
+// Moves the weather on and fades the overworld's rain and thunder towards it
+pub fn update_weather(
+    mut commands: Commands,
+    mut weather: ResMut<Weather>,
+    mut layers: Query<(Entity, Option<&mut Rain>, Option<&mut Thunder>), With<Overworld>>,
+) {
+    if !weather.frozen {
+        weather.remaining -= 1;
+        if weather.remaining <= 0 {
+            let next = weather.next();
+            weather.set(next, None);
+        }
+    }
+
+    let Ok((layer, rain, thunder)) = layers.get_single_mut() else {
+        return;
+    };
+    let (rain_target, thunder_target) = weather.kind.levels();
+    // Only touched when they move, so clients aren't sent the same level
+    // every tick
+    match rain {
+        Some(mut rain) if rain.0 != rain_target => rain.0 = fade(rain.0, rain_target),
+        Some(_) => {}
+        None => {
+            commands.entity(layer).insert(Rain(fade(0.0, rain_target)));
+        }
+    }
+    match thunder {
+        Some(mut thunder) if thunder.0 != thunder_target => thunder.0 = fade(thunder.0, thunder_target),
+        Some(_) => {}
+        None => {
+            commands.entity(layer).insert(Thunder(fade(0.0, thunder_target)));
+        }
+    }
+}
@@ -13,17 +13,117 @@ mod components;
 mod world;
 
 use commands::{
+    biome::{BiomeCommand, LocateBiomeCommand, handle_biome_command, handle_locatebiome_command},
+    confirm::{ConfirmCommand, handle_confirm_command},
     core::{VersionCommand, handle_version_command},
+    debug::{DebugCommand, handle_debug_command},
     gamemode::{GamemodeCommand, handle_gamemode_command},
+    hud::{HudCommand, handle_hud_command},
+    kit::{KitCommand, handle_kit_command},
+    locate::{LocateCommand, handle_locate_command},
+    locateblock::{LocateBlockCommand, handle_locateblock_command},
+    logs::{LogsCommand, handle_logs_command},
+    mem::{MemCommand, handle_mem_command},
+    nether::{NetherCommand, handle_nether_command},
+    minigame::{MinigameCommand, handle_minigame_command},
     op::{OpCommand, handle_op_command},
+    party::{PartyCommand, handle_party_command},
+    playtime::{PlaytimeCommand, handle_playtime_command},
+    position::{JumpToCommand, PosCommand, TopCommand, handle_jumpto_command, handle_pos_command, handle_top_command},
+    replay::{ReplayCommand, handle_replay_command},
+    save_all::{SaveAllCommand, handle_save_all_command},
+    seed::{SeedCommand, handle_seed_command},
+    settings::{SettingsCommand, handle_settings_command},
+    setspawner::{SetSpawnerCommand, handle_setspawner_command},
+    setworldspawn::{SetWorldSpawnCommand, handle_setworldspawn_command},
+    spectate::{SpectateCommand, handle_spectate_command},
+    stop::{StopCommand, handle_stop_command},
+    structure::{StructureCommand, handle_structure_command},
+    sudo::{SudoCommand, handle_sudo_command},
+    team::{TeamCommand, handle_team_command},
+    trader::{TraderCommand, handle_trader_command},
     teleport::{TeleportCommand, handle_teleport_command},
+    time::{TimeCommand, handle_time_command},
+    weather::{WeatherCommand, handle_weather_command},
+    warp::{HomeCommand, SetWarpCommand, WarpCommand, handle_home_command, handle_setwarp_command, handle_warp_command},
+    world::{WorldCommand, handle_world_command},
+    worldgen::{WorldGenCommand, handle_worldgen_command},
 };
 use components::{
-    building::{digging, place_blocks}, chat::chat_message_event, console::{handle_console_command, ConsoleCommandEvent, ConsoleCommandReceiver}, core::ServerVersion
+    attempts::{
+        clear_attempts, collect_block_attempts, collect_chat_attempts, report_cancelled_attempts, AttemptStage, Attempts,
+        BlockBreakAttempt, BlockPlaceAttempt, ChatAttempt, DamageAttempt,
+    },
+    beacon::{
+        apply_beacon_effects, close_beacon_screens, handle_beacon_updates, open_beacons, restore_beacons, sync_beacon_screens,
+        Beacons,
+    },
+    block_rules::setup_block_rules,
+    boss::{damage_bosses, spawn_withers, tick_boss_projectiles, tick_bosses, update_bosses, BossDefeatedEvent},
+    building::{digging, place_blocks, protect_blocks}, chat::{announce_joins, announce_leaves, chat_message_event},
+    combat::{attack_mobs, attack_players, collect_attacks, fall_damage, init_fall_trackers, take_fall_damage},
+    client_settings::handle_client_settings, console::{handle_console_command, ConsoleCommandEvent, ConsoleCommandReceiver}, core::ServerVersion,
+    confirm::Confirmations,
+    consumables::{finish_eating, land_pearls, use_consumables},
+    command_block::{restore_command_blocks, run_command_blocks, setup_command_blocks, update_command_blocks},
+    container::{
+        open_containers, register_placed_containers, remove_broken_containers, restore_containers, store_container_items,
+        Containers,
+    },
+    cooldown::{enforce_item_cooldowns, init_clients_cooldowns, setup_item_cooldowns, ItemUseEvent},
+    creative::{filter_creative_items, setup_creative_rules},
+    decoration::{
+        drop_unsupported_decorations, interact_decorations, load_decorations, place_decorations, save_decorations,
+        use_paintings, DecorationsDirty,
+    },
+    dispenser::{trigger_dispensers, PoweredDispensers},
+    end::{
+        activate_end_portals, destroy_end_crystals, finish_dragon_fight, heal_dragon, move_dragon, setup_end,
+        start_dragon_fight, use_end_portals, DragonFight,
+    },
+    entity_rules::{despawn_expired_entities, entity_cramming, setup_entity_rules, track_entity_age},
+    experience::{
+        attract_experience_orbs, drop_boss_experience, merge_experience_orbs, pickup_experience_orbs,
+        sync_experience_bar,
+    },
+    ghost::{update_ghost_blocks, GhostBlocks},
+    hopper::{hopper_pickup_items, tick_hoppers, HopperScheduler},
+    hud::{init_clients_hud, receive_hud_messages, render_huds, update_builtin_hud_elements, HudElements, HudMessage},
+    minigame::{
+        handle_minigame_ends, handle_minigame_joins, handle_minigame_leaves, setup_minigames, tick_minigames,
+        EndMinigameRequest, GameStageChangeEvent, JoinMinigameRequest, LeaveMinigameRequest, PlayerJoinedGameEvent,
+        PlayerLeftGameEvent,
+    },
+    mob_behavior::{burn_undead_in_sunlight, init_mob_aggression, update_spider_aggression},
+    party::{party_disconnects, tick_party_invites, update_party_display_names, Parties},
+    logging::{log_plugin, CONSOLE, NET},
+    loot::setup_loot_tables,
+    memory::{log_memory_usage, setup_memory_reports},
+    menu::{click_menus, close_menus},
+    metrics::{record_metrics, setup_metrics},
+    permissions::{init_clients_permissions, registered_command_scopes, setup_permission_groups},
+    playerdata::{init_clients_player_data, save_changed_player_data},
+    playtime::{end_sessions, start_sessions},
+    protocol::CrystalCallbacks,
+    recipe::{init_clients_recipes, setup_recipes, unlock_recipes},
+    replay::{play_replays, record_block_changes, record_movement, update_recordings, Recorder, Replays},
+    redstone::{release_buttons, toggle_redstone_inputs, PressedButtons},
+    sleep::{announce_sleepers, enter_beds, leave_beds, setup_sleep, skip_night},
+    spawner::{register_placed_spawners, save_spawners, setup_spawners, tick_spawners},
+    spectate::update_spectators,
+    spleef::{spleef_digging, spleef_eliminations, spleef_joins, spleef_stage_changes, SpleefGames},
+    team::{init_clients_teams, protect_teammates, team_disconnects, Teams},
+    time::{advance_time, setup_time},
+    trading::{
+        buy_selected_trades, close_trader_menus, handle_trade_selection, load_traders, open_trader_menus, save_traders,
+        send_trade_offers, setup_traders,
+    },
+    warps::setup_warps,
+    weather::{setup_weather, update_weather},
 };
 use crossbeam_channel::{Sender, unbounded}; use tracing::{error, info};
 use valence::{
-    command::{AddCommand, CommandScopeRegistry}, prelude::*, rand::seq::SliceRandom
+    command::{AddCommand, CommandRegistry, CommandScopeRegistry}, network::NetworkSettings, prelude::*, rand::seq::SliceRandom
 };
 
 // Constants
@@ -70,57 +170,393 @@ fn main() {
     let (tx, rx) = unbounded();
     start_console_input_thread(tx);
 
+    // With the version gate on, valence listens behind it
+    let callbacks = CrystalCallbacks::load();
+    let address = callbacks.start_gate();
+
     App::new()
-        .add_plugins(DefaultPlugins)
+        .insert_resource(NetworkSettings {
+            address,
+            callbacks: callbacks.into(),
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins.set(log_plugin()))
         // -- Startup Systems --
         .add_systems(
             Startup,
             (
                 core_server_setup,
                 world::setup_world,
-                setup_core_commands,
+                setup_end,
+                world::nether::setup_nether.after(world::setup_world),
+                world::worlds::setup_worlds.after(world::setup_world),
+                setup_minigames,
+                setup_item_cooldowns,
+                setup_recipes,
+                setup_creative_rules,
+                setup_command_blocks,
+                setup_entity_rules,
+                setup_traders,
+                // Bevy takes at most 20 systems per tuple
+                (setup_time, setup_weather, setup_sleep),
+                setup_warps,
+                setup_block_rules,
+                setup_loot_tables,
+                setup_spawners,
+                setup_metrics,
+                setup_memory_reports,
             ),
         )
+        .add_systems(PostStartup, setup_core_commands)
         // -- Update Systems --
+        .configure_sets(
+            Update,
+            (
+                AttemptStage::Collect,
+                AttemptStage::Protect,
+                AttemptStage::Validate,
+                AttemptStage::Modify,
+                AttemptStage::Apply,
+                AttemptStage::Monitor,
+            )
+                .chain(),
+        )
         .add_systems(
             Update,
             (
                 // World systems
                 (
                     world::init_clients_world,
+                    world::respawn_players,
+                    world::prefetch::prefetch_arrivals,
                     world::update_client_views,
                     world::send_recv_chunks,
+                    world::worlds::update_world_views,
+                    world::worlds::send_recv_world_chunks,
+                    world::anvil::generate_missing_anvil_chunks,
+                    world::teleport::queue_safe_teleports,
+                    world::teleport::resolve_safe_teleports,
+                    world::deferred::apply_pending_blocks,
+                    world::structures::spawn_structure_contents,
+                    world::locate::finish_searches,
                     // "remove unviewed chunks" is run later.
                 )
                     .chain(),
                 // Core systems
                 despawn_disconnected_clients,
                 leave_handler,
-                chat_message_event,
-                digging,
-                place_blocks,
+                handle_client_settings,
+                // Attempt systems
+                (
+                    (collect_block_attempts, collect_chat_attempts).in_set(AttemptStage::Collect),
+                    protect_blocks.in_set(AttemptStage::Protect),
+                    report_cancelled_attempts.in_set(AttemptStage::Monitor),
+                ),
+                chat_message_event.in_set(AttemptStage::Apply),
+                (announce_joins, announce_leaves),
+                digging.in_set(AttemptStage::Apply),
+                place_blocks.in_set(AttemptStage::Apply),
+                world::events::broadcast_world_events.after(digging).after(place_blocks),
+                world::border::enforce_world_border,
+                update_ghost_blocks,
                 // Console systems
                 poll_console_commands,
                 handle_console_command, // Ensure this is defined in components/console.rs
                 // Command handlers (from commands module)
-                handle_version_command,
-                handle_teleport_command,
-                handle_gamemode_command,
-                handle_op_command,
+                (
+                    handle_version_command,
+                    handle_teleport_command,
+                    handle_gamemode_command,
+                    handle_op_command,
+                    handle_minigame_command,
+                    handle_party_command,
+                    handle_team_command,
+                    handle_kit_command,
+                    handle_trader_command,
+                    handle_locateblock_command,
+                    handle_top_command,
+                    handle_jumpto_command,
+                    handle_pos_command,
+                    handle_logs_command,
+                    handle_playtime_command,
+                    handle_hud_command,
+                    // Bevy takes at most 20 systems per tuple
+                    (
+                        handle_structure_command,
+                        handle_setspawner_command,
+                        handle_mem_command,
+                        handle_sudo_command,
+                        handle_nether_command,
+                        handle_confirm_command,
+                        handle_stop_command,
+                        handle_world_command,
+                        handle_spectate_command,
+                        handle_setworldspawn_command,
+                        handle_replay_command,
+                        handle_debug_command,
+                        handle_worldgen_command,
+                        handle_biome_command,
+                        handle_locatebiome_command,
+                        handle_locate_command,
+                        handle_time_command,
+                        handle_warp_command,
+                        handle_setwarp_command,
+                        handle_home_command,
+                    ),
+                    (
+                        handle_weather_command,
+                        handle_seed_command,
+                        handle_settings_command,
+                        handle_save_all_command,
+                    ),
+                ),
+                // Player data systems
+                (
+                    init_clients_player_data,
+                    init_clients_permissions,
+                    init_clients_recipes,
+                    unlock_recipes,
+                    start_sessions,
+                    end_sessions,
+                    save_changed_player_data,
+                )
+                    .chain(),
+                // Replay systems
+                (update_recordings, record_movement, record_block_changes, play_replays).chain(),
+                // HUD systems
+                (init_clients_hud, update_builtin_hud_elements, receive_hud_messages, render_huds).chain(),
+            ),
+        )
+        // -- Gameplay Systems --
+        .add_systems(
+            Update,
+            (
+                // Party systems
+                (party_disconnects, tick_party_invites, update_party_display_names),
+                // Team systems
+                (init_clients_teams, team_disconnects, protect_teammates.in_set(AttemptStage::Protect)),
+                // Item systems
+                (
+                    init_clients_cooldowns,
+                    (enforce_item_cooldowns, use_consumables).chain(),
+                    filter_creative_items,
+                    land_pearls.after(world::physics::simulate_physics),
+                    finish_eating,
+                ),
+                (restore_command_blocks, update_command_blocks, run_command_blocks).chain(),
+                // Entity systems
+                (world::physics::simulate_physics, track_entity_age, despawn_expired_entities, entity_cramming).chain(),
+                (init_mob_aggression, update_spider_aggression, burn_undead_in_sunlight),
+                // Combat systems
+                (
+                    init_fall_trackers,
+                    (collect_attacks, fall_damage).in_set(AttemptStage::Collect),
+                    (attack_mobs.before(world::physics::simulate_physics), attack_players, take_fall_damage)
+                        .in_set(AttemptStage::Apply),
+                ),
+                // Experience systems
+                (
+                    attract_experience_orbs.before(world::physics::simulate_physics),
+                    merge_experience_orbs,
+                    pickup_experience_orbs,
+                    drop_boss_experience.after(update_bosses),
+                    sync_experience_bar.after(pickup_experience_orbs),
+                ),
+                // Decoration systems
+                (
+                    load_decorations,
+                    place_decorations,
+                    interact_decorations,
+                    use_paintings,
+                    drop_unsupported_decorations.after(digging),
+                    save_decorations,
+                )
+                    .chain(),
+                // Menu and trading systems
+                (
+                    (click_menus, close_menus).chain(),
+                    (
+                        load_traders,
+                        open_trader_menus,
+                        send_trade_offers,
+                        handle_trade_selection,
+                        buy_selected_trades,
+                        close_trader_menus,
+                        save_traders,
+                    )
+                        .chain(),
+                ),
+                // Container systems
+                (
+                    restore_containers,
+                    open_containers,
+                    register_placed_containers.after(place_blocks),
+                    remove_broken_containers,
+                    tick_hoppers,
+                    hopper_pickup_items,
+                ),
+                // Spawner systems
+                (register_placed_spawners.after(place_blocks), tick_spawners, save_spawners).chain(),
+                // Time, weather and sleep systems
+                (advance_time, update_weather, enter_beds, leave_beds, announce_sleepers, skip_night).chain(),
+                // Redstone systems
+                (toggle_redstone_inputs, release_buttons, trigger_dispensers).chain(),
+                // Beacon systems
+                (
+                    restore_beacons,
+                    open_beacons.after(place_blocks),
+                    sync_beacon_screens,
+                    handle_beacon_updates,
+                    close_beacon_screens,
+                    apply_beacon_effects,
+                )
+                    .chain(),
+                // Boss systems
+                (
+                    spawn_withers.after(place_blocks),
+                    damage_bosses,
+                    tick_bosses,
+                    tick_boss_projectiles,
+                    update_bosses,
+                )
+                    .chain(),
+                // End systems
+                (
+                    activate_end_portals.after(place_blocks),
+                    use_end_portals,
+                    start_dragon_fight,
+                    move_dragon,
+                    heal_dragon,
+                    destroy_end_crystals,
+                    finish_dragon_fight.after(update_bosses),
+                )
+                    .chain(),
+                // Nether systems
+                world::nether::update_nether_chunks,
+                // Spectator systems
+                update_spectators,
+                // Metrics systems
+                (record_metrics, log_memory_usage),
+            ),
+        )
+        // -- Minigame Systems --
+        .add_systems(
+            Update,
+            (
+                (
+                    handle_minigame_joins,
+                    handle_minigame_leaves,
+                    handle_minigame_ends,
+                    tick_minigames,
+                )
+                    .chain(),
+                (
+                    spleef_joins,
+                    spleef_stage_changes,
+                    spleef_digging.in_set(AttemptStage::Modify),
+                    spleef_eliminations,
+                )
+                    .chain()
+                    .after(tick_minigames),
             ),
         )
         // Must be run in `Last` because viewer_count needs to update first.
-        .add_systems(Last, world::remove_unviewed_chunks)
+        .add_systems(
+            Last,
+            (
+                store_container_items,
+                world::storage::track_edited_chunks,
+                world::storage::autosave_chunks,
+                world::remove_unviewed_chunks,
+                world::worlds::remove_unviewed_world_chunks,
+            )
+                .chain(),
+        )
+        .add_systems(First, (world::throttle::start_tick_timer, clear_attempts))
+        .add_systems(Last, world::throttle::update_chunk_throttle.after(world::remove_unviewed_chunks))
         // -- Resources --
         .insert_resource(ConsoleCommandReceiver { receiver: rx })
         .insert_resource(ServerVersion(VERSION.into()))
+        .init_resource::<Attempts<BlockBreakAttempt>>()
+        .init_resource::<Attempts<BlockPlaceAttempt>>()
+        .init_resource::<Attempts<ChatAttempt>>()
+        .init_resource::<Attempts<DamageAttempt>>()
+        .init_resource::<SpleefGames>()
+        .init_resource::<Parties>()
+        .init_resource::<Teams>()
+        .init_resource::<world::ChunkTickets>()
+        .init_resource::<world::ChunkTimings>()
+        .init_resource::<world::teleport::PendingTeleports>()
+        .init_resource::<world::deferred::PendingBlocks>()
+        .init_resource::<world::locate::LocateSearches>()
+        .init_resource::<Containers>()
+        .init_resource::<HopperScheduler>()
+        .init_resource::<PressedButtons>()
+        .init_resource::<PoweredDispensers>()
+        .init_resource::<Beacons>()
+        .init_resource::<DragonFight>()
+        .init_resource::<HudElements>()
+        .init_resource::<DecorationsDirty>()
+        .init_resource::<Confirmations>()
+        .init_resource::<GhostBlocks>()
+        .init_resource::<Replays>()
+        .insert_resource(Recorder::load())
         // -- Events --
         .add_event::<ConsoleCommandEvent>()
+        .add_event::<JoinMinigameRequest>()
+        .add_event::<LeaveMinigameRequest>()
+        .add_event::<EndMinigameRequest>()
+        .add_event::<GameStageChangeEvent>()
+        .add_event::<PlayerJoinedGameEvent>()
+        .add_event::<PlayerLeftGameEvent>()
+        .add_event::<ItemUseEvent>()
+        .add_event::<BossDefeatedEvent>()
+        .add_event::<world::teleport::SafeTeleportRequest>()
+        .add_event::<world::events::WorldEvent>()
+        .add_event::<HudMessage>()
+        .add_event::<world::structures::StructurePlaced>()
+        .add_event::<world::storage::ChunkRestored>()
         // -- Commands --
         .add_command::<VersionCommand>()
         .add_command::<GamemodeCommand>()
         .add_command::<TeleportCommand>()
         .add_command::<OpCommand>()
+        .add_command::<MinigameCommand>()
+        .add_command::<PartyCommand>()
+        .add_command::<TeamCommand>()
+        .add_command::<KitCommand>()
+        .add_command::<TraderCommand>()
+        .add_command::<LocateBlockCommand>()
+        .add_command::<TopCommand>()
+        .add_command::<JumpToCommand>()
+        .add_command::<PosCommand>()
+        .add_command::<LogsCommand>()
+        .add_command::<PlaytimeCommand>()
+        .add_command::<HudCommand>()
+        .add_command::<StructureCommand>()
+        .add_command::<SetSpawnerCommand>()
+        .add_command::<MemCommand>()
+        .add_command::<SudoCommand>()
+        .add_command::<NetherCommand>()
+        .add_command::<ConfirmCommand>()
+        .add_command::<StopCommand>()
+        .add_command::<WorldCommand>()
+        .add_command::<SpectateCommand>()
+        .add_command::<SetWorldSpawnCommand>()
+        .add_command::<ReplayCommand>()
+        .add_command::<DebugCommand>()
+        .add_command::<WorldGenCommand>()
+        .add_command::<BiomeCommand>()
+        .add_command::<LocateBiomeCommand>()
+        .add_command::<LocateCommand>()
+        .add_command::<TimeCommand>()
+        .add_command::<WarpCommand>()
+        .add_command::<SetWarpCommand>()
+        .add_command::<HomeCommand>()
+        .add_command::<WeatherCommand>()
+        .add_command::<SeedCommand>()
+        .add_command::<SettingsCommand>()
+        .add_command::<SaveAllCommand>()
         .run();
 }
 
@@ -130,19 +566,27 @@ fn core_server_setup() {
     info!("Hello! Running {}.", VERSION);
 }
 
-fn setup_core_commands(mut command_scopes: ResMut<CommandScopeRegistry>) {
+// Runs after startup, once every command is in the registry with its scopes
+fn setup_core_commands(
+    mut commands: Commands,
+    registry: Res<CommandRegistry>,
+    mut command_scopes: ResMut<CommandScopeRegistry>,
+) {
+    let scopes = registered_command_scopes(&registry);
+    let scopes: Vec<&str> = scopes.iter().map(String::as_str).collect();
     // --- Admin commands ---
-    command_scopes.link("crystal.admin", "crystal.command.version");
-    command_scopes.link("crystal.admin", "crystal.command.gamemode");
-    command_scopes.link("crystal.admin", "crystal.command.teleport");
-    command_scopes.link("crystal.admin", "crystal.command.op");
+    for scope in &scopes {
+        command_scopes.link("crystal.admin", scope);
+    }
+    // --- Permission groups ---
+    setup_permission_groups(&mut commands, &mut command_scopes, &scopes);
     // NOTE: Normal commands TBA
 }
 
 fn leave_handler(mut removed_clients: RemovedComponents<Client>) {
     // TODO: store player name before getting removed
     for entity in removed_clients.read() {
-        info!("Client entity {:?} left the game :(", entity);
+        info!(target: NET, "Client entity {:?} left the game :(", entity);
     }
 }
 
@@ -153,11 +597,11 @@ fn start_console_input_thread(sender: Sender<String>) {
         for line in io::BufRead::lines(stdin.lock()) {
             if let Ok(line) = line {
                 if sender.send(line).is_err() {
-                    error!("[console_thread] Main thread channel closed, exiting.");
+                    error!(target: CONSOLE, "Main thread channel closed, exiting.");
                     break;
                 }
             } else {
-                error!("[console_thread] Error reading line from stdin.");
+                error!(target: CONSOLE, "Error reading line from stdin.");
                 break;
             }
         }
@@ -1,108 +1,754 @@
 // src/world.rs
 
-use std::collections::HashMap;
+use std::array;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
-use std::sync::Arc;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread;
-use std::time::SystemTime;
+use std::time::{Duration, Instant};
 
 use flume::{Receiver, Sender};
 use noise::{NoiseFn, SuperSimplex};
-use tracing::info;
+use serde::Deserialize;
+use tracing::{debug_span, info, warn};
 use valence::command::scopes::CommandScopes;
 use valence::message::SendMessage;
 use valence::op_level::OpLevel;
 // Needed for init_clients_world messages
 use valence::prelude::*;
+use valence::anvil::AnvilLevel;
+use valence::entity::living::Health;
 use valence::spawn::IsFlat;
-
+use valence::status::RequestRespawnEvent;
+
+use crate::components::config::load_config;
+use crate::components::logging::{NET, WORLDGEN};
+
+pub mod anvil;
+pub mod batch;
+pub mod biomes;
+pub mod border;
+pub mod deferred;
+pub mod events;
+pub mod features;
+pub mod flat;
+pub mod locate;
+pub mod nether;
+pub mod ores;
+pub mod physics;
+pub mod prefetch;
+pub mod raycast;
+pub mod regression;
+pub mod remap;
+pub mod storage;
+pub mod structures;
+pub mod surface;
+pub mod teleport;
+pub mod throttle;
+pub mod trees;
+pub mod villages;
+pub mod worlds;
+
+use border::{BorderConfig, WorldBorder};
+use features::PlacedFeature;
+use flat::SuperflatPreset;
+use biomes::{Biome, Climate};
+use ores::OreConfig;
+use structures::{region_hash, PlacedStructure, StructureConfig, StructurePlaced, StructurePool};
+use throttle::{ChunkThrottle, ThrottleConfig};
+use surface::{SurfaceContext, SurfaceRule};
+use villages::VillageConfig;
 use crate::components::core::set_op_status; // Import for OP status
 
 // --- Constants ---
 pub const SPAWN_POS: DVec3 = DVec3::new(0.5, 200.0, 0.5); // Centered in block, high up
-const HEIGHT: u32 = 192; // World height
-const SEA_LEVEL: f64 = 47.0;
+/// The overworld's build limits when the config doesn't set them.
+const DEFAULT_MIN_Y: i32 = -64;
+const DEFAULT_HEIGHT: u32 = 384;
+/// How far from y 0 the client accepts build limits, and the most blocks
+/// between them.
+const MAX_BUILD_Y: i32 = 2032;
+const MAX_HEIGHT: u32 = 4064;
+/// How far and how finely `/locatebiome` searches.
+const BIOME_SEARCH_RADIUS: i32 = 6400;
+const BIOME_SEARCH_STEP: i32 = 32;
+/// How many regions out `/locate structure` searches.
+const STRUCTURE_SEARCH_REGIONS: i32 = 16;
+/// Sand beaches reach this far below sea level, and up to this far above it
+/// where the gravel noise is highest.
+const BEACH_DEPTH: i32 = 3;
+const BEACH_HEIGHT: f64 = 3.0;
+/// Bedrock fills the bottom layer and thins out over the ones above it.
+pub const BEDROCK_LAYERS: i32 = 5;
+const BEDROCK_SALT: u64 = 0x6265_6472;
+/// Stone and deepslate mix over this many blocks above and below the
+/// preset's `deepslate_level`.
+const DEEPSLATE_BLEND: i32 = 4;
+const DEEPSLATE_SALT: u64 = 0x6465_6570;
 
 // --- Structs and Types ---
 
-// State shared between chunk generation worker threads
-struct ChunkWorkerState {
-    sender: Sender<(ChunkPos, UnloadedChunk)>,
-    receiver: Receiver<ChunkPos>,
+/// Marks the main (overworld) layer. Systems that only make sense in the
+/// overworld filter on this so other dimensions can exist alongside it.
+#[derive(Component)]
+pub struct Overworld;
+
+/// Terrain-shaping parameters for the noise generator. Scales are divisors
+/// applied to block coordinates before sampling, so bigger means smoother.
+/// Octaves are how many layers of finer and finer noise are added together;
+/// each layer is `lacunarity` times finer and `persistence` times as strong
+/// as the one before.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TerrainPreset {
+    pub sea_level: f64,
+    /// Height of the flattest terrain above sea level.
+    pub base_height: f64,
+    /// How far hills rise above `base_height` (and how tall the overhang band
+    /// above them is) in the hilliest areas.
+    pub height_multiplier: f64,
+    /// How tall the overhang band is compared to the hills under it. Above 1
+    /// gives taller peaks, floating chunks and cliffs that lean out.
+    pub overhang_multiplier: f64,
+    /// Hilliness never drops below this, from 0 to 1.
+    pub min_hilliness: f64,
+    /// Higher values make flat areas more common and hills rarer.
+    pub hilliness_exponent: f64,
+    pub hilliness_scale: f64,
+    pub density_scale: f64,
+    pub density_octaves: u32,
+    pub density_lacunarity: f64,
+    /// Lower values smooth out the small bumps and overhangs.
+    pub density_persistence: f64,
+    /// Surfaces below this are dirt instead of grass.
+    pub grass_level: i32,
+    pub gravel_scale: f64,
+    pub gravel_octaves: u32,
+    /// How far below `grass_level` the line gravel starts under wanders.
+    pub gravel_depth: f64,
+    pub stone_scale: f64,
+    pub grass_scale: f64,
+    pub grass_octaves: u32,
+    /// Divisor for the temperature and humidity noise; bigger biomes.
+    pub biome_scale: f64,
+    /// Divisor for the noise that decides where oceans are; bigger
+    /// continents and oceans.
+    pub continent_scale: f64,
+    /// Divisor for the river noise; longer, further apart rivers.
+    pub river_scale: f64,
+    /// How far below sea level the ocean floor and river beds are.
+    pub ocean_depth: f64,
+    pub river_depth: f64,
+    /// Stone below this height is deepslate, blending into it over a few
+    /// blocks either side.
+    pub deepslate_level: i32,
+}
+
+impl Default for TerrainPreset {
+    fn default() -> Self {
+        Self {
+            sea_level: 47.0,
+            base_height: 15.0,
+            height_multiplier: 100.0,
+            overhang_multiplier: 1.0,
+            min_hilliness: 0.1,
+            hilliness_exponent: 2.0,
+            hilliness_scale: 400.0,
+            density_scale: 100.0,
+            density_octaves: 4,
+            density_lacunarity: 2.0,
+            density_persistence: 0.5,
+            grass_level: 55,
+            gravel_scale: 10.0,
+            gravel_octaves: 3,
+            gravel_depth: 6.0,
+            stone_scale: 15.0,
+            grass_scale: 5.0,
+            grass_octaves: 4,
+            biome_scale: 600.0,
+            continent_scale: 1500.0,
+            river_scale: 800.0,
+            ocean_depth: 18.0,
+            river_depth: 4.0,
+            deepslate_level: 16,
+        }
+    }
+}
+
+impl TerrainPreset {
+    /// Names of the presets `builtin` knows.
+    pub const BUILTIN: [&str; 3] = ["default", "amplified", "islands"];
+
+    /// Presets that ship with the server.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            // Extreme mountains, with a tall band of overhangs, arches and
+            // floating rock above them.
+            "amplified" => Some(Self {
+                base_height: 10.0,
+                height_multiplier: 70.0,
+                overhang_multiplier: 2.5,
+                hilliness_exponent: 1.5,
+                density_scale: 60.0,
+                density_persistence: 0.6,
+                ..Self::default()
+            }),
+            // Mostly ocean, with islands where it gets hilly.
+            "islands" => Some(Self {
+                sea_level: 62.0,
+                base_height: -25.0,
+                height_multiplier: 60.0,
+                min_hilliness: 0.0,
+                hilliness_exponent: 3.0,
+                hilliness_scale: 250.0,
+                grass_level: 64,
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Where overworld chunks come from.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorldMode {
+    /// Generate terrain, using chunks from `import_world` where it has them.
+    #[default]
+    Generate,
+    /// Only serve `import_world`; chunks it doesn't have stay empty.
+    Load,
+}
+
+/// A seed as written in the config or on the command line: a number, or any
+/// text, which is hashed the way vanilla hashes text seeds.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum SeedSetting {
+    Number(i64),
+    Text(String),
+}
+
+impl SeedSetting {
+    pub fn parse(text: &str) -> Self {
+        text.trim().parse().map(Self::Number).unwrap_or_else(|_| Self::Text(text.to_owned()))
+    }
+
+    pub fn value(&self) -> u32 {
+        match self {
+            SeedSetting::Number(n) => *n as u32,
+            SeedSetting::Text(text) => match text.trim().parse::<i64>() {
+                Ok(n) => n as u32,
+                Err(_) => text.encode_utf16().fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32)) as u32,
+            },
+        }
+    }
+}
+
+/// `config/worldgen.json`
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WorldGenConfig {
+    pub mode: WorldMode,
+    /// Fixed world seed. Without one, a random seed is picked the first time
+    /// and kept in `world/seed.txt`. `--seed <seed>` on the command line
+    /// overrides both.
+    pub seed: Option<SeedSetting>,
+    /// Name of a built-in preset or one from `presets`. `"flat"` skips the
+    /// noise terrain for a classic superflat world.
+    pub preset: String,
+    pub presets: HashMap<String, TerrainPreset>,
+    /// A vanilla superflat preset string, including pre-1.13 ones. When set,
+    /// the world is flat and the terrain preset is ignored.
+    pub superflat: Option<String>,
+    /// Path to a vanilla world folder to serve chunks from. Required in
+    /// `load` mode.
+    pub import_world: Option<String>,
+    /// The lowest y blocks can be at, and how many layers there are above
+    /// it. Both are multiples of 16. Terrain heights in presets count from
+    /// `min_y`. Extra worlds share the main world's limits.
+    pub min_y: i32,
+    pub height: u32,
+    pub throttle: ThrottleConfig,
+    pub structures: StructureConfig,
+    pub ores: OreConfig,
+    /// Grass, flowers, boulders, lakes and the rest, by biome. Replaces the
+    /// default registry entirely when set.
+    pub features: Vec<PlacedFeature>,
+    /// What the top blocks of each column are made of. Replaces the default
+    /// rules entirely when set.
+    pub surface: Vec<SurfaceRule>,
+    pub villages: VillageConfig,
+    pub border: BorderConfig,
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        Self {
+            mode: WorldMode::default(),
+            seed: None,
+            preset: "default".into(),
+            presets: HashMap::new(),
+            superflat: None,
+            import_world: None,
+            min_y: DEFAULT_MIN_Y,
+            height: DEFAULT_HEIGHT,
+            throttle: ThrottleConfig::default(),
+            structures: StructureConfig::default(),
+            ores: OreConfig::default(),
+            features: PlacedFeature::defaults(),
+            surface: SurfaceRule::defaults(),
+            villages: VillageConfig::default(),
+            border: BorderConfig::default(),
+        }
+    }
+}
+
+impl WorldGenConfig {
+    /// Whether `preset` names a custom preset, a built-in one or `"flat"`.
+    pub fn has_preset(&self, preset: &str) -> bool {
+        preset == "flat" || self.presets.contains_key(preset) || TerrainPreset::builtin(preset).is_some()
+    }
+
+    /// `min_y` and `height` rounded down to whole sections and kept within
+    /// what clients accept.
+    pub fn build_limits(&self) -> (i32, u32) {
+        let min_y = (self.min_y.div_euclid(16) * 16).clamp(-MAX_BUILD_Y, MAX_BUILD_Y - 16);
+        let height = (self.height / 16 * 16).clamp(16, MAX_HEIGHT).min((MAX_BUILD_Y - min_y) as u32);
+        if (min_y, height) != (self.min_y, self.height) {
+            warn!(
+                target: WORLDGEN,
+                "World limits min_y {} height {} aren't allowed, using min_y {min_y} height {height}",
+                self.min_y,
+                self.height
+            );
+        }
+        (min_y, height)
+    }
+
+    /// The superflat preset string to use, if the world is flat. A custom
+    /// `superflat` wins over `"preset": "flat"`.
+    pub fn superflat_preset(&self) -> Option<&str> {
+        self.superflat.as_deref().or((self.preset == "flat").then_some(flat::CLASSIC))
+    }
+
+    /// The selected preset, preferring custom presets over built-in ones.
+    pub fn terrain(&self) -> TerrainPreset {
+        if let Some(preset) = self.presets.get(&self.preset) {
+            return preset.clone();
+        }
+        if self.preset == "flat" {
+            return TerrainPreset::default();
+        }
+        TerrainPreset::builtin(&self.preset).unwrap_or_else(|| {
+            warn!(target: WORLDGEN, "Unknown worldgen preset {:?}, using default", self.preset);
+            TerrainPreset::default()
+        })
+    }
+}
+
+/// What the 2D noise gives for each column of the chunk being generated,
+/// `[z][x]`. It's sampled once up front, so the terrain pass and decorations
+/// don't sample it again for every block or every question about a column.
+struct ChunkColumns {
+    pos: ChunkPos,
+    climate: [[Climate; 16]; 16],
+    /// `bounds_for` each column's climate.
+    bounds: [[(f64, f64); 16]; 16],
+    gravel: [[f64; 16]; 16],
+    stone: [[f64; 16]; 16],
+    /// What `surface_height` gives, found by the terrain pass.
+    surface: [[Option<i32>; 16]; 16],
+}
+
+impl ChunkColumns {
+    /// The `(x, z)` index of a block column, if it's in the chunk.
+    fn index(&self, x: i32, z: i32) -> Option<(usize, usize)> {
+        let (x, z) = (x - self.pos.x * 16, z - self.pos.z * 16);
+        ((0..16).contains(&x) && (0..16).contains(&z)).then_some((x as usize, z as usize))
+    }
+}
+
+/// Everything needed to generate terrain for one seed and preset.
+pub struct ChunkGenerator {
+    seed: u32,
+    /// Blocks in a generated column, from the world's `min_y` up.
+    height: u32,
+    terrain: TerrainPreset,
+    flat: Option<(SuperflatPreset, BiomeId)>,
+    ores: OreConfig,
+    features: Vec<PlacedFeature>,
+    surface: Vec<SurfaceRule>,
+    villages: VillageConfig,
+    /// Registry ids of `Biome::ALL`, in the same order.
+    biome_ids: [BiomeId; Biome::ALL.len()],
+    /// The same for their snowy versions.
+    snowy_biome_ids: [BiomeId; Biome::ALL.len()],
+    structure_config: StructureConfig,
+    /// Shared with `/structure`, which changes it while chunks generate.
+    structures: RwLock<StructurePool>,
     // Noise functions
     density: SuperSimplex,
     hilly: SuperSimplex,
     stone: SuperSimplex,
     gravel: SuperSimplex,
     grass: SuperSimplex,
+    trees: SuperSimplex,
+    temperature: SuperSimplex,
+    humidity: SuperSimplex,
+    continent: SuperSimplex,
+    river: SuperSimplex,
+}
+
+/// A chunk a worker finished.
+pub struct GeneratedChunk {
+    pub pos: ChunkPos,
+    pub chunk: UnloadedChunk,
+    /// Structures that start in this chunk.
+    pub structures: Vec<PlacedStructure>,
+    /// How long generating it took, or `None` if it was loaded from disk.
+    pub generated_in: Option<Duration>,
+    /// Block entities of a chunk loaded from disk, as offsets inside it.
+    pub block_entities: Vec<[u32; 3]>,
+}
+
+// State shared between chunk generation worker threads
+struct ChunkWorkerState {
+    sender: Sender<GeneratedChunk>,
+    receiver: Receiver<ChunkPos>,
+    /// Swapped by `/worldgen set`. Chunks already being generated finish
+    /// with the old one.
+    generator: RwLock<Arc<ChunkGenerator>>,
+    /// Saved chunks here are loaded instead of generated.
+    save_dir: Option<PathBuf>,
 }
 
-// Resource holding the state for queuing and receiving generated chunks
+/// Where new players appear in the overworld.
 #[derive(Resource)]
+pub struct WorldSpawn(pub DVec3);
+
+impl Default for WorldSpawn {
+    fn default() -> Self {
+        Self(SPAWN_POS)
+    }
+}
+
+impl WorldSpawn {
+    /// The spawn set with `/setworldspawn`, if there is one.
+    fn load_saved() -> Option<DVec3> {
+        let text = fs::read_to_string(SPAWN_FILE).ok()?;
+        let mut coords = text.split_whitespace().map(|c| c.parse::<f64>().ok());
+        match (coords.next()??, coords.next()??, coords.next()??) {
+            (x, y, z) if coords.next().is_none() => Some(DVec3::new(x, y, z)),
+            _ => {
+                warn!(target: WORLDGEN, "Ignoring {SPAWN_FILE}, it should be three numbers");
+                None
+            }
+        }
+    }
+
+    /// Keeps this spawn over restarts.
+    pub fn save(&self) -> std::io::Result<()> {
+        fs::create_dir_all(Path::new(SPAWN_FILE).parent().unwrap_or(Path::new(".")))?;
+        fs::write(SPAWN_FILE, format!("{} {} {}", self.0.x, self.0.y, self.0.z))
+    }
+}
+
+/// The overworld's seed and generator, and the settings it was built from.
+#[derive(Resource)]
+pub struct WorldGenerator {
+    pub seed: u32,
+    pub generator: Arc<ChunkGenerator>,
+    pub config: WorldGenConfig,
+}
+
+// State for queuing and receiving generated chunks. The overworld's is a
+// resource; other generated worlds keep theirs on their layer.
+#[derive(Resource, Component)]
 pub struct GameState {
     /// Chunks that need to be generated. Chunks without a priority have already
     /// been sent to the thread pool.
     pending: HashMap<ChunkPos, Option<Priority>>,
     sender: Sender<ChunkPos>, // Sends chunk positions TO workers
-    receiver: Receiver<(ChunkPos, UnloadedChunk)>, // Receives finished chunks FROM workers
+    receiver: Receiver<GeneratedChunk>, // Receives finished chunks FROM workers
+    workers: Arc<ChunkWorkerState>,
 }
 
+impl GameState {
+    /// Starts `threads` workers generating chunks with `generator`, loading
+    /// the ones saved in `save_dir` instead.
+    pub fn start(generator: Arc<ChunkGenerator>, save_dir: Option<PathBuf>, threads: usize) -> Self {
+        let (finished_sender, finished_receiver) = flume::unbounded();
+        let (pending_sender, pending_receiver) = flume::unbounded();
+        let worker_shared_state = Arc::new(ChunkWorkerState {
+            sender: finished_sender,
+            receiver: pending_receiver,
+            generator: RwLock::new(generator),
+            save_dir,
+        });
+        for _ in 0..threads {
+            let state_clone = worker_shared_state.clone();
+            thread::spawn(move || chunk_worker(state_clone));
+        }
+        Self {
+            pending: HashMap::new(),
+            sender: pending_sender,
+            receiver: finished_receiver,
+            workers: worker_shared_state,
+        }
+    }
+
+    /// The generator the workers are using.
+    pub fn generator(&self) -> Arc<ChunkGenerator> {
+        self.workers.generator.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Makes the workers generate chunks with `generator` from now on.
+    /// Chunks that are already loaded or saved stay as they are.
+    pub fn set_generator(&self, generator: Arc<ChunkGenerator>) {
+        *self.workers.generator.write().unwrap_or_else(PoisonError::into_inner) = generator;
+    }
+
+    /// Queues a chunk ahead of everything players are waiting on.
+    pub fn request_chunk(&mut self, pos: ChunkPos) {
+        self.pending.entry(pos).or_insert(Some(0));
+    }
+
+    /// Chunks queued or being generated.
+    pub fn queued_chunks(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queues `pos` if `layer` doesn't have it yet. If it's already waiting,
+    /// it moves up to `priority` when that's sooner.
+    fn queue_chunk(&mut self, layer: &ChunkLayer, pos: ChunkPos, priority: Priority) {
+        if layer.chunk(pos).is_some() {
+            return;
+        }
+        match self.pending.entry(pos) {
+            // If priority is None, it's already sent to a worker, do nothing.
+            Entry::Occupied(mut oe) => {
+                if let Some(current) = oe.get_mut() {
+                    *current = (*current).min(priority);
+                }
+            }
+            Entry::Vacant(ve) => {
+                ve.insert(Some(priority));
+            }
+        }
+    }
+
+    /// Takes at most `budget` finished chunks from the workers. Any past the
+    /// budget wait in the channel until the next tick.
+    fn receive_chunks(&mut self, budget: usize) -> Vec<GeneratedChunk> {
+        let received: Vec<_> = self.receiver.try_iter().take(budget).collect();
+        received
+            .into_iter()
+            .filter(|GeneratedChunk { pos, .. }| match self.pending.remove(pos) {
+                // Ensure it was actually sent (priority was None)
+                Some(None) => true,
+                Some(priority) => {
+                    info!(target: WORLDGEN, "Received chunk {:?} that still had priority?", pos);
+                    self.pending.insert(*pos, priority);
+                    false
+                }
+                None => {
+                    info!(target: WORLDGEN, "Received unexpected chunk {:?}", pos);
+                    false
+                }
+            })
+            .collect()
+    }
+
+    /// Sends at most `budget` of the closest queued chunks to the workers.
+    /// The rest keep their priority for next tick. `before_send` runs for each
+    /// chunk just before it goes.
+    fn dispatch_chunks(&mut self, budget: usize, mut before_send: impl FnMut(ChunkPos)) {
+        let mut to_send: Vec<(Priority, ChunkPos)> = self
+            .pending
+            .iter()
+            .filter_map(|(pos, priority)| Some(((*priority)?, *pos)))
+            .collect();
+        to_send.sort_unstable_by_key(|(pri, _)| *pri);
+        to_send.truncate(budget);
+
+        for (_, pos) in to_send {
+            // Clear the priority (marks as sent)
+            if let Some(priority) = self.pending.get_mut(&pos) {
+                *priority = None;
+            }
+            before_send(pos);
+            if let Err(e) = self.sender.try_send(pos) {
+                // Failed to send (channel closed or full?). Log and put priority back.
+                info!(target: WORLDGEN, "Failed to send chunk {:?} to worker: {}", pos, e);
+                if let Some(prio_opt) = self.pending.get_mut(&pos) {
+                    *prio_opt = Some(0);
+                }
+            }
+        }
+    }
+}
+
+/// How long each loaded overworld chunk took to generate, for the ones that
+/// were generated this run.
+#[derive(Resource, Default)]
+pub struct ChunkTimings(pub HashMap<ChunkPos, Duration>);
+
+/// Chunks kept loaded even with nobody viewing them. The overworld's is a
+/// resource; extra worlds keep theirs on their layer.
+#[derive(Resource, Component, Default)]
+pub struct ChunkTickets(pub HashSet<ChunkPos>);
+
 /// The order in which chunks should be processed by the thread pool. Smaller
 /// values are sent first (closer chunks).
 type Priority = u64;
 
 // --- Setup Function ---
 
+const SEED_FILE: &str = "world/seed.txt";
+const SPAWN_FILE: &str = "world/spawn.txt";
+
+/// `--seed <seed>` or `--seed=<seed>` from the command line.
+fn seed_from_args() -> Option<SeedSetting> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            return args.next().map(|value| SeedSetting::parse(&value));
+        }
+        if let Some(value) = arg.strip_prefix("--seed=") {
+            return Some(SeedSetting::parse(value));
+        }
+    }
+    None
+}
+
+/// The command line wins over the config, which wins over the seed saved by
+/// an earlier run. If there's none of those, a new random seed is saved.
+fn choose_seed(config: &WorldGenConfig) -> u32 {
+    let (seed, source) = if let Some(seed) = seed_from_args() {
+        (seed.value(), "command line")
+    } else if let Some(seed) = &config.seed {
+        (seed.value(), "config/worldgen.json")
+    } else if let Ok(saved) = fs::read_to_string(SEED_FILE) {
+        (SeedSetting::parse(&saved).value(), SEED_FILE)
+    } else {
+        let seed = valence::rand::random::<u32>();
+        let result = fs::create_dir_all(Path::new(SEED_FILE).parent().unwrap_or(Path::new(".")))
+            .and_then(|_| fs::write(SEED_FILE, seed.to_string()));
+        if let Err(e) = result {
+            warn!(target: WORLDGEN, "Couldn't save the new seed to {SEED_FILE}: {e}");
+        }
+        (seed, "new random seed")
+    };
+    info!(target: WORLDGEN, "Using generation seed: {seed} ({source})");
+    seed
+}
+
+/// Builds the generator `worldgen` describes for `seed`.
+pub fn build_generator(worldgen: &WorldGenConfig, seed: u32, biomes: &BiomeRegistry) -> ChunkGenerator {
+    let flat = worldgen.superflat_preset().and_then(|preset| match preset.parse::<SuperflatPreset>() {
+        Ok(flat) => {
+            let biome = biomes.index_of(flat.biome.as_str_ident()).unwrap_or_else(|| {
+                warn!(target: WORLDGEN, "Unknown superflat biome {}, using the default", flat.biome);
+                BiomeId::default()
+            });
+            Some((flat, biome))
+        }
+        Err(e) => {
+            warn!(target: WORLDGEN, "Invalid superflat preset {preset:?}: {e}");
+            None
+        }
+    });
+    if flat.is_some() {
+        info!(target: WORLDGEN, "Using superflat preset: {}", worldgen.superflat_preset().unwrap_or_default());
+    } else {
+        info!(target: WORLDGEN, "Using worldgen preset: {}", worldgen.preset);
+    }
+
+    ChunkGenerator::new(seed, worldgen.terrain(), flat)
+        .with_height(worldgen.height)
+        .with_biomes(biomes)
+        .with_ores(worldgen.ores.clone())
+        .with_features(worldgen.features.clone())
+        .with_surface(worldgen.surface.clone())
+        .with_villages(worldgen.villages.clone())
+        .with_structures(worldgen.structures.clone(), StructurePool::load())
+}
+
 pub fn setup_world(
     mut commands: Commands,
     server: Res<Server>,
-    dimensions: Res<DimensionTypeRegistry>,
+    mut dimensions: ResMut<DimensionTypeRegistry>,
     biomes: Res<BiomeRegistry>,
 ) {
-    info!("Setting up procedural world generation...");
-    let seconds_per_day = 86_400;
-    let seed = (SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        / seconds_per_day) as u32;
-
-    info!("Using generation seed: {seed}");
-
-    let (finished_sender, finished_receiver) = flume::unbounded();
-    let (pending_sender, pending_receiver) = flume::unbounded();
-
-    let worker_shared_state = Arc::new(ChunkWorkerState {
-        sender: finished_sender,
-        receiver: pending_receiver,
-        density: SuperSimplex::new(seed),
-        hilly: SuperSimplex::new(seed.wrapping_add(1)),
-        stone: SuperSimplex::new(seed.wrapping_add(2)),
-        gravel: SuperSimplex::new(seed.wrapping_add(3)),
-        grass: SuperSimplex::new(seed.wrapping_add(4)),
-    });
+    info!(target: WORLDGEN, "Setting up procedural world generation...");
+    let mut worldgen = load_config::<WorldGenConfig>("worldgen.json");
+    (worldgen.min_y, worldgen.height) = worldgen.build_limits();
+    // Every layer made with the overworld dimension type gets these limits,
+    // so this has to happen before any of them exist
+    if let Some(overworld) = dimensions.get_mut(ident!("overworld")) {
+        overworld.min_y = worldgen.min_y;
+        overworld.height = worldgen.height as i32;
+        overworld.logical_height = worldgen.height as i32;
+    }
+    info!(target: WORLDGEN, "World is {} blocks tall from y {}", worldgen.height, worldgen.min_y);
+    let seed = choose_seed(&worldgen);
+    let generator = Arc::new(build_generator(&worldgen, seed, &biomes));
+    let saver = storage::ChunkSaver::start(storage::SAVE_DIR);
+    commands.insert_resource(WorldGenerator { seed, generator: generator.clone(), config: worldgen.clone() });
 
     // Start worker threads
     // let core_count = thread::available_parallelism().map_or(1, |p| p.get());
     let core_count = 7;
-    info!("Spawning {} chunk generation worker threads...", core_count);
-    for _ in 0..core_count {
-        let state_clone = worker_shared_state.clone();
-        thread::spawn(move || chunk_worker(state_clone));
-    }
-
-    // Insert GameState resource for main thread communication
-    commands.insert_resource(GameState {
-        pending: HashMap::new(),
-        sender: pending_sender,
-        receiver: finished_receiver,
-    });
+    info!(target: WORLDGEN, "Spawning {} chunk generation worker threads...", core_count);
+    commands.insert_resource(GameState::start(generator.clone(), Some(saver.dir.clone()), core_count));
 
     // Spawn the main world layer entity
     let layer = LayerBundle::new(ident!("overworld"), &dimensions, &biomes, &server);
-    commands.spawn(layer);
+    let min_y = layer.chunk.min_y();
+    let mut layer_entity = commands.spawn((layer, Overworld));
+    let border = WorldBorder(worldgen.border.clone());
+    if border.0.enabled {
+        info!(target: WORLDGEN, "World border {} blocks from {:?}", border.0.radius, border.0.center);
+        layer_entity.insert(border.bundle());
+    }
+
+    let mut spawn = WorldSpawn(generator.find_spawn(min_y));
+    let mut imported = None;
+    if let Some(path) = worldgen.import_world.as_deref().map(Path::new) {
+        if let Some(level) = anvil::open_level(path, &biomes) {
+            layer_entity.insert(level);
+            // Imported terrain doesn't match the generator's, so without the
+            // world's own spawn players drop in from high up
+            spawn.0 = anvil::read_level_spawn(path).unwrap_or(SPAWN_POS);
+            info!(target: WORLDGEN, "Using the imported world's spawn: {:?}", spawn.0);
+            let prefetcher = prefetch::RegionPrefetcher::start(path.join("region"));
+            prefetcher.prefetch_around(ChunkPos::from(spawn.0));
+            let generate_missing = worldgen.mode == WorldMode::Generate;
+            imported = Some((anvil::ImportedRegions::new(path, generate_missing), prefetcher));
+        }
+    }
+    if let Some(saved) = WorldSpawn::load_saved() {
+        info!(target: WORLDGEN, "Using the spawn from {SPAWN_FILE}: {saved:?}");
+        spawn.0 = saved;
+    }
+    if worldgen.mode == WorldMode::Load && imported.is_none() {
+        warn!(target: WORLDGEN, "worldgen mode is load but there's no world to load, generating instead");
+    }
+    if let Some((regions, prefetcher)) = imported {
+        commands.insert_resource(regions);
+        commands.insert_resource(prefetcher);
+    }
+    commands.insert_resource(remap::Remapper::load());
+    commands.insert_resource(ChunkThrottle::new(worldgen.throttle.clone()));
+    commands.insert_resource(saver);
+    commands.insert_resource(spawn);
+    commands.insert_resource(border);
 
-    info!("World layer spawned.");
+    info!(target: WORLDGEN, "World layer spawned.");
 }
 
 // --- World-Related Systems ---
@@ -124,13 +770,13 @@ pub fn init_clients_world(
         ),
         Added<Client>,
     >,
-    layers: Query<Entity, (With<ChunkLayer>, With<EntityLayer>)>,
+    layers: Query<Entity, With<Overworld>>,
+    spawn: Res<WorldSpawn>,
+    world_gen: Res<WorldGenerator>,
 ) {
-    if layers.is_empty() {
+    let Ok(layer) = layers.get_single() else {
         return;
-    }
-
-    let layer = layers.single();
+    };
 
     for (
         mut layer_id,
@@ -148,9 +794,10 @@ pub fn init_clients_world(
         layer_id.0 = layer;
         visible_chunk_layer.0 = layer;
         visible_entity_layers.0.insert(layer);
-        pos.set(SPAWN_POS);
+        pos.set(spawn.0);
         *game_mode = GameMode::Creative;
-        is_flat.0 = false;
+        // Flat worlds get the low, flat-world horizon
+        is_flat.0 = world_gen.generator.is_flat();
 
         client.send_chat_message(
             "[Crystal] ".color(Color::RED) + "Welcome to Crystal!".color(Color::GOLD),
@@ -165,124 +812,175 @@ pub fn init_clients_world(
         );
 
         info!(
+            target: NET,
+            player = %username.0,
             "{} initialized in world at {:?}",
-            username.0, SPAWN_POS
+            username.0, spawn.0
         );
     }
 }
 
-// Removes chunks from memory when no players are viewing them
-// [x] TODO: add this back later (when I fix it)
-pub fn remove_unviewed_chunks(mut layers: Query<&mut ChunkLayer>) {
+// Sends players who died back to the world spawn
+pub fn respawn_players(
+    mut events: EventReader<RequestRespawnEvent>,
+    mut clients: Query<(
+        &mut EntityLayerId,
+        &mut VisibleChunkLayer,
+        &mut VisibleEntityLayers,
+        &mut Position,
+        &mut Health,
+    )>,
+    layers: Query<Entity, With<Overworld>>,
+    spawn: Res<WorldSpawn>,
+) {
+    let Ok(overworld) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        let Ok((mut layer_id, mut visible_chunk_layer, mut visible_entity_layers, mut pos, mut health)) =
+            clients.get_mut(event.client)
+        else {
+            continue;
+        };
+        health.0 = 20.0;
+        // Setting the layer again, even the same one, is what sends the respawn
+        send_to_layer(overworld, spawn.0, &mut layer_id, &mut visible_chunk_layer, &mut visible_entity_layers, &mut pos);
+    }
+}
+
+/// Moves a player into another layer (dimension) at `pos`. Valence sends the
+/// respawn packet itself when the visible chunk layer changes.
+pub fn send_to_layer(
+    layer: Entity,
+    pos: DVec3,
+    layer_id: &mut EntityLayerId,
+    visible_chunk_layer: &mut VisibleChunkLayer,
+    visible_entity_layers: &mut VisibleEntityLayers,
+    position: &mut Position,
+) {
+    visible_entity_layers.0.remove(&layer_id.0);
+    visible_entity_layers.0.insert(layer);
+    layer_id.0 = layer;
+    visible_chunk_layer.0 = layer;
+    position.set(pos);
+}
+
+/// Whether `client` is currently in the overworld layer. Block interactions
+/// handled by overworld-only systems check this first.
+pub fn in_overworld(clients: &Query<&VisibleChunkLayer>, client: Entity, overworld: Entity) -> bool {
+    clients.get(client).is_ok_and(|layer| layer.0 == overworld)
+}
+
+// Removes chunks from memory when no players are viewing them, saving the
+// ones that were edited
+pub fn remove_unviewed_chunks(
+    mut layers: Query<&mut ChunkLayer, With<Overworld>>,
+    tickets: Res<ChunkTickets>,
+    mut saver: ResMut<storage::ChunkSaver>,
+    mut timings: ResMut<ChunkTimings>,
+) {
     let Ok(mut layer) = layers.get_single_mut() else {
         return;
     };
 
-    layer.retain_chunks(|_pos, chunk| chunk.viewer_count() > 0);
+    layer.retain_chunks(|pos, chunk| {
+        let keep = chunk.viewer_count() > 0 || tickets.0.contains(&pos);
+        if !keep {
+            timings.0.remove(&pos);
+            if saver.is_dirty(pos) {
+                saver.save_unloading(pos, chunk);
+            }
+        }
+        keep
+    });
 }
 
 // Queues chunks to be generated based on player view distance changes
 pub fn update_client_views(
-    layers: Query<&mut ChunkLayer>, // Change to immutable borrow if possible
-    mut clients: Query<(&mut Client, View, OldView)>, // Removed mut Client here
+    layers: Query<(Entity, &ChunkLayer, Has<AnvilLevel>), With<Overworld>>,
+    mut clients: Query<(&mut Client, Ref<VisibleChunkLayer>, View, OldView)>, // Removed mut Client here
     mut state: ResMut<GameState>,
+    border: Res<WorldBorder>,
 ) {
-    let Ok(layer) = layers.get_single() else {
+    let Ok((layer_entity, layer, imported)) = layers.get_single() else {
         return;
     }; // Use immutable borrow if layer isn't modified
+    // The anvil plugin loads viewed chunks itself and hands back missing ones
+    if imported {
+        return;
+    }
 
-    for (client, view, old_view) in &mut clients {
-        // Use _client if not needed directly
+    for (client, visible_layer, view, old_view) in &mut clients {
+        // Players in other dimensions don't need overworld chunks
+        if visible_layer.0 != layer_entity {
+            continue;
+        }
         let view = view.get();
-        let old_view = old_view.get(); // Get old view unconditionally
-
-        // Function to queue a chunk position if needed
+        let old_view = old_view.get();
+        // Nothing is generated past the world border
         let queue_pos = |pos: ChunkPos| {
-            if layer.chunk(pos).is_none() {
-                // Check if chunk doesn't exist yet
-                match state.pending.entry(pos) {
-                    // Already pending? Update priority if current view is closer.
-                    Entry::Occupied(mut oe) => {
-                        if let Some(priority) = oe.get_mut() {
-                            let dist = view.pos.distance_squared(pos);
-                            *priority = (*priority).min(dist);
-                        }
-                        // If priority is None, it's already sent to worker, do nothing.
-                    }
-                    // Not pending? Add it with current view distance priority.
-                    Entry::Vacant(ve) => {
-                        let dist = view.pos.distance_squared(pos);
-                        ve.insert(Some(dist));
-                    }
-                }
+            if border.contains_chunk(pos) {
+                state.queue_chunk(layer, pos, view.pos.distance_squared(pos));
             }
         };
 
         // Queue all the new chunks in the view to be sent to the thread pool.
-        if client.is_added() {
+        // Players coming back from another dimension need their whole view again.
+        if client.is_added() || visible_layer.is_changed() {
             view.iter().for_each(queue_pos);
-        } else {
-            if old_view != view {
-                view.diff(old_view).for_each(queue_pos);
-            }
+        } else if old_view != view {
+            view.diff(old_view).for_each(queue_pos);
         }
     }
 }
 
+/// Inserts a generated chunk and announces the structures that start in it.
+pub fn insert_generated_chunk(
+    layer_entity: Entity,
+    layer: &mut ChunkLayer,
+    generated: GeneratedChunk,
+    structures: &mut EventWriter<StructurePlaced>,
+) {
+    layer.insert_chunk(generated.pos, generated.chunk);
+    for structure in generated.structures {
+        let origin = structure.origin;
+        structures.send(StructurePlaced {
+            layer: layer_entity,
+            name: structure.name,
+            origin: BlockPos::new(origin.x, origin.y + layer.min_y(), origin.z),
+            schematic: structure.schematic,
+        });
+    }
+}
+
 // Sends pending chunks to workers and receives/inserts finished chunks
-pub fn send_recv_chunks(mut layers: Query<&mut ChunkLayer>, mut state: ResMut<GameState>) {
-    let Ok(mut layer) = layers.get_single_mut() else {
+pub fn send_recv_chunks(
+    mut layers: Query<(Entity, &mut ChunkLayer), With<Overworld>>,
+    mut state: ResMut<GameState>,
+    throttle: Res<ChunkThrottle>,
+    mut structures: EventWriter<StructurePlaced>,
+    mut restored: EventWriter<storage::ChunkRestored>,
+    mut saver: ResMut<storage::ChunkSaver>,
+    mut timings: ResMut<ChunkTimings>,
+) {
+    let Ok((layer_entity, mut layer)) = layers.get_single_mut() else {
         return;
     };
 
     // Insert the chunks that are finished generating into the instance.
-    let received_chunks: Vec<_> = state.receiver.try_iter().collect(); // Collect into a temporary variable
-    for (pos, chunk) in received_chunks {
-        if let Some(prio_opt) = state.pending.remove(&pos) {
-            if prio_opt.is_none() { // Ensure it was actually sent (priority was None)
-                // Inside the `if prio_opt.is_none()` block:
-                // info!("Attempting to insert chunk at {:?}", pos); // Log *before* calling
-                layer.insert_chunk(pos, chunk);
-                // info!("Successfully called insert_chunk for {:?}", pos); // Log *after* calling
-            } else {
-                // Chunk finished but shouldn't have? Log warning.
-                info!("Received chunk {:?} that still had priority?", pos);
-                state.pending.insert(pos, prio_opt); // Put it back? Or just discard?
-                // panic!("LITERALLY MAX CONFIRMATION");
-            }
-        } else {
-            // Received a chunk that wasn't pending? Should not happen.
-            info!("Received unexpected chunk {:?}", pos);
-            // panic!("I SWEAR THIS ISNT HIT");
+    for generated in state.receive_chunks(throttle.budget) {
+        if let Some(took) = generated.generated_in {
+            timings.0.insert(generated.pos, took);
         }
-    }
-    // for (pos, chunk) in state.receiver.drain() {
-    //     layer.insert_chunk(pos, chunk);
-    //     // assert!(state.pending.remove(&pos).is_some());
-    // }
-
-    // Collect chunks that have a priority set (ready to be sent).
-    let mut to_send: Vec<(Priority, ChunkPos)> = Vec::new();
-    for (pos, priority) in &mut state.pending {
-        if let Some(pri) = priority.take() {
-            // Take the priority, leaving None (marks as sent)
-            to_send.push((pri, *pos));
+        let block_entities = storage::ChunkRestored::new(generated.pos, layer.min_y(), &generated.block_entities);
+        insert_generated_chunk(layer_entity, &mut layer, generated, &mut structures);
+        if let Some(block_entities) = block_entities {
+            restored.send(block_entities);
         }
     }
 
-    // Sort chunks by ascending priority (distance).
-    to_send.sort_unstable_by_key(|(pri, _)| *pri);
-
-    // Send the sorted chunks to the worker pool.
-    for (_, pos) in to_send {
-        if let Err(e) = state.sender.try_send(pos) {
-            // Failed to send (channel closed or full?). Log and put priority back.
-            info!("Failed to send chunk {:?} to worker: {}", pos, e);
-            if let Some(prio_opt) = state.pending.get_mut(&pos) {
-                *prio_opt = Some(0); // Put back with some priority? Or remove?
-            }
-        }
-    }
+    // Workers read saved chunks from disk, so they have to be there by now
+    state.dispatch_chunks(throttle.budget, |pos| saver.ensure_written(pos));
 }
 
 // --- Chunk Generation Worker ---
@@ -622,135 +1320,576 @@ fn chunk_worker(state: Arc<ChunkWorkerState>) {
 */
 fn chunk_worker(state: Arc<ChunkWorkerState>) {
     while let Ok(pos) = state.receiver.recv() {
-        let mut chunk = UnloadedChunk::with_height(HEIGHT);
+        if let Some(saved) = state.save_dir.as_deref().and_then(|dir| storage::read_chunk(dir, pos)) {
+            let loaded = GeneratedChunk {
+                pos,
+                chunk: saved.chunk,
+                structures: Vec::new(),
+                generated_in: None,
+                block_entities: saved.block_entities,
+            };
+            if let Err(e) = state.sender.try_send(loaded) {
+                info!(target: WORLDGEN, "Failed to send loaded chunk {:?}: {}", pos, e);
+            }
+            continue;
+        }
+        let _span = debug_span!(target: WORLDGEN, "generate_chunk", x = pos.x, z = pos.z).entered();
+        let started_at = Instant::now();
+        let generator = state.generator.read().unwrap_or_else(PoisonError::into_inner).clone();
+        let chunk = generator.generate(pos);
+        let structures = generator.structure_starts(pos);
+        let generated = GeneratedChunk {
+            pos,
+            chunk,
+            structures,
+            generated_in: Some(started_at.elapsed()),
+            block_entities: Vec::new(),
+        };
+        if let Err(e) = state.sender.try_send(generated) {
+            info!(target: WORLDGEN, "Failed to send finished chunk {:?}: {}", pos, e);
+        }
+    }
+    info!(target: WORLDGEN, "Chunk worker thread shutting down.");
+}
 
-        // Precompute noise values that depend only on x and z
-        let mut gravel_noise_cache = [[0.0; 16]; 16];
-        let mut stone_noise_cache = [[0.0; 16]; 16];
-        for z in 0..16 {
-            let world_z_base = (pos.z * 16) + z as i32;
-            for x in 0..16 {
-                let world_x = (pos.x * 16) + x as i32;
-                let p_col = DVec3::new(world_x as f64, 0.0, world_z_base as f64);
-                gravel_noise_cache[z][x] = fbm(&state.gravel, p_col / 10.0, 3, 2.0, 0.5);
-                stone_noise_cache[z][x] = noise01(&state.stone, p_col / 15.0);
+impl ChunkGenerator {
+    pub fn new(seed: u32, terrain: TerrainPreset, flat: Option<(SuperflatPreset, BiomeId)>) -> Self {
+        Self {
+            seed,
+            height: DEFAULT_HEIGHT,
+            terrain,
+            flat,
+            ores: OreConfig::default(),
+            features: PlacedFeature::defaults(),
+            surface: SurfaceRule::defaults(),
+            villages: VillageConfig::default(),
+            biome_ids: [BiomeId::default(); Biome::ALL.len()],
+            snowy_biome_ids: [BiomeId::default(); Biome::ALL.len()],
+            structure_config: StructureConfig::default(),
+            structures: RwLock::new(StructurePool::default()),
+            density: SuperSimplex::new(seed),
+            hilly: SuperSimplex::new(seed.wrapping_add(1)),
+            stone: SuperSimplex::new(seed.wrapping_add(2)),
+            gravel: SuperSimplex::new(seed.wrapping_add(3)),
+            grass: SuperSimplex::new(seed.wrapping_add(4)),
+            trees: SuperSimplex::new(seed.wrapping_add(5)),
+            temperature: SuperSimplex::new(seed.wrapping_add(6)),
+            humidity: SuperSimplex::new(seed.wrapping_add(7)),
+            continent: SuperSimplex::new(seed.wrapping_add(8)),
+            river: SuperSimplex::new(seed.wrapping_add(9)),
+        }
+    }
+
+    pub fn with_structures(mut self, config: StructureConfig, pool: StructurePool) -> Self {
+        self.structure_config = config;
+        self.structures = RwLock::new(pool);
+        self
+    }
+
+    pub fn with_height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn with_ores(mut self, ores: OreConfig) -> Self {
+        self.ores = ores;
+        self
+    }
+
+    pub fn with_features(mut self, features: Vec<PlacedFeature>) -> Self {
+        self.features = features;
+        self
+    }
+
+    pub fn with_surface(mut self, surface: Vec<SurfaceRule>) -> Self {
+        self.surface = surface;
+        self
+    }
+
+    pub fn with_villages(mut self, villages: VillageConfig) -> Self {
+        self.villages = villages;
+        self
+    }
+
+    /// Looks up the biomes written into chunks. Without this every chunk
+    /// gets the registry's default biome.
+    pub fn with_biomes(mut self, registry: &BiomeRegistry) -> Self {
+        let lookup = |name: &str| {
+            let id = Ident::new(name).ok().and_then(|ident| registry.index_of(ident.as_str_ident()));
+            if id.is_none() {
+                warn!(target: WORLDGEN, "Biome {name} is missing from the registry");
+            }
+            id
+        };
+        for biome in Biome::ALL {
+            let id = lookup(biome.ident()).unwrap_or_default();
+            self.biome_ids[biome.index()] = id;
+            self.snowy_biome_ids[biome.index()] = lookup(biome.snowy_ident()).unwrap_or(id);
+        }
+        self
+    }
+
+    pub fn structures(&self) -> RwLockReadGuard<'_, StructurePool> {
+        self.structures.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn structures_mut(&self) -> RwLockWriteGuard<'_, StructurePool> {
+        self.structures.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.flat.is_some()
+    }
+
+    /// Generator y of the highest solid block in a column, or `None` if the
+    /// column is under water.
+    pub fn surface_height(&self, x: i32, z: i32) -> Option<i32> {
+        if let Some((flat, _)) = &self.flat {
+            return Some(flat.height().min(self.height) as i32 - 1);
+        }
+        let (lower, upper) = self.column_bounds(x as f64, z as f64);
+        let top = (upper.ceil() as i32).min(self.height as i32 - 1);
+        let surface = (0..=top).rev().find(|y| self.in_column(x as f64, *y as f64, z as f64, lower, upper))?;
+        (surface >= self.terrain.sea_level as i32).then_some(surface)
+    }
+
+    /// Where players should arrive: on the ground at the middle of the world,
+    /// or the nearest dry land to it. Layer y `min_y` is generator y 0.
+    pub fn find_spawn(&self, min_y: i32) -> DVec3 {
+        let (x, z, y) = spiral(0, 0, 256, 8)
+            .find_map(|(x, z)| Some((x, z, self.surface_height(x, z)?)))
+            // All water, so float at sea level
+            .unwrap_or((0, 0, self.terrain.sea_level as i32 - 1));
+        info!(target: WORLDGEN, "Spawn is at {x} {} {z}", y + 1 + min_y);
+        DVec3::new(x as f64 + 0.5, (y + 1 + min_y) as f64, z as f64 + 0.5)
+    }
+
+    /// The nearest column to `x`, `z` in `biome`, checking every
+    /// `BIOME_SEARCH_STEP` blocks out to `BIOME_SEARCH_RADIUS`.
+    pub fn find_biome(&self, biome: Biome, x: i32, z: i32) -> Option<(i32, i32)> {
+        spiral(x, z, BIOME_SEARCH_RADIUS, BIOME_SEARCH_STEP).find(|(x, z)| self.biome_at(*x, *z) == biome)
+    }
+
+    /// Names `find_structure` can look for: the structure pool, and
+    /// `village` if villages generate.
+    pub fn structure_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.structures().entries().map(|entry| entry.name.clone()).collect();
+        if self.villages.enabled && !self.is_flat() && !names.iter().any(|name| name == "village") {
+            names.push("village".to_owned());
+        }
+        names
+    }
+
+    /// The middle of the nearest structure called `name` to `x`, `z`, out
+    /// to `STRUCTURE_SEARCH_REGIONS` regions. Pool structures come before
+    /// villages if one is called `village`.
+    pub fn find_structure(&self, name: &str, x: i32, z: i32) -> Option<(i32, i32)> {
+        // A copy, so `/structure` can change the pool during a long search
+        let pool = self.structures().clone();
+        if pool.contains(name) {
+            let surface = |x, z| self.surface_height(x, z);
+            let config = &self.structure_config;
+            return structures::find_structure(self.seed, config, &pool, name, (x, z), STRUCTURE_SEARCH_REGIONS, surface)
+                .map(|found| found.center());
+        }
+        if name != "village" || self.is_flat() {
+            return None;
+        }
+        villages::find_village(
+            self.seed,
+            &self.villages,
+            x,
+            z,
+            STRUCTURE_SEARCH_REGIONS,
+            |x, z| self.biome_at(x, z),
+            |x, z| self.surface_height(x, z),
+        )
+    }
+
+    /// Where a player could stand in a column, as layer coordinates. Over
+    /// water that's the water's surface.
+    pub fn standing_spot(&self, x: i32, z: i32, min_y: i32) -> DVec3 {
+        let y = self.surface_height(x, z).unwrap_or(self.terrain.sea_level as i32 - 1);
+        DVec3::new(x as f64 + 0.5, (y + 1 + min_y) as f64, z as f64 + 0.5)
+    }
+
+    /// Structures whose start chunk is `pos`.
+    pub fn structure_starts(&self, pos: ChunkPos) -> Vec<PlacedStructure> {
+        let pool = self.structures();
+        if pool.is_empty() {
+            return Vec::new();
+        }
+        let spacing = self.structure_config.spacing.max(1);
+        let region = (pos.x.div_euclid(spacing), pos.z.div_euclid(spacing));
+        structures::place_in_region(self.seed, &self.structure_config, &pool, region, |x, z| self.surface_height(x, z))
+            .filter(|structure| structure.start == pos)
+            .into_iter()
+            .collect()
+    }
+
+    fn place_structures(&self, pos: ChunkPos, chunk: &mut UnloadedChunk) {
+        let pool = self.structures();
+        let placed = structures::structures_near(self.seed, &self.structure_config, &pool, pos, |x, z| {
+            self.surface_height(x, z)
+        });
+        for structure in placed {
+            structure.paste_into(pos, chunk);
+        }
+    }
+
+    pub fn climate(&self, world_x: f64, world_z: f64) -> Climate {
+        let terrain = &self.terrain;
+        let p_col = DVec3::new(world_x, 0.0, world_z);
+        Climate {
+            temperature: noise01(&self.temperature, p_col / terrain.biome_scale),
+            humidity: noise01(&self.humidity, p_col / terrain.biome_scale),
+            hilliness: noise01(&self.hilly, p_col / terrain.hilliness_scale),
+            continentalness: noise01(&self.continent, p_col / terrain.continent_scale),
+            river: noise01(&self.river, p_col / terrain.river_scale),
+        }
+    }
+
+    /// The biome of a column in generated terrain. Flat worlds have a single
+    /// biome of their own, so this is only meaningful without one.
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        self.climate(x as f64, z as f64).biome()
+    }
+
+    /// Where solid terrain always starts (`lower`) and always ends (`upper`)
+    /// in a column; in between it's decided by 3D noise.
+    fn column_bounds(&self, world_x: f64, world_z: f64) -> (f64, f64) {
+        self.bounds_for(&self.climate(world_x, world_z))
+    }
+
+    fn bounds_for(&self, climate: &Climate) -> (f64, f64) {
+        let terrain = &self.terrain;
+        let hilly = lerp(terrain.min_hilliness, 1.0, climate.hilliness).powf(terrain.hilliness_exponent);
+        let (ocean, river) = (climate.ocean_weight(), climate.river_weight());
+        // Oceans and rivers flatten out as well as sinking
+        let hills = terrain.height_multiplier * hilly * climate.height_scale() * (1.0 - ocean) * (1.0 - river);
+        let base_terrain_height = terrain.sea_level; // Start terrain above sea level
+        let land = base_terrain_height + terrain.base_height + hills;
+        let sea_floor = lerp(land, terrain.sea_level - terrain.ocean_depth, ocean);
+        let lower = lerp(sea_floor, terrain.sea_level - terrain.river_depth, river);
+        let upper = lower + hills * terrain.overhang_multiplier;
+        (lower, upper)
+    }
+
+    /// Generates a single chunk. Only depends on the seed, preset and
+    /// position, so the same inputs always give the same chunk.
+    pub fn generate(&self, pos: ChunkPos) -> UnloadedChunk {
+        let mut chunk = match &self.flat {
+            Some((flat, biome)) => flat.generate(self.height, *biome),
+            None => {
+                let mut columns = self.sample_columns(pos);
+                let mut chunk = self.generate_terrain(&mut columns);
+                self.decorate(&columns, &mut chunk);
+                villages::place_villages(
+                    self.seed,
+                    &self.villages,
+                    pos,
+                    &mut chunk,
+                    |x, z| self.cached_biome(&columns, x, z),
+                    |x, z| self.cached_surface(&columns, x, z),
+                );
+                chunk
             }
+        };
+        self.place_structures(pos, &mut chunk);
+        chunk
+    }
+
+    /// Features on top of the terrain. Structures are pasted after this, so
+    /// they replace anything decorations put in their way.
+    fn decorate(&self, columns: &ChunkColumns, chunk: &mut UnloadedChunk) {
+        let pos = columns.pos;
+        ores::place_ores(self.seed, &self.ores, pos, chunk);
+        trees::place_trees(
+            self.seed,
+            &self.trees,
+            pos,
+            chunk,
+            |x, z, chance| self.cached_biome(columns, x, z).tree_chance(chance),
+            |x, z| {
+                self.cached_surface(columns, x, z).filter(|y| {
+                    self.top_block(columns, x, z, *y) == BlockState::GRASS_BLOCK && *y + 8 < self.height as i32
+                })
+            },
+        );
+        features::place_features(
+            self.seed,
+            &self.features,
+            pos,
+            chunk,
+            self.terrain.sea_level as i32,
+            |x, z| self.cached_biome(columns, x, z),
+            |x, y, z| self.grass_noise(x, y, z),
+        );
+    }
+
+    /// The 2D noise of every column in the chunk at `pos`. The surface
+    /// heights are filled in by `generate_terrain`.
+    fn sample_columns(&self, pos: ChunkPos) -> ChunkColumns {
+        let world = |i: usize, base: i32| base * 16 + i as i32;
+        let climate: [[Climate; 16]; 16] =
+            array::from_fn(|z| array::from_fn(|x| self.climate(world(x, pos.x) as f64, world(z, pos.z) as f64)));
+        ChunkColumns {
+            pos,
+            climate,
+            bounds: array::from_fn(|z| array::from_fn(|x| self.bounds_for(&climate[z][x]))),
+            gravel: array::from_fn(|z| array::from_fn(|x| self.gravel_noise(world(x, pos.x), world(z, pos.z)))),
+            stone: array::from_fn(|z| {
+                array::from_fn(|x| {
+                    let p_col = DVec3::new(world(x, pos.x) as f64, 0.0, world(z, pos.z) as f64);
+                    noise01(&self.stone, p_col / self.terrain.stone_scale)
+                })
+            }),
+            surface: [[None; 16]; 16],
         }
+    }
+
+    /// `biome_at`, from `columns` when the column is in their chunk.
+    fn cached_biome(&self, columns: &ChunkColumns, x: i32, z: i32) -> Biome {
+        match columns.index(x, z) {
+            Some((x, z)) => columns.climate[z][x].biome(),
+            None => self.biome_at(x, z),
+        }
+    }
+
+    /// `surface_height`, from `columns` when the column is in their chunk.
+    fn cached_surface(&self, columns: &ChunkColumns, x: i32, z: i32) -> Option<i32> {
+        match columns.index(x, z) {
+            Some((x, z)) => columns.surface[z][x],
+            None => self.surface_height(x, z),
+        }
+    }
+
+    fn generate_terrain(&self, columns: &mut ChunkColumns) -> UnloadedChunk {
+        let terrain = &self.terrain;
+        let pos = columns.pos;
+        let mut chunk = UnloadedChunk::with_height(self.height);
+        let sea_level = terrain.sea_level as i32;
+
+        // Nothing is above the highest terrain or the water, so those blocks
+        // are left as the air the chunk starts out as. Sections that are
+        // wholly between that terrain and the water's surface are filled
+        // with water in one go, leaving out the top layer of water, which
+        // might be ice.
+        let highest = columns.bounds.iter().flatten().map(|(_, upper)| upper.ceil() as i32).max().unwrap_or(0);
+        let water_sections = ((highest + 1).max(0) + 15) / 16..(sea_level - 1).max(0) / 16;
+        for sect_y in water_sections.clone() {
+            chunk.fill_block_state_section(sect_y as u32, BlockState::WATER);
+        }
+        let water_filled = water_sections.start * 16..water_sections.end * 16;
 
         for z in 0u32..16u32 {
             let z = z as usize;
             let world_z_base = (pos.z * 16) + z as i32;
-            
+        
             for x in 0u32..16u32 {
                 let x = x as usize;
                 let world_x = (pos.x * 16) + x as i32;
-                let p_col = DVec3::new(world_x as f64, 0.0, world_z_base as f64);
 
-                let gravel_noise = gravel_noise_cache[z][x];
-                let gravel_height = 55 - 1 - (gravel_noise * 6.0).floor() as i32;
+                let (gravel_height, beach_top) = self.surface_lines(columns.gravel[z][x]);
 
-                let stone_noise = stone_noise_cache[z][x];
+                let stone_noise = columns.stone[z][x];
                 let mut surface_depth = (stone_noise * 5.0).max(1.0).round() as u32;
 
-                let hilly = lerp(0.1, 1.0, noise01(&state.hilly, p_col / 400.0)).powi(2);
-                let base_terrain_height = SEA_LEVEL as f64; // Start terrain above sea level
-                let lower = base_terrain_height + 15.0 + 100.0 * hilly;
-                let upper = lower + 100.0 * hilly;
+                let climate = columns.climate[z][x];
+                let biome = climate.biome();
+                let (lower, upper) = columns.bounds[z][x];
+                let top = (upper.ceil() as i32).max(sea_level - 1).min(self.height as i32 - 1);
 
                 let mut in_terrain = false;
-                let mut all_air = true;
+                let mut column = SurfaceContext {
+                    biome,
+                    depth: 0,
+                    y: 0,
+                    surface_y: 0,
+                    sea_level,
+                    grass_level: terrain.grass_level,
+                    gravel_height,
+                    beach: false,
+                };
+                // Only the highest surface is open to the sky, not ground under overhangs
+                let mut under_sky = true;
 
                 let x_u32 = x as u32;
                 let z_u32 = z as u32;
 
-                for y in (0..HEIGHT as i32).rev() {
+                for y in (0..=top).rev() {
+                    if water_filled.contains(&y) {
+                        continue;
+                    }
                     let p_y = y as f64;
-                    let in_terrain_result = in_column_optimized(&state, world_x as f64, p_y, world_z_base as f64, lower, upper);
-                    
+                    let in_terrain_result = self.in_column(world_x as f64, p_y, world_z_base as f64, lower, upper);
+                
                     if in_terrain_result {
-                        all_air = false;
-                        
                         if !in_terrain {
                             in_terrain = true;
-                            let block = if y < gravel_height {
-                                BlockState::GRAVEL
-                            } else if y < 55 {
-                                BlockState::DIRT
-                            } else {
-                                BlockState::GRASS_BLOCK
+                            column = SurfaceContext {
+                                depth: 0,
+                                y,
+                                surface_y: y,
+                                beach: under_sky && (sea_level - BEACH_DEPTH..=beach_top).contains(&y),
+                                ..column
                             };
+                            let mut block = surface::surface_block(&self.surface, &column);
+                            // Snow settles on open ground that's above the water
+                            if under_sky && y >= sea_level && y + 1 < self.height as i32 && climate.freezes_at(y, sea_level) {
+                                if block == BlockState::GRASS_BLOCK {
+                                    block = block.set(PropName::Snowy, PropValue::True);
+                                }
+                                chunk.set_block_state(x_u32, y as u32 + 1, z_u32, BlockState::SNOW);
+                            }
                             chunk.set_block_state(x_u32, y as u32, z_u32, block);
+                            if under_sky {
+                                // The same as `surface_height` finds for the column
+                                columns.surface[z][x] = (y >= sea_level).then_some(y);
+                            }
+                            under_sky = false;
                             surface_depth = (stone_noise * 5.0).max(1.0).round() as u32;
                         } else if surface_depth > 0 {
                             surface_depth -= 1;
-                            let block = if y < gravel_height {
-                                BlockState::GRAVEL
+                            column = SurfaceContext { depth: column.depth + 1, y, ..column };
+                            let block = surface::surface_block(&self.surface, &column);
+                            chunk.set_block_state(x_u32, y as u32, z_u32, block);
+                        } else {
+                            let block = if self.is_deepslate(world_x, y, world_z_base) {
+                                BlockState::DEEPSLATE
                             } else {
-                                BlockState::DIRT
+                                BlockState::STONE
                             };
                             chunk.set_block_state(x_u32, y as u32, z_u32, block);
-                        } else {
-                            chunk.set_block_state(x_u32, y as u32, z_u32, BlockState::STONE);
                         }
                     } else {
                         in_terrain = false;
-                        
-                        if y < SEA_LEVEL as i32 {
+                    
+                        if y == sea_level - 1 && climate.freezes_at(y, sea_level) {
+                            chunk.set_block_state(x_u32, y as u32, z_u32, BlockState::ICE);
+                        } else if y < sea_level {
                             chunk.set_block_state(x_u32, y as u32, z_u32, BlockState::WATER);
-                        } else {
-                            chunk.set_block_state(x_u32, y as u32, z_u32, BlockState::AIR);
                         }
                     }
 
                     // Generate caves below the terrain but above sea level
                     // TODO: caves
                     // if y >= SEA_LEVEL as i32&& y < lower as i32 {
-                    //     let cave_noise = fbm(&state.cave, DVec3::new(world_x as f64, y as f64, world_z_base as f64) / 50.0, 3, 2.0, 0.5);
+                    //     let cave_noise = fbm(&self.cave, DVec3::new(world_x as f64, y as f64, world_z_base as f64) / 50.0, 3, 2.0, 0.5);
                     //     if cave_noise < 0.3 {
                     //         chunk.set_block_state(x_u32, y as u32, z_u32, BlockState::AIR);
                     //     }
                     // }
-
-                    if y > 1 && chunk.block_state(x_u32, y as u32, z_u32) == BlockState::GRASS_BLOCK {
-                        let py = y as u32 + 1;
-                        if py + 1 < HEIGHT {
-                            let density = fbm(&state.grass, DVec3::new(world_x as f64, y as f64, world_z_base as f64) / 5.0, 4, 2.0, 0.7);
-                            if density > 0.55 {
-                                if density > 0.7 {
-                                    let upper = BlockState::TALL_GRASS.set(PropName::Half, PropValue::Upper);
-                                    let lower = BlockState::TALL_GRASS.set(PropName::Half, PropValue::Lower);
-                                    chunk.set_block_state(x_u32, py + 1, z_u32, upper);
-                                    chunk.set_block_state(x_u32, py, z_u32, lower);
-                                } else {
-                                    chunk.set_block_state(x_u32, py, z_u32, BlockState::GRASS);
-                                }
-                            }
-                        }
-                    }
                 }
 
-                if all_air && lower > SEA_LEVEL {
-                    continue;
+                for y in 0..BEDROCK_LAYERS {
+                    if self.is_bedrock(world_x, y, world_z_base) {
+                        chunk.set_block_state(x_u32, y as u32, z_u32, BlockState::BEDROCK);
+                    }
                 }
             }
         }
 
-        if let Err(e) = state.sender.try_send((pos, chunk)) {
-            info!("Failed to send finished chunk {:?}: {}", pos, e);
+        self.write_biomes(columns, &mut chunk);
+        chunk
+    }
+
+    /// Whether a block of the floor is bedrock. The bottom layer always is,
+    /// and each one above is less likely to be, so the top edge is jagged.
+    fn is_bedrock(&self, x: i32, y: i32, z: i32) -> bool {
+        let roll = (region_hash(self.seed, x, z, BEDROCK_SALT ^ ((y as u64) << 16)) >> 11) as f64 / (1u64 << 53) as f64;
+        y == 0 || roll < 1.0 - y as f64 / BEDROCK_LAYERS as f64
+    }
+
+    /// The heights gravel starts under and beaches reach up to in a column
+    /// with this gravel noise.
+    fn surface_lines(&self, gravel_noise: f64) -> (i32, i32) {
+        let gravel_height = self.terrain.grass_level - 1 - (gravel_noise * self.terrain.gravel_depth).floor() as i32;
+        // The same noise makes the edge of beaches wander up and down the shore
+        let beach_top = self.terrain.sea_level as i32 + 1 + (gravel_noise * BEACH_HEIGHT).round() as i32;
+        (gravel_height, beach_top)
+    }
+
+    /// The noise the gravel and beach lines of a column follow.
+    fn gravel_noise(&self, x: i32, z: i32) -> f64 {
+        let p_col = DVec3::new(x as f64, 0.0, z as f64);
+        fbm(&self.gravel, p_col / self.terrain.gravel_scale, self.terrain.gravel_octaves, 2.0, 0.5)
+    }
+
+    /// The noise grass grows thicker and taller where it's higher.
+    fn grass_noise(&self, x: i32, y: i32, z: i32) -> f64 {
+        let p = DVec3::new(x as f64, y as f64, z as f64);
+        fbm(&self.grass, p / self.terrain.grass_scale, self.terrain.grass_octaves, 2.0, 0.7)
+    }
+
+    /// The block the surface rules put on top of a column whose surface is
+    /// at `y`, assuming it's open to the sky.
+    fn top_block(&self, columns: &ChunkColumns, x: i32, z: i32, y: i32) -> BlockState {
+        let gravel_noise = match columns.index(x, z) {
+            Some((x, z)) => columns.gravel[z][x],
+            None => self.gravel_noise(x, z),
+        };
+        let (gravel_height, beach_top) = self.surface_lines(gravel_noise);
+        let sea_level = self.terrain.sea_level as i32;
+        let context = SurfaceContext {
+            biome: self.cached_biome(columns, x, z),
+            depth: 0,
+            y,
+            surface_y: y,
+            sea_level,
+            grass_level: self.terrain.grass_level,
+            gravel_height,
+            beach: (sea_level - BEACH_DEPTH..=beach_top).contains(&y),
+        };
+        surface::surface_block(&self.surface, &context)
+    }
+
+    /// Whether stone at this block is deepslate instead.
+    fn is_deepslate(&self, x: i32, y: i32, z: i32) -> bool {
+        let level = self.terrain.deepslate_level;
+        if y >= level + DEEPSLATE_BLEND {
+            return false;
         }
+        let roll = (region_hash(self.seed, x, z, DEEPSLATE_SALT ^ ((y as u64) << 16)) >> 11) as f64 / (1u64 << 53) as f64;
+        roll < (level + DEEPSLATE_BLEND - y) as f64 / (2 * DEEPSLATE_BLEND) as f64
     }
-    info!("Chunk worker thread shutting down.");
-}
 
-fn in_column_optimized(state: &ChunkWorkerState, world_x: f64, y: f64, world_z: f64, lower: f64, upper: f64) -> bool {
-    if y <= lower {
-        true
-    } else if y >= upper {
-        false
-    } else {
-        let density = 1.0 - lerpstep(lower, upper, y);
-        let n = fbm(&state.density, DVec3::new(world_x, y, world_z) / 100.0, 4, 2.0, 0.5);
-        n < density
+    /// Sets the biome of every 4x4 column of biome cells, from the block in
+    /// its middle, so clients colour grass and leaves to match.
+    fn write_biomes(&self, columns: &ChunkColumns, chunk: &mut UnloadedChunk) {
+        let sea_level = self.terrain.sea_level as i32;
+        for cell_z in 0..4 {
+            for cell_x in 0..4 {
+                let climate = columns.climate[cell_z as usize * 4 + 2][cell_x as usize * 4 + 2];
+                let biome = climate.biome().index();
+                for cell_y in 0..self.height / 4 {
+                    // Cells where snow settles get the snowy biome, so it snows there
+                    let id = match climate.freezes_at(cell_y as i32 * 4 + 2, sea_level) {
+                        true => self.snowy_biome_ids[biome],
+                        false => self.biome_ids[biome],
+                    };
+                    chunk.set_biome(cell_x, cell_y, cell_z, id);
+                }
+            }
+        }
+    }
+
+    fn in_column(&self, world_x: f64, y: f64, world_z: f64, lower: f64, upper: f64) -> bool {
+        if y <= lower {
+            true
+        } else if y >= upper {
+            false
+        } else {
+            let density = 1.0 - lerpstep(lower, upper, y);
+            let n = fbm(
+                &self.density,
+                DVec3::new(world_x, y, world_z) / self.terrain.density_scale,
+                self.terrain.density_octaves,
+                self.terrain.density_lacunarity,
+                self.terrain.density_persistence,
+            );
+            n < density
+        }
     }
 }
 
@@ -772,6 +1911,17 @@ fn has_terrain_at(state: &ChunkWorkerState, p: DVec3) -> bool {
     }
 }
 */
+/// Columns in square rings around `x`, `z`, `step` blocks apart, nearest
+/// ring first, out to `radius`.
+fn spiral(x: i32, z: i32, radius: i32, step: i32) -> impl Iterator<Item = (i32, i32)> {
+    (0..=radius / step).flat_map(move |ring| {
+        let r = ring * step;
+        (-r..=r)
+            .step_by(step as usize)
+            .flat_map(move |d| [(x + d, z - r), (x + d, z + r), (x - r, z + d), (x + r, z + d)])
+    })
+}
+
 fn lerp(a: f64, b: f64, t: f64) -> f64 {
     a * (1.0 - t) + b * t
 }
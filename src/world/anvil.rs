@@ -0,0 +1,127 @@
+// src/world/anvil.rs
+//
+// Serving chunks from an existing vanilla world. Valence's anvil plugin reads
+// the region files; in `generate` mode anything it can't find is handed to
+// the normal generator, in `load` mode it's left empty so the map is served
+// exactly as it is.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use tracing::{info, warn};
+use valence::anvil::{AnvilLevel, ChunkLoadEvent, ChunkLoadStatus, RegionFolder};
+use valence::nbt::{Compound, Value};
+use valence::prelude::*;
+
+use super::remap::Remapper;
+use super::storage::{read_chunk, ChunkRestored, ChunkSaver};
+use super::{GameState, Overworld};
+use crate::components::logging::WORLDGEN;
+
+/// Direct access to the imported world's region files, for chunks valence's
+/// own loader rejects.
+#[derive(Resource)]
+pub struct ImportedRegions {
+    pub folder: RegionFolder,
+    /// Whether chunks the world doesn't have are generated or left empty.
+    pub generate_missing: bool,
+}
+
+impl ImportedRegions {
+    pub fn new(path: &Path, generate_missing: bool) -> Self {
+        Self {
+            folder: RegionFolder::new(path.join("region")),
+            generate_missing,
+        }
+    }
+}
+
+/// Opens the vanilla world at `path` if it looks like one (has a `region`
+/// folder).
+pub fn open_level(path: &Path, biomes: &BiomeRegistry) -> Option<AnvilLevel> {
+    if !path.join("region").is_dir() {
+        warn!(target: WORLDGEN, "{} isn't a vanilla world (no region folder), generating instead", path.display());
+        return None;
+    }
+    info!(target: WORLDGEN, "Importing vanilla world from {}", path.display());
+    Some(AnvilLevel::new(path, biomes))
+}
+
+/// Reads the world spawn from the level's `level.dat`.
+pub fn read_level_spawn(path: &Path) -> Option<DVec3> {
+    let mut bytes = Vec::new();
+    GzDecoder::new(File::open(path.join("level.dat")).ok()?)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    let (root, _) = valence::nbt::from_binary::<String>(&mut bytes.as_slice()).ok()?;
+    let Some(Value::Compound(data)) = root.get("Data") else {
+        return None;
+    };
+    let coord = |data: &Compound, key: &str| match data.get(key) {
+        Some(Value::Int(v)) => Some(*v),
+        _ => None,
+    };
+    Some(DVec3::new(
+        coord(data, "SpawnX")? as f64 + 0.5,
+        coord(data, "SpawnY")? as f64,
+        coord(data, "SpawnZ")? as f64 + 0.5,
+    ))
+}
+
+// Chunks missing from the imported world are generated like any other. Ones
+// that failed to parse (usually blocks from another version) get a second try
+// through the remapper first. Chunks edited since the import are loaded from
+// our own saves instead.
+pub fn generate_missing_anvil_chunks(
+    mut events: EventReader<ChunkLoadEvent>,
+    mut state: ResMut<GameState>,
+    mut layers: Query<&mut ChunkLayer, With<Overworld>>,
+    mut regions: Option<ResMut<ImportedRegions>>,
+    remapper: Res<Remapper>,
+    biomes: Res<BiomeRegistry>,
+    mut saver: ResMut<ChunkSaver>,
+    mut restored: EventWriter<ChunkRestored>,
+) {
+    let generate_missing = regions.as_ref().is_none_or(|regions| regions.generate_missing);
+    for event in events.read() {
+        if let Ok(mut layer) = layers.get_single_mut() {
+            saver.ensure_written(event.pos);
+            if let Some(saved) = read_chunk(&saver.dir, event.pos) {
+                let block_entities = ChunkRestored::new(event.pos, layer.min_y(), &saved.block_entities);
+                layer.insert_chunk(event.pos, saved.chunk);
+                if let Some(block_entities) = block_entities {
+                    restored.send(block_entities);
+                }
+                continue;
+            }
+        }
+        match &event.status {
+            ChunkLoadStatus::Success { .. } => continue,
+            ChunkLoadStatus::Empty => {}
+            ChunkLoadStatus::Failed(e) => {
+                if let (Some(regions), Ok(mut layer)) = (regions.as_mut(), layers.get_single_mut()) {
+                    let biome = biomes.index_of(ident!("plains")).unwrap_or_default();
+                    let (min_y, height) = (layer.min_y(), layer.height());
+                    match remapper.load_chunk(&mut regions.folder, event.pos, min_y, height, biome) {
+                        Ok(Some(chunk)) => {
+                            info!(target: WORLDGEN, "Loaded chunk {:?} with remapped blocks", event.pos);
+                            layer.insert_chunk(event.pos, chunk);
+                            continue;
+                        }
+                        Ok(None) => {}
+                        Err(remap_err) => warn!(target: WORLDGEN, "Remapping chunk {:?} failed too: {remap_err}", event.pos),
+                    }
+                }
+                warn!(target: WORLDGEN, "Failed to load chunk {:?} from the imported world: {e:#}", event.pos);
+            }
+        }
+        if generate_missing {
+            state.pending.entry(event.pos).or_insert(Some(0));
+        } else if let Ok(mut layer) = layers.get_single_mut() {
+            let height = layer.height();
+            layer.insert_chunk(event.pos, UnloadedChunk::with_height(height));
+        }
+    }
+}
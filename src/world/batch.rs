@@ -0,0 +1,82 @@
+// src/world/batch.rs
+//
+// Large edits to a loaded layer, like pasting a structure or blowing up a
+// crater, collected first and then written chunk by chunk. Setting blocks one
+// at a time through the layer looks up the chunk again for every block and
+// sends blocks that end up the same as before. A batch writes each chunk's
+// changes together, leaves out the ones that don't change anything and fills
+// a section in one go when it's replaced whole, so each changed section goes
+// out to clients as a single multi-block update.
+
+use std::collections::HashMap;
+
+use valence::prelude::*;
+
+const SECTION_BLOCKS: usize = 16 * 16 * 16;
+
+/// Block changes waiting to be written to a layer. A later change to the
+/// same block replaces an earlier one.
+#[derive(Default)]
+pub struct BlockBatch {
+    changes: HashMap<ChunkPos, HashMap<BlockPos, BlockState>>,
+}
+
+impl BlockBatch {
+    pub fn set(&mut self, pos: BlockPos, state: BlockState) {
+        self.changes.entry(ChunkPos::from(pos)).or_default().insert(pos, state);
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Writes the changes and returns how many blocks actually changed.
+    /// Changes in chunks that aren't loaded, or above or below the world,
+    /// are dropped; `PendingBlocks` is for changes that have to wait.
+    pub fn apply(self, layer: &mut ChunkLayer) -> usize {
+        let (min_y, height) = (layer.min_y(), layer.height() as i32);
+        let mut changed = 0;
+        for (chunk_pos, blocks) in self.changes {
+            let Some(chunk) = layer.chunk_mut(chunk_pos) else {
+                continue;
+            };
+            let mut sections: HashMap<u32, Vec<([u32; 3], BlockState)>> = HashMap::new();
+            for (pos, state) in blocks {
+                let y = pos.y - min_y;
+                if (0..height).contains(&y) {
+                    let local = [pos.x.rem_euclid(16) as u32, y as u32, pos.z.rem_euclid(16) as u32];
+                    sections.entry(y as u32 / 16).or_default().push((local, state));
+                }
+            }
+
+            for (sect_y, blocks) in sections {
+                let differs = |&([x, y, z], state): &([u32; 3], BlockState)| chunk.block_state(x, y, z) != state;
+                let count = blocks.iter().filter(|block| differs(block)).count();
+                let state = blocks[0].1;
+                // Filling doesn't clear block entities, so sections with any
+                // go block by block
+                let whole = blocks.len() == SECTION_BLOCKS
+                    && blocks.iter().all(|&([x, y, z], new)| {
+                        new == state && chunk.block_state(x, y, z).block_entity_kind().is_none()
+                    });
+                if whole && state.block_entity_kind().is_none() {
+                    if count > 0 {
+                        chunk.fill_block_state_section(sect_y, state);
+                    }
+                } else {
+                    for ([x, y, z], state) in blocks {
+                        if chunk.block_state(x, y, z) != state {
+                            chunk.set_block(x, y, z, state);
+                        }
+                    }
+                }
+                changed += count;
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,187 @@
+// src/world/biomes.rs
+//
+// Which biome each column of generated terrain is in. Two low-frequency
+// noises give every column a temperature and a humidity, and the hilliness
+// the terrain already uses says where mountains are: hilly columns are
+// mountains, hot and dry ones desert, humid ones forest and the rest plains.
+// Two more say where the land is: columns with little continentalness are
+// ocean, and a thin band down the middle of the river noise is river.
+//
+// Biomes shape the terrain as well as colouring it. Deserts are flatter,
+// mountains taller and forests have more trees. What their surfaces are made
+// of is up to the surface rules in `surface.rs`.
+// Height changes are weighted by how far into a biome a column is, so borders
+// slope instead of stepping. Oceans and rivers pull the ground down below sea
+// level, so coasts and river banks slope down into the water.
+//
+// Temperature also drops with height. Where it's below freezing, grass is
+// covered in snow and the sea freezes over, so cold regions and high peaks
+// are white whatever biome they're in. Clients are sent a snowy version of
+// the biome there, so rain falls as snow on them.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use valence::prelude::*;
+
+/// Climate values at which a column is halfway into a biome.
+const DESERT_TEMPERATURE: f64 = 0.58;
+const DESERT_HUMIDITY: f64 = 0.45;
+const FOREST_HUMIDITY: f64 = 0.55;
+const MOUNTAIN_HILLINESS: f64 = 0.62;
+const OCEAN_CONTINENTALNESS: f64 = 0.35;
+/// How close the river noise has to be to its middle for a column to be in
+/// a river, and how much further its banks reach.
+const RIVER_WIDTH: f64 = 0.02;
+const RIVER_BANK_WIDTH: f64 = 0.04;
+/// How far either side of those values the transition is spread.
+const BLEND_WIDTH: f64 = 0.08;
+/// Deserts lose this much of their hill height, mountains gain this much.
+const DESERT_FLATTENING: f64 = 0.6;
+const MOUNTAIN_BOOST: f64 = 0.6;
+/// Below this temperature, snow settles and water freezes.
+const FREEZING_TEMPERATURE: f64 = 0.3;
+/// How much colder it is for each block above sea level.
+const ALTITUDE_COOLING: f64 = 0.004;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Biome {
+    Plains,
+    Forest,
+    Desert,
+    Mountains,
+    Ocean,
+    River,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 6] = [
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Desert,
+        Biome::Mountains,
+        Biome::Ocean,
+        Biome::River,
+    ];
+
+    /// The vanilla biome sent to clients, which sets grass and foliage colour.
+    pub fn ident(self) -> &'static str {
+        match self {
+            Biome::Plains => "minecraft:plains",
+            Biome::Forest => "minecraft:forest",
+            Biome::Desert => "minecraft:desert",
+            Biome::Mountains => "minecraft:windswept_hills",
+            Biome::Ocean => "minecraft:ocean",
+            Biome::River => "minecraft:river",
+        }
+    }
+
+    /// The vanilla biome sent where it's cold enough for snow, so clients
+    /// show rain there as snowfall. Deserts never get that cold.
+    pub fn snowy_ident(self) -> &'static str {
+        match self {
+            Biome::Plains => "minecraft:snowy_plains",
+            Biome::Forest => "minecraft:snowy_taiga",
+            Biome::Desert => "minecraft:desert",
+            Biome::Mountains => "minecraft:snowy_slopes",
+            Biome::Ocean => "minecraft:frozen_ocean",
+            Biome::River => "minecraft:frozen_river",
+        }
+    }
+
+    /// The biome whose vanilla name is `name`, with or without `minecraft:`.
+    pub fn from_ident(name: &str) -> Option<Biome> {
+        let name = name.strip_prefix("minecraft:").unwrap_or(name);
+        Biome::ALL.into_iter().find(|biome| biome.ident().strip_prefix("minecraft:") == Some(name))
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Adjusts the chance of a tree from the placement noise.
+    pub fn tree_chance(self, chance: f64) -> f64 {
+        match self {
+            Biome::Plains => chance * 0.3,
+            Biome::Forest => chance.max(0.4),
+            Biome::Desert => 0.0,
+            Biome::Mountains => chance * 0.5,
+            Biome::Ocean | Biome::River => 0.0,
+        }
+    }
+}
+
+/// Biomes are written by their vanilla name, as in `ident`.
+impl<'de> Deserialize<'de> for Biome {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Biome::from_ident(&name).ok_or_else(|| D::Error::custom(format!("unknown biome {name:?}")))
+    }
+}
+
+/// A column's climate, every value from 0 to 1.
+#[derive(Clone, Copy, Debug)]
+pub struct Climate {
+    pub temperature: f64,
+    pub humidity: f64,
+    /// The raw hilliness noise, before the preset shapes it.
+    pub hilliness: f64,
+    /// How far inland a column is. Low values are ocean.
+    pub continentalness: f64,
+    /// Rivers run along the middle of this noise, at 0.5.
+    pub river: f64,
+}
+
+/// 0 well below `edge`, 1 well above it, 0.5 right on it.
+fn ramp(value: f64, edge: f64) -> f64 {
+    ((value - edge) / (2.0 * BLEND_WIDTH) + 0.5).clamp(0.0, 1.0)
+}
+
+impl Climate {
+    pub fn desert_weight(&self) -> f64 {
+        ramp(self.temperature, DESERT_TEMPERATURE) * (1.0 - ramp(self.humidity, DESERT_HUMIDITY))
+    }
+
+    pub fn mountain_weight(&self) -> f64 {
+        ramp(self.hilliness, MOUNTAIN_HILLINESS)
+    }
+
+    /// 1 out at sea, 0 inland.
+    pub fn ocean_weight(&self) -> f64 {
+        1.0 - ramp(self.continentalness, OCEAN_CONTINENTALNESS)
+    }
+
+    /// 1 in a river, falling to 0 across its banks. Rivers fade out as they
+    /// reach the sea.
+    pub fn river_weight(&self) -> f64 {
+        let distance = (self.river - 0.5).abs();
+        let weight = (1.0 - (distance - RIVER_WIDTH) / RIVER_BANK_WIDTH).clamp(0.0, 1.0);
+        weight * (1.0 - self.ocean_weight())
+    }
+
+    /// Whether it's cold enough for snow and ice at `y`.
+    pub fn freezes_at(&self, y: i32, sea_level: i32) -> bool {
+        let cooling = (y - sea_level).max(0) as f64 * ALTITUDE_COOLING;
+        self.temperature - cooling < FREEZING_TEMPERATURE
+    }
+
+    /// Multiplier for how high the terrain's hills reach.
+    pub fn height_scale(&self) -> f64 {
+        1.0 - DESERT_FLATTENING * self.desert_weight() + MOUNTAIN_BOOST * self.mountain_weight()
+    }
+
+    pub fn biome(&self) -> Biome {
+        if self.ocean_weight() >= 0.5 {
+            Biome::Ocean
+        } else if self.river_weight() >= 0.5 {
+            Biome::River
+        } else if self.mountain_weight() >= 0.5 {
+            Biome::Mountains
+        } else if self.desert_weight() >= 0.5 {
+            Biome::Desert
+        } else if self.humidity >= FOREST_HUMIDITY {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+}
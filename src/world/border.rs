@@ -0,0 +1,145 @@
+// src/world/border.rs
+//
+// The overworld's border: a square around `center` that's `radius` blocks
+// from the middle to each edge. Chunks entirely outside it aren't generated,
+// clients draw the vanilla border wall, and players who end up outside are
+// either pushed back in or take damage until they walk back, depending on
+// `mode`. Creative and spectator players are never damaged.
+//
+// `"border": { "enabled": true, "radius": 2000, "mode": "damage" }` in
+// `config/worldgen.json`.
+
+use serde::Deserialize;
+use valence::{
+    entity::living::Health,
+    prelude::*,
+    world_border::{WorldBorderBundle, WorldBorderCenter, WorldBorderLerp, WorldBorderWarnBlocks},
+};
+
+use super::Overworld;
+
+const TICKS_PER_SECOND: f32 = 20.0;
+
+/// What happens to players outside the border.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderMode {
+    /// Teleported back just inside the edge.
+    #[default]
+    Push,
+    /// Hurt every tick, more the further out they are.
+    Damage,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BorderConfig {
+    pub enabled: bool,
+    /// X and Z of the middle of the border.
+    pub center: [f64; 2],
+    /// Blocks from the center to each edge.
+    pub radius: f64,
+    pub mode: BorderMode,
+    /// Health lost per second for each block a player is past the edge.
+    pub damage_per_block: f32,
+    /// Clients tint the screen red this many blocks from the edge.
+    pub warning_blocks: i32,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            center: [0.0, 0.0],
+            radius: 5000.0,
+            mode: BorderMode::default(),
+            damage_per_block: 0.2,
+            warning_blocks: 5,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct WorldBorder(pub BorderConfig);
+
+impl WorldBorder {
+    /// How far `pos` is outside the border, or 0 inside it.
+    pub fn distance_outside(&self, pos: DVec3) -> f64 {
+        if !self.0.enabled {
+            return 0.0;
+        }
+        let [cx, cz] = self.0.center;
+        let dx = (pos.x - cx).abs() - self.0.radius;
+        let dz = (pos.z - cz).abs() - self.0.radius;
+        dx.max(dz).max(0.0)
+    }
+
+    /// Whether any of the chunk is inside the border.
+    pub fn contains_chunk(&self, pos: ChunkPos) -> bool {
+        if !self.0.enabled {
+            return true;
+        }
+        let [cx, cz] = self.0.center;
+        let (min_x, min_z) = ((pos.x * 16) as f64, (pos.z * 16) as f64);
+        min_x + 16.0 > cx - self.0.radius
+            && min_x < cx + self.0.radius
+            && min_z + 16.0 > cz - self.0.radius
+            && min_z < cz + self.0.radius
+    }
+
+    /// The closest point to `pos` that's half a block inside the border.
+    pub fn clamp(&self, pos: DVec3) -> DVec3 {
+        let [cx, cz] = self.0.center;
+        let inner = (self.0.radius - 0.5).max(0.0);
+        DVec3::new(pos.x.clamp(cx - inner, cx + inner), pos.y, pos.z.clamp(cz - inner, cz + inner))
+    }
+
+    /// The components that make clients draw the border.
+    pub fn bundle(&self) -> WorldBorderBundle {
+        let [x, z] = self.0.center;
+        let diameter = self.0.radius * 2.0;
+        WorldBorderBundle {
+            center: WorldBorderCenter { x, z },
+            lerp: WorldBorderLerp {
+                current_diameter: diameter,
+                target_diameter: diameter,
+                remaining_ticks: 0,
+            },
+            warn_blocks: WorldBorderWarnBlocks(self.0.warning_blocks),
+            ..Default::default()
+        }
+    }
+}
+
+// Pushes back or hurts players outside the overworld's border
+pub fn enforce_world_border(
+    mut clients: Query<(&mut Client, &mut Position, &mut Health, &GameMode, &VisibleChunkLayer)>,
+    layers: Query<Entity, With<Overworld>>,
+    border: Option<Res<WorldBorder>>,
+) {
+    let (Ok(overworld), Some(border)) = (layers.get_single(), border) else {
+        return;
+    };
+    if !border.0.enabled {
+        return;
+    }
+
+    for (mut client, mut pos, mut health, game_mode, visible_layer) in &mut clients {
+        let outside = border.distance_outside(pos.0);
+        if visible_layer.0 != overworld || outside <= 0.0 || *game_mode == GameMode::Spectator {
+            continue;
+        }
+        match border.0.mode {
+            BorderMode::Push => {
+                let inside = border.clamp(pos.0);
+                pos.set(inside);
+                client.send_action_bar_message("You can't go past the world border".color(Color::RED));
+            }
+            BorderMode::Damage if *game_mode != GameMode::Creative => {
+                let damage = border.0.damage_per_block * outside as f32 / TICKS_PER_SECOND;
+                health.0 = (health.0 - damage).max(0.0);
+            }
+            BorderMode::Damage => {}
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// src/world/deferred.rs
+//
+// Block changes that may land in chunks which aren't loaded. Explosions,
+// growing trees and anything else that reaches across a chunk edge can call
+// `set_block_deferred`: the change happens immediately if the chunk is
+// loaded, otherwise it's kept and applied as soon as the chunk is generated
+// or loaded.
+
+use std::collections::HashMap;
+
+use tracing::debug;
+use valence::prelude::*;
+
+use super::storage::ChunkSaver;
+use super::Overworld;
+use crate::components::logging::WORLDGEN;
+
+/// Decides whether a queued change still applies to the block that's there
+/// once the chunk loads.
+pub type BlockFilter = fn(BlockState) -> bool;
+
+struct PendingChange {
+    pos: BlockPos,
+    state: BlockState,
+    filter: Option<BlockFilter>,
+}
+
+#[derive(Resource, Default)]
+pub struct PendingBlocks {
+    changes: HashMap<(Entity, ChunkPos), Vec<PendingChange>>,
+}
+
+impl PendingBlocks {
+    /// Sets the block now if its chunk is loaded, otherwise once it is.
+    pub fn set_block_deferred(&mut self, layer_entity: Entity, layer: &mut ChunkLayer, pos: BlockPos, state: BlockState) {
+        self.change(layer_entity, layer, pos, state, None);
+    }
+
+    /// Like `set_block_deferred`, but only replaces blocks `filter` accepts,
+    /// checked against whatever is there when the change is applied.
+    pub fn replace_block_deferred(
+        &mut self,
+        layer_entity: Entity,
+        layer: &mut ChunkLayer,
+        pos: BlockPos,
+        state: BlockState,
+        filter: BlockFilter,
+    ) {
+        self.change(layer_entity, layer, pos, state, Some(filter));
+    }
+
+    fn change(
+        &mut self,
+        layer_entity: Entity,
+        layer: &mut ChunkLayer,
+        pos: BlockPos,
+        state: BlockState,
+        filter: Option<BlockFilter>,
+    ) {
+        match layer.block(pos) {
+            Some(current) => {
+                if filter.is_none_or(|filter| filter(current.state)) {
+                    layer.set_block(pos, state);
+                }
+            }
+            // Below or above the world isn't unloaded, it just doesn't exist
+            None if pos.y < layer.min_y() || pos.y >= layer.min_y() + layer.height() as i32 => {}
+            None => self
+                .changes
+                .entry((layer_entity, ChunkPos::from(pos)))
+                .or_default()
+                .push(PendingChange { pos, state, filter }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Block changes still waiting for their chunk.
+    pub fn queued_changes(&self) -> usize {
+        self.changes.values().map(Vec::len).sum()
+    }
+}
+
+// Runs after new chunks are inserted, so queued changes land in the same tick
+// the chunk appears.
+pub fn apply_pending_blocks(
+    mut pending: ResMut<PendingBlocks>,
+    mut layers: Query<(&mut ChunkLayer, Has<Overworld>)>,
+    mut saver: ResMut<ChunkSaver>,
+) {
+    if pending.is_empty() {
+        return;
+    }
+    pending.changes.retain(|(layer_entity, chunk_pos), changes| {
+        let Ok((mut layer, overworld)) = layers.get_mut(*layer_entity) else {
+            // The layer is gone, so the changes can never apply
+            return false;
+        };
+        if layer.chunk(*chunk_pos).is_none() {
+            return true;
+        }
+        for change in changes.drain(..) {
+            let Some(current) = layer.block(change.pos) else {
+                continue;
+            };
+            if change.filter.is_none_or(|filter| filter(current.state)) {
+                layer.set_block(change.pos, change.state);
+            }
+        }
+        if overworld {
+            saver.mark_dirty(*chunk_pos);
+        }
+        debug!(target: WORLDGEN, "applied deferred block changes in {chunk_pos:?}");
+        false
+    });
+}
@@ -0,0 +1,100 @@
+// src/world/events.rs
+//
+// Anything players would see or hear happening in the world goes through
+// `WorldEvent`. Systems send one instead of playing effects themselves;
+// `broadcast_world_events` turns it into sounds and particles for nearby
+// clients, and anything else that cares (scripting, anti-cheat, sculk
+// sensors) can read the same events.
+
+use valence::protocol::packets::play::WorldEventS2c;
+use valence::protocol::WritePacket;
+use valence::prelude::*;
+
+/// vanilla's "block broken" level event: break particles and sound.
+const LEVEL_EVENT_BLOCK_BREAK: i32 = 2001;
+
+#[derive(Clone, Copy, Debug)]
+pub enum WorldEventKind {
+    BlockBroken { state: BlockState },
+    BlockPlaced { state: BlockState },
+    Explosion { power: f32 },
+    /// A door, trapdoor, gate, lever or button was opened/pressed (`on`) or
+    /// closed/released.
+    BlockToggled { state: BlockState, on: bool },
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct WorldEvent {
+    pub layer: Entity,
+    pub pos: DVec3,
+    /// Who caused it, if anyone. Their client already predicted the effect,
+    /// so it isn't sent back to them.
+    pub source: Option<Entity>,
+    pub kind: WorldEventKind,
+}
+
+impl WorldEvent {
+    pub fn at_block(layer: Entity, pos: BlockPos, source: Option<Entity>, kind: WorldEventKind) -> Self {
+        Self {
+            layer,
+            pos: DVec3::new(pos.x as f64 + 0.5, pos.y as f64 + 0.5, pos.z as f64 + 0.5),
+            source,
+            kind,
+        }
+    }
+}
+
+fn toggle_sound(kind: BlockKind, on: bool) -> Sound {
+    let name = kind.to_str();
+    match (name, on) {
+        ("lever", _) => Sound::BlockLeverClick,
+        _ if name.ends_with("_trapdoor") && on => Sound::BlockWoodenTrapdoorOpen,
+        _ if name.ends_with("_trapdoor") => Sound::BlockWoodenTrapdoorClose,
+        _ if name.ends_with("_door") && on => Sound::BlockWoodenDoorOpen,
+        _ if name.ends_with("_door") => Sound::BlockWoodenDoorClose,
+        _ if name.ends_with("_fence_gate") && on => Sound::BlockFenceGateOpen,
+        _ if name.ends_with("_fence_gate") => Sound::BlockFenceGateClose,
+        _ if name.starts_with("stone") || name.starts_with("polished_blackstone") => {
+            if on { Sound::BlockStoneButtonClickOn } else { Sound::BlockStoneButtonClickOff }
+        }
+        _ if on => Sound::BlockWoodenButtonClickOn,
+        _ => Sound::BlockWoodenButtonClickOff,
+    }
+}
+
+pub fn broadcast_world_events(mut events: EventReader<WorldEvent>, mut layers: Query<&mut ChunkLayer>) {
+    for event in events.read() {
+        let Ok(mut layer) = layers.get_mut(event.layer) else {
+            continue;
+        };
+        match event.kind {
+            WorldEventKind::BlockBroken { state } => {
+                let packet = WorldEventS2c {
+                    event: LEVEL_EVENT_BLOCK_BREAK,
+                    location: BlockPos::from(event.pos),
+                    data: state.to_raw() as i32,
+                    disable_relative_volume: false,
+                };
+                match event.source {
+                    Some(source) => layer.view_except_writer(event.pos, source).write_packet(&packet),
+                    None => layer.view_writer(event.pos).write_packet(&packet),
+                }
+            }
+            // The placing client plays its own sound
+            WorldEventKind::BlockPlaced { .. } => {}
+            WorldEventKind::Explosion { power } => {
+                let particle = if power >= 2.0 { Particle::ExplosionEmitter } else { Particle::Explosion };
+                layer.play_particle(&particle, false, event.pos, Vec3::ZERO, 0.0, 1);
+                layer.play_sound(Sound::EntityGenericExplode, SoundCategory::Block, event.pos, 4.0, 1.0);
+            }
+            WorldEventKind::BlockToggled { state, on } => {
+                let pitch = match (state.to_kind(), on) {
+                    (BlockKind::Lever, true) => 0.6,
+                    (BlockKind::Lever, false) => 0.5,
+                    _ => 1.0,
+                };
+                layer.play_sound(toggle_sound(state.to_kind(), on), SoundCategory::Block, event.pos, 1.0, pitch);
+            }
+        }
+    }
+}
@@ -0,0 +1,431 @@
+// src/world/features.rs
+//
+// The small things on and around the surface of generated terrain: grass,
+// flowers, ferns, dead bushes, pumpkins, sugar cane along the shore, lily
+// pads, seagrass and kelp in the water, and the odd boulder or small lake.
+//
+// Which of them a world has comes from a registry: a list of placed features,
+// each a feature, the biomes it's placed in (all of them if none are listed)
+// and a density. Density is a chance per column for plants, rolled from the
+// seed so chunks always come out the same, and a chance per chunk for
+// boulders and lakes. Features are placed in list order, and a plant only
+// goes where nothing else has grown yet. Flowers come in patches: every
+// `PATCH_SIZE` square picks one kind, so a meadow is mostly one colour.
+//
+// Boulders and lakes are small enough to be placed whole inside their chunk,
+// so unlike trees they never need to look at the neighbours. Sugar cane only
+// checks for water inside the chunk too, so shores along chunk edges have a
+// little less.
+//
+// `"features"` in `config/worldgen.json` replaces the default registry:
+// `{ "features": [ { "feature": "flowers", "density": 0.05, "biomes": ["minecraft:plains"] },
+//                  { "feature": "lake", "density": 0.1 }, { "feature": "grass", "density": 1.0 } ] }`
+
+use serde::Deserialize;
+use valence::prelude::*;
+
+use super::biomes::Biome;
+use super::structures::region_hash;
+
+const FEATURE_SALT: u64 = 0x7665_6700;
+/// Flowers in the same square of this many blocks are all one kind.
+const PATCH_SIZE: i32 = 8;
+/// Grass grows where the grass noise is above the first value, and is tall
+/// above the second.
+const GRASS_NOISE: f64 = 0.55;
+const TALL_GRASS_NOISE: f64 = 0.7;
+/// Boulders and lakes stay this far from chunk edges so they fit whole.
+const BOULDER_RADIUS: i32 = 2;
+const LAKE_RADIUS: i32 = 4;
+const LAKE_DEPTH: i32 = 3;
+/// Kelp grows in water at least this deep, and lily pads in water at most
+/// this deep.
+const KELP_DEPTH: u32 = 5;
+const LILY_PAD_DEPTH: u32 = 3;
+const MAX_SUGAR_CANE: u32 = 3;
+
+const PLAINS_FLOWERS: [BlockState; 5] = [
+    BlockState::DANDELION,
+    BlockState::POPPY,
+    BlockState::OXEYE_DAISY,
+    BlockState::AZURE_BLUET,
+    BlockState::CORNFLOWER,
+];
+const FOREST_FLOWERS: [BlockState; 4] = [
+    BlockState::LILY_OF_THE_VALLEY,
+    BlockState::ALLIUM,
+    BlockState::POPPY,
+    BlockState::DANDELION,
+];
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Short and tall grass on grass blocks, in patches from the grass noise.
+    Grass,
+    Flowers,
+    Ferns,
+    /// On sand.
+    DeadBushes,
+    Pumpkins,
+    /// On sand, dirt or grass beside water.
+    SugarCane,
+    /// Once per chunk, on dry ground.
+    Boulder,
+    /// Once per chunk, in a dip on flat enough dry ground.
+    Lake,
+    /// On shallow water.
+    LilyPads,
+    /// On the floor under water.
+    Seagrass,
+    /// Up from the floor of water deep enough for it.
+    Kelp,
+}
+
+impl Feature {
+    /// Whether the density is per chunk instead of per column.
+    fn per_chunk(self) -> bool {
+        matches!(self, Feature::Boulder | Feature::Lake)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PlacedFeature {
+    pub feature: Feature,
+    /// Chance per column, or per chunk for boulders and lakes.
+    pub density: f64,
+    /// The biomes it's placed in. None listed means every biome.
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+}
+
+impl PlacedFeature {
+    fn new(feature: Feature, density: f64, biomes: &[Biome]) -> Self {
+        Self { feature, density, biomes: biomes.to_vec() }
+    }
+
+    fn allows(&self, biome: Biome) -> bool {
+        self.biomes.is_empty() || self.biomes.contains(&biome)
+    }
+
+    /// What the generator places without a `"features"` config.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new(Feature::Lake, 0.03, &[Biome::Plains, Biome::Forest]),
+            Self::new(Feature::Boulder, 0.05, &[]),
+            Self::new(Feature::Flowers, 0.03, &[Biome::Plains, Biome::Forest]),
+            Self::new(Feature::Ferns, 0.02, &[Biome::Forest, Biome::Mountains]),
+            Self::new(Feature::Pumpkins, 0.002, &[Biome::Plains, Biome::Forest]),
+            Self::new(Feature::DeadBushes, 0.01, &[Biome::Desert]),
+            Self::new(Feature::SugarCane, 0.15, &[]),
+            Self::new(Feature::Grass, 1.0, &[]),
+            Self::new(Feature::LilyPads, 0.04, &[Biome::River]),
+            Self::new(Feature::Kelp, 0.15, &[Biome::Ocean]),
+            Self::new(Feature::Seagrass, 0.3, &[]),
+        ]
+    }
+}
+
+/// A number from 0 to 1 for a column and feature, the same every time.
+fn roll(seed: u32, x: i32, z: i32, feature: Feature) -> f64 {
+    (region_hash(seed, x, z, FEATURE_SALT + feature as u64) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The y of the highest block in a column that something could stand on, or
+/// `None` if the column is under water. Leaves don't count, so plants grow
+/// under trees.
+fn ground(chunk: &UnloadedChunk, x: u32, z: u32) -> Option<u32> {
+    for y in (0..chunk.height()).rev() {
+        let state = chunk.block_state(x, y, z);
+        if state.is_liquid() {
+            return None;
+        }
+        if state.blocks_motion() && !state.to_kind().to_str().ends_with("_leaves") {
+            return Some(y);
+        }
+    }
+    None
+}
+
+/// The y of the top of the water in a column and of the block it's on, if
+/// the column is under water.
+fn water(chunk: &UnloadedChunk, x: u32, z: u32) -> Option<(u32, u32)> {
+    let top = (0..chunk.height()).rev().find(|y| !chunk.block_state(x, *y, z).is_air())?;
+    if chunk.block_state(x, top, z) != BlockState::WATER {
+        return None;
+    }
+    let floor = (0..top).rev().find(|y| chunk.block_state(x, *y, z) != BlockState::WATER)?;
+    Some((top, floor))
+}
+
+/// Whether any block beside this one in the chunk is water.
+fn next_to_water(chunk: &UnloadedChunk, x: u32, y: u32, z: u32) -> bool {
+    [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().any(|(dx, dz)| {
+        let (x, z) = (x as i32 + dx, z as i32 + dz);
+        (0..16).contains(&x) && (0..16).contains(&z) && chunk.block_state(x as u32, y, z as u32) == BlockState::WATER
+    })
+}
+
+/// Whether a plant can go at a spot: it's air.
+fn is_open(chunk: &UnloadedChunk, x: u32, y: u32, z: u32) -> bool {
+    y < chunk.height() && chunk.block_state(x, y, z).is_air()
+}
+
+/// A column being decorated.
+struct Column {
+    x: u32,
+    z: u32,
+    world_x: i32,
+    world_z: i32,
+}
+
+/// Places everything in `features` that belongs in the chunk at `pos`.
+/// `biome_at` gives the biome of a column, `grass_noise` the grass noise at a
+/// block, and `sea_level` is in generator y.
+pub fn place_features(
+    seed: u32,
+    features: &[PlacedFeature],
+    pos: ChunkPos,
+    chunk: &mut UnloadedChunk,
+    sea_level: i32,
+    biome_at: impl Fn(i32, i32) -> Biome,
+    grass_noise: impl Fn(i32, i32, i32) -> f64,
+) {
+    for placed in features.iter().filter(|placed| placed.feature.per_chunk()) {
+        if roll(seed, pos.x, pos.z, placed.feature) >= placed.density {
+            continue;
+        }
+        let hash = region_hash(seed, pos.x, pos.z, FEATURE_SALT + placed.feature as u64 + 0x100);
+        let allowed = |x, z| placed.allows(biome_at(pos.x * 16 + x, pos.z * 16 + z));
+        match placed.feature {
+            Feature::Boulder => place_boulder(hash, chunk, sea_level, allowed),
+            Feature::Lake => place_lake(hash, chunk, sea_level, allowed),
+            _ => {}
+        }
+    }
+
+    for z in 0..16u32 {
+        for x in 0..16u32 {
+            let column = Column { x, z, world_x: pos.x * 16 + x as i32, world_z: pos.z * 16 + z as i32 };
+            let biome = biome_at(column.world_x, column.world_z);
+            for placed in features.iter().filter(|placed| !placed.feature.per_chunk() && placed.allows(biome)) {
+                let roll = roll(seed, column.world_x, column.world_z, placed.feature);
+                if roll < placed.density {
+                    place_plant(seed, placed, roll, chunk, &column, biome, &grass_noise);
+                }
+            }
+        }
+    }
+}
+
+/// Places one plant feature in a column, if the column suits it. `roll` is
+/// below the feature's density; lower rolls give bigger plants.
+fn place_plant(
+    seed: u32,
+    placed: &PlacedFeature,
+    roll: f64,
+    chunk: &mut UnloadedChunk,
+    column: &Column,
+    biome: Biome,
+    grass_noise: &impl Fn(i32, i32, i32) -> f64,
+) {
+    let (x, z) = (column.x, column.z);
+    if let Some((top, floor)) = water(chunk, x, z) {
+        let depth = top - floor;
+        let on_floor = chunk.block_state(x, floor, z);
+        let floor_open = matches!(on_floor, BlockState::SAND | BlockState::GRAVEL | BlockState::DIRT)
+            && chunk.block_state(x, floor + 1, z) == BlockState::WATER;
+        match placed.feature {
+            Feature::LilyPads if depth <= LILY_PAD_DEPTH && is_open(chunk, x, top + 1, z) => {
+                chunk.set_block_state(x, top + 1, z, BlockState::LILY_PAD);
+            }
+            Feature::Kelp if depth >= KELP_DEPTH && floor_open => {
+                // Kelp stops a block or more short of the surface
+                let length = 1 + ((depth - 2) as f64 * (1.0 - roll / placed.density)) as u32;
+                for dy in 1..length {
+                    chunk.set_block_state(x, floor + dy, z, BlockState::KELP_PLANT);
+                }
+                chunk.set_block_state(x, floor + length, z, BlockState::KELP);
+            }
+            Feature::Seagrass if floor_open => {
+                // Tall seagrass needs two blocks of water above the floor
+                if roll < placed.density / 4.0 && depth >= 2 {
+                    let tall = BlockState::TALL_SEAGRASS;
+                    chunk.set_block_state(x, floor + 1, z, tall.set(PropName::Half, PropValue::Lower));
+                    chunk.set_block_state(x, floor + 2, z, tall.set(PropName::Half, PropValue::Upper));
+                } else {
+                    chunk.set_block_state(x, floor + 1, z, BlockState::SEAGRASS);
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    let Some(y) = ground(chunk, x, z) else {
+        return;
+    };
+    if !is_open(chunk, x, y + 1, z) {
+        return;
+    }
+    let surface = chunk.block_state(x, y, z);
+    let mut two_tall = |chunk: &mut UnloadedChunk, plant: BlockState| {
+        chunk.set_block_state(x, y + 1, z, plant.set(PropName::Half, PropValue::Lower));
+        chunk.set_block_state(x, y + 2, z, plant.set(PropName::Half, PropValue::Upper));
+    };
+
+    match placed.feature {
+        Feature::Grass if surface == BlockState::GRASS_BLOCK => {
+            let noise = grass_noise(column.world_x, y as i32, column.world_z);
+            if noise > TALL_GRASS_NOISE && is_open(chunk, x, y + 2, z) {
+                two_tall(chunk, BlockState::TALL_GRASS);
+            } else if noise > GRASS_NOISE {
+                chunk.set_block_state(x, y + 1, z, BlockState::GRASS);
+            }
+        }
+        Feature::Flowers if surface == BlockState::GRASS_BLOCK => {
+            let flowers: &[BlockState] = if biome == Biome::Forest { &FOREST_FLOWERS } else { &PLAINS_FLOWERS };
+            let patch = region_hash(
+                seed,
+                column.world_x.div_euclid(PATCH_SIZE),
+                column.world_z.div_euclid(PATCH_SIZE),
+                FEATURE_SALT,
+            );
+            chunk.set_block_state(x, y + 1, z, flowers[(patch % flowers.len() as u64) as usize]);
+        }
+        Feature::Ferns if surface == BlockState::GRASS_BLOCK => {
+            // One in four is a large fern, if there's room
+            if roll < placed.density / 4.0 && is_open(chunk, x, y + 2, z) {
+                two_tall(chunk, BlockState::LARGE_FERN);
+            } else {
+                chunk.set_block_state(x, y + 1, z, BlockState::FERN);
+            }
+        }
+        Feature::Pumpkins if surface == BlockState::GRASS_BLOCK => {
+            chunk.set_block_state(x, y + 1, z, BlockState::PUMPKIN);
+        }
+        Feature::DeadBushes if surface == BlockState::SAND => {
+            chunk.set_block_state(x, y + 1, z, BlockState::DEAD_BUSH);
+        }
+        Feature::SugarCane
+            if matches!(surface, BlockState::SAND | BlockState::DIRT | BlockState::GRASS_BLOCK)
+                && next_to_water(chunk, x, y, z) =>
+        {
+            // Taller the luckier the roll
+            let tall = 1 + (MAX_SUGAR_CANE as f64 * (1.0 - roll / placed.density)) as u32;
+            for dy in 1..=tall.min(MAX_SUGAR_CANE) {
+                if !is_open(chunk, x, y + dy, z) {
+                    break;
+                }
+                chunk.set_block_state(x, y + dy, z, BlockState::SUGAR_CANE);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the ground in a column is a natural surface boulders and lakes
+/// can go on.
+fn is_natural_ground(state: BlockState) -> bool {
+    matches!(state, BlockState::GRASS_BLOCK | BlockState::DIRT | BlockState::STONE)
+}
+
+/// A lumpy ball of cobblestone and mossy cobblestone half sunk into the
+/// ground somewhere in the chunk. `allowed` says whether a column's biome
+/// has boulders.
+fn place_boulder(hash: u64, chunk: &mut UnloadedChunk, sea_level: i32, allowed: impl Fn(i32, i32) -> bool) {
+    let span = (16 - 2 * BOULDER_RADIUS) as u64;
+    let (cx, cz) = (BOULDER_RADIUS + (hash % span) as i32, BOULDER_RADIUS + ((hash >> 8) % span) as i32);
+    let Some(ground_y) = ground(chunk, cx as u32, cz as u32) else {
+        return;
+    };
+    let ground_y = ground_y as i32;
+    let on = chunk.block_state(cx as u32, ground_y as u32, cz as u32);
+    if !allowed(cx, cz) || ground_y < sea_level || !is_natural_ground(on) {
+        return;
+    }
+    let radius = 1 + ((hash >> 16) & 1) as i32;
+    if ground_y + radius >= chunk.height() as i32 {
+        return;
+    }
+
+    for dy in -radius..=radius {
+        for dz in -radius..=radius {
+            for dx in -radius..=radius {
+                let bit = ((dx + 2) * 25 + (dz + 2) * 5 + (dy + 2)) as u32 % 40;
+                // Shave off some of the outermost blocks so it isn't a cube
+                let outer = dx.abs() + dy.abs() + dz.abs() > radius + 1;
+                if outer || (dx.abs() + dy.abs() + dz.abs() == radius + 1 && (hash >> (20 + bit)) & 1 == 0) {
+                    continue;
+                }
+                let (x, y, z) = ((cx + dx) as u32, (ground_y + dy) as u32, (cz + dz) as u32);
+                let mossy = (hash >> (bit % 20)) & 3 == 0;
+                let block = if mossy { BlockState::MOSSY_COBBLESTONE } else { BlockState::COBBLESTONE };
+                chunk.set_block_state(x, y, z, block);
+            }
+        }
+    }
+}
+
+/// A small round pond, up to `LAKE_DEPTH` deep in the middle, with its
+/// surface at the lowest ground around its edge so it never spills. Skipped
+/// where the ground is too uneven, under water, in the wrong biome or has
+/// something standing on it.
+fn place_lake(hash: u64, chunk: &mut UnloadedChunk, sea_level: i32, allowed: impl Fn(i32, i32) -> bool) {
+    let span = (16 - 2 * LAKE_RADIUS) as u64;
+    let (cx, cz) = (LAKE_RADIUS + (hash % span) as i32, LAKE_RADIUS + ((hash >> 8) % span) as i32);
+    let radius = 2 + ((hash >> 16) % (LAKE_RADIUS as u64 - 1)) as i32;
+    let inside = |dx: i32, dz: i32| dx * dx + dz * dz <= radius * radius;
+
+    // The rim is the ring of columns just outside the lake
+    let mut level = i32::MAX;
+    let mut lowest = i32::MAX;
+    for dz in -radius - 1..=radius + 1 {
+        for dx in -radius - 1..=radius + 1 {
+            let (x, z) = (cx + dx, cz + dz);
+            if !(0..16).contains(&x) || !(0..16).contains(&z) {
+                continue;
+            }
+            let Some(ground_y) = ground(chunk, x as u32, z as u32) else {
+                return;
+            };
+            let ground_y = ground_y as i32;
+            if !allowed(x, z) || !is_natural_ground(chunk.block_state(x as u32, ground_y as u32, z as u32)) {
+                return;
+            }
+            if inside(dx, dz) {
+                // Nothing standing on the lake, like a tree, gets cut through
+                if !is_open(chunk, x as u32, ground_y as u32 + 1, z as u32) {
+                    return;
+                }
+                lowest = lowest.min(ground_y);
+            } else {
+                level = level.min(ground_y);
+            }
+        }
+    }
+    if level == i32::MAX || level < sea_level || level - lowest > LAKE_DEPTH || level - LAKE_DEPTH < 1 {
+        return;
+    }
+
+    for dz in -radius..=radius {
+        for dx in -radius..=radius {
+            if !inside(dx, dz) {
+                continue;
+            }
+            let (x, z) = ((cx + dx) as u32, (cz + dz) as u32);
+            // Deepest in the middle, a block deep at the edge
+            let from_middle = (dx * dx + dz * dz) as f64 / (radius * radius) as f64;
+            let depth = 1 + ((LAKE_DEPTH - 1) as f64 * (1.0 - from_middle)) as i32;
+            let bottom = level - depth;
+            let ground_y = ground(chunk, x, z).map_or(level, |y| y as i32);
+            for y in bottom + 1..=ground_y.max(level) {
+                let state = if y <= level { BlockState::WATER } else { BlockState::AIR };
+                chunk.set_block_state(x, y as u32, z, state);
+            }
+            if chunk.block_state(x, bottom as u32, z) == BlockState::GRASS_BLOCK {
+                chunk.set_block_state(x, bottom as u32, z, BlockState::DIRT);
+            }
+        }
+    }
+}
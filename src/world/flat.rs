@@ -0,0 +1,184 @@
+// src/world/flat.rs
+//
+// Superflat worlds from vanilla preset strings: layers from the bottom up,
+// then the biome, e.g.
+// `minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block;minecraft:plains`.
+// Anything after the biome (structure options) is ignored.
+//
+// Presets copied from before 1.13 start with a version number and are read
+// too: version 3 names blocks but numbers the biome
+// (`3;minecraft:bedrock,2*minecraft:dirt,minecraft:grass;1;village`) and
+// version 2 numbers both (`2;7,2x3,2;1;village`). Only the blocks and biomes
+// flat worlds were usually made of have numbers here.
+
+use std::str::FromStr;
+
+use valence::prelude::*;
+
+const DEFAULT_BIOME: &str = "minecraft:plains";
+
+/// Block ids of pre-1.13 presets.
+const LEGACY_BLOCK_IDS: &[(u32, &str)] = &[
+    (0, "air"),
+    (1, "stone"),
+    (2, "grass_block"),
+    (3, "dirt"),
+    (4, "cobblestone"),
+    (5, "oak_planks"),
+    (7, "bedrock"),
+    (8, "water"),
+    (9, "water"),
+    (10, "lava"),
+    (11, "lava"),
+    (12, "sand"),
+    (13, "gravel"),
+    (24, "sandstone"),
+    (35, "white_wool"),
+    (78, "snow"),
+    (79, "ice"),
+    (80, "snow_block"),
+    (82, "clay"),
+    (87, "netherrack"),
+    (88, "soul_sand"),
+    (89, "glowstone"),
+    (98, "stone_bricks"),
+    (121, "end_stone"),
+    (159, "white_terracotta"),
+    (172, "terracotta"),
+];
+/// Block names of pre-1.13 presets that mean something else now.
+const LEGACY_BLOCK_NAMES: &[(&str, &str)] = &[
+    ("grass", "grass_block"),
+    ("snow_layer", "snow"),
+    ("stonebrick", "stone_bricks"),
+    ("planks", "oak_planks"),
+    ("wool", "white_wool"),
+    ("hardened_clay", "terracotta"),
+    ("flowing_water", "water"),
+    ("flowing_lava", "lava"),
+];
+/// Biome ids of pre-1.13 presets.
+const LEGACY_BIOME_IDS: &[(u32, &str)] = &[
+    (0, "ocean"),
+    (1, "plains"),
+    (2, "desert"),
+    (3, "windswept_hills"),
+    (4, "forest"),
+    (5, "taiga"),
+    (6, "swamp"),
+    (7, "river"),
+    (8, "nether_wastes"),
+    (9, "the_end"),
+    (10, "frozen_ocean"),
+    (11, "frozen_river"),
+    (12, "snowy_plains"),
+    (14, "mushroom_fields"),
+    (16, "beach"),
+    (21, "jungle"),
+    (24, "deep_ocean"),
+    (27, "birch_forest"),
+    (29, "dark_forest"),
+    (35, "savanna"),
+    (37, "badlands"),
+];
+/// What `"preset": "flat"` generates: bedrock, two dirt and grass on top.
+pub const CLASSIC: &str = "minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block;minecraft:plains";
+
+/// A superflat world described by a vanilla preset string, e.g.
+/// `minecraft:bedrock,2*minecraft:dirt,minecraft:grass_block;minecraft:plains`.
+#[derive(Clone, Debug)]
+pub struct SuperflatPreset {
+    /// Layers from the bottom up, with how many blocks thick each one is.
+    pub layers: Vec<(BlockState, u32)>,
+    pub biome: Ident<String>,
+}
+
+impl SuperflatPreset {
+    /// Total thickness of all layers.
+    pub fn height(&self) -> u32 {
+        self.layers.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn generate(&self, height: u32, biome: BiomeId) -> UnloadedChunk {
+        let mut chunk = UnloadedChunk::with_height(height);
+        chunk.fill_biomes(biome);
+
+        let mut y = 0;
+        for (block, count) in &self.layers {
+            for _ in 0..*count {
+                if y >= height {
+                    return chunk;
+                }
+                for z in 0..16 {
+                    for x in 0..16 {
+                        chunk.set_block_state(x, y, z, *block);
+                    }
+                }
+                y += 1;
+            }
+        }
+        chunk
+    }
+}
+
+fn legacy_name(ids: &[(u32, &'static str)], id: &str) -> Option<&'static str> {
+    let id = id.parse::<u32>().ok()?;
+    ids.iter().find(|(known, _)| *known == id).map(|(_, name)| *name)
+}
+
+/// A layer like `2*minecraft:dirt`. `legacy` presets can also count with an
+/// `x`, as in `2x3`, and number their blocks.
+fn parse_layer(layer: &str, legacy: bool) -> Result<(BlockState, u32), String> {
+    let counted = layer.split_once('*').or_else(|| {
+        layer
+            .split_once('x')
+            .filter(|(count, _)| legacy && !count.trim().is_empty() && count.trim().bytes().all(|b| b.is_ascii_digit()))
+    });
+    let (count, id) = match counted {
+        Some((count, id)) => {
+            let count = count
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("invalid layer count in {layer:?}"))?;
+            (count, id)
+        }
+        None => (1, layer),
+    };
+    let id = id.trim();
+    let mut name = id.strip_prefix("minecraft:").unwrap_or(id);
+    if legacy {
+        // Numbered blocks can have a data value after a colon
+        let number = name.split(':').next().unwrap_or(name);
+        name = legacy_name(LEGACY_BLOCK_IDS, number)
+            .or_else(|| LEGACY_BLOCK_NAMES.iter().find(|(old, _)| *old == name).map(|(_, new)| *new))
+            .unwrap_or(name);
+    }
+    let kind = BlockKind::from_str(name).ok_or_else(|| format!("unknown block {id:?}"))?;
+    Ok((kind.to_state(), count))
+}
+
+impl FromStr for SuperflatPreset {
+    type Err = String;
+
+    fn from_str(preset: &str) -> Result<Self, Self::Err> {
+        let mut parts = preset.trim().split(';').peekable();
+        let legacy = parts.next_if(|version| version.trim().parse::<u32>().is_ok()).is_some();
+        let layers = parts
+            .next()
+            .filter(|layers| !layers.trim().is_empty())
+            .ok_or("preset has no layers")?
+            .split(',')
+            .map(|layer| parse_layer(layer, legacy))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let biome = parts.next().map(str::trim).filter(|b| !b.is_empty()).unwrap_or(DEFAULT_BIOME);
+        let biome = match legacy_name(LEGACY_BIOME_IDS, biome) {
+            Some(name) if legacy => format!("minecraft:{name}"),
+            None if legacy && biome.parse::<u32>().is_ok() => return Err(format!("unknown biome id {biome}")),
+            _ => biome.to_owned(),
+        };
+        let biome = Ident::new(biome.clone()).map_err(|_| format!("invalid biome {biome:?}"))?;
+
+        Ok(Self { layers, biome })
+    }
+}
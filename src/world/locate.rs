@@ -0,0 +1,82 @@
+// src/world/locate.rs
+//
+// Searches of a world's generator that take too long for one tick, like
+// finding the nearest desert. Each runs on a thread of its own and reports
+// back to whoever asked once it's done. The generator alone decides where
+// things are, so searches reach far past the loaded chunks.
+
+use std::thread;
+
+use flume::Receiver;
+use valence::op_level::OpLevel;
+use valence::prelude::*;
+
+/// Ops at this level or above get a teleport link with the result.
+const TELEPORT_OP_LEVEL: u8 = 2;
+
+struct Search {
+    executor: Entity,
+    /// The command it's for, which prefixes the result.
+    command: &'static str,
+    /// What it's looking for, e.g. `minecraft:desert`.
+    what: String,
+    from: DVec3,
+    result: Receiver<Option<DVec3>>,
+}
+
+#[derive(Resource, Default)]
+pub struct LocateSearches(Vec<Search>);
+
+impl LocateSearches {
+    /// Runs `search` in the background. It returns where `what` is, if it
+    /// found it.
+    pub fn start(
+        &mut self,
+        executor: Entity,
+        command: &'static str,
+        what: String,
+        from: DVec3,
+        search: impl FnOnce() -> Option<DVec3> + Send + 'static,
+    ) {
+        let (sender, result) = flume::bounded(1);
+        thread::spawn(move || {
+            let _ = sender.send(search());
+        });
+        self.0.push(Search { executor, command, what, from, result });
+    }
+}
+
+// Tells executors what their finished searches found
+pub fn finish_searches(mut searches: ResMut<LocateSearches>, mut clients: Query<(&mut Client, &OpLevel)>) {
+    searches.0.retain(|search| {
+        let found = match search.result.try_recv() {
+            Ok(found) => found,
+            Err(flume::TryRecvError::Empty) => return true,
+            // The thread panicked
+            Err(flume::TryRecvError::Disconnected) => None,
+        };
+        // Whoever asked might have left in the meantime
+        let Ok((mut client, op_level)) = clients.get_mut(search.executor) else {
+            return false;
+        };
+        let Some(found) = found else {
+            client.send_chat_message(format!("[{}] couldn't find {} nearby", search.command, search.what).color(Color::RED));
+            return false;
+        };
+
+        let (x, y, z) = (found.x.floor() as i32, found.y.floor() as i32, found.z.floor() as i32);
+        let distance = DVec3::new(found.x - search.from.x, 0.0, found.z - search.from.z).length();
+        let mut message = format!("[{}] nearest {} at {x} {y} {z} ({distance:.0} blocks away)", search.command, search.what)
+            .color(Color::GREEN);
+        if op_level.get() >= TELEPORT_OP_LEVEL {
+            message = message
+                + " "
+                + "[teleport]"
+                    .color(Color::AQUA)
+                    .on_click_run_command(format!("/tp {x} {y} {z}"))
+                    .on_hover_show_text("Click to teleport there");
+        }
+        client.send_chat_message(message);
+        false
+    });
+}
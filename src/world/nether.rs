@@ -0,0 +1,190 @@
+// src/world/nether.rs
+//
+// The Nether: a second layer using the vanilla `the_nether` dimension type,
+// with its own generator. Terrain is netherrack carved by 3D noise between a
+// bedrock floor and ceiling, with a lava sea at `LAVA_LEVEL`, soul sand on
+// some of the low ground, quartz in the netherrack and glowstone hanging from
+// the roof.
+//
+// Chunks are generated on the main thread as players look at them, a few per
+// tick, and dropped again once nobody can see them. Nothing here is saved, so
+// changes to the Nether are lost when its chunks unload.
+
+use std::collections::HashMap;
+
+use noise::SuperSimplex;
+use tracing::info;
+use valence::prelude::*;
+
+use super::structures::region_hash;
+use super::{fbm, noise01, WorldGenerator};
+use crate::components::logging::WORLDGEN;
+
+/// Where players arrive, on a small platform carved out at startup.
+pub const NETHER_SPAWN_POS: DVec3 = DVec3::new(0.5, 70.0, 0.5);
+/// The top of the terrain; the rest of the dimension's height is empty.
+const NETHER_TOP: i32 = 128;
+const LAVA_LEVEL: i32 = 31;
+/// Chunks around spawn generated at startup, so arrivals land on something.
+const PREGEN_RADIUS: i32 = 2;
+/// Most chunks generated per tick, so walking around doesn't stall the server.
+const CHUNKS_PER_TICK: usize = 2;
+/// Keeps Nether hashes apart from overworld ones for the same coordinates.
+const NETHER_SALT: u64 = 0x6e65_7468;
+
+/// Marks the Nether's layer.
+#[derive(Component)]
+pub struct TheNether;
+
+#[derive(Resource)]
+pub struct NetherGenerator {
+    seed: u32,
+    biome: BiomeId,
+    density: SuperSimplex,
+    soul_sand: SuperSimplex,
+}
+
+impl NetherGenerator {
+    fn new(seed: u32, biome: BiomeId) -> Self {
+        Self {
+            seed,
+            biome,
+            density: SuperSimplex::new(seed.wrapping_add(100)),
+            soul_sand: SuperSimplex::new(seed.wrapping_add(101)),
+        }
+    }
+
+    /// A random number from 0 to 1 for a block, the same every time.
+    fn roll(&self, x: i32, y: i32, z: i32, salt: u64) -> f64 {
+        (region_hash(self.seed, x, z, NETHER_SALT ^ salt ^ ((y as u64) << 16)) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn is_bedrock(&self, x: i32, y: i32, z: i32) -> bool {
+        // Bedrock gets patchier over the bottom and under the top five layers
+        let depth = y.min(NETHER_TOP - 1 - y);
+        depth < 5 && self.roll(x, y, z, 0) < 1.0 - depth as f64 / 5.0
+    }
+
+    fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        let noise = fbm(&self.density, DVec3::new(x as f64 / 80.0, y as f64 / 40.0, z as f64 / 80.0), 4, 2.0, 0.5);
+        // Solid ground gets more likely towards the floor and the roof
+        let floor = ((LAVA_LEVEL - 8 - y) as f64 / 24.0).max(0.0);
+        let roof = ((y - (NETHER_TOP - 28)) as f64 / 24.0).max(0.0);
+        noise + floor + roof > 0.58
+    }
+
+    pub fn generate(&self, pos: ChunkPos, height: u32) -> UnloadedChunk {
+        let mut chunk = UnloadedChunk::with_height(height);
+        chunk.fill_biomes(self.biome);
+        let top = NETHER_TOP.min(height as i32);
+
+        for z in 0..16 {
+            for x in 0..16 {
+                let (world_x, world_z) = (pos.x * 16 + x as i32, pos.z * 16 + z as i32);
+                let solid: Vec<bool> = (0..top).map(|y| self.is_solid(world_x, y, world_z)).collect();
+                let soul_sand = noise01(&self.soul_sand, DVec3::new(world_x as f64, 0.0, world_z as f64) / 40.0) > 0.65;
+
+                for y in 0..top {
+                    let (air_above, air_below) = (
+                        !solid.get(y as usize + 1).copied().unwrap_or(true),
+                        y > 0 && !solid[y as usize - 1],
+                    );
+                    let block = if y == 0 || y == top - 1 || self.is_bedrock(world_x, y, world_z) {
+                        BlockState::BEDROCK
+                    } else if !solid[y as usize] {
+                        if y <= LAVA_LEVEL { BlockState::LAVA } else { BlockState::AIR }
+                    } else if soul_sand && y < LAVA_LEVEL + 12 && (air_above || solid.get(y as usize + 3) == Some(&false)) {
+                        BlockState::SOUL_SAND
+                    } else if self.roll(world_x, y, world_z, 1) < 0.008 {
+                        BlockState::NETHER_QUARTZ_ORE
+                    } else {
+                        BlockState::NETHERRACK
+                    };
+                    chunk.set_block_state(x, y as u32, z, block);
+
+                    // Glowstone hangs in clumps from the underside of the roof
+                    if solid[y as usize] && air_below && y > LAVA_LEVEL + 40 && self.roll(world_x, y, world_z, 2) < 0.015 {
+                        let length = 1 + (self.roll(world_x, y, world_z, 3) * 3.0) as i32;
+                        for dy in 1..=length.min(y - 1) {
+                            if !solid[(y - dy) as usize] {
+                                chunk.set_block_state(x, (y - dy) as u32, z, BlockState::GLOWSTONE);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        chunk
+    }
+}
+
+fn build_spawn_platform(layer: &mut ChunkLayer) {
+    let center = BlockPos::from(NETHER_SPAWN_POS);
+    for x in -2..=2 {
+        for z in -2..=2 {
+            layer.set_block([center.x + x, center.y - 1, center.z + z], BlockState::OBSIDIAN);
+            for y in 0..3 {
+                layer.set_block([center.x + x, center.y + y, center.z + z], BlockState::AIR);
+            }
+        }
+    }
+}
+
+pub fn setup_nether(
+    mut commands: Commands,
+    server: Res<Server>,
+    dimensions: Res<DimensionTypeRegistry>,
+    biomes: Res<BiomeRegistry>,
+    world_gen: Res<WorldGenerator>,
+) {
+    let mut layer = LayerBundle::new(ident!("the_nether"), &dimensions, &biomes, &server);
+    let biome = biomes.index_of(ident!("nether_wastes")).unwrap_or_default();
+    let generator = NetherGenerator::new(world_gen.seed, biome);
+    let height = layer.chunk.height();
+
+    for cz in -PREGEN_RADIUS..=PREGEN_RADIUS {
+        for cx in -PREGEN_RADIUS..=PREGEN_RADIUS {
+            let pos = ChunkPos::new(cx, cz);
+            layer.chunk.insert_chunk(pos, generator.generate(pos, height));
+        }
+    }
+    build_spawn_platform(&mut layer.chunk);
+
+    commands.spawn((layer, TheNether));
+    commands.insert_resource(generator);
+    info!(target: WORLDGEN, "created the Nether");
+}
+
+// Generates the Nether chunks players can see and drops the ones they can't
+pub fn update_nether_chunks(
+    mut layers: Query<(Entity, &mut ChunkLayer), With<TheNether>>,
+    clients: Query<(&VisibleChunkLayer, View)>,
+    generator: Option<Res<NetherGenerator>>,
+) {
+    let (Ok((nether, mut layer)), Some(generator)) = (layers.get_single_mut(), generator) else {
+        return;
+    };
+
+    // Missing chunks by how close they are to the nearest player
+    let mut missing = HashMap::new();
+    for (visible_layer, view) in &clients {
+        if visible_layer.0 != nether {
+            continue;
+        }
+        let view = view.get();
+        for pos in view.iter().filter(|pos| layer.chunk(*pos).is_none()) {
+            let distance = view.pos.distance_squared(pos);
+            missing.entry(pos).and_modify(|d| *d = distance.min(*d)).or_insert(distance);
+        }
+    }
+    let mut missing: Vec<_> = missing.into_iter().collect();
+    missing.sort_unstable_by_key(|(_, distance)| *distance);
+    let height = layer.height();
+    for (pos, _) in missing.into_iter().take(CHUNKS_PER_TICK) {
+        layer.insert_chunk(pos, generator.generate(pos, height));
+    }
+
+    layer.retain_chunks(|pos, chunk| {
+        chunk.viewer_count() > 0 || (pos.x.abs() <= PREGEN_RADIUS && pos.z.abs() <= PREGEN_RADIUS)
+    });
+}
@@ -0,0 +1,142 @@
+// src/world/ores.rs
+//
+// Ore veins in the stone of generated terrain. Each ore has a number of veins
+// per chunk, a vein size and a band of heights it can start in. Veins are
+// blobs grown by a short random walk from a start point picked from the seed
+// and chunk position, and only ever replace stone, or deepslate with the
+// deepslate version of the ore.
+//
+// A vein can wander over its chunk's edge, so every chunk also grows the
+// veins of its neighbours and keeps the blocks that land inside it.
+//
+// `config/worldgen.json`:
+// `{ "ores": { "coal": { "veins_per_chunk": 20, "size": 12, "min_y": 5, "max_y": 130 }, ... } }`
+
+use serde::Deserialize;
+use valence::prelude::*;
+use valence::rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::structures::region_hash;
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct OreSettings {
+    /// 0 turns the ore off.
+    pub veins_per_chunk: u32,
+    /// Roughly how many blocks a vein has.
+    pub size: u32,
+    /// Generator heights veins start between.
+    pub min_y: i32,
+    pub max_y: i32,
+}
+
+impl Default for OreSettings {
+    fn default() -> Self {
+        Self {
+            veins_per_chunk: 0,
+            size: 8,
+            min_y: 5,
+            max_y: 64,
+        }
+    }
+}
+
+/// Ores in the order they're placed. Ores only replace stone and deepslate,
+/// so earlier ones win where veins overlap.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct OreConfig {
+    pub coal: OreSettings,
+    pub iron: OreSettings,
+    pub gold: OreSettings,
+    pub diamond: OreSettings,
+}
+
+impl Default for OreConfig {
+    fn default() -> Self {
+        // Heights are scaled to this generator's sea level of 47 rather than
+        // vanilla's 63.
+        Self {
+            coal: OreSettings {
+                veins_per_chunk: 20,
+                size: 12,
+                min_y: 5,
+                max_y: 130,
+            },
+            iron: OreSettings {
+                veins_per_chunk: 10,
+                size: 8,
+                min_y: 5,
+                max_y: 60,
+            },
+            gold: OreSettings {
+                veins_per_chunk: 2,
+                size: 8,
+                min_y: 5,
+                max_y: 30,
+            },
+            diamond: OreSettings {
+                veins_per_chunk: 1,
+                size: 6,
+                min_y: 5,
+                max_y: 16,
+            },
+        }
+    }
+}
+
+impl OreConfig {
+    /// Each ore, in stone and in deepslate.
+    fn ores(&self) -> [((BlockState, BlockState), &OreSettings); 4] {
+        [
+            ((BlockState::COAL_ORE, BlockState::DEEPSLATE_COAL_ORE), &self.coal),
+            ((BlockState::IRON_ORE, BlockState::DEEPSLATE_IRON_ORE), &self.iron),
+            ((BlockState::GOLD_ORE, BlockState::DEEPSLATE_GOLD_ORE), &self.gold),
+            ((BlockState::DIAMOND_ORE, BlockState::DEEPSLATE_DIAMOND_ORE), &self.diamond),
+        ]
+    }
+}
+
+/// Grows every vein that reaches into the chunk at `pos`.
+pub fn place_ores(seed: u32, config: &OreConfig, pos: ChunkPos, chunk: &mut UnloadedChunk) {
+    let height = chunk.height() as i32;
+    let (min_x, min_z) = (pos.x * 16, pos.z * 16);
+    for (index, ((ore, deepslate_ore), settings)) in config.ores().into_iter().enumerate() {
+        if settings.veins_per_chunk == 0 || settings.max_y < settings.min_y {
+            continue;
+        }
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                let (source_x, source_z) = (pos.x + dx, pos.z + dz);
+                let mut rng = StdRng::seed_from_u64(region_hash(seed, source_x, source_z, 0x6f72_6500 + index as u64));
+                for _ in 0..settings.veins_per_chunk {
+                    let mut block = BlockPos::new(
+                        source_x * 16 + rng.gen_range(0..16),
+                        rng.gen_range(settings.min_y..=settings.max_y),
+                        source_z * 16 + rng.gen_range(0..16),
+                    );
+                    for _ in 0..settings.size {
+                        let (x, z) = (block.x - min_x, block.z - min_z);
+                        if (0..16).contains(&x) && (0..16).contains(&z) && (0..height).contains(&block.y) {
+                            let (x, y, z) = (x as u32, block.y as u32, z as u32);
+                            match chunk.block_state(x, y, z) {
+                                BlockState::STONE => chunk.set_block_state(x, y, z, ore),
+                                BlockState::DEEPSLATE => chunk.set_block_state(x, y, z, deepslate_ore),
+                                _ => {}
+                            }
+                        }
+                        // Random walk, so veins come out as lumpy blobs
+                        match rng.gen_range(0..6) {
+                            0 => block.x += 1,
+                            1 => block.x -= 1,
+                            2 => block.y += 1,
+                            3 => block.y -= 1,
+                            4 => block.z += 1,
+                            _ => block.z -= 1,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// src/world/physics.rs
+//
+// Shared collision and movement code. Valence doesn't simulate entities, so
+// anything that should fall, slide or stop against blocks goes through here
+// rather than each system checking single blocks its own way.
+//
+// Movement follows vanilla's order: the motion is clipped against every block
+// shape near the box on Y first, then X, then Z.
+
+use valence::prelude::*;
+
+/// An axis-aligned box in world coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: DVec3,
+    pub max: DVec3,
+}
+
+impl Aabb {
+    pub fn new(min: DVec3, max: DVec3) -> Self {
+        Self { min: min.min(max), max: min.max(max) }
+    }
+
+    /// An entity-style box: `width` wide, standing on `feet`.
+    pub fn from_feet(feet: DVec3, width: f64, height: f64) -> Self {
+        let half = width / 2.0;
+        Self::new(
+            DVec3::new(feet.x - half, feet.y, feet.z - half),
+            DVec3::new(feet.x + half, feet.y + height, feet.z + half),
+        )
+    }
+
+    pub fn translate(self, offset: DVec3) -> Self {
+        Self { min: self.min + offset, max: self.max + offset }
+    }
+
+    /// Grows the box in the direction of `motion`, covering everything it
+    /// could touch while moving.
+    pub fn stretch(self, motion: DVec3) -> Self {
+        Self { min: self.min + motion.min(DVec3::ZERO), max: self.max + motion.max(DVec3::ZERO) }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmplt(other.max).all() && self.max.cmpgt(other.min).all()
+    }
+
+    /// How far this box can move along `axis` by `motion` before touching
+    /// `obstacle`. Returns `motion` unchanged if they can't meet.
+    fn clip(&self, obstacle: &Aabb, axis: usize, motion: f64) -> f64 {
+        // Only boxes overlapping on the other two axes can block
+        for other in (0..3).filter(|a| *a != axis) {
+            if self.max[other] <= obstacle.min[other] || self.min[other] >= obstacle.max[other] {
+                return motion;
+            }
+        }
+        if motion > 0.0 && self.max[axis] <= obstacle.min[axis] {
+            motion.min(obstacle.min[axis] - self.max[axis])
+        } else if motion < 0.0 && self.min[axis] >= obstacle.max[axis] {
+            motion.max(obstacle.max[axis] - self.min[axis])
+        } else {
+            motion
+        }
+    }
+}
+
+/// Collision boxes of every block overlapping `region`, in world space.
+/// Unloaded chunks have no collision.
+pub fn block_boxes(layer: &ChunkLayer, region: &Aabb) -> Vec<Aabb> {
+    let min = region.min.floor().as_ivec3();
+    let max = region.max.ceil().as_ivec3();
+    let mut boxes = Vec::new();
+    for y in min.y..max.y {
+        for z in min.z..max.z {
+            for x in min.x..max.x {
+                let Some(block) = layer.block([x, y, z]) else {
+                    continue;
+                };
+                let offset = DVec3::new(x as f64, y as f64, z as f64);
+                boxes.extend(
+                    block
+                        .state
+                        .collision_shapes()
+                        .map(|shape| Aabb::new(shape.min() + offset, shape.max() + offset))
+                        .filter(|shape| shape.intersects(region)),
+                );
+            }
+        }
+    }
+    boxes
+}
+
+/// Which axes a sweep was stopped on.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Collisions {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl Collisions {
+    pub fn any(&self) -> bool {
+        self.x || self.y || self.z
+    }
+}
+
+/// Moves `aabb` by `motion`, stopping against blocks. Returns the motion that
+/// actually happened and which axes were blocked.
+pub fn sweep(layer: &ChunkLayer, aabb: Aabb, motion: DVec3) -> (DVec3, Collisions) {
+    let obstacles = block_boxes(layer, &aabb.stretch(motion));
+    let mut moved = aabb;
+    let mut actual = DVec3::ZERO;
+    for axis in [1, 0, 2] {
+        let wanted = motion[axis];
+        let allowed = obstacles.iter().fold(wanted, |m, obstacle| moved.clip(obstacle, axis, m));
+        actual[axis] = allowed;
+        let mut offset = DVec3::ZERO;
+        offset[axis] = allowed;
+        moved = moved.translate(offset);
+    }
+    let blocked = |axis: usize| (actual[axis] - motion[axis]).abs() > 1e-7;
+    (actual, Collisions { x: blocked(0), y: blocked(1), z: blocked(2) })
+}
+
+/// Makes the server move an entity instead of only the client predicting it.
+/// Speeds are in blocks per tick, matching vanilla's constants.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PhysicsBody {
+    pub width: f64,
+    pub height: f64,
+    pub gravity: f64,
+    /// Multiplier applied to the velocity every tick.
+    pub drag: f64,
+    pub velocity: DVec3,
+    pub on_ground: bool,
+}
+
+impl PhysicsBody {
+    pub fn item(velocity: Vec3) -> Self {
+        Self {
+            width: 0.25,
+            height: 0.25,
+            gravity: 0.04,
+            drag: 0.98,
+            // `Velocity` is in blocks per second
+            velocity: velocity.as_dvec3() / 20.0,
+            on_ground: false,
+        }
+    }
+
+    pub fn experience_orb(velocity: Vec3) -> Self {
+        Self {
+            width: 0.5,
+            height: 0.5,
+            gravity: 0.03,
+            drag: 0.98,
+            velocity: velocity.as_dvec3() / 20.0,
+            on_ground: false,
+        }
+    }
+
+    /// A mob-sized body at rest. Mobs only get one once something pushes
+    /// them.
+    pub fn mob() -> Self {
+        Self {
+            width: 0.6,
+            height: 1.8,
+            gravity: 0.08,
+            drag: 0.98,
+            velocity: DVec3::ZERO,
+            on_ground: false,
+        }
+    }
+
+    pub fn arrow(velocity: Vec3) -> Self {
+        Self {
+            width: 0.5,
+            height: 0.5,
+            gravity: 0.05,
+            drag: 0.99,
+            velocity: velocity.as_dvec3() / 20.0,
+            on_ground: false,
+        }
+    }
+}
+
+pub fn simulate_physics(mut bodies: Query<(&mut Position, &mut PhysicsBody, &EntityLayerId)>, layers: Query<&ChunkLayer>) {
+    for (mut pos, mut body, layer_id) in &mut bodies {
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        body.velocity.y -= body.gravity;
+        let aabb = Aabb::from_feet(pos.0, body.width, body.height);
+        let (moved, collisions) = sweep(layer, aabb, body.velocity);
+        if moved != DVec3::ZERO {
+            pos.0 += moved;
+        }
+
+        body.on_ground = collisions.y && body.velocity.y < 0.0;
+        if collisions.x {
+            body.velocity.x = 0.0;
+        }
+        if collisions.y {
+            body.velocity.y = 0.0;
+        }
+        if collisions.z {
+            body.velocity.z = 0.0;
+        }
+        let drag = body.drag;
+        body.velocity *= drag;
+        if body.on_ground {
+            // Ground friction, like vanilla's 0.6 block slipperiness
+            body.velocity.x *= 0.6;
+            body.velocity.z *= 0.6;
+        }
+    }
+}
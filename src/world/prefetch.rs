@@ -0,0 +1,99 @@
+// src/world/prefetch.rs
+//
+// Gets the area around a player ready before they need it. When a client
+// joins or teleports, the chunks around their new position are queued ahead
+// of their regular view (first in line for the workers), and for imported
+// worlds the 9 region files around them are read in the background so the
+// anvil loader finds them in the OS cache instead of waiting on disk.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+
+use flume::Sender;
+use tracing::debug;
+use valence::prelude::*;
+
+use super::{GameState, Overworld};
+use crate::components::logging::STORAGE;
+
+/// Chunks queued in each direction around an arriving player.
+const PREFETCH_RADIUS: i32 = 3;
+/// Moving further than this in one tick counts as a teleport.
+const TELEPORT_DISTANCE: f64 = 64.0;
+/// Regions read recently enough that reading them again is pointless.
+const RECENT_REGIONS: usize = 64;
+
+/// Background reader for an imported world's region files.
+#[derive(Resource)]
+pub struct RegionPrefetcher {
+    sender: Sender<(i32, i32)>,
+}
+
+impl RegionPrefetcher {
+    pub fn start(region_dir: PathBuf) -> Self {
+        let (sender, receiver) = flume::unbounded::<(i32, i32)>();
+        thread::spawn(move || {
+            let mut recent: VecDeque<(i32, i32)> = VecDeque::with_capacity(RECENT_REGIONS);
+            while let Ok(region) = receiver.recv() {
+                if recent.contains(&region) {
+                    continue;
+                }
+                let path = region_dir.join(format!("r.{}.{}.mca", region.0, region.1));
+                if let Ok(mut file) = File::open(&path) {
+                    let _ = io::copy(&mut file, &mut io::sink());
+                    debug!(target: STORAGE, "Prefetched {}", path.display());
+                }
+                if recent.len() == RECENT_REGIONS {
+                    recent.pop_front();
+                }
+                recent.push_back(region);
+            }
+        });
+        Self { sender }
+    }
+
+    /// Reads the region containing `pos` and its 8 neighbours.
+    pub fn prefetch_around(&self, pos: ChunkPos) {
+        let (rx, rz) = (pos.x.div_euclid(32), pos.z.div_euclid(32));
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                let _ = self.sender.send((rx + dx, rz + dz));
+            }
+        }
+    }
+}
+
+pub fn prefetch_arrivals(
+    clients: Query<(Ref<Position>, &OldPosition, &VisibleChunkLayer), With<Client>>,
+    layers: Query<(Entity, &ChunkLayer), With<Overworld>>,
+    mut state: ResMut<GameState>,
+    prefetcher: Option<Res<RegionPrefetcher>>,
+) {
+    let Ok((layer_entity, layer)) = layers.get_single() else {
+        return;
+    };
+    for (pos, old_pos, visible_layer) in &clients {
+        let arrived = pos.is_added() || (pos.is_changed() && pos.0.distance(old_pos.get()) > TELEPORT_DISTANCE);
+        if !arrived || visible_layer.0 != layer_entity {
+            continue;
+        }
+
+        let center = ChunkPos::from(pos.0);
+        if let Some(prefetcher) = &prefetcher {
+            prefetcher.prefetch_around(center);
+            // The anvil plugin loads chunks itself, so don't queue generation
+            continue;
+        }
+        for z in center.z - PREFETCH_RADIUS..=center.z + PREFETCH_RADIUS {
+            for x in center.x - PREFETCH_RADIUS..=center.x + PREFETCH_RADIUS {
+                let chunk = ChunkPos::new(x, z);
+                if layer.chunk(chunk).is_none() {
+                    state.pending.entry(chunk).or_insert(Some(0));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// src/world/regression.rs
+//
+// Catches accidental worldgen changes. Generation is deterministic for a given
+// seed and preset, so hashing a few chunks gives a fingerprint that must stay
+// the same unless the change is intentional (existing worlds would otherwise
+// get seams where old and new chunks meet).
+//
+// `worldgen record` in the console saves the current fingerprints, and
+// `worldgen verify` compares against them. `worldgen bench` times generation
+// of a larger area for every preset, to compare before and after a change
+// that should make it faster. The test at the bottom checks a few chunks
+// against hashes checked in next to this file, so `cargo test` catches it too;
+// `worldgen hashes` prints the current ones to check in after a change.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Instant;
+
+use valence::prelude::*;
+
+use super::{ChunkGenerator, TerrainPreset};
+use crate::components::config::CONFIG_DIR;
+
+const FINGERPRINT_FILE: &str = "worldgen_fingerprints.json";
+const CHECK_SEEDS: [u32; 3] = [0, 1, 20_000];
+const CHECK_PRESETS: [&str; 3] = ["default", "amplified", "islands"];
+/// Chunks checked in each direction from the origin.
+const CHECK_RADIUS: i32 = 1;
+/// Chunks timed in each direction from the origin by `bench`.
+const BENCH_RADIUS: i32 = 4;
+
+/// The chunks checked by the test against `regression_chunks.json`.
+const TEST_SEED: u32 = 12_345;
+const TEST_PRESET: &str = "default";
+const TEST_CHUNKS: [(i32, i32); 5] = [(0, 0), (1, -1), (-7, 12), (31, 31), (-250, 100)];
+
+/// preset -> seed -> fingerprint
+type Fingerprints = BTreeMap<String, BTreeMap<u32, u64>>;
+
+/// FNV-1a over every block state in the chunk. Unlike `DefaultHasher`, this
+/// is guaranteed not to change between Rust versions.
+pub fn chunk_hash(chunk: &UnloadedChunk) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for y in 0..chunk.height() {
+        for z in 0..16 {
+            for x in 0..16 {
+                for byte in chunk.block_state(x, y, z).to_raw().to_le_bytes() {
+                    hash ^= byte as u64;
+                    hash = hash.wrapping_mul(0x0100_0000_01b3);
+                }
+            }
+        }
+    }
+    hash
+}
+
+/// Combined hash of the chunks around the origin for one seed and preset.
+pub fn fingerprint(seed: u32, terrain: &TerrainPreset) -> u64 {
+    let generator = ChunkGenerator::new(seed, terrain.clone(), None);
+    let mut hash: u64 = 0;
+    for z in -CHECK_RADIUS..=CHECK_RADIUS {
+        for x in -CHECK_RADIUS..=CHECK_RADIUS {
+            let chunk = generator.generate(ChunkPos::new(x, z));
+            hash = hash.rotate_left(5) ^ chunk_hash(&chunk);
+        }
+    }
+    hash
+}
+
+fn current_fingerprints() -> Fingerprints {
+    CHECK_PRESETS
+        .iter()
+        .filter_map(|name| Some((name.to_string(), TerrainPreset::builtin(name)?)))
+        .map(|(name, terrain)| {
+            let seeds = CHECK_SEEDS
+                .iter()
+                .map(|seed| (*seed, fingerprint(*seed, &terrain)))
+                .collect();
+            (name, seeds)
+        })
+        .collect()
+}
+
+/// "x,z" -> hash of each test chunk, as checked in next to this file.
+pub fn test_chunk_hashes() -> BTreeMap<String, u64> {
+    let terrain = TerrainPreset::builtin(TEST_PRESET).expect("builtin preset");
+    let generator = ChunkGenerator::new(TEST_SEED, terrain, None);
+    TEST_CHUNKS
+        .iter()
+        .map(|&(x, z)| (format!("{x},{z}"), chunk_hash(&generator.generate(ChunkPos::new(x, z)))))
+        .collect()
+}
+
+/// Saves the current fingerprints as the expected ones.
+pub fn record() -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&current_fingerprints()).map_err(|e| e.to_string())?;
+    fs::create_dir_all(CONFIG_DIR).map_err(|e| e.to_string())?;
+    fs::write(format!("{CONFIG_DIR}/{FINGERPRINT_FILE}"), json).map_err(|e| e.to_string())
+}
+
+/// Regenerates the check chunks and returns a description of every preset
+/// and seed whose output changed.
+pub fn verify() -> Result<Vec<String>, String> {
+    let path = format!("{CONFIG_DIR}/{FINGERPRINT_FILE}");
+    let contents = fs::read_to_string(&path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let expected: Fingerprints = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+
+    let mut mismatches = Vec::new();
+    for (preset, seeds) in current_fingerprints() {
+        for (seed, actual) in seeds {
+            match expected.get(&preset).and_then(|s| s.get(&seed)) {
+                Some(expected) if *expected == actual => {}
+                Some(expected) => {
+                    mismatches.push(format!("{preset} seed {seed}: expected {expected:016x}, got {actual:016x}"))
+                }
+                None => mismatches.push(format!("{preset} seed {seed}: no recorded fingerprint")),
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Generates the chunks around the origin with every preset and returns how
+/// long each took per chunk, on this thread, as lines to log.
+pub fn bench() -> Vec<String> {
+    let chunks = (2 * BENCH_RADIUS + 1).pow(2);
+    CHECK_PRESETS
+        .iter()
+        .filter_map(|name| Some((name, TerrainPreset::builtin(name)?)))
+        .map(|(name, terrain)| {
+            let generator = ChunkGenerator::new(CHECK_SEEDS[0], terrain, None);
+            let start = Instant::now();
+            for z in -BENCH_RADIUS..=BENCH_RADIUS {
+                for x in -BENCH_RADIUS..=BENCH_RADIUS {
+                    generator.generate(ChunkPos::new(x, z));
+                }
+            }
+            let ms = start.elapsed().as_secs_f64() * 1000.0;
+            format!("{name}: {chunks} chunks in {ms:.0} ms, {:.2} ms per chunk", ms / chunks as f64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_match_checked_in_hashes() {
+        let expected: BTreeMap<String, u64> = serde_json::from_str(include_str!("regression_chunks.json")).unwrap();
+        assert_eq!(
+            test_chunk_hashes(),
+            expected,
+            "{TEST_PRESET} seed {TEST_SEED} generated differently; if that's intended, run `worldgen hashes` \
+             in the console and check in what it prints"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+// src/world/remap.rs
+//
+// Worlds saved by other game versions use block and item names this server
+// doesn't know (renamed or newly added blocks). Valence refuses to load a
+// chunk containing any of them, so failed chunks are re-read here with every
+// name passed through a mapping table. Names that still don't resolve become
+// the fallback block instead of failing the whole chunk.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use serde::Deserialize;
+use tracing::warn;
+use valence::anvil::RegionFolder;
+use valence::nbt::{Compound, List, Value};
+use valence::prelude::*;
+
+use crate::components::config::load_config;
+use crate::components::logging::WORLDGEN;
+
+/// Renames between other versions and 1.20.1, used unless the config file
+/// overrides the same name.
+const BUILTIN_BLOCKS: &[(&str, &str)] = &[
+    // 1.20.3+
+    ("short_grass", "grass"),
+    // pre-1.17
+    ("grass_path", "dirt_path"),
+    // pre-1.14
+    ("sign", "oak_sign"),
+    ("wall_sign", "oak_wall_sign"),
+    // 1.21+
+    ("trial_spawner", "spawner"),
+    ("vault", "spawner"),
+    ("heavy_core", "stone"),
+];
+
+const BUILTIN_ITEMS: &[(&str, &str)] = &[
+    ("short_grass", "grass"),
+    ("grass_path", "dirt_path"),
+    ("turtle_scute", "scute"),
+];
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RemapConfig {
+    /// Old block name -> block name on this version.
+    pub blocks: HashMap<String, String>,
+    /// Old item name -> item name on this version.
+    pub items: HashMap<String, String>,
+    /// Used for blocks that are neither known nor remapped.
+    pub fallback_block: String,
+}
+
+impl Default for RemapConfig {
+    fn default() -> Self {
+        Self {
+            blocks: HashMap::new(),
+            items: HashMap::new(),
+            fallback_block: "minecraft:stone".to_owned(),
+        }
+    }
+}
+
+fn strip_namespace(name: &str) -> &str {
+    name.strip_prefix("minecraft:").unwrap_or(name)
+}
+
+/// Resolves block and item names from other versions.
+#[derive(Resource)]
+pub struct Remapper {
+    blocks: HashMap<String, String>,
+    items: HashMap<String, String>,
+    fallback: BlockState,
+}
+
+impl Remapper {
+    pub fn load() -> Self {
+        let config: RemapConfig = load_config("block_remap.json");
+        let table = |builtin: &[(&str, &str)], custom: HashMap<String, String>| {
+            let mut table: HashMap<String, String> = builtin
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect();
+            table.extend(
+                custom
+                    .into_iter()
+                    .map(|(from, to)| (strip_namespace(&from).to_owned(), strip_namespace(&to).to_owned())),
+            );
+            table
+        };
+        let fallback = BlockKind::from_str(strip_namespace(&config.fallback_block))
+            .map(BlockKind::to_state)
+            .unwrap_or_else(|| {
+                warn!(target: WORLDGEN, "Unknown fallback block {:?}, using stone", config.fallback_block);
+                BlockState::STONE
+            });
+
+        Self {
+            blocks: table(BUILTIN_BLOCKS, config.blocks),
+            items: table(BUILTIN_ITEMS, config.items),
+            fallback,
+        }
+    }
+
+    /// Maps a block name to one this version knows, or `None` if there's no
+    /// such block even after remapping.
+    pub fn block_kind(&self, name: &str) -> Option<BlockKind> {
+        let name = strip_namespace(name);
+        BlockKind::from_str(name).or_else(|| BlockKind::from_str(self.blocks.get(name)?))
+    }
+
+    pub fn item_kind(&self, name: &str) -> Option<ItemKind> {
+        let name = strip_namespace(name);
+        ItemKind::from_str(name).or_else(|| ItemKind::from_str(self.items.get(name)?))
+    }
+
+    /// Builds a block state from a palette entry (`Name` plus optional
+    /// `Properties`). Properties the block doesn't have are dropped.
+    fn palette_state(&self, entry: &Compound, unknown: &mut HashSet<String>) -> BlockState {
+        let Some(Value::String(name)) = entry.get("Name") else {
+            return self.fallback;
+        };
+        let Some(kind) = self.block_kind(name) else {
+            unknown.insert(name.clone());
+            return self.fallback;
+        };
+
+        let mut state = kind.to_state();
+        if let Some(Value::Compound(props)) = entry.get("Properties") {
+            for (prop, value) in props {
+                let Value::String(value) = value else { continue };
+                if let (Some(prop), Some(value)) = (PropName::from_str(prop), PropValue::from_str(value)) {
+                    state = state.set(prop, value);
+                }
+            }
+        }
+        state
+    }
+
+    /// Re-reads a chunk valence couldn't parse, remapping every palette entry.
+    /// Only blocks are restored; biomes are filled with `biome` and block
+    /// entities are dropped.
+    pub fn load_chunk(
+        &self,
+        region: &mut RegionFolder,
+        pos: ChunkPos,
+        min_y: i32,
+        height: u32,
+        biome: BiomeId,
+    ) -> Result<Option<UnloadedChunk>, String> {
+        let Some(raw) = region.get_chunk(pos.x, pos.z).map_err(|e| e.to_string())? else {
+            return Ok(None);
+        };
+        let Some(Value::List(List::Compound(sections))) = raw.data.get("sections") else {
+            return Err("chunk has no sections".to_owned());
+        };
+
+        let mut chunk = UnloadedChunk::with_height(height);
+        chunk.fill_biomes(biome);
+
+        let mut unknown = HashSet::new();
+        for section in sections {
+            let Some(Value::Byte(section_y)) = section.get("Y") else {
+                continue;
+            };
+            let Some(Value::Compound(states)) = section.get("block_states") else {
+                continue;
+            };
+            let Some(Value::List(List::Compound(palette))) = states.get("palette") else {
+                continue;
+            };
+            let palette: Vec<BlockState> = palette.iter().map(|e| self.palette_state(e, &mut unknown)).collect();
+            let data = match states.get("data") {
+                Some(Value::LongArray(data)) => data.as_slice(),
+                _ => &[],
+            };
+
+            let base_y = *section_y as i32 * 16 - min_y;
+            for (i, block) in section_blocks(&palette, data).enumerate() {
+                let y = base_y + (i >> 8) as i32;
+                if y < 0 || y >= height as i32 {
+                    continue;
+                }
+                chunk.set_block_state((i & 15) as u32, y as u32, ((i >> 4) & 15) as u32, block);
+            }
+        }
+
+        if !unknown.is_empty() {
+            warn!(target: WORLDGEN, "Chunk {pos:?} has unknown blocks {unknown:?}, replaced with {:?}", self.fallback);
+        }
+        Ok(Some(chunk))
+    }
+}
+
+/// Unpacks the 4096 blocks of a section, in YZX order. Since 1.16 entries
+/// don't span longs, and palettes always use at least 4 bits per entry.
+fn section_blocks<'a>(palette: &'a [BlockState], data: &'a [i64]) -> impl Iterator<Item = BlockState> + 'a {
+    let bits = (usize::BITS - palette.len().saturating_sub(1).leading_zeros()).max(4) as usize;
+    let per_long = 64 / bits;
+    let mask = (1u64 << bits) - 1;
+    (0..4096).map(move |i| {
+        if palette.len() <= 1 || data.is_empty() {
+            return palette.first().copied().unwrap_or(BlockState::AIR);
+        }
+        let long = data.get(i / per_long).copied().unwrap_or(0) as u64;
+        let index = ((long >> ((i % per_long) * bits)) & mask) as usize;
+        palette.get(index).copied().unwrap_or(BlockState::AIR)
+    })
+}
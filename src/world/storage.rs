@@ -0,0 +1,405 @@
+// src/world/storage.rs
+//
+// Writing chunks to disk without stalling the tick. The main thread only
+// copies the block ids and block entities of dirty chunks into a snapshot; a
+// background thread builds the palette, compresses and writes the file.
+// Snapshots of the same chunk queued close together are coalesced so only the
+// latest is written.
+//
+// Chunks are stored one per file as gzipped
+// `[magic][height][palette][indices][block entities]`. Files from before
+// block entities were saved simply end after the indices.
+//
+// Containers, beacons and command blocks keep their state in their block
+// entity's NBT, so it's saved with the chunk. When a saved chunk comes back,
+// a `ChunkRestored` event lists its block entities so those systems can pick
+// them up again.
+//
+// Only edited overworld chunks are saved, when they unload and on autosave.
+// Edits are noticed through `WorldEvent`s; code that changes blocks without
+// sending one marks the chunk itself with `ChunkSaver::mark_block_dirty`.
+// When a chunk is needed again, the saved copy is used instead of generating
+// it.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flume::{Receiver, RecvTimeoutError, Sender};
+use tracing::{error, info};
+use valence::nbt::{self, Compound};
+use valence::prelude::*;
+
+use super::Overworld;
+use super::events::{WorldEvent, WorldEventKind};
+use crate::components::logging::STORAGE;
+
+pub const SAVE_DIR: &str = "world/chunks";
+const MAGIC: &[u8; 4] = b"CRYC";
+/// Dirty chunks are snapshotted this often.
+const AUTOSAVE_INTERVAL_TICKS: i64 = 20 * 60;
+/// How long the writer waits for more snapshots before writing.
+const COALESCE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Block ids and block entities of one chunk, copied out of the world so it
+/// can be written elsewhere.
+pub struct ChunkSnapshot {
+    height: u32,
+    blocks: Vec<u16>,
+    /// NBT of the block entities, by index into `blocks`.
+    block_entities: Vec<(u32, Compound)>,
+}
+
+impl ChunkSnapshot {
+    pub fn of(chunk: &impl Chunk) -> Self {
+        let height = chunk.height();
+        let mut blocks = Vec::with_capacity((height * 256) as usize);
+        let mut block_entities = Vec::new();
+        for y in 0..height {
+            for z in 0..16 {
+                for x in 0..16 {
+                    let state = chunk.block_state(x, y, z);
+                    if state.block_entity_kind().is_some()
+                        && let Some(nbt) = chunk.block_entity(x, y, z)
+                    {
+                        block_entities.push((blocks.len() as u32, nbt.clone()));
+                    }
+                    blocks.push(state.to_raw());
+                }
+            }
+        }
+        Self { height, blocks, block_entities }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut palette: Vec<u16> = Vec::new();
+        let mut index_of: HashMap<u16, u16> = HashMap::new();
+        let indices: Vec<u16> = self
+            .blocks
+            .iter()
+            .map(|raw| {
+                *index_of.entry(*raw).or_insert_with(|| {
+                    palette.push(*raw);
+                    (palette.len() - 1) as u16
+                })
+            })
+            .collect();
+
+        let mut bytes = Vec::with_capacity(12 + (palette.len() + indices.len()) * 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+        for value in palette.iter().chain(&indices) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.block_entities.len() as u32).to_le_bytes());
+        for (index, compound) in &self.block_entities {
+            let mut encoded = Vec::new();
+            if let Err(e) = nbt::to_binary(compound, &mut encoded, "") {
+                error!(target: STORAGE, "failed to encode a block entity: {e}");
+                encoded.clear();
+            }
+            bytes.extend_from_slice(&index.to_le_bytes());
+            bytes.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&encoded);
+        }
+        bytes
+    }
+}
+
+/// A chunk read back from disk.
+pub struct SavedChunk {
+    pub chunk: UnloadedChunk,
+    /// Where its block entities are, as offsets inside the chunk.
+    pub block_entities: Vec<[u32; 3]>,
+}
+
+/// Sent when a saved overworld chunk is put back in the world, so systems
+/// that track block entities in memory can load them from their NBT.
+#[derive(Event, Debug)]
+pub struct ChunkRestored {
+    pub pos: ChunkPos,
+    pub block_entities: Vec<BlockPos>,
+}
+
+impl ChunkRestored {
+    /// `None` if the chunk has no block entities to restore.
+    pub fn new(pos: ChunkPos, min_y: i32, offsets: &[[u32; 3]]) -> Option<Self> {
+        if offsets.is_empty() {
+            return None;
+        }
+        let block_entities = offsets
+            .iter()
+            .map(|[x, y, z]| BlockPos::new(pos.x * 16 + *x as i32, min_y + *y as i32, pos.z * 16 + *z as i32))
+            .collect();
+        Some(Self { pos, block_entities })
+    }
+}
+
+fn chunk_path(dir: &Path, pos: ChunkPos) -> PathBuf {
+    dir.join(format!("{}.{}.chunk", pos.x, pos.z))
+}
+
+fn write_chunk(dir: &Path, pos: ChunkPos, snapshot: &ChunkSnapshot) -> std::io::Result<()> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(&snapshot.encode())?;
+    let compressed = encoder.finish()?;
+
+    // Write then rename so a crash mid-write can't leave a torn chunk
+    let path = chunk_path(dir, pos);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, compressed)?;
+    fs::rename(tmp, path)
+}
+
+/// Reads a saved chunk, or `None` if there isn't one (or it's unreadable).
+pub fn read_chunk(dir: &Path, pos: ChunkPos) -> Option<SavedChunk> {
+    let mut bytes = Vec::new();
+    GzDecoder::new(fs::File::open(chunk_path(dir, pos)).ok()?)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if bytes.get(..4)? != MAGIC {
+        return None;
+    }
+    let word = |i: usize| Some(u32::from_le_bytes(bytes.get(i..i + 4)?.try_into().ok()?));
+    let height = word(4)?;
+    let palette_len = word(8)? as usize;
+    let blocks_end = 12 + (palette_len + (height * 256) as usize) * 2;
+    let values: Vec<u16> = bytes
+        .get(12..blocks_end)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let (palette, indices) = values.split_at_checked(palette_len)?;
+    let palette: Vec<BlockState> = palette
+        .iter()
+        .map(|raw| BlockState::from_raw(*raw).unwrap_or(BlockState::AIR))
+        .collect();
+
+    let offset = |i: usize| [(i & 15) as u32, (i >> 8) as u32, ((i >> 4) & 15) as u32];
+    let mut chunk = UnloadedChunk::with_height(height);
+    for (i, index) in indices.iter().enumerate() {
+        let [x, y, z] = offset(i);
+        chunk.set_block_state(x, y, z, *palette.get(*index as usize)?);
+    }
+
+    let mut block_entities = Vec::new();
+    if bytes.len() > blocks_end {
+        let count = word(blocks_end)?;
+        let mut at = blocks_end + 4;
+        for _ in 0..count {
+            let (index, len) = (word(at)? as usize, word(at + 4)? as usize);
+            let mut encoded = bytes.get(at + 8..at + 8 + len)?;
+            at += 8 + len;
+            let Ok((compound, _)) = nbt::from_binary::<String>(&mut encoded) else {
+                error!(target: STORAGE, "skipping an unreadable block entity in chunk {pos:?}");
+                continue;
+            };
+            let [x, y, z] = offset(index);
+            if y < height {
+                chunk.set_block_entity(x, y, z, Some(compound));
+                block_entities.push([x, y, z]);
+            }
+        }
+    }
+    Some(SavedChunk { chunk, block_entities })
+}
+
+enum SaveJob {
+    Write(ChunkPos, ChunkSnapshot),
+    /// Write everything queued now and reply with how many chunks that was.
+    Flush(Sender<usize>),
+}
+
+fn write_pending(dir: &Path, pending: &mut HashMap<ChunkPos, ChunkSnapshot>) -> usize {
+    let mut written = 0;
+    for (pos, snapshot) in pending.drain() {
+        match write_chunk(dir, pos, &snapshot) {
+            Ok(()) => written += 1,
+            Err(e) => error!(target: STORAGE, "failed to save chunk {pos:?}: {e}"),
+        }
+    }
+    written
+}
+
+fn saver_thread(jobs: Receiver<SaveJob>, dir: PathBuf) {
+    let mut pending: HashMap<ChunkPos, ChunkSnapshot> = HashMap::new();
+    let mut last_write = Instant::now();
+    loop {
+        match jobs.recv_timeout(COALESCE_WINDOW) {
+            Ok(SaveJob::Write(pos, snapshot)) => {
+                pending.insert(pos, snapshot);
+                // Keep coalescing unless writes have been held back too long
+                if last_write.elapsed() < COALESCE_WINDOW {
+                    continue;
+                }
+            }
+            Ok(SaveJob::Flush(done)) => {
+                let _ = done.send(write_pending(&dir, &mut pending));
+                last_write = Instant::now();
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                write_pending(&dir, &mut pending);
+                return;
+            }
+        }
+        if !pending.is_empty() {
+            write_pending(&dir, &mut pending);
+        }
+        last_write = Instant::now();
+    }
+}
+
+/// Tracks edited chunks and hands them to the background writer.
+#[derive(Resource)]
+pub struct ChunkSaver {
+    pub dir: PathBuf,
+    dirty: HashSet<ChunkPos>,
+    /// Chunks queued when they unloaded, which might not be written yet.
+    unloaded: HashSet<ChunkPos>,
+    jobs: Sender<SaveJob>,
+}
+
+impl ChunkSaver {
+    pub fn start(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        if let Err(e) = fs::create_dir_all(&dir) {
+            error!(target: STORAGE, "can't create {}: {e}", dir.display());
+        }
+        let (jobs, receiver) = flume::unbounded();
+        let thread_dir = dir.clone();
+        thread::spawn(move || saver_thread(receiver, thread_dir));
+        Self {
+            dir,
+            dirty: HashSet::new(),
+            unloaded: HashSet::new(),
+            jobs,
+        }
+    }
+
+    pub fn mark_dirty(&mut self, pos: ChunkPos) {
+        self.dirty.insert(pos);
+    }
+
+    pub fn mark_block_dirty(&mut self, pos: BlockPos) {
+        self.dirty.insert(ChunkPos::from(pos));
+    }
+
+    pub fn is_dirty(&self, pos: ChunkPos) -> bool {
+        self.dirty.contains(&pos)
+    }
+
+    /// Edited chunks that haven't been queued for saving yet.
+    pub fn dirty_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Snapshots one chunk and queues it, clearing its dirty flag.
+    pub fn save(&mut self, pos: ChunkPos, chunk: &impl Chunk) {
+        self.dirty.remove(&pos);
+        let _ = self.jobs.send(SaveJob::Write(pos, ChunkSnapshot::of(chunk)));
+    }
+
+    /// Queues a dirty chunk that's about to be unloaded.
+    pub fn save_unloading(&mut self, pos: ChunkPos, chunk: &impl Chunk) {
+        self.save(pos, chunk);
+        self.unloaded.insert(pos);
+    }
+
+    /// Makes sure a chunk saved when it unloaded is on disk before it's read
+    /// back, flushing the writer if it might not be.
+    pub fn ensure_written(&mut self, pos: ChunkPos) {
+        if self.unloaded.contains(&pos) {
+            self.flush();
+            self.unloaded.clear();
+        }
+    }
+
+    /// Queues every dirty chunk still loaded in `layer`. Returns how many
+    /// were queued.
+    pub fn save_dirty(&mut self, layer: &ChunkLayer) -> usize {
+        let mut queued = 0;
+        for pos in std::mem::take(&mut self.dirty) {
+            if let Some(chunk) = layer.chunk(pos) {
+                let _ = self.jobs.send(SaveJob::Write(pos, ChunkSnapshot::of(chunk)));
+                queued += 1;
+            }
+        }
+        queued
+    }
+
+    /// Blocks until everything queued so far is on disk. Returns how many
+    /// chunks were written by this flush.
+    pub fn flush(&self) -> usize {
+        let (done, wait) = flume::bounded(1);
+        if self.jobs.send(SaveJob::Flush(done)).is_err() {
+            return 0;
+        }
+        wait.recv().unwrap_or(0)
+    }
+
+    /// Queues every dirty chunk in `layer` and waits until they, and anything
+    /// queued before them, are on disk. Returns how many chunks were written.
+    pub fn save_all(&mut self, layer: Option<&ChunkLayer>) -> usize {
+        if let Some(layer) = layer {
+            self.save_dirty(layer);
+        }
+        let written = self.flush();
+        self.unloaded.clear();
+        written
+    }
+}
+
+pub fn autosave_chunks(
+    layers: Query<&ChunkLayer, With<Overworld>>,
+    mut saver: ResMut<ChunkSaver>,
+    server: Res<Server>,
+) {
+    if server.current_tick() % AUTOSAVE_INTERVAL_TICKS != 0 {
+        return;
+    }
+    let Ok(layer) = layers.get_single() else {
+        return;
+    };
+    let queued = saver.save_dirty(layer);
+    if queued > 0 {
+        info!(target: STORAGE, "autosaving {queued} chunks");
+    }
+}
+
+// Marks the chunks touched by block changes in the overworld as dirty.
+pub fn track_edited_chunks(
+    mut events: EventReader<WorldEvent>,
+    layers: Query<Entity, With<Overworld>>,
+    mut saver: ResMut<ChunkSaver>,
+) {
+    let Ok(overworld) = layers.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if event.layer != overworld {
+            continue;
+        }
+        let center = BlockPos::from(event.pos);
+        match event.kind {
+            WorldEventKind::Explosion { power } => {
+                let r = (power as f64).ceil() as i32;
+                for cx in (center.x - r).div_euclid(16)..=(center.x + r).div_euclid(16) {
+                    for cz in (center.z - r).div_euclid(16)..=(center.z + r).div_euclid(16) {
+                        saver.mark_dirty(ChunkPos::new(cx, cz));
+                    }
+                }
+            }
+            _ => saver.mark_block_dirty(center),
+        }
+    }
+}
@@ -0,0 +1,390 @@
+// src/world/structures.rs
+//
+// Saved structures generated into new terrain. The world is cut into regions
+// of `spacing` chunks and each region gets one structure, started in a chunk
+// picked from the seed and the region position. Since that only depends on
+// the seed, every chunk can work out by itself which structures reach into it
+// and paste its own part of them, whatever order chunks are generated in.
+//
+// The pool is edited in-game with `/structure register` and kept in
+// `world/structure_pool.json`. Spacing is set in `config/worldgen.json`:
+// `"structures": { "spacing": 24, "separation": 8 }`.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use valence::prelude::*;
+
+use super::WorldGenerator;
+use crate::components::container::{Containers, PendingLoot};
+use crate::components::decoration::DecorationsDirty;
+use crate::components::logging::{STORAGE, WORLDGEN};
+use crate::components::loot::{LootTables, STRUCTURE_CHESTS};
+use crate::components::schematic::Schematic;
+use crate::components::spawner::{SpawnerConfig, Spawners};
+
+pub const POOL_FILE: &str = "world/structure_pool.json";
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct StructureConfig {
+    /// Size of a region in chunks. Each region has one structure.
+    pub spacing: i32,
+    /// Structures start at least this many chunks from the next region, so
+    /// neighbouring ones don't end up right next to each other.
+    pub separation: i32,
+}
+
+impl Default for StructureConfig {
+    fn default() -> Self {
+        Self {
+            spacing: 24,
+            separation: 8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PoolEntry {
+    pub name: String,
+    /// Relative chance of being picked for a region.
+    pub weight: u32,
+}
+
+/// The structures that can generate, with their schematics loaded.
+#[derive(Default, Clone)]
+pub struct StructurePool {
+    entries: Vec<(PoolEntry, Arc<Schematic>)>,
+}
+
+impl StructurePool {
+    /// Reads the pool file, leaving out structures whose file is gone.
+    pub fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(POOL_FILE) else {
+            return Self::default();
+        };
+        let saved: Vec<PoolEntry> = match serde_json::from_str(&contents) {
+            Ok(saved) => saved,
+            Err(e) => {
+                error!(target: STORAGE, "failed to parse {POOL_FILE}: {e}");
+                return Self::default();
+            }
+        };
+        let mut pool = Self::default();
+        for entry in saved {
+            match Schematic::load(&entry.name) {
+                Ok(schematic) => pool.entries.push((entry, Arc::new(schematic))),
+                Err(e) => warn!(target: WORLDGEN, "structure {} is in the pool but can't be loaded: {e}", entry.name),
+            }
+        }
+        info!(target: WORLDGEN, "loaded {} structures into the pool", pool.entries.len());
+        pool
+    }
+
+    pub fn save(&self) {
+        let saved: Vec<&PoolEntry> = self.entries.iter().map(|(entry, _)| entry).collect();
+        let result = fs::create_dir_all(Path::new(POOL_FILE).parent().unwrap_or(Path::new(".")))
+            .and_then(|_| serde_json::to_string_pretty(&saved).map_err(std::io::Error::other))
+            .and_then(|json| fs::write(POOL_FILE, json));
+        if let Err(e) = result {
+            error!(target: STORAGE, "failed to save {POOL_FILE}: {e}");
+        }
+    }
+
+    /// Adds a structure, or changes its weight and schematic if it's already
+    /// in the pool.
+    pub fn register(&mut self, name: &str, weight: u32, schematic: Schematic) {
+        let entry = PoolEntry {
+            name: name.to_owned(),
+            weight,
+        };
+        match self.entries.iter_mut().find(|(e, _)| e.name == name) {
+            Some(existing) => *existing = (entry, Arc::new(schematic)),
+            None => self.entries.push((entry, Arc::new(schematic))),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|(e, _)| e.name != name);
+        self.entries.len() != before
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &PoolEntry> {
+        self.entries.iter().map(|(entry, _)| entry)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|(entry, _)| entry.name == name)
+    }
+
+    /// Picks an entry by weight. `roll` is any random number.
+    fn pick(&self, roll: u64) -> Option<&(PoolEntry, Arc<Schematic>)> {
+        let total: u64 = self.entries.iter().map(|(e, _)| e.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = roll % total;
+        self.entries.iter().find(|(entry, _)| {
+            if roll < entry.weight as u64 {
+                return true;
+            }
+            roll -= entry.weight as u64;
+            false
+        })
+    }
+
+    /// How many chunks the biggest structure can reach from its start chunk.
+    fn reach(&self) -> i32 {
+        self.entries
+            .iter()
+            .map(|(_, s)| s.size[0].max(s.size[2]) / 16 + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A structure picked for a region, with its minimum corner in generator
+/// coordinates (y counts from the bottom of the chunk).
+#[derive(Clone, Debug)]
+pub struct PlacedStructure {
+    pub name: String,
+    pub start: ChunkPos,
+    pub origin: BlockPos,
+    pub schematic: Arc<Schematic>,
+}
+
+impl PlacedStructure {
+    /// The block column in the middle of the structure.
+    pub fn center(&self) -> (i32, i32) {
+        let [sx, _, sz] = self.schematic.size;
+        (self.origin.x + sx / 2, self.origin.z + sz / 2)
+    }
+
+    /// Whether any part of the structure is inside the chunk.
+    pub fn intersects(&self, pos: ChunkPos) -> bool {
+        let [sx, _, sz] = self.schematic.size;
+        let (min_x, min_z) = (pos.x * 16, pos.z * 16);
+        self.origin.x < min_x + 16 && self.origin.x + sx > min_x && self.origin.z < min_z + 16 && self.origin.z + sz > min_z
+    }
+
+    /// Writes the part of the structure that's inside the chunk at `pos`.
+    pub fn paste_into(&self, pos: ChunkPos, chunk: &mut UnloadedChunk) {
+        let height = chunk.height() as i32;
+        for ([x, y, z], state) in self.schematic.iter() {
+            if state == BlockState::STRUCTURE_VOID {
+                continue;
+            }
+            let (bx, by, bz) = (self.origin.x + x - pos.x * 16, self.origin.y + y, self.origin.z + z - pos.z * 16);
+            if (0..16).contains(&bx) && (0..16).contains(&bz) && (0..height).contains(&by) {
+                chunk.set_block_state(bx as u32, by as u32, bz as u32, state);
+            }
+        }
+    }
+}
+
+/// Cheap, seedable hash for picking structure positions. Not cryptographic,
+/// just well mixed.
+pub fn region_hash(seed: u32, x: i32, z: i32, salt: u64) -> u64 {
+    let mut h = (seed as u64) ^ (((x as u32 as u64) << 32) | z as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ salt;
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// The chunk a region's structure starts in.
+pub fn region_start(seed: u32, config: &StructureConfig, region: (i32, i32)) -> ChunkPos {
+    let spacing = config.spacing.max(1);
+    let range = (spacing - config.separation).max(1);
+    let hash = region_hash(seed, region.0, region.1, 0);
+    ChunkPos::new(
+        region.0 * spacing + (hash % range as u64) as i32,
+        region.1 * spacing + ((hash >> 32) % range as u64) as i32,
+    )
+}
+
+/// Decides which structure a region gets and where, given a way to find the
+/// surface height (generator y of the top solid block, or `None` where
+/// nothing should be built, like under water).
+pub fn place_in_region(
+    seed: u32,
+    config: &StructureConfig,
+    pool: &StructurePool,
+    region: (i32, i32),
+    surface: impl Fn(i32, i32) -> Option<i32>,
+) -> Option<PlacedStructure> {
+    let start = region_start(seed, config, region);
+    let (entry, schematic) = pool.pick(region_hash(seed, region.0, region.1, 1))?;
+    let anchor_x = start.x * 16 + 8;
+    let anchor_z = start.z * 16 + 8;
+    let ground = surface(anchor_x, anchor_z)?;
+    Some(PlacedStructure {
+        name: entry.name.clone(),
+        start,
+        origin: schematic.origin_for_anchor(BlockPos::new(anchor_x, ground + 1, anchor_z)),
+        schematic: schematic.clone(),
+    })
+}
+
+/// Every structure that reaches into the chunk at `pos`.
+pub fn structures_near(
+    seed: u32,
+    config: &StructureConfig,
+    pool: &StructurePool,
+    pos: ChunkPos,
+    surface: impl Fn(i32, i32) -> Option<i32>,
+) -> Vec<PlacedStructure> {
+    if pool.is_empty() {
+        return Vec::new();
+    }
+    let spacing = config.spacing.max(1);
+    let reach = pool.reach();
+    let regions_x = (pos.x - reach).div_euclid(spacing)..=(pos.x + reach).div_euclid(spacing);
+    let regions_z = (pos.z - reach).div_euclid(spacing)..=(pos.z + reach).div_euclid(spacing);
+    let mut placed = Vec::new();
+    for rz in regions_z {
+        for rx in regions_x.clone() {
+            let start = region_start(seed, config, (rx, rz));
+            if (start.x - pos.x).abs() > reach || (start.z - pos.z).abs() > reach {
+                continue;
+            }
+            if let Some(structure) = place_in_region(seed, config, pool, (rx, rz), &surface)
+                && structure.intersects(pos)
+            {
+                placed.push(structure);
+            }
+        }
+    }
+    placed
+}
+
+/// The nearest region to block `x`, `z` that `found` finds something in,
+/// searching square rings of regions of `spacing` chunks out to `radius`
+/// regions. `found` gives the block column of what's in a region. A region
+/// one ring further out can still be nearer, at a corner, so the ring after
+/// the first hit is checked too.
+pub fn nearest_region<T>(
+    spacing: i32,
+    x: i32,
+    z: i32,
+    radius: i32,
+    mut found: impl FnMut((i32, i32)) -> Option<(T, (i32, i32))>,
+) -> Option<T> {
+    let size = spacing.max(1) * 16;
+    let (center_x, center_z) = (x.div_euclid(size), z.div_euclid(size));
+    let mut nearest: Option<(T, i64)> = None;
+    let mut last_ring = radius;
+    for ring in 0..=radius {
+        if ring > last_ring {
+            break;
+        }
+        let regions = (-ring..=ring).flat_map(|rz| (-ring..=ring).map(move |rx| (rx, rz)));
+        for (rx, rz) in regions.filter(|(rx, rz)| rx.abs().max(rz.abs()) == ring) {
+            let Some((thing, (fx, fz))) = found((center_x + rx, center_z + rz)) else {
+                continue;
+            };
+            let distance = (fx - x) as i64 * (fx - x) as i64 + (fz - z) as i64 * (fz - z) as i64;
+            if nearest.as_ref().is_none_or(|(_, best)| distance < *best) {
+                nearest = Some((thing, distance));
+            }
+        }
+        if nearest.is_some() {
+            last_ring = last_ring.min(ring + 1);
+        }
+    }
+    nearest.map(|(thing, _)| thing)
+}
+
+/// The nearest structure called `name` to the block column `from`, out to
+/// `radius` regions away.
+pub fn find_structure(
+    seed: u32,
+    config: &StructureConfig,
+    pool: &StructurePool,
+    name: &str,
+    from: (i32, i32),
+    radius: i32,
+    surface: impl Fn(i32, i32) -> Option<i32>,
+) -> Option<PlacedStructure> {
+    nearest_region(config.spacing, from.0, from.1, radius, |region| {
+        // Which structure a region gets doesn't need the terrain, so
+        // regions with others are skipped before sampling any
+        let (entry, _) = pool.pick(region_hash(seed, region.0, region.1, 1))?;
+        if entry.name != name {
+            return None;
+        }
+        let structure = place_in_region(seed, config, pool, region, &surface)?;
+        let center = structure.center();
+        Some((structure, center))
+    })
+}
+
+/// Sent when a structure's blocks have been generated or pasted, so the parts
+/// that aren't blocks (container contents, decorations) can be added.
+#[derive(Event, Clone, Debug)]
+pub struct StructurePlaced {
+    pub layer: Entity,
+    pub name: String,
+    /// Minimum corner in world coordinates.
+    pub origin: BlockPos,
+    pub schematic: Arc<Schematic>,
+}
+
+pub fn spawn_structure_contents(
+    mut commands: Commands,
+    mut events: EventReader<StructurePlaced>,
+    mut containers: ResMut<Containers>,
+    mut dirty: ResMut<DecorationsDirty>,
+    loot: Res<LootTables>,
+    world_gen: Res<WorldGenerator>,
+    mut spawners: ResMut<Spawners>,
+    spawner_config: Res<SpawnerConfig>,
+) {
+    for event in events.read() {
+        let [ox, oy, oz] = [event.origin.x, event.origin.y, event.origin.z];
+        // Chests saved empty are filled from a loot table when first opened
+        let own_table = format!("chests/{}", event.name);
+        let table = if loot.get(&own_table).is_some() { own_table.as_str() } else { STRUCTURE_CHESTS };
+        for ([x, y, z], state) in event.schematic.iter() {
+            if state.to_kind() == BlockKind::Spawner {
+                spawners.track(BlockPos::new(ox + x, oy + y, oz + z), &spawner_config);
+                continue;
+            }
+            if !matches!(state.to_kind(), BlockKind::Chest | BlockKind::TrappedChest | BlockKind::Barrel)
+                || event.schematic.containers.iter().any(|c| c.offset == [x, y, z])
+            {
+                continue;
+            }
+            let pos = BlockPos::new(ox + x, oy + y, oz + z);
+            containers.loot.insert(
+                pos,
+                PendingLoot {
+                    table: table.to_owned(),
+                    seed: region_hash(world_gen.seed, pos.x, pos.z, pos.y as u64),
+                },
+            );
+        }
+        for container in &event.schematic.containers {
+            let [x, y, z] = container.offset;
+            let Some(block) = event.schematic.get(container.offset) else {
+                continue;
+            };
+            let items = container.items.iter().map(|(slot, stack)| (*slot, stack.stack()));
+            containers.create_with(&mut commands, BlockPos::new(ox + x, oy + y, oz + z), block.to_kind(), items);
+        }
+        let mut decorations = event.schematic.decorations.clone();
+        decorations.translate([ox, oy, oz]);
+        decorations.spawn(&mut commands, event.layer);
+        dirty.0 |= !decorations.is_empty();
+        info!(target: WORLDGEN, "placed structure {} at {:?}", event.name, event.origin);
+    }
+}
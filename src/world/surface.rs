@@ -0,0 +1,115 @@
+// src/world/surface.rs
+//
+// What the top few blocks of each column of generated terrain are made of.
+// The surface is a list of rules, tried in order for every block from the top
+// of the terrain down through the layer above the stone. The first rule whose
+// conditions all hold picks the block; if none does, it's dirt.
+//
+// The default rules give sand beaches, gravel near the water, sandy deserts,
+// bare mountain peaks and grass everywhere else. `"surface"` in
+// `config/worldgen.json` replaces them all, e.g. to give forests podzol:
+// `{ "surface": [ { "when": ["beach"], "block": "sand" },
+//                 { "when": ["top", { "biome": ["minecraft:forest"] }], "block": "podzol" },
+//                 { "when": ["top"], "block": "grass_block" },
+//                 { "block": "dirt" } ] }`
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use valence::prelude::*;
+
+use super::biomes::Biome;
+
+/// Mountain surfaces this far above sea level are bare stone.
+const STONE_PEAK_HEIGHT: i32 = 70;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// The top block of the column.
+    Top,
+    /// The column's top is in the band of beach along the water line.
+    Beach,
+    /// The block is below the wandering line gravel starts under.
+    BelowGravel,
+    /// The column is in one of these biomes.
+    Biome(Vec<Biome>),
+    /// The column's top is at least this far above sea level.
+    SurfaceAboveSea(i32),
+    /// The column's top is below the preset's `grass_level`.
+    BelowGrassLevel,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SurfaceRule {
+    /// Every condition has to hold. No conditions always matches.
+    #[serde(default)]
+    pub when: Vec<Condition>,
+    #[serde(deserialize_with = "block_state")]
+    pub block: BlockState,
+}
+
+/// Everything rules can check about one block of a column's surface.
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceContext {
+    pub biome: Biome,
+    /// How far below the column's top the block is; 0 is the top.
+    pub depth: u32,
+    pub y: i32,
+    /// The y of the column's top block.
+    pub surface_y: i32,
+    pub sea_level: i32,
+    pub grass_level: i32,
+    pub gravel_height: i32,
+    pub beach: bool,
+}
+
+impl Condition {
+    fn holds(&self, context: &SurfaceContext) -> bool {
+        match self {
+            Condition::Top => context.depth == 0,
+            Condition::Beach => context.beach,
+            Condition::BelowGravel => context.y < context.gravel_height,
+            Condition::Biome(biomes) => biomes.contains(&context.biome),
+            Condition::SurfaceAboveSea(height) => context.surface_y >= context.sea_level + height,
+            Condition::BelowGrassLevel => context.surface_y < context.grass_level,
+        }
+    }
+}
+
+impl SurfaceRule {
+    fn new(when: Vec<Condition>, block: BlockState) -> Self {
+        Self { when, block }
+    }
+
+    /// The surface the generator has always had.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new(vec![Condition::Beach], BlockState::SAND),
+            Self::new(vec![Condition::BelowGravel], BlockState::GRAVEL),
+            Self::new(vec![Condition::Top, Condition::Biome(vec![Biome::Desert])], BlockState::SAND),
+            Self::new(vec![Condition::Biome(vec![Biome::Desert])], BlockState::SANDSTONE),
+            Self::new(
+                vec![Condition::Biome(vec![Biome::Mountains]), Condition::SurfaceAboveSea(STONE_PEAK_HEIGHT)],
+                BlockState::STONE,
+            ),
+            Self::new(vec![Condition::Top, Condition::BelowGrassLevel], BlockState::DIRT),
+            Self::new(vec![Condition::Top], BlockState::GRASS_BLOCK),
+            Self::new(vec![], BlockState::DIRT),
+        ]
+    }
+}
+
+/// The block the first matching rule picks.
+pub fn surface_block(rules: &[SurfaceRule], context: &SurfaceContext) -> BlockState {
+    rules
+        .iter()
+        .find(|rule| rule.when.iter().all(|condition| condition.holds(context)))
+        .map_or(BlockState::DIRT, |rule| rule.block)
+}
+
+/// A block by name, with or without `minecraft:`.
+fn block_state<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BlockState, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    let kind = BlockKind::from_str(name.strip_prefix("minecraft:").unwrap_or(&name));
+    kind.map(BlockKind::to_state).ok_or_else(|| D::Error::custom(format!("unknown block {name:?}")))
+}
@@ -0,0 +1,154 @@
+// src/world/teleport.rs
+//
+// Teleporting into chunks that aren't loaded yet (or into a wall) is handled
+// here rather than by each command. A `SafeTeleportRequest` loads the target
+// chunk through the usual generation pipeline (the overworld's, or the extra
+// world's the player is in), keeps it loaded with a ticket while waiting, then
+// moves the player to the nearest spot they can stand in.
+
+use valence::anvil::AnvilLevel;
+use valence::prelude::*;
+
+use super::worlds::NamedWorld;
+use super::{ChunkTickets, GameState, Overworld};
+
+/// Give up if the destination hasn't loaded after this many ticks.
+const TELEPORT_TIMEOUT_TICKS: u32 = 20 * 10;
+
+/// Moves `entity` to a safe spot at or near `target` in its current layer.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SafeTeleportRequest {
+    pub entity: Entity,
+    pub target: DVec3,
+}
+
+struct PendingTeleport {
+    request: SafeTeleportRequest,
+    ticks_waited: u32,
+    /// The layer a ticket was taken in to load the destination.
+    ticket: Option<Entity>,
+}
+
+#[derive(Resource, Default)]
+pub struct PendingTeleports(Vec<PendingTeleport>);
+
+fn is_standable(layer: &ChunkLayer, pos: BlockPos) -> bool {
+    let state = |offset: i32| {
+        layer
+            .block([pos.x, pos.y + offset, pos.z])
+            .map_or(BlockState::AIR, |block| block.state)
+    };
+    let dangerous = |state: BlockState| matches!(state.to_kind(), BlockKind::Lava | BlockKind::Fire | BlockKind::MagmaBlock);
+    let (ground, feet, head) = (state(-1), state(0), state(1));
+    ground.blocks_motion() && !dangerous(ground) && !feet.blocks_motion() && !head.blocks_motion() && !dangerous(feet)
+}
+
+/// Finds the closest standable Y in the column, looking up from `target`
+/// first (so players end up on top of whatever they'd be stuck in), then down.
+pub fn safe_position(layer: &ChunkLayer, target: DVec3) -> Option<DVec3> {
+    let min_y = layer.min_y() + 1;
+    let max_y = layer.min_y() + layer.height() as i32 - 2;
+    let start = (target.y.floor() as i32).clamp(min_y, max_y);
+    let column = |y: i32| BlockPos::new(target.x.floor() as i32, y, target.z.floor() as i32);
+
+    (start..=max_y)
+        .chain((min_y..start).rev())
+        .find(|y| is_standable(layer, column(*y)))
+        .map(|y| DVec3::new(target.x.floor() + 0.5, y as f64, target.z.floor() + 0.5))
+}
+
+pub fn queue_safe_teleports(
+    mut requests: EventReader<SafeTeleportRequest>,
+    mut pending: ResMut<PendingTeleports>,
+    mut overworld: Query<(Entity, &ChunkLayer, Option<&mut AnvilLevel>), With<Overworld>>,
+    mut worlds: Query<(&ChunkLayer, &mut GameState, &mut ChunkTickets), With<NamedWorld>>,
+    entity_layers: Query<&EntityLayerId>,
+    mut state: ResMut<GameState>,
+    mut tickets: ResMut<ChunkTickets>,
+) {
+    for request in requests.read() {
+        let chunk = ChunkPos::from(request.target);
+        let layer_id = entity_layers.get(request.entity).ok().map(|id| id.0);
+        let mut ticket = None;
+        // The Nether and the End are fully generated up front
+        if let Ok((layer_entity, layer, anvil)) = overworld.get_single_mut()
+            && layer_id == Some(layer_entity)
+        {
+            if layer.chunk(chunk).is_none() {
+                tickets.0.insert(chunk);
+                ticket = Some(layer_entity);
+                match anvil {
+                    Some(mut anvil) => anvil.force_chunk_load(chunk),
+                    None => state.request_chunk(chunk),
+                }
+            }
+        } else if let Some(layer_entity) = layer_id
+            && let Ok((layer, mut world_state, mut world_tickets)) = worlds.get_mut(layer_entity)
+            && layer.chunk(chunk).is_none()
+        {
+            world_tickets.0.insert(chunk);
+            ticket = Some(layer_entity);
+            world_state.request_chunk(chunk);
+        }
+        pending.0.push(PendingTeleport {
+            request: *request,
+            ticks_waited: 0,
+            ticket,
+        });
+    }
+}
+
+/// Gives back the ticket `teleport` took, in whichever world it was taken.
+fn release_ticket(teleport: &PendingTeleport, tickets: &mut ChunkTickets, world_tickets: &mut Query<&mut ChunkTickets>) {
+    let Some(layer) = teleport.ticket else {
+        return;
+    };
+    let chunk = ChunkPos::from(teleport.request.target);
+    match world_tickets.get_mut(layer) {
+        Ok(mut world_tickets) => world_tickets.0.remove(&chunk),
+        Err(_) => tickets.0.remove(&chunk),
+    };
+}
+
+pub fn resolve_safe_teleports(
+    mut pending: ResMut<PendingTeleports>,
+    mut tickets: ResMut<ChunkTickets>,
+    mut world_tickets: Query<&mut ChunkTickets>,
+    layers: Query<&ChunkLayer>,
+    mut entities: Query<(&mut Position, &EntityLayerId, Option<&mut Client>)>,
+) {
+    pending.0.retain_mut(|teleport| {
+        let SafeTeleportRequest { entity, target } = teleport.request;
+        let chunk = ChunkPos::from(target);
+        let Ok((mut position, layer_id, client)) = entities.get_mut(entity) else {
+            release_ticket(teleport, &mut tickets, &mut world_tickets);
+            return false;
+        };
+        let Ok(layer) = layers.get(layer_id.0) else {
+            return false;
+        };
+
+        if layer.chunk(chunk).is_none() {
+            teleport.ticks_waited += 1;
+            if teleport.ticks_waited < TELEPORT_TIMEOUT_TICKS {
+                return true;
+            }
+            release_ticket(teleport, &mut tickets, &mut world_tickets);
+            if let Some(mut client) = client {
+                client.send_chat_message("[tp] destination didn't load in time, teleport cancelled".color(Color::RED));
+            }
+            return false;
+        }
+
+        release_ticket(teleport, &mut tickets, &mut world_tickets);
+        match safe_position(layer, target) {
+            Some(safe) => position.set(safe),
+            None => {
+                if let Some(mut client) = client {
+                    client.send_chat_message("[tp] nowhere safe to stand there, teleport cancelled".color(Color::RED));
+                }
+            }
+        }
+        false
+    });
+}
@@ -0,0 +1,72 @@
+// src/world/throttle.rs
+//
+// Keeps chunk generation from starving the tick loop. The time spent in each
+// tick (MSPT) is tracked, and the number of chunks inserted into the world and
+// handed to the workers per tick shrinks while ticks run long, then ramps
+// back up once the server has headroom again.
+
+use std::time::Instant;
+
+use serde::Deserialize;
+use tracing::info;
+use valence::prelude::*;
+
+use crate::components::logging::WORLDGEN;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ThrottleConfig {
+    /// Above this many milliseconds per tick, the chunk budget is halved.
+    pub target_mspt: f32,
+    pub min_chunks_per_tick: usize,
+    pub max_chunks_per_tick: usize,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            target_mspt: 40.0,
+            min_chunks_per_tick: 4,
+            max_chunks_per_tick: 64,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ChunkThrottle {
+    config: ThrottleConfig,
+    tick_start: Instant,
+    /// Smoothed milliseconds per tick.
+    pub mspt: f32,
+    /// Chunks that may be inserted, and separately dispatched, this tick.
+    pub budget: usize,
+}
+
+impl ChunkThrottle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self {
+            budget: config.max_chunks_per_tick,
+            config,
+            tick_start: Instant::now(),
+            mspt: 0.0,
+        }
+    }
+}
+
+pub fn start_tick_timer(mut throttle: ResMut<ChunkThrottle>) {
+    throttle.tick_start = Instant::now();
+}
+
+pub fn update_chunk_throttle(mut throttle: ResMut<ChunkThrottle>) {
+    let elapsed = throttle.tick_start.elapsed().as_secs_f32() * 1000.0;
+    throttle.mspt = throttle.mspt * 0.9 + elapsed * 0.1;
+
+    let ThrottleConfig { target_mspt, min_chunks_per_tick, max_chunks_per_tick } = throttle.config;
+    let budget = throttle.budget;
+    if throttle.mspt > target_mspt && budget > min_chunks_per_tick {
+        throttle.budget = (budget / 2).max(min_chunks_per_tick);
+        info!(target: WORLDGEN, "MSPT {:.1} over {target_mspt}, chunk budget lowered to {}", throttle.mspt, throttle.budget);
+    } else if throttle.mspt < target_mspt / 2.0 && budget < max_chunks_per_tick {
+        throttle.budget = (budget + 2).min(max_chunks_per_tick);
+    }
+}
@@ -0,0 +1,173 @@
+// src/world/trees.rs
+//
+// Oak and birch trees on grass. The world is split into cells of `CELL_SIZE`
+// blocks and each cell gets at most one tree, at a spot picked from the seed,
+// so trunks never end up next to each other. A low-frequency placement noise
+// decides how likely a cell is to have a tree, which the biome then adjusts,
+// so there are clumps of trees even in plains and forests have gaps.
+//
+// Canopies reach `CANOPY_RADIUS` blocks past the trunk, so a chunk also looks
+// at cells just outside it and pastes in the part of any tree that hangs
+// over. Everything only depends on the seed and the terrain, so neighbouring
+// chunks agree on where the trees are without talking to each other.
+
+use noise::SuperSimplex;
+use valence::prelude::*;
+
+use super::noise01;
+use super::structures::region_hash;
+
+const CELL_SIZE: i32 = 5;
+const CANOPY_RADIUS: i32 = 2;
+/// Divisor for block coordinates when sampling the placement noise.
+const PLACEMENT_SCALE: f64 = 120.0;
+/// Placement noise below this only has the odd lone tree.
+const FOREST_THRESHOLD: f64 = 0.5;
+/// Chance of a tree in a cell outside forests.
+const LONE_TREE_CHANCE: f64 = 0.03;
+/// Keeps tree hashes apart from structure hashes for the same coordinates.
+const TREE_SALT: u64 = 0x7472_6565;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum TreeKind {
+    Oak,
+    Birch,
+}
+
+impl TreeKind {
+    fn log(self) -> BlockState {
+        match self {
+            TreeKind::Oak => BlockState::OAK_LOG,
+            TreeKind::Birch => BlockState::BIRCH_LOG,
+        }
+    }
+
+    fn leaves(self) -> BlockState {
+        let leaves = match self {
+            TreeKind::Oak => BlockState::OAK_LEAVES,
+            TreeKind::Birch => BlockState::BIRCH_LEAVES,
+        };
+        // Nothing here decays leaves, but a vanilla server loading the saved
+        // world would.
+        leaves.set(PropName::Persistent, PropValue::True)
+    }
+}
+
+struct Tree {
+    kind: TreeKind,
+    /// The lowest log.
+    base: BlockPos,
+    trunk_height: i32,
+    /// Random bits for trimming canopy corners.
+    shape: u64,
+}
+
+impl Tree {
+    /// Leaf positions, layer by layer from the bottom of the canopy.
+    fn leaves(&self) -> impl Iterator<Item = BlockPos> + '_ {
+        let top = self.base.y + self.trunk_height;
+        (top - 3..=top).flat_map(move |y| {
+            let radius = if y >= top - 1 { 1 } else { CANOPY_RADIUS };
+            (-radius..=radius).flat_map(move |dx| {
+                (-radius..=radius).filter_map(move |dz| {
+                    let corner = dx.abs() == radius && dz.abs() == radius;
+                    // The very top never has corners, other layers lose
+                    // some of theirs at random.
+                    let bit = (y - top + 3) * 4 + (dx > 0) as i32 * 2 + (dz > 0) as i32;
+                    if corner && (y == top || (self.shape >> bit) & 1 == 0) {
+                        return None;
+                    }
+                    Some(BlockPos::new(self.base.x + dx, y, self.base.z + dz))
+                })
+            })
+        })
+    }
+}
+
+/// The tree in a cell, if it has one. `adjust` changes the chance of a tree
+/// at a spot, and `ground` gives the y of the grass block on top of a column,
+/// or `None` if the column isn't grass.
+fn tree_in_cell(
+    seed: u32,
+    placement: &SuperSimplex,
+    cell_x: i32,
+    cell_z: i32,
+    adjust: &impl Fn(i32, i32, f64) -> f64,
+    ground: &impl Fn(i32, i32) -> Option<i32>,
+) -> Option<Tree> {
+    let hash = region_hash(seed, cell_x, cell_z, TREE_SALT);
+    let x = cell_x * CELL_SIZE + (hash % CELL_SIZE as u64) as i32;
+    let z = cell_z * CELL_SIZE + ((hash >> 8) & 0xff) as i32 % CELL_SIZE;
+
+    let density = noise01(placement, DVec3::new(x as f64, 0.0, z as f64) / PLACEMENT_SCALE);
+    let chance = adjust(x, z, LONE_TREE_CHANCE.max((density - FOREST_THRESHOLD) / (1.0 - FOREST_THRESHOLD)));
+    let roll = ((hash >> 16) & 0xffff) as f64 / 65536.0;
+    if roll >= chance {
+        return None;
+    }
+
+    let kind = if (hash >> 32) & 0xf < 3 { TreeKind::Birch } else { TreeKind::Oak };
+    let trunk_height = match kind {
+        TreeKind::Oak => 4,
+        TreeKind::Birch => 5,
+    } + ((hash >> 36) & 0xff) as i32 % 3;
+    let y = ground(x, z)?;
+    Some(Tree {
+        kind,
+        base: BlockPos::new(x, y + 1, z),
+        trunk_height,
+        shape: hash >> 44,
+    })
+}
+
+/// Pastes the parts of all trees that reach into the chunk at `pos`.
+pub fn place_trees(
+    seed: u32,
+    placement: &SuperSimplex,
+    pos: ChunkPos,
+    chunk: &mut UnloadedChunk,
+    adjust: impl Fn(i32, i32, f64) -> f64,
+    ground: impl Fn(i32, i32) -> Option<i32>,
+) {
+    let (min_x, min_z) = (pos.x * 16, pos.z * 16);
+    let cells = |min: i32| (min - CANOPY_RADIUS).div_euclid(CELL_SIZE)..=(min + 15 + CANOPY_RADIUS).div_euclid(CELL_SIZE);
+    let mut trees = Vec::new();
+    for cell_x in cells(min_x) {
+        for cell_z in cells(min_z) {
+            if let Some(tree) = tree_in_cell(seed, placement, cell_x, cell_z, &adjust, &ground) {
+                trees.push(tree);
+            }
+        }
+    }
+
+    let height = chunk.height() as i32;
+    let mut set = |block: BlockPos, state: BlockState, replace: fn(BlockState) -> bool| {
+        let (x, z) = (block.x - min_x, block.z - min_z);
+        if !(0..16).contains(&x) || !(0..16).contains(&z) || !(0..height).contains(&block.y) {
+            return;
+        }
+        let (x, y, z) = (x as u32, block.y as u32, z as u32);
+        if replace(chunk.block_state(x, y, z)) {
+            chunk.set_block_state(x, y, z, state);
+        }
+    };
+
+    // All leaves go in before any logs, so overlapping trees come out the same
+    // whichever order they're pasted in.
+    for tree in &trees {
+        let leaves = tree.kind.leaves();
+        for block in tree.leaves() {
+            set(block, leaves, |state| !state.blocks_motion() && !state.is_liquid());
+        }
+    }
+    for tree in &trees {
+        let below = BlockPos::new(tree.base.x, tree.base.y - 1, tree.base.z);
+        set(below, BlockState::DIRT, |state| state == BlockState::GRASS_BLOCK);
+        for dy in 0..tree.trunk_height {
+            let block = BlockPos::new(tree.base.x, tree.base.y + dy, tree.base.z);
+            set(block, tree.kind.log(), |state| {
+                (!state.blocks_motion() && !state.is_liquid()) || state.to_kind().to_str().ends_with("_leaves")
+            });
+        }
+    }
+}
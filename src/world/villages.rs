@@ -0,0 +1,496 @@
+// src/world/villages.rs
+//
+// Small villages in plains and deserts: one or two streets crossing at the
+// middle, with houses and wheat farms along them. The world is cut into
+// regions like for saved structures and each region can have a village,
+// centred in a chunk picked from the seed.
+//
+// A village's layout (where streets, houses and farms go) only depends on the
+// seed and region, so every chunk can lay it out by itself and build the part
+// that's inside it. The terrain is then asked for the ground height under
+// each house and farm, and pieces on ground that's too steep or under water
+// are left out. Streets follow the ground block by block.
+//
+// `config/worldgen.json`:
+// `"villages": { "enabled": true, "spacing": 32, "separation": 8, "max_slope": 3 }`
+
+use serde::Deserialize;
+use valence::prelude::*;
+use valence::rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::biomes::Biome;
+use super::structures::{nearest_region, region_hash, region_start, StructureConfig};
+
+/// Keeps village hashes apart from structure hashes for the same region.
+const VILLAGE_SALT: u64 = 0x7669_6c6c;
+/// How far a village can reach from its centre chunk, in chunks.
+const REACH: i32 = 3;
+/// Gap between a street's edge and the pieces along it.
+const SETBACK: i32 = 2;
+/// Houses and farms clear this far above their floor, for trees in the way.
+const CLEARANCE: i32 = 10;
+/// Foundations go at most this far down to reach the ground.
+const FOUNDATION_DEPTH: i32 = 4;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct VillageConfig {
+    pub enabled: bool,
+    /// Size of a region in chunks. Each region can have one village.
+    pub spacing: i32,
+    /// Villages are centred at least this many chunks from the next region.
+    pub separation: i32,
+    /// Most the ground under a house or farm can vary by, in blocks.
+    pub max_slope: i32,
+}
+
+impl Default for VillageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spacing: 32,
+            separation: 8,
+            max_slope: 3,
+        }
+    }
+}
+
+impl VillageConfig {
+    fn placement(&self) -> StructureConfig {
+        StructureConfig {
+            spacing: self.spacing,
+            separation: self.separation,
+        }
+    }
+}
+
+/// Blocks a village is built from, picked by the biome at its centre.
+#[derive(Clone, Copy, Debug)]
+struct Palette {
+    path: BlockState,
+    /// Streets over water.
+    bridge: BlockState,
+    floor: BlockState,
+    wall: BlockState,
+    corner: BlockState,
+    roof: BlockState,
+    door: BlockState,
+}
+
+impl Palette {
+    fn for_biome(biome: Biome) -> Option<Self> {
+        match biome {
+            Biome::Plains => Some(Self {
+                path: BlockState::DIRT_PATH,
+                bridge: BlockState::OAK_PLANKS,
+                floor: BlockState::COBBLESTONE,
+                wall: BlockState::OAK_PLANKS,
+                corner: BlockState::OAK_LOG,
+                roof: BlockState::SPRUCE_PLANKS,
+                door: BlockState::OAK_DOOR,
+            }),
+            Biome::Desert => Some(Self {
+                path: BlockState::SMOOTH_SANDSTONE,
+                bridge: BlockState::JUNGLE_PLANKS,
+                floor: BlockState::SMOOTH_SANDSTONE,
+                wall: BlockState::SANDSTONE,
+                corner: BlockState::CUT_SANDSTONE,
+                roof: BlockState::SMOOTH_SANDSTONE,
+                door: BlockState::JUNGLE_DOOR,
+            }),
+            Biome::Forest | Biome::Mountains | Biome::Ocean | Biome::River => None,
+        }
+    }
+}
+
+/// A rectangle of columns, corners included.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Area {
+    min_x: i32,
+    min_z: i32,
+    max_x: i32,
+    max_z: i32,
+}
+
+impl Area {
+    fn new((x1, z1): (i32, i32), (x2, z2): (i32, i32)) -> Self {
+        Self {
+            min_x: x1.min(x2),
+            min_z: z1.min(z2),
+            max_x: x1.max(x2),
+            max_z: z1.max(z2),
+        }
+    }
+
+    fn chunk(pos: ChunkPos) -> Self {
+        Self::new((pos.x * 16, pos.z * 16), (pos.x * 16 + 15, pos.z * 16 + 15))
+    }
+
+    fn intersects(&self, other: &Area) -> bool {
+        self.min_x <= other.max_x && self.max_x >= other.min_x && self.min_z <= other.max_z && self.max_z >= other.min_z
+    }
+
+    fn intersection(&self, other: &Area) -> Self {
+        Self {
+            min_x: self.min_x.max(other.min_x),
+            min_z: self.min_z.max(other.min_z),
+            max_x: self.max_x.min(other.max_x),
+            max_z: self.max_z.min(other.max_z),
+        }
+    }
+
+    fn union(&self, other: &Area) -> Self {
+        Self {
+            min_x: self.min_x.min(other.min_x),
+            min_z: self.min_z.min(other.min_z),
+            max_x: self.max_x.max(other.max_x),
+            max_z: self.max_z.max(other.max_z),
+        }
+    }
+
+    fn columns(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (self.min_x..=self.max_x).flat_map(move |x| (self.min_z..=self.max_z).map(move |z| (x, z)))
+    }
+
+    fn is_edge(&self, x: i32, z: i32) -> bool {
+        x == self.min_x || x == self.max_x || z == self.min_z || z == self.max_z
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PieceKind {
+    House,
+    Farm,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Piece {
+    kind: PieceKind,
+    area: Area,
+    /// The column on the street side houses are entered from.
+    door: (i32, i32),
+    /// Which way the door faces, away from the street.
+    facing: PropValue,
+    /// Random bits for crop growth.
+    seed: u64,
+}
+
+#[derive(Clone, Debug)]
+struct Village {
+    /// Where the streets cross.
+    center: (i32, i32),
+    streets: Vec<Area>,
+    pieces: Vec<Piece>,
+    bounds: Area,
+}
+
+impl Village {
+    /// Lays out the village for a region. This doesn't look at the terrain,
+    /// so it can't say whether the village actually gets built.
+    fn layout(seed: u32, config: &VillageConfig, region: (i32, i32)) -> Self {
+        let start = region_start(seed ^ VILLAGE_SALT as u32, &config.placement(), region);
+        let mut rng = StdRng::seed_from_u64(region_hash(seed, region.0, region.1, VILLAGE_SALT));
+        let (cx, cz) = (start.x * 16 + 8, start.z * 16 + 8);
+
+        let main_along_x = rng.r#gen::<bool>();
+        let mut streets = vec![(main_along_x, rng.gen_range(14..=28))];
+        if rng.gen_bool(0.6) {
+            streets.push((!main_along_x, rng.gen_range(10..=20)));
+        }
+
+        let mut village = Village {
+            center: (cx, cz),
+            streets: Vec::new(),
+            pieces: Vec::new(),
+            bounds: Area::new((cx, cz), (cx, cz)),
+        };
+        // `along` runs down the street and `across` away from it, so both
+        // directions share the code below.
+        for &(along_x, half_length) in &streets {
+            let at = move |along: i32, across: i32| if along_x { (cx + along, cz + across) } else { (cx + across, cz + along) };
+            village.streets.push(Area::new(at(-half_length, -1), at(half_length, 1)));
+        }
+
+        for &(along_x, half_length) in &streets {
+            let at = move |along: i32, across: i32| if along_x { (cx + along, cz + across) } else { (cx + across, cz + along) };
+            for side in [-1, 1] {
+                let near = side * (1 + SETBACK + 1);
+                let mut along = -half_length;
+                while along < half_length {
+                    let roll = rng.gen_range(0..10);
+                    let (kind, width, depth) = match roll {
+                        0..6 => (PieceKind::House, rng.gen_range(5..=7), rng.gen_range(5..=6)),
+                        6..9 => (PieceKind::Farm, 9, 7),
+                        _ => {
+                            along += 4;
+                            continue;
+                        }
+                    };
+                    if along + width - 1 > half_length {
+                        break;
+                    }
+                    let far = near + side * (depth - 1);
+                    let door_along = along + width / 2;
+                    let facing = match (along_x, side) {
+                        (true, 1) => PropValue::North,
+                        (true, _) => PropValue::South,
+                        (false, 1) => PropValue::West,
+                        (false, _) => PropValue::East,
+                    };
+                    let piece = Piece {
+                        kind,
+                        area: Area::new(at(along, near), at(along + width - 1, far)),
+                        door: at(door_along, near),
+                        facing,
+                        seed: rng.r#gen(),
+                    };
+                    // A path from the street to the door.
+                    let walk = Area::new(at(door_along, side * 2), at(door_along, near - side));
+                    let clear = !village.streets.iter().any(|street| street.intersects(&piece.area))
+                        && !village
+                            .pieces
+                            .iter()
+                            .any(|other| other.area.intersects(&piece.area) || other.area.intersects(&walk));
+                    if clear {
+                        if kind == PieceKind::House {
+                            village.streets.push(walk);
+                        }
+                        village.pieces.push(piece);
+                    }
+                    along += width + rng.gen_range(1..=3);
+                }
+            }
+        }
+
+        for area in village.streets.iter().chain(village.pieces.iter().map(|piece| &piece.area)) {
+            village.bounds = village.bounds.union(area);
+        }
+        village
+    }
+
+    fn intersects(&self, pos: ChunkPos) -> bool {
+        self.bounds.intersects(&Area::chunk(pos))
+    }
+
+    /// Builds the part of the village inside the chunk at `pos`. `ground`
+    /// gives the y of the top solid block of a column in generated terrain,
+    /// or `None` where nothing should be built, like under water.
+    fn build(&self, pos: ChunkPos, chunk: &mut UnloadedChunk, palette: &Palette, max_slope: i32, ground: &impl Fn(i32, i32) -> Option<i32>) {
+        let inside = Area::chunk(pos);
+        let mut blocks = ChunkBlocks { chunk, min_x: pos.x * 16, min_z: pos.z * 16 };
+        for street in self.streets.iter().filter(|street| street.intersects(&inside)) {
+            for (x, z) in street.intersection(&inside).columns() {
+                blocks.lay_path(x, z, palette);
+            }
+        }
+        for piece in &self.pieces {
+            if !piece.area.intersects(&inside) {
+                continue;
+            }
+            let Some(floor) = piece.floor(max_slope, ground) else {
+                continue;
+            };
+            match piece.kind {
+                PieceKind::House => piece.build_house(&mut blocks, floor, palette),
+                PieceKind::Farm => piece.build_farm(&mut blocks, floor, palette),
+            }
+        }
+    }
+}
+
+impl Piece {
+    /// The floor height, if the ground under the piece is dry and flat enough.
+    fn floor(&self, max_slope: i32, ground: &impl Fn(i32, i32) -> Option<i32>) -> Option<i32> {
+        let Area { min_x, min_z, max_x, max_z } = self.area;
+        let samples = [
+            (min_x, min_z),
+            (max_x, min_z),
+            (min_x, max_z),
+            (max_x, max_z),
+            ((min_x + max_x) / 2, (min_z + max_z) / 2),
+        ];
+        let mut heights = Vec::with_capacity(samples.len());
+        for (x, z) in samples {
+            heights.push(ground(x, z)?);
+        }
+        let (low, high) = (*heights.iter().min()?, *heights.iter().max()?);
+        (high - low <= max_slope).then_some(high)
+    }
+
+    /// Fills down to the ground under the floor and clears the space above.
+    fn prepare(&self, blocks: &mut ChunkBlocks, floor: i32, foundation: BlockState) {
+        for (x, z) in self.area.columns() {
+            for y in (floor - FOUNDATION_DEPTH..floor).rev() {
+                if blocks.get(x, y, z).is_none_or(|state| !is_clearable(state) && !state.is_liquid()) {
+                    break;
+                }
+                blocks.set(x, y, z, foundation);
+            }
+            for y in floor + 1..=floor + CLEARANCE {
+                blocks.set(x, y, z, BlockState::AIR);
+            }
+        }
+    }
+
+    fn build_house(&self, blocks: &mut ChunkBlocks, floor: i32, palette: &Palette) {
+        self.prepare(blocks, floor, palette.floor);
+        let area = self.area;
+        let (mid_x, mid_z) = ((area.min_x + area.max_x) / 2, (area.min_z + area.max_z) / 2);
+        for (x, z) in area.columns() {
+            blocks.set(x, floor, z, palette.floor);
+            blocks.set(x, floor + 4, z, palette.roof);
+            if !area.is_edge(x, z) {
+                continue;
+            }
+            let corner = (x == area.min_x || x == area.max_x) && (z == area.min_z || z == area.max_z);
+            for y in floor + 1..=floor + 3 {
+                let window = y == floor + 2 && (x == mid_x || z == mid_z);
+                let state = if corner {
+                    palette.corner
+                } else if window {
+                    BlockState::GLASS
+                } else {
+                    palette.wall
+                };
+                blocks.set(x, y, z, state);
+            }
+        }
+        let (door_x, door_z) = self.door;
+        let door = palette.door.set(PropName::Facing, self.facing);
+        blocks.set(door_x, floor + 1, door_z, door.set(PropName::Half, PropValue::Lower));
+        blocks.set(door_x, floor + 2, door_z, door.set(PropName::Half, PropValue::Upper));
+        blocks.set(mid_x, floor + 1, mid_z, BlockState::TORCH);
+    }
+
+    fn build_farm(&self, blocks: &mut ChunkBlocks, floor: i32, palette: &Palette) {
+        self.prepare(blocks, floor, BlockState::DIRT);
+        let area = self.area;
+        // The water runs down the long side so every crop is within reach.
+        let along_x = area.max_x - area.min_x >= area.max_z - area.min_z;
+        let (mid_x, mid_z) = ((area.min_x + area.max_x) / 2, (area.min_z + area.max_z) / 2);
+        let farmland = BlockState::FARMLAND.set(PropName::Moisture, PropValue::_7);
+        for (index, (x, z)) in area.columns().enumerate() {
+            if area.is_edge(x, z) {
+                blocks.set(x, floor, z, palette.corner);
+            } else if (along_x && z == mid_z) || (!along_x && x == mid_x) {
+                blocks.set(x, floor, z, BlockState::WATER);
+            } else {
+                blocks.set(x, floor, z, farmland);
+                let age = PropValue::from_u16(((self.seed >> (index % 60)) % 8) as u16).unwrap_or(PropValue::_0);
+                blocks.set(x, floor + 1, z, BlockState::WHEAT.set(PropName::Age, age));
+            }
+        }
+    }
+}
+
+/// Writes blocks given in world x and z into one chunk, dropping anything
+/// outside it.
+struct ChunkBlocks<'a> {
+    chunk: &'a mut UnloadedChunk,
+    min_x: i32,
+    min_z: i32,
+}
+
+impl ChunkBlocks<'_> {
+    fn local(&self, x: i32, y: i32, z: i32) -> Option<(u32, u32, u32)> {
+        let (x, z) = (x - self.min_x, z - self.min_z);
+        ((0..16).contains(&x) && (0..16).contains(&z) && (0..self.chunk.height() as i32).contains(&y))
+            .then_some((x as u32, y as u32, z as u32))
+    }
+
+    fn get(&self, x: i32, y: i32, z: i32) -> Option<BlockState> {
+        let (x, y, z) = self.local(x, y, z)?;
+        Some(self.chunk.block_state(x, y, z))
+    }
+
+    fn set(&mut self, x: i32, y: i32, z: i32, state: BlockState) {
+        if let Some((x, y, z)) = self.local(x, y, z) {
+            self.chunk.set_block_state(x, y, z, state);
+        }
+    }
+
+    /// Turns the top of a column into street, clearing plants and trees on it.
+    fn lay_path(&mut self, x: i32, z: i32, palette: &Palette) {
+        for y in (0..self.chunk.height() as i32).rev() {
+            let Some(state) = self.get(x, y, z) else {
+                return;
+            };
+            if state.is_liquid() {
+                self.set(x, y, z, palette.bridge);
+                return;
+            }
+            if !is_clearable(state) {
+                self.set(x, y, z, palette.path);
+                return;
+            }
+            if !state.is_air() {
+                self.set(x, y, z, BlockState::AIR);
+            }
+        }
+    }
+}
+
+/// Air, plants and trees: things a village can build over.
+fn is_clearable(state: BlockState) -> bool {
+    let name = state.to_kind().to_str();
+    (!state.blocks_motion() && !state.is_liquid()) || name.ends_with("_leaves") || name.ends_with("_log")
+}
+
+/// What a village is built from, or `None` if it isn't built: its centre has
+/// to be on dry land in a biome villages are built in.
+fn palette_for(village: &Village, biome: &impl Fn(i32, i32) -> Biome, ground: &impl Fn(i32, i32) -> Option<i32>) -> Option<Palette> {
+    let (x, z) = village.center;
+    let palette = Palette::for_biome(biome(x, z))?;
+    ground(x, z)?;
+    Some(palette)
+}
+
+/// Builds the parts of all villages that reach into the chunk at `pos`.
+pub fn place_villages(
+    seed: u32,
+    config: &VillageConfig,
+    pos: ChunkPos,
+    chunk: &mut UnloadedChunk,
+    biome: impl Fn(i32, i32) -> Biome,
+    ground: impl Fn(i32, i32) -> Option<i32>,
+) {
+    if !config.enabled {
+        return;
+    }
+    let spacing = config.spacing.max(1);
+    let regions = |min: i32| (min - REACH).div_euclid(spacing)..=(min + REACH).div_euclid(spacing);
+    for region_x in regions(pos.x) {
+        for region_z in regions(pos.z) {
+            // Laying out is cheap, so villages that don't reach this chunk
+            // are skipped before sampling any terrain.
+            let village = Village::layout(seed, config, (region_x, region_z));
+            if !village.intersects(pos) {
+                continue;
+            }
+            if let Some(palette) = palette_for(&village, &biome, &ground) {
+                village.build(pos, chunk, &palette, config.max_slope, &ground);
+            }
+        }
+    }
+}
+
+/// The centre of the nearest village to block `x`, `z`, out to `radius`
+/// regions away.
+pub fn find_village(
+    seed: u32,
+    config: &VillageConfig,
+    x: i32,
+    z: i32,
+    radius: i32,
+    biome: impl Fn(i32, i32) -> Biome,
+    ground: impl Fn(i32, i32) -> Option<i32>,
+) -> Option<(i32, i32)> {
+    if !config.enabled {
+        return None;
+    }
+    nearest_region(config.spacing, x, z, radius, |region| {
+        let village = Village::layout(seed, config, region);
+        palette_for(&village, &biome, &ground)?;
+        Some((village.center, village.center))
+    })
+}
@@ -0,0 +1,137 @@
+// src/world/worlds.rs
+//
+// Extra worlds next to the overworld. Each is a layer of its own with its own
+// generator, chunk queue and worker threads, listed by name in
+// `config/worlds.json` with the same terrain settings as `config/worldgen.json`:
+// `{ "worlds": { "flatland": { "preset": "flat" }, "isles": { "preset": "islands", "seed": 42 } } }`
+// A world without a seed gets one from the overworld's seed and its name.
+// `min_y` and `height` are always the overworld's, since every world uses the
+// same dimension type.
+//
+// Like the Nether, extra worlds aren't saved: their chunks are generated
+// again after they unload, and edits to them are lost.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tracing::info;
+use valence::prelude::*;
+
+use super::structures::StructurePlaced;
+use super::throttle::ChunkThrottle;
+use super::{build_generator, insert_generated_chunk, ChunkTickets, GameState, WorldGenConfig, WorldGenerator};
+use crate::components::config::load_config;
+use crate::components::logging::WORLDGEN;
+
+/// The name `/world` uses for the overworld.
+pub const OVERWORLD_NAME: &str = "overworld";
+
+/// `config/worlds.json`
+#[derive(Deserialize)]
+#[serde(default)]
+struct WorldsConfig {
+    worlds: HashMap<String, WorldGenConfig>,
+    /// Chunk generation threads for each world.
+    worker_threads: usize,
+}
+
+impl Default for WorldsConfig {
+    fn default() -> Self {
+        Self {
+            worlds: HashMap::new(),
+            worker_threads: 2,
+        }
+    }
+}
+
+/// Marks the layer of an extra world.
+#[derive(Component)]
+pub struct NamedWorld {
+    pub name: String,
+    /// Where players arrive.
+    pub spawn: DVec3,
+    pub flat: bool,
+    pub seed: u32,
+    /// What its generator was built from.
+    pub config: WorldGenConfig,
+}
+
+/// A seed for `name` that's different from the overworld's but the same
+/// every run.
+fn derive_seed(seed: u32, name: &str) -> u32 {
+    name.bytes().fold(seed ^ 0x9e37_79b9, |h, b| h.wrapping_mul(31).wrapping_add(b as u32))
+}
+
+pub fn setup_worlds(
+    mut commands: Commands,
+    server: Res<Server>,
+    dimensions: Res<DimensionTypeRegistry>,
+    biomes: Res<BiomeRegistry>,
+    world_gen: Res<WorldGenerator>,
+) {
+    let config = load_config::<WorldsConfig>("worlds.json");
+    for (name, mut worldgen) in config.worlds {
+        if name == OVERWORLD_NAME {
+            info!(target: WORLDGEN, "skipping world {name}, that's the main world's name");
+            continue;
+        }
+        // They share the overworld dimension type, and with it its limits
+        (worldgen.min_y, worldgen.height) = (world_gen.config.min_y, world_gen.config.height);
+        let seed = worldgen.seed.as_ref().map_or_else(|| derive_seed(world_gen.seed, &name), |seed| seed.value());
+        let generator = Arc::new(build_generator(&worldgen, seed, &biomes));
+        let layer = LayerBundle::new(ident!("overworld"), &dimensions, &biomes, &server);
+
+        let spawn = generator.find_spawn(layer.chunk.min_y());
+        let world = NamedWorld {
+            name: name.clone(),
+            spawn,
+            flat: generator.is_flat(),
+            seed,
+            config: worldgen,
+        };
+        let state = GameState::start(generator, None, config.worker_threads);
+        commands.spawn((layer, world, state, ChunkTickets::default()));
+        info!(target: WORLDGEN, "created world {name} with seed {seed}");
+    }
+}
+
+// Queues the chunks players in extra worlds can see
+pub fn update_world_views(
+    mut layers: Query<(&ChunkLayer, &mut GameState), With<NamedWorld>>,
+    clients: Query<(Ref<VisibleChunkLayer>, View, OldView)>,
+) {
+    for (visible_layer, view, old_view) in &clients {
+        let Ok((layer, mut state)) = layers.get_mut(visible_layer.0) else {
+            continue;
+        };
+        let (view, old_view) = (view.get(), old_view.get());
+        let queue_pos = |pos: ChunkPos| state.queue_chunk(layer, pos, view.pos.distance_squared(pos));
+        if visible_layer.is_changed() {
+            view.iter().for_each(queue_pos);
+        } else if old_view != view {
+            view.diff(old_view).for_each(queue_pos);
+        }
+    }
+}
+
+// Sends queued chunks to each world's workers and inserts the finished ones
+pub fn send_recv_world_chunks(
+    mut layers: Query<(Entity, &mut ChunkLayer, &mut GameState), With<NamedWorld>>,
+    throttle: Res<ChunkThrottle>,
+    mut structures: EventWriter<StructurePlaced>,
+) {
+    for (layer_entity, mut layer, mut state) in &mut layers {
+        for generated in state.receive_chunks(throttle.budget) {
+            insert_generated_chunk(layer_entity, &mut layer, generated, &mut structures);
+        }
+        state.dispatch_chunks(throttle.budget, |_| {});
+    }
+}
+
+// Drops the chunks of extra worlds nobody can see or holds a ticket for
+pub fn remove_unviewed_world_chunks(mut layers: Query<(&mut ChunkLayer, &ChunkTickets), With<NamedWorld>>) {
+    for (mut layer, tickets) in &mut layers {
+        layer.retain_chunks(|pos, chunk| chunk.viewer_count() > 0 || tickets.0.contains(&pos));
+    }
+}